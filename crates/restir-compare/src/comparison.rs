@@ -0,0 +1,51 @@
+use crate::flip::FlipErrorMap;
+use crate::linear_image::{LinearImage, ensure_same_size};
+use crate::metrics::{mse, rel_mse, ssim};
+use std::fmt::{Display, Formatter};
+
+/// All metrics of a test image compared against a reference, usually the converged output of the reference path
+/// tracer
+#[derive(Clone, Debug)]
+pub struct Comparison {
+	pub mse: f32,
+	pub rel_mse: f32,
+	pub ssim: f32,
+	pub flip: FlipErrorMap,
+}
+
+impl Comparison {
+	pub fn new(reference: &LinearImage, test: &LinearImage) -> anyhow::Result<Self> {
+		ensure_same_size(reference, test)?;
+		Ok(Self {
+			mse: mse(reference, test),
+			rel_mse: rel_mse(reference, test),
+			ssim: ssim(reference, test),
+			flip: FlipErrorMap::new(reference, test),
+		})
+	}
+}
+
+impl Display for Comparison {
+	/// one `metric: value` per line, to be easily parsed by scripts
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		writeln!(f, "mse: {}", self.mse)?;
+		writeln!(f, "rel_mse: {}", self.rel_mse)?;
+		writeln!(f, "ssim: {}", self.ssim)?;
+		write!(f, "flip: {}", self.flip.mean())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use glam::Vec3;
+
+	#[test]
+	fn test_size_mismatch() {
+		let a = LinearImage::new(2, 2, vec![Vec3::ZERO; 4], false);
+		let b = LinearImage::new(4, 1, vec![Vec3::ZERO; 4], false);
+		assert!(Comparison::new(&a, &b).is_err());
+		let comparison = Comparison::new(&a, &a).unwrap();
+		assert_eq!(comparison.to_string(), "mse: 0\nrel_mse: 0\nssim: 1\nflip: 0");
+	}
+}
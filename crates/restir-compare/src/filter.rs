@@ -0,0 +1,67 @@
+/// Convolve a single channel `width`x`height` image with the separable kernel `kernel_x` ⊗ `kernel_y`, clamping
+/// lookups to the edge. Both kernels must have odd length and are centered on the pixel.
+pub fn convolve_separable(width: u32, height: u32, values: &[f32], kernel_x: &[f32], kernel_y: &[f32]) -> Vec<f32> {
+	let (width, height) = (width as i32, height as i32);
+	let convolve = |kernel: &[f32], load: &dyn Fn(i32) -> f32| {
+		let radius = (kernel.len() / 2) as i32;
+		kernel
+			.iter()
+			.enumerate()
+			.map(|(i, k)| k * load(i as i32 - radius))
+			.sum::<f32>()
+	};
+	let horizontal = (0..height)
+		.flat_map(|y| {
+			(0..width).map(move |x| {
+				convolve(kernel_x, &|dx| {
+					values[(y * width + (x + dx).clamp(0, width - 1)) as usize]
+				})
+			})
+		})
+		.collect::<Vec<_>>();
+	(0..height)
+		.flat_map(|y| {
+			let horizontal = &horizontal;
+			(0..width).map(move |x| {
+				convolve(kernel_y, &|dy| {
+					horizontal[((y + dy).clamp(0, height - 1) * width + x) as usize]
+				})
+			})
+		})
+		.collect()
+}
+
+/// A gaussian kernel with standard deviation `sigma` in pixels, cut off at `radius`, normalized to a sum of 1
+pub fn gaussian_kernel(sigma: f32, radius: u32) -> Vec<f32> {
+	let kernel = (-(radius as i32)..=radius as i32)
+		.map(|x| f32::exp(-((x * x) as f32) / (2. * sigma * sigma)))
+		.collect::<Vec<_>>();
+	let sum = kernel.iter().sum::<f32>();
+	kernel.into_iter().map(|k| k / sum).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_gaussian_kernel() {
+		let kernel = gaussian_kernel(1.5, 5);
+		assert_eq!(kernel.len(), 11);
+		assert!((kernel.iter().sum::<f32>() - 1.).abs() < 1e-6);
+		assert_eq!(kernel[4], kernel[6]);
+		assert!(kernel[5] > kernel[4]);
+	}
+
+	#[test]
+	fn test_convolve_separable() {
+		let values = [0., 0., 0., 0., 9., 0., 0., 0., 0.];
+		let box_kernel = [1. / 3.; 3];
+		let result = convolve_separable(3, 3, &values, &box_kernel, &box_kernel);
+		assert!(result.iter().all(|v| (v - 1.).abs() < 1e-6), "{result:?}");
+
+		// edges are clamped, so a constant image stays constant
+		let constant = convolve_separable(3, 3, &[2.; 9], &[0.5, 0.25, 0.25], &[1., 0., 0.]);
+		assert!(constant.iter().all(|v| (v - 2.).abs() < 1e-6), "{constant:?}");
+	}
+}
@@ -0,0 +1,322 @@
+//! A perceptual error map following "FLIP: A Difference Evaluator for Alternating Images" by Andersson et al. Both
+//! images are filtered by the contrast sensitivity of the human eye, the remaining color difference is then amplified
+//! where edges and points differ. HDR images are tonemapped at a single exposure instead of HDR-FLIP's exposure range.
+
+use crate::filter::{convolve_separable, gaussian_kernel};
+use crate::linear_image::LinearImage;
+use core::f32::consts::{PI, SQRT_2};
+use glam::{Mat3, Vec3};
+use image::{Rgb, RgbImage};
+
+/// Pixels per degree of the viewing setup, the default of FLIP: a 0.7m wide 4K monitor viewed from 0.7m away
+pub const FLIP_PIXELS_PER_DEGREE: f32 = 67.0;
+
+const D65_WHITE: Vec3 = Vec3::new(0.950_428_5, 1., 1.088_900_4);
+
+const XYZ_FROM_LINEAR_RGB: Mat3 = Mat3::from_cols_array(&[
+	0.412_390_8,
+	0.212_639,
+	0.019_330_8,
+	0.357_584_33,
+	0.715_168_7,
+	0.119_194_78,
+	0.180_480_8,
+	0.072_192_32,
+	0.950_532_2,
+]);
+
+/// Contrast sensitivity of a channel, as a sum of two gaussians over the distance in degrees of visual angle
+struct Csf {
+	a: [f32; 2],
+	b: [f32; 2],
+}
+
+const CSF_ACHROMATIC: Csf = Csf {
+	a: [1., 0.],
+	b: [0.0047, 1e-5],
+};
+const CSF_RED_GREEN: Csf = Csf {
+	a: [1., 0.],
+	b: [0.0053, 1e-5],
+};
+const CSF_BLUE_YELLOW: Csf = Csf {
+	a: [34.1, 13.5],
+	b: [0.04, 0.025],
+};
+
+/// exponent of the color difference
+const QC: f32 = 0.7;
+/// exponent of the feature difference
+const QF: f32 = 0.5;
+/// color differences below `PC * cmax` are compressed into [0, PT]
+const PC: f32 = 0.4;
+const PT: f32 = 0.95;
+/// width of the edge and point detection filters, in degrees of visual angle
+const FEATURE_WIDTH: f32 = 0.082;
+
+/// Per pixel perceptual error in [0, 1]
+#[derive(Clone, Debug)]
+pub struct FlipErrorMap {
+	pub width: u32,
+	pub height: u32,
+	pub errors: Vec<f32>,
+}
+
+impl FlipErrorMap {
+	/// The FLIP error of `test` compared against `reference`, which must have the same size.
+	pub fn new(reference: &LinearImage, test: &LinearImage) -> Self {
+		let tonemap = reference.is_hdr || test.is_hdr;
+		let (width, height) = (reference.width, reference.height);
+		let reference = reference.to_display(tonemap);
+		let test = test.to_display(tonemap);
+
+		let filtered_reference = csf_filter(width, height, &reference);
+		let filtered_test = csf_filter(width, height, &test);
+		let cmax = hyab(hunt(lab(Vec3::Y)), hunt(lab(Vec3::Z))).powf(QC);
+		let reference_features = Features::new(width, height, &reference);
+		let test_features = Features::new(width, height, &test);
+
+		let errors = (0..reference.len())
+			.map(|i| {
+				let color = hyab(hunt(lab(filtered_reference[i])), hunt(lab(filtered_test[i]))).powf(QC);
+				let color = if color < PC * cmax {
+					color * PT / (PC * cmax)
+				} else {
+					PT + (color - PC * cmax) / (cmax - PC * cmax) * (1. - PT)
+				};
+				let edge = f32::abs(reference_features.edges[i] - test_features.edges[i]);
+				let point = f32::abs(reference_features.points[i] - test_features.points[i]);
+				let feature = (f32::max(edge, point) / SQRT_2).powf(QF);
+				color.powf(1. - feature)
+			})
+			.collect();
+		Self { width, height, errors }
+	}
+
+	pub fn mean(&self) -> f32 {
+		(self.errors.iter().map(|&e| e as f64).sum::<f64>() / self.errors.len() as f64) as f32
+	}
+
+	/// The error map colored with the magma colormap, from black for no error to light yellow for maximum error
+	pub fn heatmap(&self) -> RgbImage {
+		RgbImage::from_fn(self.width, self.height, |x, y| {
+			let color = magma(self.errors[(y * self.width + x) as usize]);
+			Rgb(color.to_array().map(|c| (c.clamp(0., 1.) * 255.).round() as u8))
+		})
+	}
+}
+
+/// Filter the colors in opponent color space by the contrast sensitivity of each channel, returns linear RGB
+fn csf_filter(width: u32, height: u32, colors: &[Vec3]) -> Vec<Vec3> {
+	let ycxcz = colors.iter().map(|&c| ycxcz(c)).collect::<Vec<_>>();
+	let filtered = [CSF_ACHROMATIC, CSF_RED_GREEN, CSF_BLUE_YELLOW]
+		.iter()
+		.enumerate()
+		.map(|(channel, csf)| {
+			let values = ycxcz.iter().map(|c| c[channel]).collect::<Vec<_>>();
+			csf.filter(width, height, &values)
+		})
+		.collect::<Vec<_>>();
+	(0..colors.len())
+		.map(|i| {
+			linear_rgb_from_ycxcz(Vec3::new(filtered[0][i], filtered[1][i], filtered[2][i]))
+				.clamp(Vec3::ZERO, Vec3::ONE)
+		})
+		.collect()
+}
+
+impl Csf {
+	fn filter(&self, width: u32, height: u32, values: &[f32]) -> Vec<f32> {
+		// radius of the widest gaussian of all channels, so that all filters cover the same area
+		let max_b = f32::max(CSF_BLUE_YELLOW.b[0], CSF_BLUE_YELLOW.b[1]);
+		let radius = (3. * f32::sqrt(max_b / (2. * PI * PI)) * FLIP_PIXELS_PER_DEGREE).ceil() as i32;
+		let mut sum = vec![0.; values.len()];
+		let mut weight_sum = 0.;
+		for (a, b) in self.a.into_iter().zip(self.b) {
+			if a == 0. {
+				continue;
+			}
+			let kernel = (-radius..=radius)
+				.map(|x| {
+					let degrees = x as f32 / FLIP_PIXELS_PER_DEGREE;
+					f32::exp(-PI * PI * degrees * degrees / b)
+				})
+				.collect::<Vec<_>>();
+			let weight = a * f32::sqrt(PI / b);
+			let kernel_sum = kernel.iter().sum::<f32>();
+			weight_sum += weight * kernel_sum * kernel_sum;
+			let filtered = convolve_separable(width, height, values, &kernel, &kernel);
+			for (sum, filtered) in sum.iter_mut().zip(filtered) {
+				*sum += weight * filtered;
+			}
+		}
+		sum.into_iter().map(|s| s / weight_sum).collect()
+	}
+}
+
+/// Magnitudes of the edge and point detectors on the achromatic channel
+struct Features {
+	edges: Vec<f32>,
+	points: Vec<f32>,
+}
+
+impl Features {
+	fn new(width: u32, height: u32, colors: &[Vec3]) -> Self {
+		let achromatic = colors.iter().map(|&c| (ycxcz(c).x + 16.) / 116.).collect::<Vec<_>>();
+		let sigma = 0.5 * FEATURE_WIDTH * FLIP_PIXELS_PER_DEGREE;
+		let radius = (3. * sigma).ceil() as u32;
+		let gaussian = gaussian_kernel(sigma, radius);
+		let offsets = || (-(radius as i32)..=radius as i32).map(|x| x as f32);
+		let edge = normalize_signed(offsets().zip(&gaussian).map(|(x, g)| -x * g).collect());
+		let point = normalize_signed(
+			offsets()
+				.zip(&gaussian)
+				.map(|(x, g)| (x * x / (sigma * sigma) - 1.) * g)
+				.collect(),
+		);
+
+		let magnitude = |kernel: &[f32]| {
+			let x = convolve_separable(width, height, &achromatic, kernel, &gaussian);
+			let y = convolve_separable(width, height, &achromatic, &gaussian, kernel);
+			x.into_iter().zip(y).map(|(x, y)| f32::hypot(x, y)).collect()
+		};
+		Self {
+			edges: magnitude(&edge),
+			points: magnitude(&point),
+		}
+	}
+}
+
+/// Scale the positive weights of `kernel` to sum to 1 and the negative ones to -1
+fn normalize_signed(kernel: Vec<f32>) -> Vec<f32> {
+	let positive = kernel.iter().filter(|&&k| k > 0.).sum::<f32>();
+	let negative = -kernel.iter().filter(|&&k| k < 0.).sum::<f32>();
+	kernel
+		.into_iter()
+		.map(|k| if k > 0. { k / positive } else { k / negative })
+		.collect()
+}
+
+fn ycxcz(linear_rgb: Vec3) -> Vec3 {
+	let xyz = XYZ_FROM_LINEAR_RGB * linear_rgb / D65_WHITE;
+	Vec3::new(116. * xyz.y - 16., 500. * (xyz.x - xyz.y), 200. * (xyz.y - xyz.z))
+}
+
+fn linear_rgb_from_ycxcz(ycxcz: Vec3) -> Vec3 {
+	let y = (ycxcz.x + 16.) / 116.;
+	let xyz = Vec3::new(ycxcz.y / 500. + y, y, y - ycxcz.z / 200.) * D65_WHITE;
+	XYZ_FROM_LINEAR_RGB.inverse() * xyz
+}
+
+/// CIELAB of a linear RGB color
+fn lab(linear_rgb: Vec3) -> Vec3 {
+	let f = |t: f32| {
+		const DELTA: f32 = 6. / 29.;
+		if t > DELTA * DELTA * DELTA {
+			t.cbrt()
+		} else {
+			t / (3. * DELTA * DELTA) + 4. / 29.
+		}
+	};
+	let xyz = (XYZ_FROM_LINEAR_RGB * linear_rgb / D65_WHITE).to_array().map(f);
+	Vec3::new(116. * xyz[1] - 16., 500. * (xyz[0] - xyz[1]), 200. * (xyz[1] - xyz[2]))
+}
+
+/// The Hunt effect: chroma appears weaker at low luminance
+fn hunt(lab: Vec3) -> Vec3 {
+	Vec3::new(lab.x, 0.01 * lab.x * lab.y, 0.01 * lab.x * lab.z)
+}
+
+/// Color difference combining the absolute difference in lightness with the euclidean distance in chroma
+fn hyab(a: Vec3, b: Vec3) -> f32 {
+	let d = a - b;
+	d.x.abs() + f32::hypot(d.y, d.z)
+}
+
+/// Polynomial fit of the magma colormap by Matt Zucker, returns sRGB encoded colors
+fn magma(t: f32) -> Vec3 {
+	const C: [Vec3; 7] = [
+		Vec3::new(-0.002_136_485, -0.000_749_655_1, -0.005_386_128),
+		Vec3::new(0.251_660_54, 0.677_523_24, 2.494_026_6),
+		Vec3::new(8.353_717, -3.577_719_5, 0.314_467_9),
+		Vec3::new(-27.668_733, 14.264_731, -13.649_213),
+		Vec3::new(52.176_14, -27.943_607, 12.944_169),
+		Vec3::new(-50.768_524, 29.046_583, 4.234_153),
+		Vec3::new(18.655_705, -11.489_774, -5.601_961_5),
+	];
+	let t = t.clamp(0., 1.);
+	C.iter().rev().fold(Vec3::ZERO, |acc, &c| acc * t + c)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn constant(color: Vec3) -> LinearImage {
+		LinearImage::new(16, 16, vec![color; 16 * 16], false)
+	}
+
+	#[test]
+	fn test_ycxcz_roundtrip() {
+		for c in [Vec3::ZERO, Vec3::ONE, Vec3::new(0.2, 0.7, 0.1), Vec3::new(1., 0., 0.5)] {
+			let roundtrip = linear_rgb_from_ycxcz(ycxcz(c));
+			assert!(roundtrip.abs_diff_eq(c, 1e-4), "{c} != {roundtrip}");
+		}
+		assert!((lab(Vec3::ONE) - Vec3::new(100., 0., 0.)).abs().max_element() < 1e-2);
+	}
+
+	#[test]
+	fn test_identical() {
+		let image = constant(Vec3::new(0.3, 0.6, 0.1));
+		let flip = FlipErrorMap::new(&image, &image);
+		assert!(flip.errors.iter().all(|&e| e == 0.));
+	}
+
+	#[test]
+	fn test_error_increases() {
+		let reference = constant(Vec3::splat(0.5));
+		let slight = FlipErrorMap::new(&reference, &constant(Vec3::splat(0.45))).mean();
+		let strong = FlipErrorMap::new(&reference, &constant(Vec3::splat(0.05))).mean();
+		let green_blue = FlipErrorMap::new(&constant(Vec3::Y), &constant(Vec3::Z)).mean();
+		assert!(0. < slight && slight < strong && strong < 1., "{slight} {strong}");
+		assert!(
+			(green_blue - 1.).abs() < 1e-3,
+			"the most different colors have maximum error: {green_blue}"
+		);
+	}
+
+	#[test]
+	fn test_edges() {
+		// a single pixel wide line is both an edge and a point, which a blurry version of it lacks
+		let (width, height) = (32, 32);
+		let line = |x: u32| if x == 16 { Vec3::ONE } else { Vec3::ZERO };
+		let blurry = |x: u32| Vec3::splat(if x.abs_diff(16) <= 2 { 0.2 } else { 0. });
+		let image = |f: &dyn Fn(u32) -> Vec3| {
+			LinearImage::new(
+				width,
+				height,
+				(0..width * height).map(|i| f(i % width)).collect(),
+				false,
+			)
+		};
+		let flip = FlipErrorMap::new(&image(&line), &image(&blurry));
+		assert!(flip.errors[16 * 32 + 16] > 0.5, "{}", flip.errors[16 * 32 + 16]);
+		assert!(
+			flip.errors[16 * 32] < 0.01,
+			"far from the line: {}",
+			flip.errors[16 * 32]
+		);
+	}
+
+	#[test]
+	fn test_heatmap() {
+		let flip = FlipErrorMap {
+			width: 2,
+			height: 1,
+			errors: vec![0., 1.],
+		};
+		let heatmap = flip.heatmap();
+		assert!(heatmap.get_pixel(0, 0).0.iter().all(|&c| c < 4));
+		assert!(heatmap.get_pixel(1, 0).0.iter().all(|&c| c > 180));
+	}
+}
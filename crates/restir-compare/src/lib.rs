@@ -0,0 +1,5 @@
+pub mod comparison;
+pub mod filter;
+pub mod flip;
+pub mod linear_image;
+pub mod metrics;
@@ -0,0 +1,135 @@
+use anyhow::Context;
+use glam::Vec3;
+use image::{ColorType, DynamicImage};
+use std::path::Path;
+
+/// An image of linear Rec. 709 colors, either radiance as rendered or decoded from an sRGB image
+#[derive(Clone, Debug)]
+pub struct LinearImage {
+	pub width: u32,
+	pub height: u32,
+	/// row major, starting at the top left
+	pub pixels: Vec<Vec3>,
+	/// Whether the image may contain radiance outside [0, 1], which has to be tonemapped before comparing it
+	/// perceptually
+	pub is_hdr: bool,
+}
+
+impl LinearImage {
+	pub fn new(width: u32, height: u32, pixels: Vec<Vec3>, is_hdr: bool) -> Self {
+		assert_eq!(pixels.len(), (width * height) as usize);
+		Self {
+			width,
+			height,
+			pixels,
+			is_hdr,
+		}
+	}
+
+	/// Load any image format supported by [`image`]. Floating point formats like `.hdr` and `.exr` are read as linear
+	/// radiance, all other formats are decoded from sRGB.
+	pub fn load(path: &Path) -> anyhow::Result<Self> {
+		let image = image::open(path).with_context(|| format!("failed to load {}", path.display()))?;
+		Ok(Self::from_dynamic(image))
+	}
+
+	pub fn from_dynamic(image: DynamicImage) -> Self {
+		let is_hdr = matches!(image.color(), ColorType::Rgb32F | ColorType::Rgba32F);
+		let rgb = image.into_rgb32f();
+		let pixels = rgb
+			.pixels()
+			.map(|p| {
+				let color = Vec3::from_array(p.0);
+				if is_hdr { color } else { color.map(srgb_to_linear) }
+			})
+			.collect();
+		Self::new(rgb.width(), rgb.height(), pixels, is_hdr)
+	}
+
+	pub fn get(&self, x: u32, y: u32) -> Vec3 {
+		self.pixels[(y * self.width + x) as usize]
+	}
+
+	/// Colors in [0, 1] as shown on a display, tonemapping HDR images with [`aces_tonemap`] if `tonemap` is set
+	pub fn to_display(&self, tonemap: bool) -> Vec<Vec3> {
+		self.pixels
+			.iter()
+			.map(|&c| {
+				let c = c.max(Vec3::ZERO);
+				if tonemap { aces_tonemap(c) } else { c.min(Vec3::ONE) }
+			})
+			.collect()
+	}
+}
+
+/// Ensure both images can be compared pixel by pixel
+pub fn ensure_same_size(reference: &LinearImage, test: &LinearImage) -> anyhow::Result<()> {
+	if (reference.width, reference.height) != (test.width, test.height) {
+		anyhow::bail!(
+			"reference is {}x{} but test image is {}x{}",
+			reference.width,
+			reference.height,
+			test.width,
+			test.height
+		);
+	}
+	Ok(())
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+	if c <= 0.04045 {
+		c / 12.92
+	} else {
+		((c + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+	if c <= 0.0031308 {
+		c * 12.92
+	} else {
+		1.055 * c.powf(1. / 2.4) - 0.055
+	}
+}
+
+/// Relative luminance of a linear Rec. 709 color
+pub fn luminance(c: Vec3) -> f32 {
+	c.dot(Vec3::new(0.2126, 0.7152, 0.0722))
+}
+
+/// Krzysztof Narkowicz's fit of the ACES filmic tonemapping curve, the same one used by HDR-FLIP
+pub fn aces_tonemap(c: Vec3) -> Vec3 {
+	let c = c * 0.6;
+	((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)).clamp(Vec3::ZERO, Vec3::ONE)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use image::{Rgb, Rgb32FImage, RgbImage};
+
+	#[test]
+	fn test_srgb_roundtrip() {
+		for i in 0..=255 {
+			let c = i as f32 / 255.;
+			assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-5, "{c}");
+		}
+	}
+
+	#[test]
+	fn test_from_dynamic() {
+		let ldr = RgbImage::from_pixel(2, 1, Rgb([255, 188, 0]));
+		let ldr = LinearImage::from_dynamic(ldr.into());
+		assert!(!ldr.is_hdr);
+		assert!(
+			ldr.get(1, 0).abs_diff_eq(Vec3::new(1., 0.5, 0.), 5e-3),
+			"{}",
+			ldr.get(1, 0)
+		);
+
+		let hdr = Rgb32FImage::from_pixel(1, 2, Rgb([4., 0.5, 0.]));
+		let hdr = LinearImage::from_dynamic(hdr.into());
+		assert!(hdr.is_hdr);
+		assert_eq!(hdr.get(0, 1), Vec3::new(4., 0.5, 0.));
+	}
+}
@@ -0,0 +1,27 @@
+use anyhow::Context;
+use restir_compare::comparison::Comparison;
+use restir_compare::linear_image::LinearImage;
+use std::path::PathBuf;
+
+const USAGE: &str = "usage: restir-compare <reference> <test> [flip heatmap output]";
+
+/// Compare a test image against a reference and print all metrics, optionally writing the FLIP error map as a heatmap
+pub fn main() -> anyhow::Result<()> {
+	let args = std::env::args_os().skip(1).map(PathBuf::from).collect::<Vec<_>>();
+	let (reference, test, heatmap) = match &args[..] {
+		[reference, test] => (reference, test, None),
+		[reference, test, heatmap] => (reference, test, Some(heatmap)),
+		_ => anyhow::bail!("{USAGE}"),
+	};
+
+	let comparison = Comparison::new(&LinearImage::load(reference)?, &LinearImage::load(test)?)?;
+	println!("{comparison}");
+	if let Some(heatmap) = heatmap {
+		comparison
+			.flip
+			.heatmap()
+			.save(heatmap)
+			.with_context(|| format!("failed to write {}", heatmap.display()))?;
+	}
+	Ok(())
+}
@@ -0,0 +1,120 @@
+use crate::filter::{convolve_separable, gaussian_kernel};
+use crate::linear_image::{LinearImage, linear_to_srgb, luminance};
+use glam::Vec3;
+
+/// Added to the squared reference in [`rel_mse`], so that black pixels don't divide by zero
+pub const REL_MSE_EPSILON: f32 = 1e-2;
+
+/// Mean squared error over all pixels and channels
+pub fn mse(reference: &LinearImage, test: &LinearImage) -> f32 {
+	mean_per_channel(reference, test, |r, t| (t - r) * (t - r))
+}
+
+/// Mean squared error relative to the squared reference, so that dark regions contribute as much as bright ones
+pub fn rel_mse(reference: &LinearImage, test: &LinearImage) -> f32 {
+	mean_per_channel(reference, test, |r, t| (t - r) * (t - r) / (r * r + REL_MSE_EPSILON))
+}
+
+fn mean_per_channel(reference: &LinearImage, test: &LinearImage, f: impl Fn(Vec3, Vec3) -> Vec3) -> f32 {
+	let sum = reference
+		.pixels
+		.iter()
+		.zip(&test.pixels)
+		.map(|(&r, &t)| f(r, t).to_array().iter().map(|&e| e as f64).sum::<f64>())
+		.sum::<f64>();
+	(sum / (reference.pixels.len() * 3) as f64) as f32
+}
+
+/// standard deviation of the gaussian window [`ssim`] computes local statistics in
+pub const SSIM_SIGMA: f32 = 1.5;
+pub const SSIM_RADIUS: u32 = 5;
+
+/// Mean structural similarity of the sRGB encoded luminance of both images as shown on a display, see [`ssim_map`].
+pub fn ssim(reference: &LinearImage, test: &LinearImage) -> f32 {
+	let map = ssim_map(reference, test);
+	(map.iter().map(|&s| s as f64).sum::<f64>() / map.len() as f64) as f32
+}
+
+/// Structural similarity per pixel, see "Image Quality Assessment: From Error Visibility to Structural Similarity" by
+/// Wang et al. HDR images are tonemapped with [`aces_tonemap`](crate::linear_image::aces_tonemap) first.
+pub fn ssim_map(reference: &LinearImage, test: &LinearImage) -> Vec<f32> {
+	const C1: f32 = 0.01 * 0.01;
+	const C2: f32 = 0.03 * 0.03;
+	let tonemap = reference.is_hdr || test.is_hdr;
+	let display_luminance = |image: &LinearImage| {
+		image
+			.to_display(tonemap)
+			.into_iter()
+			.map(|c| linear_to_srgb(luminance(c)))
+			.collect::<Vec<_>>()
+	};
+	let x = display_luminance(reference);
+	let y = display_luminance(test);
+	let kernel = gaussian_kernel(SSIM_SIGMA, SSIM_RADIUS);
+	let blur = |values: &[f32]| convolve_separable(reference.width, reference.height, values, &kernel, &kernel);
+	let product = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(a, b)| a * b).collect::<Vec<_>>();
+
+	let mu_x = blur(&x);
+	let mu_y = blur(&y);
+	let xx = blur(&product(&x, &x));
+	let yy = blur(&product(&y, &y));
+	let xy = blur(&product(&x, &y));
+	(0..x.len())
+		.map(|i| {
+			let var_x = xx[i] - mu_x[i] * mu_x[i];
+			let var_y = yy[i] - mu_y[i] * mu_y[i];
+			let cov = xy[i] - mu_x[i] * mu_y[i];
+			((2. * mu_x[i] * mu_y[i] + C1) * (2. * cov + C2))
+				/ ((mu_x[i] * mu_x[i] + mu_y[i] * mu_y[i] + C1) * (var_x + var_y + C2))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn gradient(width: u32, height: u32) -> LinearImage {
+		let pixels = (0..height)
+			.flat_map(|y| (0..width).map(move |x| Vec3::new(x as f32 / width as f32, y as f32 / height as f32, 0.5)))
+			.collect();
+		LinearImage::new(width, height, pixels, false)
+	}
+
+	#[test]
+	fn test_identical() {
+		let image = gradient(32, 16);
+		assert_eq!(mse(&image, &image), 0.);
+		assert_eq!(rel_mse(&image, &image), 0.);
+		assert!((ssim(&image, &image) - 1.).abs() < 1e-4);
+	}
+
+	#[test]
+	fn test_mse() {
+		let reference = LinearImage::new(2, 1, vec![Vec3::ZERO, Vec3::ONE], true);
+		let test = LinearImage::new(2, 1, vec![Vec3::splat(2.), Vec3::ONE], true);
+		assert!((mse(&reference, &test) - 2.).abs() < 1e-6);
+		assert!((rel_mse(&reference, &test) - 2. / REL_MSE_EPSILON).abs() < 1e-3);
+	}
+
+	#[test]
+	fn test_ssim_decreases_with_noise() {
+		let reference = gradient(64, 64);
+		let noisy = |amplitude: f32| {
+			// deterministic white noise in [0, 1) from an LCG
+			let mut state = 1u32;
+			let pixels = reference
+				.pixels
+				.iter()
+				.map(|&c| {
+					state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+					c + ((state >> 8) as f32 / (1 << 24) as f32 - 0.5) * amplitude
+				})
+				.collect();
+			LinearImage::new(64, 64, pixels, false)
+		};
+		let slight = ssim(&reference, &noisy(0.05));
+		let strong = ssim(&reference, &noisy(0.5));
+		assert!(slight < 1. && strong < slight, "{slight} {strong}");
+	}
+}
@@ -0,0 +1,48 @@
+use crate::brdf::{Brdf, BrdfSample, cosine_hemisphere_pdf, same_hemisphere, sample_cosine_hemisphere, schlick_weight};
+use core::f32::consts::FRAC_1_PI;
+use glam::{Vec2, Vec3};
+
+/// The diffuse lobe of the Disney BRDF, see "Physically Based Shading at Disney" by Burley. Darkens grazing angles of
+/// smooth surfaces and adds retro-reflection to rough ones. Sampled like [`Lambert`](crate::brdf::lambert::Lambert).
+#[derive(Copy, Clone, Debug)]
+pub struct DisneyDiffuse {
+	pub base_color: Vec3,
+	/// perceptual roughness in [0, 1]
+	pub roughness: f32,
+}
+
+impl DisneyDiffuse {
+	pub fn new(base_color: Vec3, roughness: f32) -> Self {
+		Self { base_color, roughness }
+	}
+}
+
+impl Brdf for DisneyDiffuse {
+	fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+		if !same_hemisphere(wo, wi) {
+			return Vec3::ZERO;
+		}
+		let h = (wo + wi).normalize();
+		let cos_d = wi.dot(h);
+		let fd90 = 0.5 + 2. * self.roughness * cos_d * cos_d;
+		let fl = 1. + (fd90 - 1.) * schlick_weight(wi.z);
+		let fv = 1. + (fd90 - 1.) * schlick_weight(wo.z);
+		self.base_color * FRAC_1_PI * fl * fv
+	}
+
+	fn sample(&self, wo: Vec3, u: Vec2) -> BrdfSample {
+		let wi = sample_cosine_hemisphere(u);
+		BrdfSample {
+			wi,
+			pdf: self.pdf(wo, wi),
+		}
+	}
+
+	fn pdf(&self, wo: Vec3, wi: Vec3) -> f32 {
+		if same_hemisphere(wo, wi) {
+			cosine_hemisphere_pdf(wi.z)
+		} else {
+			0.
+		}
+	}
+}
@@ -0,0 +1,115 @@
+use crate::brdf::{Brdf, BrdfSample, fresnel_schlick, same_hemisphere};
+use core::f32::consts::PI;
+use glam::{Vec2, Vec3};
+use spirv_std::num_traits::Float;
+
+/// Lower bound of `alpha`, as a perfectly smooth surface would be a dirac delta
+pub const GGX_MIN_ALPHA: f32 = 1e-3;
+
+/// The GGX or Trowbridge-Reitz microfacet BRDF with height-correlated Smith masking-shadowing and Schlick's fresnel
+/// approximation. Samples the distribution of visible normals, see "Sampling the GGX Distribution of Visible Normals"
+/// by Heitz.
+#[derive(Copy, Clone, Debug)]
+pub struct Ggx {
+	/// isotropic roughness, the square of the perceptual roughness
+	pub alpha: f32,
+	/// reflectance at normal incidence
+	pub f0: Vec3,
+}
+
+impl Ggx {
+	/// A GGX lobe with the perceptual `roughness` in [0, 1]
+	pub fn new(roughness: f32, f0: Vec3) -> Self {
+		Self::from_alpha(roughness * roughness, f0)
+	}
+
+	pub fn from_alpha(alpha: f32, f0: Vec3) -> Self {
+		Self {
+			alpha: f32::max(alpha, GGX_MIN_ALPHA),
+			f0,
+		}
+	}
+
+	/// The normal distribution function of the microfacet normal `h`
+	pub fn d(&self, h: Vec3) -> f32 {
+		let a2 = self.alpha * self.alpha;
+		let d = h.z * h.z * (a2 - 1.) + 1.;
+		a2 / (PI * d * d)
+	}
+
+	/// Smith's `Λ` of direction `w`
+	fn lambda(&self, w: Vec3) -> f32 {
+		let cos2 = w.z * w.z;
+		let tan2 = f32::max(1. - cos2, 0.) / cos2;
+		((1. + self.alpha * self.alpha * tan2).sqrt() - 1.) / 2.
+	}
+
+	/// Smith masking function of direction `w`
+	pub fn g1(&self, w: Vec3) -> f32 {
+		1. / (1. + self.lambda(w))
+	}
+
+	/// Height-correlated Smith masking-shadowing function
+	pub fn g2(&self, wo: Vec3, wi: Vec3) -> f32 {
+		1. / (1. + self.lambda(wo) + self.lambda(wi))
+	}
+
+	pub fn fresnel(&self, cos_theta: f32) -> Vec3 {
+		fresnel_schlick(self.f0, cos_theta)
+	}
+
+	/// Sample a microfacet normal visible from `wo`
+	pub fn sample_visible_normal(&self, wo: Vec3, u: Vec2) -> Vec3 {
+		// stretch the view direction to the hemisphere configuration
+		let vh = Vec3::new(self.alpha * wo.x, self.alpha * wo.y, wo.z).normalize();
+		let len2 = vh.x * vh.x + vh.y * vh.y;
+		let t1 = if len2 > 0. {
+			Vec3::new(-vh.y, vh.x, 0.) / len2.sqrt()
+		} else {
+			Vec3::X
+		};
+		let t2 = vh.cross(t1);
+
+		// uniformly sample the projected area of the hemisphere
+		let r = u.x.sqrt();
+		let phi = 2. * PI * u.y;
+		let p1 = r * phi.cos();
+		let s = 0.5 * (1. + vh.z);
+		let p2 = (1. - s) * f32::max(1. - p1 * p1, 0.).sqrt() + s * r * phi.sin();
+		let nh = t1 * p1 + t2 * p2 + vh * f32::max(1. - p1 * p1 - p2 * p2, 0.).sqrt();
+
+		// unstretch
+		Vec3::new(self.alpha * nh.x, self.alpha * nh.y, f32::max(nh.z, 1e-6)).normalize()
+	}
+}
+
+impl Brdf for Ggx {
+	fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+		if !same_hemisphere(wo, wi) {
+			return Vec3::ZERO;
+		}
+		let h = (wo + wi).normalize();
+		self.fresnel(wo.dot(h)) * self.d(h) * self.g2(wo, wi) / (4. * wo.z * wi.z)
+	}
+
+	fn sample(&self, wo: Vec3, u: Vec2) -> BrdfSample {
+		if wo.z <= 0. {
+			return BrdfSample::INVALID;
+		}
+		let h = self.sample_visible_normal(wo, u);
+		let wi = 2. * wo.dot(h) * h - wo;
+		BrdfSample {
+			wi,
+			pdf: self.pdf(wo, wi),
+		}
+	}
+
+	fn pdf(&self, wo: Vec3, wi: Vec3) -> f32 {
+		if !same_hemisphere(wo, wi) {
+			return 0.;
+		}
+		// the visible normal pdf `G1(wo) max(0, wo·h) D(h) / wo.z` times the jacobian of reflection `1 / (4 wo·h)`
+		let h = (wo + wi).normalize();
+		self.g1(wo) * self.d(h) / (4. * wo.z)
+	}
+}
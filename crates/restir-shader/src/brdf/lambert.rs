@@ -0,0 +1,41 @@
+use crate::brdf::{Brdf, BrdfSample, cosine_hemisphere_pdf, same_hemisphere, sample_cosine_hemisphere};
+use core::f32::consts::FRAC_1_PI;
+use glam::{Vec2, Vec3};
+
+/// An ideal diffuse reflector
+#[derive(Copy, Clone, Debug)]
+pub struct Lambert {
+	pub albedo: Vec3,
+}
+
+impl Lambert {
+	pub fn new(albedo: Vec3) -> Self {
+		Self { albedo }
+	}
+}
+
+impl Brdf for Lambert {
+	fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+		if same_hemisphere(wo, wi) {
+			self.albedo * FRAC_1_PI
+		} else {
+			Vec3::ZERO
+		}
+	}
+
+	fn sample(&self, wo: Vec3, u: Vec2) -> BrdfSample {
+		let wi = sample_cosine_hemisphere(u);
+		BrdfSample {
+			wi,
+			pdf: self.pdf(wo, wi),
+		}
+	}
+
+	fn pdf(&self, wo: Vec3, wi: Vec3) -> f32 {
+		if same_hemisphere(wo, wi) {
+			cosine_hemisphere_pdf(wi.z)
+		} else {
+			0.
+		}
+	}
+}
@@ -0,0 +1,338 @@
+//! BRDF lobes with importance sampling. All directions are normalized, point away from the surface and are given in a
+//! local [`ShadingFrame`] with the normal at +Z.
+
+use core::f32::consts::{FRAC_1_PI, PI};
+use glam::{Vec2, Vec3};
+use spirv_std::num_traits::Float;
+
+pub mod disney;
+pub mod ggx;
+pub mod lambert;
+
+pub trait Brdf {
+	/// The BRDF value of light arriving from `wi` being reflected towards `wo`, without the cosine term
+	fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3;
+
+	/// Sample an incident direction `wi` for the outgoing direction `wo`, with `u` uniformly distributed in [0, 1)²
+	fn sample(&self, wo: Vec3, u: Vec2) -> BrdfSample;
+
+	/// The solid angle pdf of [`Self::sample`] returning `wi`
+	fn pdf(&self, wo: Vec3, wi: Vec3) -> f32;
+}
+
+/// A sampled incident direction and its solid angle pdf. The sample is invalid if `pdf` is 0, e.g. if `wi` ended up
+/// below the surface.
+#[derive(Copy, Clone, Debug)]
+pub struct BrdfSample {
+	pub wi: Vec3,
+	pub pdf: f32,
+}
+
+impl BrdfSample {
+	pub const INVALID: Self = Self { wi: Vec3::Z, pdf: 0. };
+
+	pub fn is_valid(&self) -> bool {
+		self.pdf > 0.
+	}
+}
+
+/// An orthonormal basis around a normal, transforming between world space and the local space BRDFs are evaluated in
+#[derive(Copy, Clone, Debug)]
+pub struct ShadingFrame {
+	pub tangent: Vec3,
+	pub bitangent: Vec3,
+	pub normal: Vec3,
+}
+
+impl ShadingFrame {
+	/// A frame with an arbitrary tangent around the normalized `normal`
+	pub fn new(normal: Vec3) -> Self {
+		let (tangent, bitangent) = normal.any_orthonormal_pair();
+		Self {
+			tangent,
+			bitangent,
+			normal,
+		}
+	}
+
+	pub fn to_local(&self, world: Vec3) -> Vec3 {
+		Vec3::new(
+			world.dot(self.tangent),
+			world.dot(self.bitangent),
+			world.dot(self.normal),
+		)
+	}
+
+	pub fn to_world(&self, local: Vec3) -> Vec3 {
+		self.tangent * local.x + self.bitangent * local.y + self.normal * local.z
+	}
+}
+
+/// Sample a direction on the +Z hemisphere proportional to its cosine, see [`cosine_hemisphere_pdf`]
+pub fn sample_cosine_hemisphere(u: Vec2) -> Vec3 {
+	let r = u.x.sqrt();
+	let phi = 2. * PI * u.y;
+	let z = f32::max(1. - u.x, 0.).sqrt();
+	Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f32) -> f32 {
+	f32::max(cos_theta, 0.) * FRAC_1_PI
+}
+
+/// Schlick's approximation of the fresnel reflectance
+pub fn fresnel_schlick(f0: Vec3, cos_theta: f32) -> Vec3 {
+	f0 + (1. - f0) * schlick_weight(cos_theta)
+}
+
+/// `(1 - cos_theta)^5`
+pub fn schlick_weight(cos_theta: f32) -> f32 {
+	let m = (1. - cos_theta).clamp(0., 1.);
+	let m2 = m * m;
+	m2 * m2 * m
+}
+
+/// Whether both directions are on the side of the normal
+fn same_hemisphere(wo: Vec3, wi: Vec3) -> bool {
+	wo.z > 0. && wi.z > 0.
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::brdf::disney::DisneyDiffuse;
+	use crate::brdf::ggx::Ggx;
+	use crate::brdf::lambert::Lambert;
+	use crate::brdf::{Brdf, ShadingFrame};
+	use crate::random::Rng;
+	use crate::utils::color::luminance;
+	use core::f32::consts::PI;
+	use glam::{UVec2, Vec2, Vec3};
+
+	/// stratified samples per dimension
+	const STRATA: u32 = 512;
+
+	fn stratified(mut f: impl FnMut(Vec2)) {
+		let mut rng = Rng::new(UVec2::new(17, 4), 0, 0);
+		for y in 0..STRATA {
+			for x in 0..STRATA {
+				let jitter = Vec2::new(rng.next_f32(), rng.next_f32());
+				f((Vec2::new(x as f32, y as f32) + jitter) / STRATA as f32);
+			}
+		}
+	}
+
+	fn uniform_hemisphere(u: Vec2) -> Vec3 {
+		let z = u.x;
+		let r = f32::sqrt(f32::max(1. - z * z, 0.));
+		let phi = 2. * PI * u.y;
+		Vec3::new(r * phi.cos(), r * phi.sin(), z)
+	}
+
+	fn direction(theta: f32) -> Vec3 {
+		Vec3::new(theta.sin(), 0., theta.cos())
+	}
+
+	/// `∫ f(wo, wi) cos(wi) dwi` estimated by uniformly sampling the hemisphere
+	fn reflectance_uniform(brdf: &impl Brdf, wo: Vec3) -> Vec3 {
+		let mut sum = Vec3::ZERO;
+		stratified(|u| {
+			let wi = uniform_hemisphere(u);
+			sum += brdf.eval(wo, wi) * wi.z * 2. * PI;
+		});
+		sum / (STRATA * STRATA) as f32
+	}
+
+	/// `∫ f(wo, wi) cos(wi) dwi` estimated by importance sampling the BRDF
+	fn reflectance_sampled(brdf: &impl Brdf, wo: Vec3) -> Vec3 {
+		let mut sum = Vec3::ZERO;
+		stratified(|u| {
+			let sample = brdf.sample(wo, u);
+			if sample.is_valid() {
+				let pdf = brdf.pdf(wo, sample.wi);
+				assert!(
+					(sample.pdf - pdf).abs() <= 1e-3 * pdf,
+					"sampled pdf {} differs from pdf {pdf}",
+					sample.pdf
+				);
+				sum += brdf.eval(wo, sample.wi) * sample.wi.z / sample.pdf;
+			}
+		});
+		sum / (STRATA * STRATA) as f32
+	}
+
+	/// `∫ pdf(wo, wi) dwi` over the hemisphere
+	fn pdf_integral(brdf: &impl Brdf, wo: Vec3) -> f32 {
+		let mut sum = 0.;
+		stratified(|u| sum += brdf.pdf(wo, uniform_hemisphere(u)) * 2. * PI);
+		sum / (STRATA * STRATA) as f32
+	}
+
+	fn assert_close(actual: f32, expected: f32, tolerance: f32, what: &str, param: f32) {
+		assert!(
+			(actual - expected).abs() <= tolerance,
+			"{what} {param}: expected {expected} got {actual}"
+		);
+	}
+
+	#[test]
+	fn test_lambert_white_furnace() {
+		let lambert = Lambert::new(Vec3::ONE);
+		for theta in [0., 0.5, 1.2] {
+			let wo = direction(theta);
+			assert_close(luminance(reflectance_uniform(&lambert, wo)), 1., 0.01, "uniform", theta);
+			assert_close(luminance(reflectance_sampled(&lambert, wo)), 1., 1e-4, "sampled", theta);
+			assert_close(pdf_integral(&lambert, wo), 1., 0.01, "pdf", theta);
+		}
+	}
+
+	#[test]
+	fn test_ggx_weak_white_furnace() {
+		// `∫ D(h) G1(wo, h) / (4 |wo.z|) dwi` over the entire sphere is 1 for any microfacet distribution
+		for alpha in [0.3, 0.6, 1.] {
+			let ggx = Ggx::from_alpha(alpha, Vec3::ONE);
+			for theta in [0., 0.7, 1.3] {
+				let wo = direction(theta);
+				let mut sum = 0.;
+				stratified(|u| {
+					let z = u.x * 2. - 1.;
+					let r = f32::sqrt(f32::max(1. - z * z, 0.));
+					let phi = 2. * PI * u.y;
+					let wi = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+					let h = (wo + wi).normalize();
+					if h.z > 0. {
+						sum += ggx.d(h) * ggx.g1(wo) / (4. * wo.z) * 4. * PI;
+					}
+				});
+				let integral = sum / (STRATA * STRATA) as f32;
+				assert!(
+					(integral - 1.).abs() <= 0.02,
+					"alpha {alpha} theta {theta}: expected 1 got {integral}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn test_ggx_energy() {
+		// without fresnel, single scattering GGX loses energy with increasing roughness, but never gains any
+		let smooth = luminance(reflectance_sampled(&Ggx::from_alpha(0.01, Vec3::ONE), direction(0.5)));
+		assert!((smooth - 1.).abs() < 0.01, "{smooth}");
+		let mut prev = smooth;
+		for alpha in [0.1, 0.3, 0.6, 1.] {
+			let reflectance = luminance(reflectance_sampled(&Ggx::from_alpha(alpha, Vec3::ONE), direction(0.5)));
+			assert!(reflectance <= 1.001, "alpha {alpha}: {reflectance}");
+			assert!(reflectance <= prev, "alpha {alpha}: {reflectance} > {prev}");
+			prev = reflectance;
+		}
+	}
+
+	#[test]
+	fn test_reciprocity() {
+		let mut rng = Rng::new(UVec2::new(3, 9), 1, 0);
+		let lambert = Lambert::new(Vec3::new(0.2, 0.5, 0.8));
+		let ggx = Ggx::new(0.4, Vec3::new(0.9, 0.6, 0.3));
+		let disney = DisneyDiffuse::new(Vec3::new(0.2, 0.5, 0.8), 0.7);
+		for _ in 0..1000 {
+			let wo = uniform_hemisphere(Vec2::new(rng.next_f32(), rng.next_f32()));
+			let wi = uniform_hemisphere(Vec2::new(rng.next_f32(), rng.next_f32()));
+			let brdfs: [&dyn Fn(Vec3, Vec3) -> Vec3; 3] =
+				[&|wo, wi| lambert.eval(wo, wi), &|wo, wi| ggx.eval(wo, wi), &|wo, wi| {
+					disney.eval(wo, wi)
+				}];
+			for (i, eval) in brdfs.iter().enumerate() {
+				let a = eval(wo, wi);
+				let b = eval(wi, wo);
+				assert!(a.abs_diff_eq(b, 1e-4 * a.max_element().max(1.)), "brdf {i}: {a} != {b}");
+			}
+		}
+	}
+
+	fn assert_sampling_consistent(brdf: &impl Brdf, tolerance: f32, what: &str, roughness: f32) {
+		for theta in [0.1, 0.8, 1.3] {
+			let wo = direction(theta);
+			let uniform = reflectance_uniform(brdf, wo);
+			let sampled = reflectance_sampled(brdf, wo);
+			assert!(
+				uniform.abs_diff_eq(sampled, tolerance * uniform.max_element()),
+				"{what} roughness {roughness} theta {theta}: uniform {uniform} sampled {sampled}"
+			);
+			let pdf = pdf_integral(brdf, wo);
+			assert!(
+				pdf <= 1. + tolerance,
+				"{what} roughness {roughness} theta {theta}: pdf integral {pdf}"
+			);
+		}
+	}
+
+	#[test]
+	fn test_lambert_sampling() {
+		assert_sampling_consistent(&Lambert::new(Vec3::new(0.2, 0.5, 0.8)), 0.01, "lambert", 1.);
+	}
+
+	#[test]
+	fn test_ggx_sampling() {
+		for roughness in [0.6, 0.8, 1.] {
+			let ggx = Ggx::new(roughness, Vec3::new(0.9, 0.6, 0.3));
+			assert_sampling_consistent(&ggx, 0.02, "ggx", roughness);
+		}
+	}
+
+	/// `∫ f(wo, wi) cos(wi) dwi` and `∫ pdf(wo, wi) dwi` estimated by sampling microfacet normals proportional to
+	/// `D(h) cos(h)`, which unlike uniform sampling resolves the narrow lobes of smooth surfaces
+	fn ggx_reference(ggx: &Ggx, wo: Vec3) -> (Vec3, f32) {
+		let a2 = ggx.alpha * ggx.alpha;
+		let mut reflectance = Vec3::ZERO;
+		let mut pdf = 0.;
+		stratified(|u| {
+			let cos2 = (1. - u.x) / (1. + (a2 - 1.) * u.x);
+			let sin = f32::sqrt(f32::max(1. - cos2, 0.));
+			let phi = 2. * PI * u.y;
+			let h = Vec3::new(sin * phi.cos(), sin * phi.sin(), cos2.sqrt());
+			let wi = 2. * wo.dot(h) * h - wo;
+			if wi.z > 0. {
+				let reference_pdf = ggx.d(h) * h.z / (4. * wo.dot(h));
+				reflectance += ggx.eval(wo, wi) * wi.z / reference_pdf;
+				pdf += ggx.pdf(wo, wi) / reference_pdf;
+			}
+		});
+		let n = (STRATA * STRATA) as f32;
+		(reflectance / n, pdf / n)
+	}
+
+	#[test]
+	fn test_ggx_sampling_smooth() {
+		for roughness in [0.05, 0.1, 0.15, 0.2] {
+			let ggx = Ggx::new(roughness, Vec3::new(0.9, 0.6, 0.3));
+			for theta in [0.1, 0.8, 1.3] {
+				let wo = direction(theta);
+				let (reference, pdf) = ggx_reference(&ggx, wo);
+				let sampled = reflectance_sampled(&ggx, wo);
+				assert!(
+					reference.abs_diff_eq(sampled, 0.02 * reference.max_element()),
+					"ggx roughness {roughness} theta {theta}: reference {reference} sampled {sampled}"
+				);
+				assert!(
+					pdf <= 1.02,
+					"ggx roughness {roughness} theta {theta}: pdf integral {pdf}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn test_disney_sampling() {
+		for roughness in [0., 0.5, 1.] {
+			let disney = DisneyDiffuse::new(Vec3::new(0.2, 0.5, 0.8), roughness);
+			assert_sampling_consistent(&disney, 0.01, "disney", roughness);
+		}
+	}
+
+	#[test]
+	fn test_shading_frame() {
+		let normal = Vec3::new(1., 2., -3.).normalize();
+		let frame = ShadingFrame::new(normal);
+		assert!(frame.to_local(normal).abs_diff_eq(Vec3::Z, 1e-6));
+		let v = Vec3::new(-0.3, 0.4, 2.);
+		assert!(frame.to_world(frame.to_local(v)).abs_diff_eq(v, 1e-5));
+	}
+}
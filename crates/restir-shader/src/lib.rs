@@ -4,7 +4,15 @@
 // otherwise you won't see any warnings
 #![deny(warnings)]
 
+#[cfg(test)]
+extern crate alloc;
+
+pub mod brdf;
 pub mod camera;
+pub mod light;
 pub mod material;
+pub mod random;
+pub mod reference;
+pub mod restir;
 pub mod utils;
 pub mod visibility;
@@ -0,0 +1,34 @@
+//! Alias tables allow selecting an index proportional to some weights in O(1), see
+//! https://www.keithschwarz.com/darts-dice-coins/
+
+use rust_gpu_bindless_macros::BufferStructPlain;
+
+/// A single column of an alias table
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, BufferStructPlain)]
+pub struct AliasEntry {
+	/// probability of selecting this entry's own index instead of the `alias`, once this entry has been chosen
+	pub threshold: f32,
+	pub alias: u32,
+	/// probability of this entry's index being selected by [`sample_alias`]
+	pub pdf: f32,
+	/// probability of the `alias` being selected by [`sample_alias`]
+	pub alias_pdf: f32,
+}
+
+/// Select an index of an alias table with `len` entries, proportional to the weights the table was built from. `load`
+/// fetches an entry of the table and `u` should be uniformly distributed in [0, 1).
+///
+/// Returns the selected index and the probability of it being selected.
+pub fn sample_alias(len: u32, u: f32, load: impl Fn(u32) -> AliasEntry) -> (u32, f32) {
+	let scaled = u * len as f32;
+	let index = u32::min(scaled as u32, len - 1);
+	let entry = load(index);
+	// reuse the remaining fraction of `u` for the coin flip
+	let fraction = scaled - index as f32;
+	if fraction < entry.threshold {
+		(index, entry.pdf)
+	} else {
+		(entry.alias, entry.alias_pdf)
+	}
+}
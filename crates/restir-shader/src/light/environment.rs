@@ -0,0 +1,157 @@
+//! Equirectangular environment maps, importance sampled using a 2D marginal and conditional CDF over the luminance of
+//! all texels. The top row of the image is at +Y.
+
+use core::f32::consts::PI;
+use glam::{UVec2, Vec2, Vec3, Vec4, Vec4Swizzles};
+use rust_gpu_bindless_macros::BufferStruct;
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2d, StrongDesc};
+use spirv_std::num_traits::Float;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, BufferStruct)]
+pub struct EnvironmentMap {
+	pub image: StrongDesc<Image<Image2d>>,
+	/// CDF over all rows, with `size.y + 1` entries
+	pub marginal_cdf: StrongDesc<Buffer<[f32]>>,
+	/// CDF within each row, `size.y` rows each with `size.x + 1` entries
+	pub conditional_cdf: StrongDesc<Buffer<[f32]>>,
+	pub size: UVec2,
+}
+
+/// The direction of `uv` in [0, 1]² on an equirectangular map
+pub fn equirect_direction(uv: Vec2) -> Vec3 {
+	let phi = uv.x * 2. * PI;
+	let theta = uv.y * PI;
+	Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())
+}
+
+/// The inverse of [`equirect_direction`]
+pub fn equirect_uv(direction: Vec3) -> Vec2 {
+	let theta = f32::acos(direction.y.clamp(-1., 1.));
+	let mut phi = f32::atan2(direction.z, direction.x);
+	if phi < 0. {
+		phi += 2. * PI;
+	}
+	Vec2::new(phi / (2. * PI), theta / PI)
+}
+
+/// Converts a pdf over the area of the uv square of an equirectangular map to a pdf over solid angle
+pub fn equirect_solid_angle_pdf(uv_pdf: f32, uv: Vec2) -> f32 {
+	let sin_theta = f32::sin(uv.y * PI);
+	if sin_theta > 0. {
+		uv_pdf / (2. * PI * PI * sin_theta)
+	} else {
+		0.
+	}
+}
+
+/// The largest index `i` in [0, `len`) with `cdf(i) <= u`, skipping over entries with zero probability
+fn search_cdf(len: u32, u: f32, cdf: &impl Fn(u32) -> f32) -> u32 {
+	let mut low = 0;
+	let mut high = len;
+	while high - low > 1 {
+		let mid = (low + high) / 2;
+		if cdf(mid) <= u {
+			low = mid;
+		} else {
+			high = mid;
+		}
+	}
+	low
+}
+
+/// Sample one of `len` intervals of a piecewise constant 1D distribution with `len + 1` `cdf` entries, returns the
+/// continuous position in [0, 1).
+fn sample_cdf(len: u32, u: f32, cdf: &impl Fn(u32) -> f32) -> f32 {
+	let i = search_cdf(len, u, cdf);
+	let start = cdf(i);
+	let probability = cdf(i + 1) - start;
+	let offset = if probability > 0. {
+		f32::clamp((u - start) / probability, 0., 1. - f32::EPSILON)
+	} else {
+		0.
+	};
+	(i as f32 + offset) / len as f32
+}
+
+/// Sample a uv coordinate of a map with `size` proportional to the distribution described by the CDFs, see
+/// [`EnvironmentMap`] for their layout. Returns the uv and its pdf over the area of the uv square.
+pub fn sample_environment_cdf(
+	size: UVec2,
+	u: Vec2,
+	marginal_cdf: impl Fn(u32) -> f32,
+	conditional_cdf: impl Fn(u32) -> f32,
+) -> (Vec2, f32) {
+	let v = sample_cdf(size.y, u.y, &marginal_cdf);
+	let row = u32::min((v * size.y as f32) as u32, size.y - 1);
+	let row_start = row * (size.x + 1);
+	let u = sample_cdf(size.x, u.x, &|i| conditional_cdf(row_start + i));
+	let uv = Vec2::new(u, v);
+	// evaluated again instead of reusing the probabilities above, so that rounding can't select another texel
+	(uv, environment_cdf_pdf(size, uv, marginal_cdf, conditional_cdf))
+}
+
+/// The pdf of [`sample_environment_cdf`] selecting `uv`
+pub fn environment_cdf_pdf(
+	size: UVec2,
+	uv: Vec2,
+	marginal_cdf: impl Fn(u32) -> f32,
+	conditional_cdf: impl Fn(u32) -> f32,
+) -> f32 {
+	let texel = texel_of(size, uv);
+	let row_probability = marginal_cdf(texel.y + 1) - marginal_cdf(texel.y);
+	let row_start = texel.y * (size.x + 1);
+	let column_probability = conditional_cdf(row_start + texel.x + 1) - conditional_cdf(row_start + texel.x);
+	row_probability * column_probability * (size.x * size.y) as f32
+}
+
+fn texel_of(size: UVec2, uv: Vec2) -> UVec2 {
+	(uv * size.as_vec2()).as_uvec2().min(size - 1)
+}
+
+impl EnvironmentMap {
+	/// The radiance arriving from `direction`
+	pub fn radiance(&self, descriptors: &Descriptors, direction: Vec3) -> Vec3 {
+		self.radiance_uv(descriptors, equirect_uv(direction))
+	}
+
+	pub fn radiance_uv(&self, descriptors: &Descriptors, uv: Vec2) -> Vec3 {
+		let texel: Vec4 = self
+			.image
+			.access(descriptors)
+			.fetch_with_lod(texel_of(self.size, uv), 0);
+		texel.xyz()
+	}
+
+	/// Sample a direction proportional to the luminance of the map. Returns the direction, the radiance arriving from it
+	/// and its solid angle pdf.
+	pub fn sample(&self, descriptors: &Descriptors, u: Vec2) -> (Vec3, Vec3, f32) {
+		let marginal_cdf = self.marginal_cdf.access(descriptors);
+		let conditional_cdf = self.conditional_cdf.access(descriptors);
+		let (uv, uv_pdf) = sample_environment_cdf(
+			self.size,
+			u,
+			|i| marginal_cdf.load(i as usize),
+			|i| conditional_cdf.load(i as usize),
+		);
+		(
+			equirect_direction(uv),
+			self.radiance_uv(descriptors, uv),
+			equirect_solid_angle_pdf(uv_pdf, uv),
+		)
+	}
+
+	/// The solid angle pdf of [`Self::sample`] returning `direction`
+	pub fn pdf(&self, descriptors: &Descriptors, direction: Vec3) -> f32 {
+		let marginal_cdf = self.marginal_cdf.access(descriptors);
+		let conditional_cdf = self.conditional_cdf.access(descriptors);
+		let uv = equirect_uv(direction);
+		let uv_pdf = environment_cdf_pdf(
+			self.size,
+			uv,
+			|i| marginal_cdf.load(i as usize),
+			|i| conditional_cdf.load(i as usize),
+		);
+		equirect_solid_angle_pdf(uv_pdf, uv)
+	}
+}
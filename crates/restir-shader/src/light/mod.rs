@@ -0,0 +1,239 @@
+pub mod alias;
+pub mod environment;
+pub mod tree;
+
+use crate::visibility::id::{InstanceId, TriangleId};
+use crate::visibility::scene::VisiScene;
+use glam::{Vec2, Vec3};
+use num_enum::{FromPrimitive, IntoPrimitive};
+use rust_gpu_bindless_macros::BufferStructPlain;
+use rust_gpu_bindless_shaders::buffer_content::BufferStructPlain;
+use rust_gpu_bindless_shaders::descriptor::Descriptors;
+use spirv_std::num_traits::Float;
+
+/// Distance used for shadow rays towards directional and environment lights
+pub const DIRECTIONAL_LIGHT_DISTANCE: f32 = 10000.;
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, FromPrimitive, IntoPrimitive)]
+pub enum LightType {
+	#[default]
+	Point,
+	Spot,
+	Directional,
+	EmissiveTriangle,
+	Environment,
+}
+
+unsafe impl BufferStructPlain for LightType {
+	type Transfer = u32;
+
+	unsafe fn write(self) -> Self::Transfer {
+		<u32 as From<Self>>::from(self)
+	}
+
+	unsafe fn read(from: Self::Transfer) -> Self {
+		<Self as num_enum::FromPrimitive>::from_primitive(from)
+	}
+}
+
+/// A tagged light record, which fields are used depends on the [`LightType`]. Use the constructors to create one.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, BufferStructPlain)]
+pub struct Light {
+	pub light_type: LightType,
+	/// position of point and spot lights
+	pub position: Vec3,
+	/// normalized direction spot and directional lights emit light into
+	pub direction: Vec3,
+	/// * point and spot: radiant intensity in W/sr
+	/// * directional: irradiance in W/m²
+	/// * emissive triangle: radiance in W/(sr m²) emitted from the front face
+	/// * environment: average radiance of the scene's [`EnvironmentMap`](environment::EnvironmentMap), the actual
+	///   radiance is looked up from the map
+	pub emission: Vec3,
+	/// spot: cosine of the angle at which the falloff starts
+	pub cos_inner: f32,
+	/// spot: cosine of the angle at which no more light is emitted
+	pub cos_outer: f32,
+	/// emissive triangle: the instance of the emitting triangle
+	pub instance_id: InstanceId,
+	/// emissive triangle: the emitting triangle within the model of the instance
+	pub triangle_id: TriangleId,
+}
+
+/// Light arriving at some shading point
+#[derive(Copy, Clone, Debug)]
+pub struct IncidentLight {
+	/// normalized direction from the shading point towards the light
+	pub direction: Vec3,
+	pub distance: f32,
+	/// Incident radiance divided by the solid angle pdf of the point sampled on the light, not yet weighted by the
+	/// cosine term. For delta lights this is the irradiance perpendicular to `direction`.
+	pub radiance: Vec3,
+}
+
+impl IncidentLight {
+	/// The point on the light, for tracing shadow rays
+	pub fn light_position(&self, position: Vec3) -> Vec3 {
+		position + self.direction * self.distance
+	}
+}
+
+impl Light {
+	pub fn point(position: Vec3, intensity: Vec3) -> Self {
+		Self {
+			light_type: LightType::Point,
+			position,
+			emission: intensity,
+			..Self::default()
+		}
+	}
+
+	/// A spot light with its cone defined by angles in radians
+	pub fn spot(position: Vec3, direction: Vec3, intensity: Vec3, inner_angle: f32, outer_angle: f32) -> Self {
+		Self {
+			light_type: LightType::Spot,
+			position,
+			direction: direction.normalize(),
+			emission: intensity,
+			cos_inner: inner_angle.cos(),
+			cos_outer: outer_angle.cos(),
+			..Self::default()
+		}
+	}
+
+	pub fn directional(direction: Vec3, irradiance: Vec3) -> Self {
+		Self {
+			light_type: LightType::Directional,
+			direction: direction.normalize(),
+			emission: irradiance,
+			..Self::default()
+		}
+	}
+
+	pub fn emissive_triangle(instance_id: InstanceId, triangle_id: TriangleId, radiance: Vec3) -> Self {
+		Self {
+			light_type: LightType::EmissiveTriangle,
+			emission: radiance,
+			instance_id,
+			triangle_id,
+			..Self::default()
+		}
+	}
+
+	/// The environment map of the scene, with its `average_radiance` for estimating its power
+	pub fn environment(average_radiance: Vec3) -> Self {
+		Self {
+			light_type: LightType::Environment,
+			emission: average_radiance,
+			..Self::default()
+		}
+	}
+
+	/// Whether the light is a delta light, with only a single point or direction emitting light
+	pub fn is_delta(&self) -> bool {
+		!matches!(self.light_type, LightType::EmissiveTriangle | LightType::Environment)
+	}
+
+	/// Whether the light is infinitely far away and can't be bounded in space
+	pub fn is_infinite(&self) -> bool {
+		matches!(self.light_type, LightType::Directional | LightType::Environment)
+	}
+
+	/// World space vertex positions of an emissive triangle light
+	pub fn triangle_positions(&self, scene: &VisiScene, descriptors: &Descriptors) -> [Vec3; 3] {
+		let instance = scene.load_instance(descriptors, self.instance_id);
+		let model = instance.model.access(descriptors).load();
+		let indices = model.load_indices(descriptors, self.triangle_id);
+		let world_from_local = instance.world_from_local.affine;
+		[
+			world_from_local.transform_point3(model.load_vertex(descriptors, indices[0]).0),
+			world_from_local.transform_point3(model.load_vertex(descriptors, indices[1]).0),
+			world_from_local.transform_point3(model.load_vertex(descriptors, indices[2]).0),
+		]
+	}
+
+	/// The light arriving at `position`. Area lights are sampled uniformly at `sample_uv` in [0, 1)², the environment
+	/// proportional to its luminance and delta lights ignore it.
+	pub fn incident(
+		&self,
+		scene: &VisiScene,
+		descriptors: &Descriptors,
+		position: Vec3,
+		sample_uv: Vec2,
+	) -> IncidentLight {
+		match self.light_type {
+			LightType::Point | LightType::Spot => {
+				let to_light = self.position - position;
+				let distance = to_light.length();
+				let direction = to_light / distance;
+				let mut radiance = self.emission / (distance * distance);
+				if self.light_type == LightType::Spot {
+					radiance *= smoothstep(self.cos_outer, self.cos_inner, self.direction.dot(-direction));
+				}
+				IncidentLight {
+					direction,
+					distance,
+					radiance,
+				}
+			}
+			LightType::Directional => IncidentLight {
+				direction: -self.direction,
+				distance: DIRECTIONAL_LIGHT_DISTANCE,
+				radiance: self.emission,
+			},
+			LightType::EmissiveTriangle => {
+				let p = self.triangle_positions(scene, descriptors);
+				let (mut u, mut v) = (sample_uv.x, sample_uv.y);
+				if u + v > 1. {
+					u = 1. - u;
+					v = 1. - v;
+				}
+				let light_position = p[0] + (p[1] - p[0]) * u + (p[2] - p[0]) * v;
+				let cross = (p[1] - p[0]).cross(p[2] - p[0]);
+				let area = cross.length() / 2.;
+				let light_normal = cross / (2. * area);
+
+				let to_light = light_position - position;
+				let distance = to_light.length();
+				let direction = to_light / distance;
+				// area pdf 1 / area converted to solid angle
+				let cos_light = f32::max(light_normal.dot(-direction), 0.);
+				IncidentLight {
+					direction,
+					distance,
+					radiance: self.emission * cos_light * area / (distance * distance),
+				}
+			}
+			LightType::Environment => {
+				let (direction, radiance, pdf) = scene.environment.sample(descriptors, sample_uv);
+				IncidentLight {
+					direction,
+					distance: DIRECTIONAL_LIGHT_DISTANCE,
+					radiance: if pdf > 0. { radiance / pdf } else { Vec3::ZERO },
+				}
+			}
+		}
+	}
+}
+
+impl Default for Light {
+	fn default() -> Self {
+		Self {
+			light_type: LightType::Point,
+			position: Vec3::ZERO,
+			direction: Vec3::ZERO,
+			emission: Vec3::ZERO,
+			cos_inner: 0.,
+			cos_outer: 0.,
+			instance_id: unsafe { InstanceId::new_unchecked(0) },
+			triangle_id: unsafe { TriangleId::new_unchecked(0) },
+		}
+	}
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+	let t = f32::clamp((x - edge0) / (edge1 - edge0), 0., 1.);
+	t * t * (3. - 2. * t)
+}
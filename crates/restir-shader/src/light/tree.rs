@@ -0,0 +1,225 @@
+//! A light tree (or light BVH) for sampling many lights proportional to their estimated contribution at some shading
+//! point, based on "Importance Sampling of Many Lights with Adaptive Tree Splitting" by Estevez and Kulla and its
+//! implementation in pbrt-v4.
+
+use crate::light::Light;
+use glam::Vec3;
+use rust_gpu_bindless_macros::{BufferStruct, BufferStructPlain};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, StrongDesc};
+use spirv_std::num_traits::Float;
+
+/// Bit trail of lights that are not part of the tree, as they emit no light or are infinitely far away
+pub const LIGHT_TREE_NO_TRAIL: u32 = !0;
+
+/// Maximum depth of the tree, limited by the bits in the bit trail
+pub const LIGHT_TREE_MAX_DEPTH: u32 = 31;
+
+/// A node of the light tree, bounding the position, emission directions and power of all lights below it
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, BufferStructPlain)]
+pub struct LightTreeNode {
+	pub bounds_min: Vec3,
+	pub bounds_max: Vec3,
+	/// axis of the cone bounding the normals of all emitters
+	pub axis: Vec3,
+	/// cosine of the angle of the cone bounding the normals around the `axis`
+	pub cos_theta_o: f32,
+	/// cosine of the angle around each normal light is emitted into
+	pub cos_theta_e: f32,
+	/// total power of all lights below this node
+	pub power: f32,
+	pub is_leaf: bool,
+	/// inner node: the index of the left child, directly followed by the right child
+	/// leaf: the index of the light
+	pub index: u32,
+}
+
+impl LightTreeNode {
+	/// Estimate of the light arriving at `position` from all lights below this node. `normal` may be zero for points
+	/// not on a surface.
+	pub fn importance(&self, position: Vec3, normal: Vec3) -> f32 {
+		if self.power <= 0. {
+			return 0.;
+		}
+		let center = (self.bounds_min + self.bounds_max) / 2.;
+		let diagonal = self.bounds_max - self.bounds_min;
+		let d2 = f32::max(position.distance_squared(center), diagonal.length() / 2.);
+		let wi = (position - center).normalize_or_zero();
+
+		// the angles between the axis and `wi`, the cone of normals and the bounds as seen from `position`
+		let cos_theta_w = self.axis.dot(wi);
+		let sin_theta_w = safe_sqrt(1. - cos_theta_w * cos_theta_w);
+		let cos_theta_b = bound_subtended_cos(diagonal.length() / 2., position.distance_squared(center));
+		let sin_theta_b = safe_sqrt(1. - cos_theta_b * cos_theta_b);
+		let sin_theta_o = safe_sqrt(1. - self.cos_theta_o * self.cos_theta_o);
+
+		// minimum angle between any emitter normal and any direction towards `position`
+		let cos_theta_x = cos_sub_clamped(sin_theta_w, cos_theta_w, sin_theta_o, self.cos_theta_o);
+		let sin_theta_x = sin_sub_clamped(sin_theta_w, cos_theta_w, sin_theta_o, self.cos_theta_o);
+		let cos_theta_p = cos_sub_clamped(sin_theta_x, cos_theta_x, sin_theta_b, cos_theta_b);
+		if cos_theta_p <= self.cos_theta_e {
+			return 0.;
+		}
+
+		let mut importance = self.power * cos_theta_p / d2;
+		if normal != Vec3::ZERO {
+			let cos_theta_i = f32::abs(wi.dot(normal));
+			let sin_theta_i = safe_sqrt(1. - cos_theta_i * cos_theta_i);
+			importance *= cos_sub_clamped(sin_theta_i, cos_theta_i, sin_theta_b, cos_theta_b);
+		}
+		f32::max(importance, 0.)
+	}
+}
+
+fn safe_sqrt(x: f32) -> f32 {
+	f32::max(x, 0.).sqrt()
+}
+
+/// `cos(max(0, a - b))`
+fn cos_sub_clamped(sin_theta_a: f32, cos_theta_a: f32, sin_theta_b: f32, cos_theta_b: f32) -> f32 {
+	if cos_theta_a > cos_theta_b {
+		1.
+	} else {
+		cos_theta_a * cos_theta_b + sin_theta_a * sin_theta_b
+	}
+}
+
+/// `sin(max(0, a - b))`
+fn sin_sub_clamped(sin_theta_a: f32, cos_theta_a: f32, sin_theta_b: f32, cos_theta_b: f32) -> f32 {
+	if cos_theta_a > cos_theta_b {
+		0.
+	} else {
+		sin_theta_a * cos_theta_b - cos_theta_a * sin_theta_b
+	}
+}
+
+/// Cosine of the cone of directions subtended by a bounding sphere with `radius` at a squared `distance2`
+fn bound_subtended_cos(radius: f32, distance2: f32) -> f32 {
+	let radius2 = radius * radius;
+	if distance2 < radius2 {
+		-1.
+	} else {
+		safe_sqrt(1. - radius2 / distance2)
+	}
+}
+
+/// Stochastically traverse the tree by selecting children proportional to their importance. `load` fetches a node of
+/// the tree, with the root at index 0, and `u` should be uniformly distributed in [0, 1).
+///
+/// Returns the index of the selected light and the probability of selecting it, or a probability of 0 if no light
+/// contributes to `position`.
+pub fn sample_light_tree(load: impl Fn(u32) -> LightTreeNode, position: Vec3, normal: Vec3, u: f32) -> (u32, f32) {
+	let mut node = load(0);
+	if node.importance(position, normal) <= 0. {
+		return (0, 0.);
+	}
+	let mut u = u;
+	let mut pdf = 1.;
+	while !node.is_leaf {
+		let left = load(node.index);
+		let right = load(node.index + 1);
+		let left_importance = left.importance(position, normal);
+		let right_importance = right.importance(position, normal);
+		let sum = left_importance + right_importance;
+		if sum <= 0. {
+			return (0, 0.);
+		}
+		let p_left = left_importance / sum;
+		if u < p_left {
+			u = f32::min(u / p_left, 1. - f32::EPSILON);
+			pdf *= p_left;
+			node = left;
+		} else {
+			let p_right = 1. - p_left;
+			u = f32::min((u - p_left) / p_right, 1. - f32::EPSILON);
+			pdf *= p_right;
+			node = right;
+		}
+	}
+	(node.index, pdf)
+}
+
+/// The probability of [`sample_light_tree`] selecting the light with the `bit_trail`, which is the path from the root
+/// to the light's leaf. Bit `i` selects the left (0) or right (1) child at depth `i`.
+pub fn light_tree_pdf(load: impl Fn(u32) -> LightTreeNode, position: Vec3, normal: Vec3, bit_trail: u32) -> f32 {
+	if bit_trail == LIGHT_TREE_NO_TRAIL {
+		return 0.;
+	}
+	let mut node = load(0);
+	if node.importance(position, normal) <= 0. {
+		return 0.;
+	}
+	let mut bit_trail = bit_trail;
+	let mut pdf = 1.;
+	while !node.is_leaf {
+		let left = load(node.index);
+		let right = load(node.index + 1);
+		let left_importance = left.importance(position, normal);
+		let right_importance = right.importance(position, normal);
+		let sum = left_importance + right_importance;
+		if sum <= 0. {
+			return 0.;
+		}
+		let p_left = left_importance / sum;
+		if bit_trail & 1 == 0 {
+			pdf *= p_left;
+			node = left;
+		} else {
+			pdf *= 1. - p_left;
+			node = right;
+		}
+		bit_trail >>= 1;
+	}
+	pdf
+}
+
+/// The light tree of a [`VisiScene`](crate::visibility::scene::VisiScene). Directional and environment lights can't be
+/// bounded and are instead selected uniformly, with the same probability as the entire tree.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, BufferStruct)]
+pub struct LightTree {
+	/// all nodes, always contains at least the root
+	pub nodes: StrongDesc<Buffer<[LightTreeNode]>>,
+	/// the bit trail of each light, see [`light_tree_pdf`]
+	pub bit_trails: StrongDesc<Buffer<[u32]>>,
+	/// Indices of all infinite lights. Contains a single unused entry if there are none, as buffers can't be empty.
+	pub infinite_lights: StrongDesc<Buffer<[u32]>>,
+	pub infinite_light_count: u32,
+}
+
+impl LightTree {
+	/// probability of selecting any of the infinite lights, the rest is distributed by the tree
+	fn infinite_probability(&self, descriptors: &Descriptors) -> f32 {
+		let has_tree = self.nodes.access(descriptors).load(0).power > 0.;
+		let infinite = self.infinite_light_count as f32;
+		let total = infinite + if has_tree { 1. } else { 0. };
+		if total > 0. { infinite / total } else { 0. }
+	}
+
+	/// Select a light proportional to its estimated contribution at `position`, see [`sample_light_tree`].
+	pub fn sample(&self, descriptors: &Descriptors, position: Vec3, normal: Vec3, u: f32) -> (u32, f32) {
+		let p_infinite = self.infinite_probability(descriptors);
+		if u < p_infinite {
+			let count = self.infinite_light_count;
+			let i = u32::min((u / p_infinite * count as f32) as u32, count - 1);
+			let light_index = self.infinite_lights.access(descriptors).load(i as usize);
+			return (light_index, p_infinite / count as f32);
+		}
+
+		let u = f32::min((u - p_infinite) / (1. - p_infinite), 1. - f32::EPSILON);
+		let nodes = self.nodes.access(descriptors);
+		let (light_index, pdf) = sample_light_tree(|i| nodes.load(i as usize), position, normal, u);
+		(light_index, pdf * (1. - p_infinite))
+	}
+
+	/// The probability of [`Self::sample`] selecting `light` at `light_index`
+	pub fn pdf(&self, descriptors: &Descriptors, position: Vec3, normal: Vec3, light_index: u32, light: &Light) -> f32 {
+		let p_infinite = self.infinite_probability(descriptors);
+		if light.is_infinite() {
+			return p_infinite / self.infinite_light_count as f32;
+		}
+		let nodes = self.nodes.access(descriptors);
+		let bit_trail = self.bit_trails.access(descriptors).load(light_index as usize);
+		light_tree_pdf(|i| nodes.load(i as usize), position, normal, bit_trail) * (1. - p_infinite)
+	}
+}
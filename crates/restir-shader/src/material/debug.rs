@@ -1,7 +1,7 @@
-use crate::material_shader;
+use crate::material_shader_image;
 use crate::utils::view_range::DebugValueRange;
-use crate::visibility::scene::VisiTriangle;
-use glam::{Vec3, Vec4};
+use crate::visibility::scene::{VisiScene, VisiTriangle};
+use glam::{Vec2, Vec3, Vec4};
 use num_enum::{FromPrimitive, IntoPrimitive};
 use rust_gpu_bindless_macros::BufferStruct;
 use rust_gpu_bindless_shaders::buffer_content::BufferStructPlain;
@@ -16,10 +16,17 @@ pub enum DebugType {
 	InstanceId,
 	TriangleId,
 	Barycentrics,
+	Normals,
+	TexCoords,
+	/// screen space motion since the previous frame in red and green, reaching full intensity at `view_range.max`
+	/// pixels, with blue marking surfaces that weren't visible to the previous camera
+	MotionVectors,
+	/// the material of each instance, previewed by the material shaders instead of the debug material
+	Materials,
 }
 
 impl DebugType {
-	pub const MAX_VALUE: DebugType = DebugType::Barycentrics;
+	pub const MAX_VALUE: DebugType = DebugType::Materials;
 	pub const LEN: u32 = Self::MAX_VALUE as u32 + 1;
 }
 
@@ -53,9 +60,12 @@ impl Default for DebugSettings {
 	}
 }
 
-material_shader!(debug_material, DebugSettings, debug_eval);
+pub mod debug_material {
+	use super::*;
+	material_shader_image!(image, DebugSettings, debug_eval);
+}
 
-fn debug_eval(debug_settings: &DebugSettings, _: &mut Descriptors<'_>, tri: VisiTriangle) -> Vec4 {
+fn debug_eval(debug_settings: &DebugSettings, _: &mut Descriptors<'_>, scene: &VisiScene, tri: VisiTriangle) -> Vec4 {
 	let geo = tri.geo;
 	if geo.is_clear {
 		Vec4::ZERO
@@ -69,7 +79,79 @@ fn debug_eval(debug_settings: &DebugSettings, _: &mut Descriptors<'_>, tri: Visi
 			DebugType::InstanceId => Vec3::from((instance_id_color(), 0., 0.)),
 			DebugType::TriangleId => Vec3::from((triangle_id_color(), 0., 0.)),
 			DebugType::Barycentrics => tri.barycentric.lambda.0,
+			DebugType::Normals => tri.world_normal() * 0.5 + 0.5,
+			DebugType::TexCoords => {
+				let uv = tri.tex_coord().value;
+				Vec3::from((uv - uv.floor(), 0.))
+			}
+			DebugType::MotionVectors => match scene.motion_vector(&tri) {
+				Some(motion) => {
+					let scale = f32::max(view_range.max as f32, 1.);
+					Vec3::from(((motion / scale * 0.5 + 0.5).clamp(Vec2::ZERO, Vec2::ONE), 0.))
+				}
+				None => Vec3::Z,
+			},
+			DebugType::Materials => Vec3::ZERO,
 		};
 		Vec4::from((color, debug_settings.debug_mix))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::material::system::image_shader::{MATERIAL_IMAGE_WG_SIZE, Param};
+	use crate::visibility::id::{InstanceId, PackedGeometryId, TriangleId};
+	use crate::visibility::scene::tests::{VIEWPORT, single_triangle_scene};
+	use alloc::boxed::Box;
+	use core::iter::repeat_n;
+	use glam::{UVec2, UVec4};
+	use rust_gpu_bindless_shaders::descriptor::{CpuDescriptors, CpuImage, Image2d, Image2dU};
+
+	#[test]
+	fn test_debug_material_normals() {
+		let mut cpu = CpuDescriptors::new();
+		let scene = single_triangle_scene(&mut cpu);
+		let scene = cpu.alloc_buffer(scene);
+		let geo = PackedGeometryId::new(InstanceId::new(0).unwrap(), TriangleId::new(0).unwrap());
+		let pixels = (VIEWPORT.x * VIEWPORT.y) as usize;
+		let packed_vertex_image = cpu.alloc_image::<Image2dU>(CpuImage::new(
+			VIEWPORT,
+			repeat_n(UVec4::new(geo.to_u32(), 0, 0, 0), pixels),
+		));
+		let output_image = cpu.alloc_mut_image::<Image2d>(CpuImage::new(VIEWPORT, repeat_n(Vec4::ZERO, pixels)));
+
+		// the entry point's params are `'static`
+		let cpu: &'static CpuDescriptors = Box::leak(Box::new(cpu));
+		let param = Param {
+			scene: scene.to_transient(cpu),
+			packed_vertex_image: packed_vertex_image.to_transient(cpu),
+			output_image: output_image.to_transient(cpu),
+			inner: DebugSettings {
+				debug_type: DebugType::Normals,
+				..DebugSettings::default()
+			},
+		};
+		let workgroups = VIEWPORT / MATERIAL_IMAGE_WG_SIZE;
+		for wg_y in 0..workgroups.y {
+			for wg_x in 0..workgroups.x {
+				for inv_y in 0..MATERIAL_IMAGE_WG_SIZE.y {
+					for inv_x in 0..MATERIAL_IMAGE_WG_SIZE.x {
+						let wg_id = UVec2::new(wg_x, wg_y).extend(0);
+						let inv_id = UVec2::new(inv_x, inv_y).extend(0);
+						debug_material::image(cpu.descriptors(), &param, wg_id, inv_id);
+					}
+				}
+			}
+		}
+
+		let descriptors = cpu.descriptors();
+		let output_image = output_image.access(&descriptors);
+		for y in 0..VIEWPORT.y {
+			for x in 0..VIEWPORT.x {
+				// the geometric normal +Z of the triangle facing the camera
+				assert_eq!(output_image.read(UVec2::new(x, y)), Vec4::new(0.5, 0.5, 1., 1.));
+			}
+		}
+	}
+}
@@ -1,2 +1,3 @@
 pub mod debug;
+pub mod pbr;
 pub mod system;
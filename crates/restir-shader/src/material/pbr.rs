@@ -0,0 +1,278 @@
+//! A metallic-roughness material following glTF's `pbrMetallicRoughness`. The material shader resolves its surfaces for
+//! ReSTIR to shade, and can preview them lit by all lights of the scene and the environment, without any shadows.
+
+use crate::brdf::ggx::Ggx;
+use crate::brdf::lambert::Lambert;
+use crate::brdf::{Brdf, BrdfSample, ShadingFrame};
+use crate::light::{Light, LightType};
+use crate::material::debug::DebugSettings;
+use crate::material_shader;
+use crate::utils::color::luminance;
+use crate::visibility::barycentric::Interpolated;
+use crate::visibility::scene::{VisiScene, VisiTriangle};
+use glam::{UVec2, Vec2, Vec3, Vec4, Vec4Swizzles, vec3};
+use rust_gpu_bindless_macros::BufferStruct;
+use rust_gpu_bindless_shaders::descriptor::{Descriptors, Image, Image2d, Sampler, StrongDesc};
+
+/// Reflectance at normal incidence of all dielectrics
+pub const DIELECTRIC_F0: f32 = 0.04;
+
+/// Textures and factors of a metallic-roughness material. Each texture is multiplied by its factor, so materials
+/// without some texture can use a 1x1 white texture instead.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, BufferStruct)]
+pub struct PbrMaterial {
+	/// linear base color and alpha, usually sampled from an sRGB image
+	pub base_color: StrongDesc<Image<Image2d>>,
+	/// metalness in the red channel
+	pub metallic: StrongDesc<Image<Image2d>>,
+	/// perceptual roughness in the red channel, squared to get the GGX alpha
+	pub roughness: StrongDesc<Image<Image2d>>,
+	/// tangent space normal map, encoded as `normal * 0.5 + 0.5`
+	pub normal: StrongDesc<Image<Image2d>>,
+	/// linear emitted radiance, usually sampled from an sRGB image
+	pub emissive: StrongDesc<Image<Image2d>>,
+	pub sampler: StrongDesc<Sampler>,
+	pub base_color_factor: Vec4,
+	pub emissive_factor: Vec3,
+	pub metallic_factor: f32,
+	pub roughness_factor: f32,
+	/// scales the xy components of the normal map
+	pub normal_scale: f32,
+}
+
+/// A [`PbrMaterial`] evaluated at some point on a surface
+#[repr(C)]
+#[derive(Copy, Clone, Debug, BufferStruct)]
+pub struct PbrSurface {
+	pub base_color: Vec3,
+	pub metallic: f32,
+	pub roughness: f32,
+	/// normalized shading normal in world space, with the normal map applied
+	pub normal: Vec3,
+	pub emission: Vec3,
+}
+
+impl PbrMaterial {
+	/// Sample `image` with the texture coordinate derivatives selecting the mip level
+	fn sample(&self, descriptors: &Descriptors, image: StrongDesc<Image<Image2d>>, uv: Interpolated<Vec2>) -> Vec4 {
+		let sampler = self.sampler.access(descriptors);
+		image
+			.access(descriptors)
+			.sample_by_gradient(sampler, uv.value, uv.ddx, uv.ddy)
+	}
+
+	pub fn surface(&self, descriptors: &Descriptors, tri: &VisiTriangle) -> PbrSurface {
+		let uv = tri.tex_coord();
+		let base_color = self.sample(descriptors, self.base_color, uv) * self.base_color_factor;
+		let metallic = self.sample(descriptors, self.metallic, uv).x * self.metallic_factor;
+		let roughness = self.sample(descriptors, self.roughness, uv).x * self.roughness_factor;
+		let emission = self.sample(descriptors, self.emissive, uv).xyz() * self.emissive_factor;
+
+		let tangent_normal = (self.sample(descriptors, self.normal, uv).xyz() * 2. - 1.)
+			* vec3(self.normal_scale, self.normal_scale, 1.);
+		let normal = tri.world_normal();
+		let tangent = tri.world_tangent();
+		let bitangent = normal.cross(tangent.xyz()) * tangent.w;
+		let mapped = (tangent.xyz() * tangent_normal.x + bitangent * tangent_normal.y + normal * tangent_normal.z)
+			.normalize_or_zero();
+
+		PbrSurface {
+			base_color: base_color.xyz(),
+			metallic: metallic.clamp(0., 1.),
+			roughness: roughness.clamp(0., 1.),
+			normal: if mapped == Vec3::ZERO { normal } else { mapped },
+			emission,
+		}
+	}
+}
+
+impl PbrSurface {
+	fn diffuse(&self) -> Lambert {
+		Lambert::new(self.base_color * (1. - self.metallic))
+	}
+
+	fn specular(&self) -> Ggx {
+		let f0 = Vec3::splat(DIELECTRIC_F0).lerp(self.base_color, self.metallic);
+		Ggx::new(self.roughness, f0)
+	}
+
+	/// The probability of [`Self::sample`] sampling the specular lobe instead of the diffuse lobe, proportional to
+	/// their approximate reflectance towards the local `wo`
+	fn specular_probability(&self, wo: Vec3) -> f32 {
+		let specular = luminance(self.specular().fresnel(f32::max(wo.z, 0.)));
+		let diffuse = luminance(self.diffuse().albedo) * (1. - specular);
+		if specular + diffuse > 0. {
+			specular / (specular + diffuse)
+		} else {
+			1.
+		}
+	}
+
+	/// The BRDF times the cosine term of light arriving from `wi` being reflected towards `wo`, both pointing away from
+	/// the surface. Uses a lambertian diffuse lobe and a GGX specular lobe with height-correlated Smith
+	/// masking-shadowing.
+	pub fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+		let frame = ShadingFrame::new(self.normal);
+		let wo = frame.to_local(wo);
+		let wi = frame.to_local(wi);
+		let specular = self.specular();
+		let diffuse = self.diffuse();
+		let h = (wo + wi).normalize();
+		let fresnel = specular.fresnel(f32::max(wo.dot(h), 0.));
+		((1. - fresnel) * diffuse.eval(wo, wi) + specular.eval(wo, wi)) * f32::max(wi.z, 0.)
+	}
+
+	/// Sample an incident direction in world space for the outgoing direction `wo`, by selecting one of the lobes of
+	/// [`Self::eval`] and importance sampling it. The returned pdf is of the mixture of both lobes.
+	pub fn sample(&self, wo: Vec3, u: Vec2) -> BrdfSample {
+		let frame = ShadingFrame::new(self.normal);
+		let wo_local = frame.to_local(wo);
+		// reuse `u.x` to select the lobe, remapped to [0, 1) afterward
+		let specular_probability = self.specular_probability(wo_local);
+		let sample = if u.x < specular_probability {
+			self.specular()
+				.sample(wo_local, Vec2::new(u.x / specular_probability, u.y))
+		} else {
+			let u_x = (u.x - specular_probability) / (1. - specular_probability);
+			self.diffuse().sample(wo_local, Vec2::new(u_x, u.y))
+		};
+		if !sample.is_valid() {
+			return BrdfSample::INVALID;
+		}
+		let wi = frame.to_world(sample.wi);
+		BrdfSample {
+			wi,
+			pdf: self.pdf(wo, wi),
+		}
+	}
+
+	/// The solid angle pdf of [`Self::sample`] returning `wi`
+	pub fn pdf(&self, wo: Vec3, wi: Vec3) -> f32 {
+		let frame = ShadingFrame::new(self.normal);
+		let wo = frame.to_local(wo);
+		let wi = frame.to_local(wi);
+		let specular_probability = self.specular_probability(wo);
+		specular_probability * self.specular().pdf(wo, wi) + (1. - specular_probability) * self.diffuse().pdf(wo, wi)
+	}
+
+	/// Reflected radiance towards `wo` of light with `radiance` arriving from `wi`, see [`Self::eval`]
+	pub fn radiance(&self, wo: Vec3, wi: Vec3, radiance: Vec3) -> Vec3 {
+		self.eval(wo, wi) * radiance
+	}
+}
+
+/// The index of `pixel` in the [`PbrSurface`]s resolved by the material shaders
+pub fn surface_index(pixel: UVec2, size: UVec2) -> usize {
+	(pixel.y * size.x + pixel.x) as usize
+}
+
+material_shader!(pbr_material, DebugSettings, pbr_preview, pbr_surface);
+
+fn pbr_surface(descriptors: &Descriptors<'_>, scene: &VisiScene, tri: &VisiTriangle) -> PbrSurface {
+	scene
+		.load_material(descriptors, tri.instance.material_id)
+		.surface(descriptors, tri)
+}
+
+/// Barycentric coordinates of the center of a triangle, in the `sample_uv` parametrization of [`Light::incident`]
+const TRIANGLE_CENTER: Vec2 = Vec2::splat(1. / 3.);
+
+/// The debug view of [`DebugType::Materials`](crate::material::debug::DebugType::Materials)
+fn pbr_preview(
+	settings: &DebugSettings,
+	descriptors: &mut Descriptors<'_>,
+	scene: &VisiScene,
+	tri: VisiTriangle,
+) -> Vec4 {
+	if tri.geo.is_clear {
+		return Vec4::ZERO;
+	}
+	let mut surface = scene.load_surface(descriptors, &tri);
+	let position = tri.world_position();
+	let wo = (scene.camera.view_from_world.translation() - position).normalize();
+	// shade the side facing the camera
+	if tri.world_geometric_normal().dot(wo) < 0. {
+		surface.normal = -surface.normal;
+	}
+
+	// every light of the scene, with emissive triangles approximated by a point light at their center
+	let lights = scene.lights.access(descriptors);
+	let mut direct = Vec3::ZERO;
+	for i in 0..scene.light_count {
+		let light: Light = lights.load(i as usize);
+		if light.light_type != LightType::Environment {
+			let incident = light.incident(scene, descriptors, position, TRIANGLE_CENTER);
+			direct += surface.radiance(wo, incident.direction, incident.radiance);
+		}
+	}
+	// a single unfiltered lookup in the normal direction, a crude approximation of diffuse environment lighting
+	let ambient =
+		scene.environment.radiance(descriptors, surface.normal) * surface.base_color * (1. - surface.metallic);
+	Vec4::from((surface.emission + direct + ambient, settings.debug_mix))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::random::Rng;
+	use core::f32::consts::PI;
+
+	/// stratified samples per dimension
+	const STRATA: u32 = 512;
+
+	#[test]
+	fn test_sampling() {
+		// importance sampling the mixture of both lobes must estimate the same reflectance as uniformly sampling the
+		// hemisphere, with a tilted normal to cover the transformation into the shading frame
+		let mut rng = Rng::new(UVec2::new(5, 7), 0, 0);
+		let normal = Vec3::new(0.3, -0.2, 1.).normalize();
+		let frame = ShadingFrame::new(normal);
+		let wo = Vec3::new(-0.6, 0.5, 1.).normalize();
+		for (metallic, roughness) in [(0., 1.), (0., 0.6), (1., 0.7), (0.5, 0.8)] {
+			let surface = PbrSurface {
+				base_color: Vec3::new(0.8, 0.5, 0.2),
+				metallic,
+				roughness,
+				normal,
+				emission: Vec3::ZERO,
+			};
+			let mut uniform = Vec3::ZERO;
+			let mut sampled = Vec3::ZERO;
+			let mut pdf_integral = 0.;
+			for y in 0..STRATA {
+				for x in 0..STRATA {
+					let jitter = Vec2::new(rng.next_f32(), rng.next_f32());
+					let u = (Vec2::new(x as f32, y as f32) + jitter) / STRATA as f32;
+
+					let r = f32::sqrt(f32::max(1. - u.x * u.x, 0.));
+					let phi = 2. * PI * u.y;
+					let wi = frame.to_world(Vec3::new(r * phi.cos(), r * phi.sin(), u.x));
+					uniform += surface.eval(wo, wi) * 2. * PI;
+					pdf_integral += surface.pdf(wo, wi) * 2. * PI;
+
+					let sample = surface.sample(wo, u);
+					if sample.is_valid() {
+						let pdf = surface.pdf(wo, sample.wi);
+						assert!(
+							(sample.pdf - pdf).abs() <= 1e-3 * pdf,
+							"sampled pdf {} differs from pdf {pdf}",
+							sample.pdf
+						);
+						sampled += surface.eval(wo, sample.wi) / sample.pdf;
+					}
+				}
+			}
+			let n = (STRATA * STRATA) as f32;
+			let (uniform, sampled, pdf_integral) = (uniform / n, sampled / n, pdf_integral / n);
+			assert!(
+				uniform.abs_diff_eq(sampled, 0.02 * uniform.max_element()),
+				"metallic {metallic} roughness {roughness}: uniform {uniform} sampled {sampled}"
+			);
+			assert!(
+				pdf_integral <= 1.02,
+				"metallic {metallic} roughness {roughness}: pdf integral {pdf_integral}"
+			);
+		}
+	}
+}
@@ -0,0 +1,81 @@
+//! Classify 8x8 tiles of the screen by the [`MaterialType`]s of their pixels, so each material shader only runs over
+//! the tiles containing it. [`material_classify`] writes a bitmask of the material types of each tile, then
+//! [`material_tile_list`](crate::material::system::tile_list::material_tile_list) collects the tiles of each type.
+
+use crate::material::system::MaterialType;
+use crate::material::system::image_shader::MATERIAL_IMAGE_WG_SIZE;
+use crate::visibility::id::GeometryId;
+use crate::visibility::scene::VisiScene;
+use glam::{UVec2, UVec3, UVec4};
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2dU, MutBuffer, TransientDesc};
+use static_assertions::{const_assert, const_assert_eq};
+
+/// Material types are tracked as a bitmask per tile, limiting the amount of material types but not of materials
+pub const MAX_MATERIAL_TYPES: u32 = 32;
+
+const_assert!(MaterialType::LEN <= MAX_MATERIAL_TYPES);
+
+/// A tile covers one workgroup of a material shader
+pub const MATERIAL_TILE_SIZE: UVec2 = MATERIAL_IMAGE_WG_SIZE;
+
+pub const MATERIAL_CLASSIFY_WG_SIZE: u32 = 64;
+
+const_assert_eq!(MATERIAL_CLASSIFY_WG_SIZE, 64);
+
+/// The amount of tiles in x and y covering the viewport
+pub fn material_tile_count(viewport_size: UVec2) -> UVec2 {
+	(viewport_size + MATERIAL_TILE_SIZE - 1) / MATERIAL_TILE_SIZE
+}
+
+/// The bit of `material_type` in the material mask of a tile
+pub fn material_bit(material_type: MaterialType) -> u32 {
+	1 << material_type as u32
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	/// the material type mask of each tile, in row-major order
+	pub tile_materials: TransientDesc<'a, MutBuffer<[u32]>>,
+}
+
+/// One invocation per tile
+#[bindless(compute(threads(64)))]
+pub fn material_classify(
+	#[bindless(descriptors)] mut descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(global_invocation_id)] inv_id: UVec3,
+) {
+	let scene = param.scene.access(&descriptors).load();
+	let size = scene.camera.viewport_size;
+	let tile_count = material_tile_count(size);
+	let tile_index = inv_id.x;
+	if tile_index >= tile_count.x * tile_count.y {
+		return;
+	}
+
+	let tile = UVec2::new(tile_index % tile_count.x, tile_index / tile_count.x);
+	let packed_vertex_image = param.packed_vertex_image.access(&descriptors);
+	let mut mask = 0;
+	for y in 0..MATERIAL_TILE_SIZE.y {
+		for x in 0..MATERIAL_TILE_SIZE.x {
+			let pixel = tile * MATERIAL_TILE_SIZE + UVec2::new(x, y);
+			if pixel.x < size.x && pixel.y < size.y {
+				let packed_geo: UVec4 = packed_vertex_image.fetch_with_lod(pixel, 0);
+				let geo = GeometryId::from_visibility(packed_geo);
+				if !geo.is_clear {
+					mask |= material_bit(scene.load_instance(&descriptors, geo.instance_id).material_type);
+				}
+			}
+		}
+	}
+	unsafe {
+		param
+			.tile_materials
+			.access(&mut descriptors)
+			.store(tile_index as usize, mask);
+	}
+}
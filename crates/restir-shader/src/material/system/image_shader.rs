@@ -1,9 +1,9 @@
 //! A material shader that is evaluated on an image
 
 use crate::material::system::MaterialEvalFn;
-use crate::visibility::id::PackedGeometryId;
+use crate::visibility::id::GeometryId;
 use crate::visibility::scene::VisiScene;
-use glam::{UVec2, UVec3, UVec4, Vec3Swizzles};
+use glam::{UVec2, UVec3, UVec4, Vec3Swizzles, Vec4, Vec4Swizzles};
 use rust_gpu_bindless_macros::BufferStruct;
 use rust_gpu_bindless_shaders::buffer_content::BufferStruct;
 use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2d, Image2dU, MutImage, TransientDesc};
@@ -31,12 +31,28 @@ pub fn material_shader_image_eval<T: BufferStruct, F: MaterialEvalFn<T>>(
 	let pixel_inbounds = pixel.x < size.x && pixel.y < size.y;
 	if pixel_inbounds {
 		let packed_geo: UVec4 = param.packed_vertex_image.access(&*descriptors).fetch_with_lod(pixel, 0);
-		let geo = PackedGeometryId::from_u32(packed_geo.x).unpack();
-		let tri = scene.load_triangle(&*descriptors, pixel, geo);
-		let out_color = eval(&param.inner, &mut *descriptors, tri);
-		unsafe {
-			param.output_image.access(&*descriptors).write(pixel, out_color);
-		}
+		let geo = GeometryId::from_visibility(packed_geo);
+		material_eval_pixel(descriptors, &scene, param.output_image, pixel, geo, &param.inner, eval);
+	}
+}
+
+/// Evaluate the material at `pixel` and alpha blend it over whatever was rendered before
+pub fn material_eval_pixel<T: BufferStruct, F: MaterialEvalFn<T>>(
+	descriptors: &mut Descriptors<'_>,
+	scene: &VisiScene,
+	output_image: TransientDesc<'_, MutImage<Image2d>>,
+	pixel: UVec2,
+	geo: GeometryId,
+	inner: &T,
+	eval: F,
+) {
+	let tri = scene.load_triangle(&*descriptors, pixel, geo);
+	let out_color = eval(inner, &mut *descriptors, scene, tri);
+	unsafe {
+		let output_image = output_image.access(&*descriptors);
+		let prev: Vec4 = output_image.read(pixel);
+		let color = prev.xyz().lerp(out_color.xyz(), out_color.w);
+		output_image.write(pixel, Vec4::from((color, 1.)));
 	}
 }
 
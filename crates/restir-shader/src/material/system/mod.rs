@@ -1,20 +1,64 @@
-use crate::visibility::scene::VisiTriangle;
+use crate::material::pbr::PbrSurface;
+use crate::visibility::scene::{VisiScene, VisiTriangle};
 use glam::Vec4;
-use rust_gpu_bindless_shaders::buffer_content::BufferStruct;
+use num_enum::{FromPrimitive, IntoPrimitive};
+use rust_gpu_bindless_shaders::buffer_content::{BufferStruct, BufferStructPlain};
 use rust_gpu_bindless_shaders::descriptor::Descriptors;
 
+pub mod classify;
 pub mod image_shader;
+pub mod tile_list;
+pub mod tile_shader;
 
-pub trait MaterialEvalFn<T: BufferStruct>: FnOnce(&T, &mut Descriptors<'_>, VisiTriangle) -> Vec4 {}
+/// The material shader resolving the surfaces of an instance. Tiles are classified by their material types, so each
+/// material shader only runs over the tiles containing it and reads the parameters of each instance from the scene.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, FromPrimitive, IntoPrimitive)]
+pub enum MaterialType {
+	/// a [`PbrMaterial`](crate::material::pbr::PbrMaterial) of [`VisiScene::materials`]
+	#[default]
+	Pbr,
+}
+
+impl MaterialType {
+	pub const MAX_VALUE: MaterialType = MaterialType::Pbr;
+	pub const LEN: u32 = Self::MAX_VALUE as u32 + 1;
+}
+
+unsafe impl BufferStructPlain for MaterialType {
+	type Transfer = u32;
+
+	unsafe fn write(self) -> Self::Transfer {
+		<u32 as From<Self>>::from(self)
+	}
+
+	unsafe fn read(from: Self::Transfer) -> Self {
+		<Self as num_enum::FromPrimitive>::from_primitive(from)
+	}
+}
+
+/// Evaluates a color to alpha blend over the output image, see [`image_shader`]
+pub trait MaterialEvalFn<T: BufferStruct>: FnOnce(&T, &mut Descriptors<'_>, &VisiScene, VisiTriangle) -> Vec4 {}
+
+impl<T: BufferStruct, I> MaterialEvalFn<T> for I where
+	I: FnOnce(&T, &mut Descriptors<'_>, &VisiScene, VisiTriangle) -> Vec4
+{
+}
+
+/// Resolves the surface of a pixel, see [`tile_shader`]
+pub trait MaterialSurfaceFn: FnOnce(&Descriptors<'_>, &VisiScene, &VisiTriangle) -> PbrSurface {}
 
-impl<T: BufferStruct, I> MaterialEvalFn<T> for I where I: FnOnce(&T, &mut Descriptors<'_>, VisiTriangle) -> Vec4 {}
+impl<I> MaterialSurfaceFn for I where I: FnOnce(&Descriptors<'_>, &VisiScene, &VisiTriangle) -> PbrSurface {}
 
+/// A material shader with an `image` shader previewing it over the entire screen and a `tiles` shader resolving its
+/// surfaces over the tiles containing it
 #[macro_export]
 macro_rules! material_shader {
-	($name:ident, $param:ty, $eval:ident) => {
+	($name:ident, $param:ty, $eval:ident, $surface:ident) => {
 		pub mod $name {
 			use super::*;
 			$crate::material_shader_image!(image, $param, $eval);
+			$crate::material_shader_tiles!(tiles, $surface);
 		}
 	};
 }
@@ -0,0 +1,47 @@
+//! Collect the tiles containing some [`MaterialType`] into a list, counting them in the dispatch_indirect arguments of
+//! the material's shader.
+
+use crate::material::system::MaterialType;
+use crate::material::system::classify::material_bit;
+use glam::UVec3;
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, MutBuffer, TransientDesc};
+use spirv_std::memory::{Scope, Semantics};
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub tile_materials: TransientDesc<'a, Buffer<[u32]>>,
+	/// dispatch_indirect arguments, must be initialized to `[0, 1, 1]`
+	pub indirect: TransientDesc<'a, MutBuffer<[u32; 3]>>,
+	/// the index of each tile containing the material, has space for all tiles
+	pub tiles: TransientDesc<'a, MutBuffer<[u32]>>,
+	pub material_type: MaterialType,
+}
+
+/// One invocation per tile
+#[bindless(compute(threads(64)))]
+pub fn material_tile_list(
+	#[bindless(descriptors)] mut descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(global_invocation_id)] inv_id: UVec3,
+) {
+	let tile_materials = param.tile_materials.access(&descriptors);
+	let tile_index = inv_id.x;
+	if tile_index as usize >= tile_materials.len() {
+		return;
+	}
+	if tile_materials.load(tile_index as usize) & material_bit(param.material_type) == 0 {
+		return;
+	}
+
+	unsafe {
+		// x of the indirect arguments counts the workgroups, one per tile
+		let indirect = param.indirect.access(&mut descriptors).into_raw_mut();
+		let slot = spirv_std::arch::atomic_i_add::<_, { Scope::Device as u32 }, { Semantics::NONE.bits() }>(
+			&mut indirect[0],
+			1,
+		);
+		param.tiles.access(&mut descriptors).store(slot as usize, tile_index);
+	}
+}
@@ -0,0 +1,75 @@
+//! A material shader that resolves the surfaces of the tiles containing its [`MaterialType`], see
+//! [`classify`](crate::material::system::classify). The resolved surfaces are what all ReSTIR passes shade.
+
+use crate::material::pbr::{PbrSurface, surface_index};
+use crate::material::system::classify::{MATERIAL_TILE_SIZE, material_tile_count};
+use crate::material::system::{MaterialSurfaceFn, MaterialType};
+use crate::visibility::id::GeometryId;
+use crate::visibility::scene::VisiScene;
+use glam::{UVec2, UVec3, UVec4, Vec3Swizzles};
+use rust_gpu_bindless_macros::BufferStruct;
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2dU, MutBuffer, TransientDesc};
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	/// the surface of each pixel, indexed by [`surface_index`]
+	pub surfaces: TransientDesc<'a, MutBuffer<[PbrSurface]>>,
+	/// the tiles containing this material type, one workgroup per tile
+	pub tiles: TransientDesc<'a, Buffer<[u32]>>,
+	/// only pixels of instances with this material type are resolved
+	pub material_type: MaterialType,
+}
+
+pub fn material_shader_tile_eval<F: MaterialSurfaceFn>(
+	descriptors: &mut Descriptors<'_>,
+	param: &Param<'_>,
+	wg_id: UVec3,
+	inv_id: UVec3,
+	eval: F,
+) {
+	let scene = param.scene.access(&*descriptors).load();
+	let size = scene.camera.viewport_size;
+	let tile_count = material_tile_count(size);
+	let tile_index = param.tiles.access(&*descriptors).load(wg_id.x as usize);
+	let tile = UVec2::new(tile_index % tile_count.x, tile_index / tile_count.x);
+	let pixel = tile * MATERIAL_TILE_SIZE + inv_id.xy();
+	let pixel_inbounds = pixel.x < size.x && pixel.y < size.y;
+	if pixel_inbounds {
+		let packed_geo: UVec4 = param.packed_vertex_image.access(&*descriptors).fetch_with_lod(pixel, 0);
+		let geo = GeometryId::from_visibility(packed_geo);
+		if !geo.is_clear && scene.load_instance(&*descriptors, geo.instance_id).material_type == param.material_type {
+			let tri = scene.load_triangle(&*descriptors, pixel, geo);
+			let surface = eval(&*descriptors, &scene, &tri);
+			unsafe {
+				param
+					.surfaces
+					.access(&mut *descriptors)
+					.store(surface_index(pixel, size), surface);
+			}
+		}
+	}
+}
+
+#[macro_export]
+macro_rules! material_shader_tiles {
+	($name:ident, $surface:ident) => {
+		#[rust_gpu_bindless_macros::bindless(compute(threads(8, 8)))]
+		pub fn $name(
+			#[bindless(descriptors)] mut descriptors: rust_gpu_bindless_shaders::descriptor::Descriptors<'_>,
+			#[bindless(param)] param: &$crate::material::system::tile_shader::Param<'static>,
+			#[spirv(workgroup_id)] wg_id: glam::UVec3,
+			#[spirv(local_invocation_id)] inv_id: glam::UVec3,
+		) {
+			$crate::material::system::tile_shader::material_shader_tile_eval(
+				&mut descriptors,
+				param,
+				wg_id,
+				inv_id,
+				$surface,
+			)
+		}
+	};
+}
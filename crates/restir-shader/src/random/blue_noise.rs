@@ -0,0 +1,35 @@
+//! Blue noise read from a tiled texture. Blue noise distributes its error at high frequencies, which looks less noisy
+//! than white noise at low sample counts and is easier to remove with spatial filters.
+
+use crate::random::pcg_hash;
+use glam::{UVec2, Vec4};
+use rust_gpu_bindless_macros::BufferStruct;
+use rust_gpu_bindless_shaders::descriptor::{Descriptors, Image, Image2d, StrongDesc};
+
+/// Width and height of the blue noise texture
+pub const BLUE_NOISE_SIZE: u32 = 64;
+
+/// `2^32` divided by the golden ratio
+const GOLDEN_RATIO_FIXED: u32 = 2654435769;
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct BlueNoise {
+	/// [`BLUE_NOISE_SIZE`]² texels with 4 independent channels of blue noise, each containing all values in [0, 1)
+	/// equally often
+	pub image: StrongDesc<Image<Image2d>>,
+}
+
+impl BlueNoise {
+	/// Blue noise in [0, 1) of a `pixel` in some `frame`. Passes of the same frame should use a different `salt` to
+	/// sample a different tile offset. Consecutive frames rotate all values by the golden ratio, which keeps the
+	/// spatial distribution while giving each pixel a low discrepancy sequence over time.
+	pub fn sample(&self, descriptors: &Descriptors, pixel: UVec2, frame: u32, salt: u32) -> Vec4 {
+		let offset = UVec2::new(pcg_hash(salt), pcg_hash(pcg_hash(salt)));
+		let texel = (pixel + offset % BLUE_NOISE_SIZE) % BLUE_NOISE_SIZE;
+		let noise: Vec4 = self.image.access(descriptors).fetch_with_lod(texel, 0);
+		let rotation = (frame.wrapping_mul(GOLDEN_RATIO_FIXED) >> 8) as f32 * (1. / (1 << 24) as f32);
+		let noise = noise + rotation;
+		noise - noise.floor()
+	}
+}
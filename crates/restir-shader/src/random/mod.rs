@@ -0,0 +1,280 @@
+pub mod blue_noise;
+pub mod sobol;
+
+use crate::random::blue_noise::BlueNoise;
+use crate::random::sobol::SobolSampler;
+use glam::{UVec2, Vec2, Vec4, Vec4Swizzles};
+use num_enum::{FromPrimitive, IntoPrimitive};
+use rust_gpu_bindless_shaders::buffer_content::BufferStructPlain;
+use rust_gpu_bindless_shaders::descriptor::Descriptors;
+
+/// PCG hash, see https://www.jcgt.org/published/0009/03/02/
+pub fn pcg_hash(input: u32) -> u32 {
+	let state = input.wrapping_mul(747796405).wrapping_add(2891336453);
+	let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+	(word >> 22) ^ word
+}
+
+/// A small random number generator for a single shader invocation, based on [`pcg_hash`]. Use it wherever an unknown
+/// amount of random numbers is needed, like resampling, or [`SobolSampler`](sobol::SobolSampler) for the dimensions of a
+/// path.
+#[derive(Copy, Clone, Debug)]
+pub struct Rng {
+	state: u32,
+}
+
+impl Rng {
+	/// Seed a new random number generator for a `pixel` in some `frame`. Passes of the same frame should use a
+	/// different `salt` to not reuse the same random numbers.
+	pub fn new(pixel: UVec2, frame: u32, salt: u32) -> Self {
+		Self {
+			state: pcg_hash(pixel.x ^ pcg_hash(pixel.y ^ pcg_hash(frame ^ pcg_hash(salt)))),
+		}
+	}
+
+	/// Seed a random number generator with a `seed` previously drawn from another one, to replay the same random
+	/// numbers in a later pass or frame.
+	pub fn from_seed(seed: u32) -> Self {
+		Self { state: seed }
+	}
+
+	pub fn next_u32(&mut self) -> u32 {
+		self.state = pcg_hash(self.state);
+		self.state
+	}
+
+	/// uniformly distributed in [0, 1)
+	pub fn next_f32(&mut self) -> f32 {
+		(self.next_u32() >> 8) as f32 * (1. / (1 << 24) as f32)
+	}
+}
+
+/// The sequence the dimensions of a path are drawn from by a [`PathSampler`]
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, FromPrimitive, IntoPrimitive)]
+pub enum SamplerType {
+	/// low discrepancy in each pixel, see [`SobolSampler`]
+	#[default]
+	Sobol,
+	/// low discrepancy over time and error distributed at high frequencies in screen space, see [`BlueNoise`]
+	BlueNoise,
+}
+
+impl SamplerType {
+	pub const MAX_VALUE: SamplerType = SamplerType::BlueNoise;
+	pub const LEN: u32 = Self::MAX_VALUE as u32 + 1;
+}
+
+unsafe impl BufferStructPlain for SamplerType {
+	type Transfer = u32;
+
+	unsafe fn write(self) -> Self::Transfer {
+		<u32 as From<Self>>::from(self)
+	}
+
+	unsafe fn read(from: Self::Transfer) -> Self {
+		<Self as num_enum::FromPrimitive>::from_primitive(from)
+	}
+}
+
+/// Random numbers for the dimensions of a path, drawn from the sequence selected by a [`SamplerType`]
+#[derive(Copy, Clone)]
+pub struct PathSampler {
+	sampler_type: SamplerType,
+	sobol: SobolSampler,
+	blue_noise: BlueNoise,
+	pixel: UVec2,
+	frame: u32,
+	salt: u32,
+	dimension: u32,
+}
+
+impl PathSampler {
+	/// See [`SobolSampler::new`] and [`BlueNoise::sample`] for the meaning of `frame` and `salt`
+	pub fn new(sampler_type: SamplerType, blue_noise: BlueNoise, pixel: UVec2, frame: u32, salt: u32) -> Self {
+		Self {
+			sampler_type,
+			sobol: SobolSampler::new(pixel, frame, salt),
+			blue_noise,
+			pixel,
+			frame,
+			salt,
+			dimension: 0,
+		}
+	}
+
+	pub fn next_4d(&mut self, descriptors: &Descriptors) -> Vec4 {
+		match self.sampler_type {
+			SamplerType::Sobol => self.sobol.next_4d(),
+			SamplerType::BlueNoise => {
+				// every 4 dimensions use a different tile offset
+				let salt = pcg_hash(self.salt).wrapping_add(self.dimension);
+				self.dimension += 1;
+				self.blue_noise.sample(descriptors, self.pixel, self.frame, salt)
+			}
+		}
+	}
+
+	pub fn next_2d(&mut self, descriptors: &Descriptors) -> Vec2 {
+		self.next_4d(descriptors).xy()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::random::sobol::SobolSampler;
+	use spirv_std::num_traits::Float;
+
+	const BINS: usize = 64;
+
+	/// Pearson's chi-square statistic of `histogram` against a uniform distribution
+	fn chi_square(histogram: &[u32]) -> f32 {
+		let total: u32 = histogram.iter().sum();
+		let expected = total as f32 / histogram.len() as f32;
+		histogram
+			.iter()
+			.map(|&count| (count as f32 - expected) * (count as f32 - expected) / expected)
+			.sum()
+	}
+
+	/// Critical value of the chi-square distribution with `dof` degrees of freedom at a significance of about 0.001,
+	/// using the Wilson-Hilferty approximation
+	fn chi_square_critical(dof: usize) -> f32 {
+		let dof = dof as f32;
+		let z = 3.09;
+		let t = 1. - 2. / (9. * dof) + z * (2. / (9. * dof)).sqrt();
+		dof * t * t * t
+	}
+
+	fn bin(value: f32, bins: usize) -> usize {
+		assert!((0. ..1.).contains(&value), "{value} not in [0, 1)");
+		(value * bins as f32) as usize
+	}
+
+	/// Square of Pearson's correlation coefficient of the pairs
+	fn correlation_squared(pairs: impl Iterator<Item = (f32, f32)>) -> f32 {
+		let (mut n, mut sa, mut sb, mut saa, mut sbb, mut sab) = (0., 0., 0., 0., 0., 0.);
+		for (a, b) in pairs {
+			n += 1.;
+			sa += a;
+			sb += b;
+			saa += a * a;
+			sbb += b * b;
+			sab += a * b;
+		}
+		let covariance = n * sab - sa * sb;
+		covariance * covariance / ((n * saa - sa * sa) * (n * sbb - sb * sb))
+	}
+
+	/// The `n`-th random number of a pixel in some frame
+	type Sample = fn(pixel: UVec2, frame: u32, salt: u32, n: u32) -> f32;
+
+	fn rng(pixel: UVec2, frame: u32, salt: u32, n: u32) -> f32 {
+		let mut rng = Rng::new(pixel, frame, salt);
+		for _ in 0..n {
+			rng.next_u32();
+		}
+		rng.next_f32()
+	}
+
+	fn sobol(pixel: UVec2, frame: u32, salt: u32, n: u32) -> f32 {
+		let mut sampler = SobolSampler::new(pixel, frame, salt);
+		for _ in 0..n / 4 {
+			sampler.next_4d();
+		}
+		sampler.next_4d()[n as usize % 4]
+	}
+
+	const SAMPLERS: [(&str, Sample); 2] = [("rng", rng), ("sobol", sobol)];
+
+	/// A neighbouring pixel, frame and salt
+	type Neighbour = fn(pixel: UVec2, frame: u32, salt: u32) -> (UVec2, u32, u32);
+
+	#[test]
+	fn test_uniform() {
+		for (name, sample) in SAMPLERS {
+			for n in 0..8 {
+				let mut histogram = [0; BINS];
+				for y in 0..64 {
+					for x in 0..64 {
+						for frame in 0..4 {
+							histogram[bin(sample(UVec2::new(x, y), frame, 0, n), BINS)] += 1;
+						}
+					}
+				}
+				let chi = chi_square(&histogram);
+				assert!(chi < chi_square_critical(BINS - 1), "{name} {n}: chi-square {chi}");
+			}
+		}
+	}
+
+	#[test]
+	fn test_decorrelated() {
+		// the n-th number of neighbouring pixels, frames and salts must be independent, though the frames of a
+		// low discrepancy sequence are anti-correlated by design
+		let neighbours: [(&str, Neighbour); 4] = [
+			("x", |p, f, s| (p + UVec2::X, f, s)),
+			("y", |p, f, s| (p + UVec2::Y, f, s)),
+			("frame", |p, f, s| (p, f + 1, s)),
+			("salt", |p, f, s| (p, f, s + 1)),
+		];
+		for (sampler, sample) in SAMPLERS {
+			for (name, neighbour) in neighbours {
+				if sampler == "sobol" && name == "frame" {
+					continue;
+				}
+				for n in 0..6 {
+					let mut histogram = [0; 16 * 16];
+					let mut pairs = [(0., 0.); 128 * 128];
+					for y in 0..128 {
+						for x in 0..128 {
+							let pixel = UVec2::new(x, y);
+							let a = sample(pixel, 7, 0, n);
+							let (pixel_b, frame_b, salt_b) = neighbour(pixel, 7, 0);
+							let b = sample(pixel_b, frame_b, salt_b, n);
+							histogram[bin(a, 16) * 16 + bin(b, 16)] += 1;
+							pairs[(y * 128 + x) as usize] = (a, b);
+						}
+					}
+					let chi = chi_square(&histogram);
+					assert!(
+						chi < chi_square_critical(16 * 16 - 1),
+						"{sampler} {name} {n}: chi-square {chi}"
+					);
+					let r2 = correlation_squared(pairs.iter().copied());
+					assert!(r2 < 0.03 * 0.03, "{sampler} {name} {n}: squared correlation {r2}");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_from_seed_replays() {
+		let seed = Rng::new(UVec2::new(3, 4), 5, 6).next_u32();
+		let mut a = Rng::from_seed(seed);
+		let mut b = Rng::from_seed(seed);
+		for _ in 0..16 {
+			assert_eq!(a.next_u32(), b.next_u32());
+		}
+		assert_ne!(Rng::from_seed(seed).next_u32(), Rng::from_seed(seed + 1).next_u32());
+	}
+
+	#[test]
+	fn test_dimensions_decorrelated() {
+		// different numbers of the same pixel must be independent
+		for (sampler, sample) in SAMPLERS {
+			for n in 0..6 {
+				let mut pairs = [(0., 0.); 128 * 128];
+				for y in 0..128 {
+					for x in 0..128 {
+						let pixel = UVec2::new(x, y);
+						pairs[(y * 128 + x) as usize] = (sample(pixel, 3, 0, n), sample(pixel, 3, 0, n + 1));
+					}
+				}
+				let r2 = correlation_squared(pairs.iter().copied());
+				assert!(r2 < 0.03 * 0.03, "{sampler} {n}: squared correlation {r2}");
+			}
+		}
+	}
+}
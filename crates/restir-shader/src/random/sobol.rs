@@ -0,0 +1,197 @@
+//! Owen-scrambled Sobol sequence using hash-based scrambling, see "Practical Hash-based Owen Scrambling" by Burley.
+//! Higher dimensions are padded from independently scrambled 4D sequences.
+
+use crate::random::pcg_hash;
+use glam::{UVec2, UVec4, Vec2, Vec4, Vec4Swizzles};
+
+/// Direction numbers of the first 4 Sobol dimensions, each entry holding the generator matrix columns of one bit
+const SOBOL_DIRECTIONS: [[u32; 4]; 32] = sobol_directions();
+
+const fn sobol_directions() -> [[u32; 4]; 32] {
+	// degree and coefficients of the primitive polynomials and the initial direction numbers of dimensions 1 to 3,
+	// see https://web.maths.unsw.edu.au/~fkuo/sobol/
+	const DEGREE: [usize; 3] = [1, 2, 3];
+	const COEFFICIENTS: [u32; 3] = [0, 1, 1];
+	const INITIAL: [[u32; 3]; 3] = [[1, 0, 0], [1, 3, 0], [1, 3, 1]];
+
+	let mut directions = [[0; 4]; 32];
+	let mut bit = 0;
+	while bit < 32 {
+		// dimension 0 is the van der Corput sequence
+		directions[bit][0] = 1 << (31 - bit);
+		bit += 1;
+	}
+	let mut dim = 1;
+	while dim < 4 {
+		let degree = DEGREE[dim - 1];
+		let coefficients = COEFFICIENTS[dim - 1];
+		let mut bit = 0;
+		while bit < 32 {
+			directions[bit][dim] = if bit < degree {
+				INITIAL[dim - 1][bit] << (31 - bit)
+			} else {
+				let prev = directions[bit - degree][dim];
+				let mut v = prev ^ (prev >> degree);
+				let mut l = 1;
+				while l < degree {
+					if (coefficients >> (degree - 1 - l)) & 1 == 1 {
+						v ^= directions[bit - l][dim];
+					}
+					l += 1;
+				}
+				v
+			};
+			bit += 1;
+		}
+		dim += 1;
+	}
+	directions
+}
+
+/// The `index`-th point of the unscrambled 4D Sobol sequence, as fixed point numbers in [0, 1)
+pub fn sobol_4d(mut index: u32) -> UVec4 {
+	let mut x = UVec4::ZERO;
+	let mut bit = 0;
+	while index != 0 {
+		if index & 1 == 1 {
+			x ^= UVec4::from_array(SOBOL_DIRECTIONS[bit]);
+		}
+		index >>= 1;
+		bit += 1;
+	}
+	x
+}
+
+/// Hash-based permutation where each bit only depends on itself and the bits below it, with constants by Nathan
+/// Vegdahl
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+	x ^= x.wrapping_mul(0x3d20adea);
+	x = x.wrapping_add(seed);
+	x = x.wrapping_mul((seed >> 16) | 1);
+	x ^= x.wrapping_mul(0x05526c56);
+	x ^= x.wrapping_mul(0x53a22864);
+	x
+}
+
+/// Owen scrambling of the fixed point number `x`: Each bit is flipped depending on the bits above it.
+pub fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+	laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// A fixed point number in [0, 1) to an `f32`, rounding down
+fn to_f32(x: u32) -> f32 {
+	(x >> 8) as f32 * (1. / (1 << 24) as f32)
+}
+
+/// Low discrepancy sampler over the frames of a pixel: The n-th frame uses the n-th point of an Owen-scrambled Sobol
+/// sequence unique to each pixel, so any power of two consecutive frames starting at 0 are well stratified. Each call
+/// to [`Self::next_4d`] or its variants consumes the next 4 dimensions.
+#[derive(Copy, Clone, Debug)]
+pub struct SobolSampler {
+	seed: u32,
+	index: u32,
+	dimension: u32,
+}
+
+impl SobolSampler {
+	/// Seed a new sampler for a `pixel` in some `frame`. Passes of the same frame should use a different `salt` to not
+	/// reuse the same sequence.
+	pub fn new(pixel: UVec2, frame: u32, salt: u32) -> Self {
+		Self {
+			seed: pcg_hash(pixel.x ^ pcg_hash(pixel.y ^ pcg_hash(salt))),
+			index: frame,
+			dimension: 0,
+		}
+	}
+
+	pub fn next_4d(&mut self) -> Vec4 {
+		let seed = pcg_hash(self.seed ^ pcg_hash(self.dimension));
+		self.dimension += 1;
+		let x = sobol_4d(nested_uniform_scramble(self.index, seed));
+		Vec4::new(
+			to_f32(nested_uniform_scramble(x.x, pcg_hash(seed))),
+			to_f32(nested_uniform_scramble(x.y, pcg_hash(seed.wrapping_add(1)))),
+			to_f32(nested_uniform_scramble(x.z, pcg_hash(seed.wrapping_add(2)))),
+			to_f32(nested_uniform_scramble(x.w, pcg_hash(seed.wrapping_add(3)))),
+		)
+	}
+
+	/// Uses the first two of the next 4 dimensions, as they form a (0, 2)-sequence
+	pub fn next_2d(&mut self) -> Vec2 {
+		self.next_4d().xy()
+	}
+
+	pub fn next_f32(&mut self) -> f32 {
+		self.next_4d().x
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Whether each of the `2^log2` points falls into a different interval of size `2^-log2`
+	fn is_stratified(points: impl Iterator<Item = u32>, log2: u32) -> bool {
+		let mut hit = [false; 1 << 10];
+		for point in points {
+			let interval = (point >> (32 - log2)) as usize;
+			if hit[interval] {
+				return false;
+			}
+			hit[interval] = true;
+		}
+		true
+	}
+
+	#[test]
+	fn test_sobol_directions() {
+		// the second dimension of Sobol's sequence: 0, 1/2, 3/4, 1/4, 5/8, 1/8, 3/8, 7/8
+		let expected = [0, 4, 6, 2, 5, 1, 3, 7];
+		for (i, expected) in expected.into_iter().enumerate() {
+			assert_eq!(sobol_4d(i as u32).y >> 29, expected, "index {i}");
+		}
+	}
+
+	#[test]
+	fn test_sobol_stratified() {
+		for log2 in 1..=10 {
+			for dim in 0..4 {
+				assert!(
+					is_stratified((0..1 << log2).map(|i| sobol_4d(i)[dim]), log2),
+					"dim {dim} of first 2^{log2} points"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn test_sampler_elementary_intervals() {
+		// scrambling retains the first two dimensions being a (0, 2)-sequence: The first 2^m points have exactly one
+		// point in each elementary interval of area 2^-m
+		const LOG2: u32 = 8;
+		for pixel in [UVec2::new(0, 0), UVec2::new(5, 3)] {
+			for dimension in 0..3 {
+				let mut points = [Vec2::ZERO; 1 << LOG2];
+				for (frame, point) in points.iter_mut().enumerate() {
+					let mut sampler = SobolSampler::new(pixel, frame as u32, 0);
+					for _ in 0..dimension {
+						sampler.next_4d();
+					}
+					*point = sampler.next_2d();
+				}
+				for x_log2 in 0..=LOG2 {
+					let y_log2 = LOG2 - x_log2;
+					let intervals = points.iter().map(|p| {
+						let x = (p.x * (1 << x_log2) as f32) as u32;
+						let y = (p.y * (1 << y_log2) as f32) as u32;
+						(y << x_log2 | x) << (32 - LOG2)
+					});
+					assert!(
+						is_stratified(intervals, LOG2),
+						"pixel {pixel} dimension {dimension} intervals {x_log2}x{y_log2}"
+					);
+				}
+			}
+		}
+	}
+}
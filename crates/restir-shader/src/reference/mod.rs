@@ -0,0 +1,154 @@
+//! Brute force unidirectional path tracing, to accumulate a converged ground truth that the bias and variance of ReSTIR
+//! can be judged against. All surfaces are the same [`DiSurface`]s ReSTIR DI shades, so that both render the same
+//! image.
+
+use crate::light::alias::sample_alias;
+use crate::light::{IncidentLight, Light, LightType};
+use crate::random::SamplerType;
+use crate::restir::di::DiSurface;
+use crate::utils::ray::Ray;
+use crate::visibility::scene::{SceneHit, VisiScene};
+use glam::{UVec2, Vec3, Vec4, Vec4Swizzles};
+use rust_gpu_bindless_macros::BufferStruct;
+use rust_gpu_bindless_shaders::descriptor::Descriptors;
+use static_assertions::const_assert_eq;
+
+pub mod path_trace;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, BufferStruct)]
+pub struct ReferenceSettings {
+	/// maximum amount of surfaces light is reflected off along each path, 1 only computes direct lighting
+	pub max_bounces: u32,
+	/// stop accumulating once this many samples per pixel have been taken, 0 accumulates forever
+	pub max_samples: u32,
+	pub sampler: SamplerType,
+}
+
+impl Default for ReferenceSettings {
+	fn default() -> Self {
+		Self {
+			max_bounces: 4,
+			max_samples: 0,
+			sampler: SamplerType::default(),
+		}
+	}
+}
+
+/// The surface hit by `ray`, with its normal facing the origin of the ray
+pub fn hit_surface(scene: &VisiScene, descriptors: &Descriptors, ray: Ray, hit: SceneHit) -> DiSurface {
+	let tri = scene.load_hit_triangle(descriptors, ray, hit);
+	DiSurface::from_triangle(scene, descriptors, &tri, -ray.direction)
+}
+
+/// Select a light proportional to its power, returns the light and the probability of selecting it. Returns `None`
+/// for scenes without any lights.
+pub fn select_light(scene: &VisiScene, descriptors: &Descriptors, u: f32) -> Option<(Light, f32)> {
+	let lights = scene.lights.access(descriptors);
+	let light_count = scene.light_count;
+	if light_count == 0 {
+		return None;
+	}
+	let alias_table = scene.light_alias_table.access(descriptors);
+	let (light_index, pdf) = sample_alias(light_count, u, |i| alias_table.load(i as usize));
+	Some((lights.load(light_index as usize), pdf))
+}
+
+/// The probability of [`select_light`] selecting the environment light, which the scene places last.
+pub fn environment_selection_pdf(scene: &VisiScene, descriptors: &Descriptors) -> f32 {
+	let lights = scene.lights.access(descriptors);
+	let light_count = scene.light_count as usize;
+	if light_count > 0 && lights.load(light_count - 1).light_type == LightType::Environment {
+		scene.light_alias_table.access(descriptors).load(light_count - 1).pdf
+	} else {
+		0.
+	}
+}
+
+/// Next event estimation: The light reflected off `surface` from a light selected proportional to its power. With
+/// `mis` the environment light is weighted against it being hit by BRDF sampling.
+pub fn direct_light(scene: &VisiScene, descriptors: &Descriptors, surface: &DiSurface, u: Vec4, mis: bool) -> Vec3 {
+	let Some((light, selection_pdf)) = select_light(scene, descriptors, u.x) else {
+		return Vec3::ZERO;
+	};
+	if selection_pdf <= 0. {
+		return Vec3::ZERO;
+	}
+	let incident = light.incident(scene, descriptors, surface.position, u.yz());
+	let radiance = surface.radiance(&incident) / selection_pdf;
+	if radiance == Vec3::ZERO || !surface.is_light_visible(scene, descriptors, &incident) {
+		return Vec3::ZERO;
+	}
+	if mis && light.light_type == LightType::Environment {
+		let light_pdf = selection_pdf * scene.environment.pdf(descriptors, incident.direction);
+		let brdf_pdf = surface.pdf(incident.direction);
+		radiance * power_heuristic(light_pdf, brdf_pdf)
+	} else {
+		radiance
+	}
+}
+
+/// Next event estimation without evaluating the BRDF, consuming the same random numbers as [`direct_light`]: The light
+/// selected proportional to its power, with its incident radiance divided by the probability of selecting it. The
+/// radiance is zero if the light is occluded or the scene has no lights.
+pub fn visible_light(scene: &VisiScene, descriptors: &Descriptors, surface: &DiSurface, u: Vec4) -> IncidentLight {
+	let mut incident = IncidentLight {
+		direction: surface.normal,
+		distance: 0.,
+		radiance: Vec3::ZERO,
+	};
+	if let Some((light, selection_pdf)) = select_light(scene, descriptors, u.x) {
+		if selection_pdf > 0. {
+			incident = light.incident(scene, descriptors, surface.position, u.yz());
+			incident.radiance /= selection_pdf;
+			if incident.radiance != Vec3::ZERO && !surface.is_light_visible(scene, descriptors, &incident) {
+				incident.radiance = Vec3::ZERO;
+			}
+		}
+	}
+	incident
+}
+
+/// MIS weight of a sample taken with `pdf` while another strategy could have taken it with `other_pdf`
+pub fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+	let a = pdf * pdf;
+	let b = other_pdf * other_pdf;
+	if a + b > 0. { a / (a + b) } else { 0. }
+}
+
+/// Progressively average `color` into the running mean `prev` of `sample_index` samples taken before it
+pub fn accumulate(prev: Vec4, color: Vec4, sample_index: u32) -> Vec4 {
+	if sample_index == 0 {
+		color
+	} else {
+		prev + (color - prev) / (sample_index + 1) as f32
+	}
+}
+
+pub const REFERENCE_WG_SIZE: UVec2 = UVec2::new(8, 8);
+
+const_assert_eq!(REFERENCE_WG_SIZE.x, 8);
+const_assert_eq!(REFERENCE_WG_SIZE.y, 8);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_power_heuristic() {
+		assert_eq!(power_heuristic(1., 0.), 1.);
+		assert_eq!(power_heuristic(0., 0.), 0.);
+		let (a, b) = (0.3, 1.7);
+		assert!((power_heuristic(a, b) + power_heuristic(b, a) - 1.).abs() < 1e-6);
+	}
+
+	#[test]
+	fn test_accumulate() {
+		let samples = [1., 4., -2., 5.].map(Vec4::splat);
+		let mean = samples
+			.iter()
+			.enumerate()
+			.fold(Vec4::splat(100.), |prev, (i, color)| accumulate(prev, *color, i as u32));
+		assert!(mean.abs_diff_eq(Vec4::splat(2.), 1e-6), "{mean}");
+	}
+}
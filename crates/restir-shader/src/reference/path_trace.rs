@@ -0,0 +1,191 @@
+//! Trace one path per pixel starting at the visibility buffer and average it into the accumulation image. The emission
+//! of the surface visible from the camera is added directly, further bounces sample the BRDF of the material. Lights
+//! are sampled with next event estimation at every vertex. The environment is additionally hit by BRDF sampled rays
+//! and both strategies are combined with MIS. All other lights can only be reached by next event estimation, which
+//! remains unbiased, as area lights are sampled over their entire surface and delta lights can't be hit by rays anyway.
+
+use crate::random::PathSampler;
+use crate::random::blue_noise::BlueNoise;
+use crate::reference::{
+	REFERENCE_WG_SIZE, ReferenceSettings, accumulate, direct_light, environment_selection_pdf, hit_surface,
+	power_heuristic,
+};
+use crate::restir::di::DiSurface;
+use crate::utils::ray::{RAY_EPSILON, Ray};
+use crate::visibility::id::GeometryId;
+use crate::visibility::scene::VisiScene;
+use glam::{UVec2, UVec3, UVec4, Vec3, Vec3Swizzles, Vec4};
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2d, Image2dU, MutImage, TransientDesc};
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	/// RGBA32F running mean of all samples taken so far
+	pub accumulation: TransientDesc<'a, MutImage<Image2d>>,
+	pub output_image: TransientDesc<'a, MutImage<Image2d>>,
+	pub settings: ReferenceSettings,
+	/// used if `settings.sampler` is [`SamplerType::BlueNoise`](crate::random::SamplerType::BlueNoise)
+	pub blue_noise: BlueNoise,
+	/// amount of samples already averaged in `accumulation`, 0 discards its contents
+	pub sample_index: u32,
+}
+
+#[bindless(compute(threads(8, 8)))]
+pub fn reference_path_trace(
+	#[bindless(descriptors)] descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(workgroup_id)] wg_id: UVec3,
+	#[spirv(local_invocation_id)] inv_id: UVec3,
+) {
+	let scene = param.scene.access(&descriptors).load();
+	let size = scene.camera.viewport_size;
+	let pixel = wg_id.xy() * REFERENCE_WG_SIZE + inv_id.xy();
+	if !(pixel.x < size.x && pixel.y < size.y) {
+		return;
+	}
+
+	let accumulation = param.accumulation.access(&descriptors);
+	let max_samples = param.settings.max_samples;
+	let mut mean: Vec4 = accumulation.read(pixel);
+	if max_samples == 0 || param.sample_index < max_samples {
+		let color = trace_path(&scene, &descriptors, pixel, param);
+		mean = accumulate(mean, Vec4::from((color, 1.)), param.sample_index);
+		unsafe {
+			accumulation.write(pixel, mean);
+		}
+	}
+	unsafe {
+		param.output_image.access(&descriptors).write(pixel, mean);
+	}
+}
+
+/// The radiance arriving at the camera through `pixel`, estimated with a single path
+fn trace_path(scene: &VisiScene, descriptors: &Descriptors, pixel: UVec2, param: &Param) -> Vec3 {
+	let size = scene.camera.viewport_size;
+	let packed_geo: UVec4 = param.packed_vertex_image.access(descriptors).fetch_with_lod(pixel, 0);
+	let geo = GeometryId::from_visibility(packed_geo);
+	if geo.is_clear {
+		let fragment_pos = (pixel.as_vec2() + 0.5) / size.as_vec2();
+		let far = scene.camera.reconstruct_from_depth(fragment_pos, 1.).world_space;
+		let direction = (far - scene.camera.view_from_world.translation()).normalize();
+		return scene.environment.radiance(descriptors, direction);
+	}
+
+	let mut sampler = PathSampler::new(param.settings.sampler, param.blue_noise, pixel, param.sample_index, 0);
+	let environment_selection_pdf = environment_selection_pdf(scene, descriptors);
+	let tri = scene.load_triangle(descriptors, pixel, geo);
+	let mut surface = DiSurface::new(scene, &tri, scene.load_surface(descriptors, &tri));
+	let mut throughput = Vec3::ONE;
+	// emissive surfaces hit by later bounces are lights reached by next event estimation instead
+	let mut radiance = surface.emission;
+	let max_bounces = param.settings.max_bounces;
+	for bounce in 0..max_bounces {
+		// the last vertex doesn't sample a BRDF ray, so there is nothing to combine the environment sample with
+		let continues = bounce + 1 < max_bounces;
+		let u = sampler.next_4d(descriptors);
+		radiance += throughput * direct_light(scene, descriptors, &surface, u, continues);
+		if !continues {
+			break;
+		}
+
+		let brdf_sample = surface.sample(sampler.next_2d(descriptors));
+		if !brdf_sample.is_valid() {
+			break;
+		}
+		let (wi, brdf_pdf) = (brdf_sample.wi, brdf_sample.pdf);
+		throughput *= surface.eval(wi) / brdf_pdf;
+
+		let ray = Ray {
+			origin: surface.position + surface.normal * RAY_EPSILON,
+			direction: wi,
+			t_min: 0.,
+			t_max: f32::INFINITY,
+		};
+		let hit = scene.trace_closest(descriptors, ray);
+		if !hit.is_hit() {
+			let light_pdf = environment_selection_pdf * scene.environment.pdf(descriptors, wi);
+			let weight = power_heuristic(brdf_pdf, light_pdf);
+			radiance += throughput * scene.environment.radiance(descriptors, wi) * weight;
+			break;
+		}
+		surface = hit_surface(scene, descriptors, ray, hit);
+	}
+
+	// a single invalid sample would poison the entire accumulation
+	if radiance.is_finite() { radiance } else { Vec3::ZERO }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::light::Light;
+	use crate::light::alias::AliasEntry;
+	use crate::visibility::id::{InstanceId, PackedGeometryId, TriangleId};
+	use crate::visibility::scene::tests::{VIEWPORT, single_triangle_scene};
+	use alloc::boxed::Box;
+	use core::iter::repeat_n;
+	use rust_gpu_bindless_shaders::descriptor::{CpuDescriptors, CpuImage};
+
+	#[test]
+	fn test_directional_light() {
+		let irradiance = Vec3::new(1., 2., 3.);
+		let mut cpu = CpuDescriptors::new();
+		let mut scene = single_triangle_scene(&mut cpu);
+		// shining straight onto the triangle facing the camera
+		scene.lights = cpu.alloc_slice([Light::directional(-Vec3::Z, irradiance)]);
+		scene.light_count = 1;
+		scene.light_alias_table = cpu.alloc_slice([AliasEntry {
+			threshold: 1.,
+			alias: 0,
+			pdf: 1.,
+			alias_pdf: 1.,
+		}]);
+		let scene_buffer = cpu.alloc_buffer(scene);
+		let geo = PackedGeometryId::new(InstanceId::new(0).unwrap(), TriangleId::new(0).unwrap());
+		let pixels = (VIEWPORT.x * VIEWPORT.y) as usize;
+		let packed_vertex_image = cpu.alloc_image::<Image2dU>(CpuImage::new(
+			VIEWPORT,
+			repeat_n(UVec4::new(geo.to_u32(), 0, 0, 0), pixels),
+		));
+		let accumulation = cpu.alloc_mut_image::<Image2d>(CpuImage::new(VIEWPORT, repeat_n(Vec4::NAN, pixels)));
+		let output_image = cpu.alloc_mut_image::<Image2d>(CpuImage::new(VIEWPORT, repeat_n(Vec4::ZERO, pixels)));
+		let blue_noise = BlueNoise {
+			image: cpu.alloc_image(CpuImage::new(UVec2::ONE, [Vec4::splat(0.5)])),
+		};
+
+		// the entry point's params are `'static`
+		let cpu: &'static CpuDescriptors = Box::leak(Box::new(cpu));
+		let pixel = VIEWPORT / 2;
+		for sample_index in 0..4 {
+			let param = Param {
+				scene: scene_buffer.to_transient(cpu),
+				packed_vertex_image: packed_vertex_image.to_transient(cpu),
+				accumulation: accumulation.to_transient(cpu),
+				output_image: output_image.to_transient(cpu),
+				settings: ReferenceSettings::default(),
+				blue_noise,
+				sample_index,
+			};
+			reference_path_trace(
+				cpu.descriptors(),
+				&param,
+				(pixel / REFERENCE_WG_SIZE).extend(0),
+				(pixel % REFERENCE_WG_SIZE).extend(0),
+			);
+		}
+
+		// the triangle is infinitely large from the camera's point of view, so no light bounces off any other surface
+		// and a delta light is estimated exactly
+		let descriptors = cpu.descriptors();
+		let tri = scene.load_triangle(&descriptors, pixel, geo.unpack());
+		let surface = DiSurface::new(&scene, &tri, scene.load_surface(&descriptors, &tri));
+		let expected = Vec4::from((surface.emission + surface.eval(Vec3::Z) * irradiance, 1.));
+		let output = output_image.access(&descriptors).read(pixel);
+		assert!(output.abs_diff_eq(expected, 1e-5), "{output} != {expected}");
+		let mean = accumulation.access(&descriptors).read(pixel);
+		assert!(mean.abs_diff_eq(expected, 1e-5), "{mean} != {expected}");
+	}
+}
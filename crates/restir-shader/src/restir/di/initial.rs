@@ -0,0 +1,89 @@
+//! Initial candidate generation: Select one out of `M` lights per pixel using RIS, with candidates sampled according
+//! to [`LightSampling`].
+
+use crate::light::alias::sample_alias;
+use crate::material::pbr::{PbrSurface, surface_index};
+use crate::random::Rng;
+use crate::restir::di::{DI_WG_SIZE, DiReservoir, DiSettings, DiSurface, LightSample, LightSampling, reservoir_index};
+use crate::visibility::id::GeometryId;
+use crate::visibility::scene::VisiScene;
+use glam::{UVec3, UVec4, Vec2, Vec3Swizzles};
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2dU, MutBuffer, TransientDesc};
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	/// the surfaces resolved by the material shaders
+	pub surfaces: TransientDesc<'a, Buffer<[PbrSurface]>>,
+	pub reservoirs: TransientDesc<'a, MutBuffer<[DiReservoir]>>,
+	pub settings: DiSettings,
+	pub frame: u32,
+}
+
+#[bindless(compute(threads(8, 8)))]
+pub fn di_initial(
+	#[bindless(descriptors)] mut descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(workgroup_id)] wg_id: UVec3,
+	#[spirv(local_invocation_id)] inv_id: UVec3,
+) {
+	let scene = param.scene.access(&descriptors).load();
+	let size = scene.camera.viewport_size;
+	let pixel = wg_id.xy() * DI_WG_SIZE + inv_id.xy();
+	if !(pixel.x < size.x && pixel.y < size.y) {
+		return;
+	}
+
+	let mut reservoir = DiReservoir::new();
+	let packed_geo: UVec4 = param.packed_vertex_image.access(&descriptors).fetch_with_lod(pixel, 0);
+	let geo = GeometryId::from_visibility(packed_geo);
+	let lights = scene.lights.access(&descriptors);
+	let light_count = scene.light_count;
+	if !geo.is_clear && light_count > 0 {
+		let tri = scene.load_triangle(&descriptors, pixel, geo);
+		let surface = DiSurface::new(
+			&scene,
+			&tri,
+			param.surfaces.access(&descriptors).load(surface_index(pixel, size)),
+		);
+		let mut rng = Rng::new(pixel, param.frame, 0);
+		let alias_table = scene.light_alias_table.access(&descriptors);
+		let candidates = param.settings.initial_candidates;
+		for _ in 0..candidates {
+			let (light_index, source_pdf) = match param.settings.light_sampling {
+				LightSampling::Power => sample_alias(light_count, rng.next_f32(), |i| alias_table.load(i as usize)),
+				LightSampling::LightTree => {
+					scene
+						.light_tree
+						.sample(&descriptors, surface.position, surface.normal, rng.next_f32())
+				}
+			};
+			let sample = LightSample {
+				light_index,
+				uv: Vec2::new(rng.next_f32(), rng.next_f32()),
+			};
+			let light = lights.load(light_index as usize);
+			let target_pdf = surface.target_pdf(&surface.incident(&scene, &descriptors, &light, sample));
+			// W = 1 / p(x) of the light selection, points on area lights are accounted for by the incident light
+			let contribution_weight = if source_pdf > 0. { 1. / source_pdf } else { 0. };
+			reservoir.update(
+				sample,
+				target_pdf,
+				contribution_weight,
+				1. / candidates as f32,
+				rng.next_f32(),
+			);
+		}
+		reservoir.finalize();
+	}
+
+	unsafe {
+		param
+			.reservoirs
+			.access(&mut descriptors)
+			.store(reservoir_index(pixel, size), reservoir);
+	}
+}
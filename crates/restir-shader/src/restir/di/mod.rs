@@ -0,0 +1,224 @@
+//! ReSTIR DI, see https://research.nvidia.com/publication/2020-07_spatiotemporal-reservoir-resampling-real-time-ray-tracing-dynamic-direct
+
+use crate::brdf::BrdfSample;
+use crate::light::{IncidentLight, Light};
+use crate::material::pbr::PbrSurface;
+use crate::restir::reservoir::Reservoir;
+use crate::utils::color::luminance;
+use crate::utils::ray::RAY_EPSILON;
+use crate::visibility::scene::{VisiScene, VisiTriangle};
+use glam::{UVec2, Vec2, Vec3};
+use num_enum::{FromPrimitive, IntoPrimitive};
+use rust_gpu_bindless_macros::{BufferStruct, BufferStructPlain};
+use rust_gpu_bindless_shaders::buffer_content::BufferStructPlain;
+use rust_gpu_bindless_shaders::descriptor::Descriptors;
+use static_assertions::const_assert_eq;
+
+pub mod initial;
+pub mod shade;
+pub mod spatial;
+pub mod temporal;
+
+/// A light sample selected by a [`DiReservoir`]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, BufferStructPlain)]
+pub struct LightSample {
+	pub light_index: u32,
+	/// the point sampled on area lights
+	pub uv: Vec2,
+}
+
+impl LightSample {
+	pub fn load_light(&self, scene: &VisiScene, descriptors: &Descriptors) -> Light {
+		scene.lights.access(descriptors).load(self.light_index as usize)
+	}
+}
+
+pub type DiReservoir = Reservoir<LightSample>;
+
+/// The source pdf initial light candidates are sampled from
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, FromPrimitive, IntoPrimitive)]
+pub enum LightSampling {
+	/// proportional to the power of each light, using the alias table
+	#[default]
+	Power,
+	/// proportional to the estimated contribution at the shading point, using the light tree
+	LightTree,
+}
+
+impl LightSampling {
+	pub const MAX_VALUE: LightSampling = LightSampling::LightTree;
+	pub const LEN: u32 = Self::MAX_VALUE as u32 + 1;
+}
+
+unsafe impl BufferStructPlain for LightSampling {
+	type Transfer = u32;
+
+	unsafe fn write(self) -> Self::Transfer {
+		<u32 as From<Self>>::from(self)
+	}
+
+	unsafe fn read(from: Self::Transfer) -> Self {
+		<Self as num_enum::FromPrimitive>::from_primitive(from)
+	}
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, BufferStruct)]
+pub struct DiSettings {
+	/// amount of light candidates `M` generated per pixel by the initial pass
+	pub initial_candidates: u32,
+	pub light_sampling: LightSampling,
+	pub temporal_reuse: bool,
+	/// the confidence of the history is capped to this multiple of the current reservoir's confidence
+	pub temporal_confidence_cap: f32,
+	/// amount of spatial reuse passes, 0 disables spatial reuse
+	pub spatial_iterations: u32,
+	/// amount of neighbors merged by each spatial pass, up to [`MAX_SPATIAL_NEIGHBORS`]
+	pub spatial_neighbors: u32,
+	/// radius in pixels in which neighbors are selected
+	pub spatial_radius: f32,
+	/// Trace visibility towards the samples of all neighbors and use the generalized balance heuristic, instead of
+	/// confidence weights that ignore differing target functions.
+	pub spatial_unbiased: bool,
+}
+
+impl Default for DiSettings {
+	fn default() -> Self {
+		Self {
+			initial_candidates: 32,
+			light_sampling: LightSampling::default(),
+			temporal_reuse: true,
+			temporal_confidence_cap: 20.,
+			spatial_iterations: 1,
+			spatial_neighbors: 5,
+			spatial_radius: 30.,
+			spatial_unbiased: false,
+		}
+	}
+}
+
+/// The surface visible at some pixel, for which direct lighting is evaluated
+#[derive(Copy, Clone, Debug)]
+pub struct DiSurface {
+	pub position: Vec3,
+	/// normalized shading normal, facing `wo`
+	pub normal: Vec3,
+	/// normalized direction light is reflected towards, to the camera or the previous vertex of a path
+	pub wo: Vec3,
+	/// the base color of the material
+	pub albedo: Vec3,
+	pub metallic: f32,
+	/// perceptual roughness
+	pub roughness: f32,
+	pub emission: Vec3,
+}
+
+impl DiSurface {
+	/// The surface of `tri` seen from the camera, with the `surface` the material shaders resolved for its pixel
+	pub fn new(scene: &VisiScene, tri: &VisiTriangle, surface: PbrSurface) -> Self {
+		let wo = (scene.camera.view_from_world.translation() - tri.world_position()).normalize();
+		Self::from_surface(tri, surface, wo)
+	}
+
+	/// The surface of `tri` seen from direction `wo`, with the material of its instance
+	pub fn from_triangle(scene: &VisiScene, descriptors: &Descriptors, tri: &VisiTriangle, wo: Vec3) -> Self {
+		Self::from_surface(tri, scene.load_surface(descriptors, tri), wo)
+	}
+
+	fn from_surface(tri: &VisiTriangle, surface: PbrSurface, wo: Vec3) -> Self {
+		let mut normal = surface.normal;
+		// flip towards the viewer based on the side of the triangle, not the interpolated normal
+		if tri.world_geometric_normal().dot(wo) < 0. {
+			normal = -normal;
+		}
+		Self {
+			position: tri.world_position(),
+			normal,
+			wo,
+			albedo: surface.base_color,
+			metallic: surface.metallic,
+			roughness: surface.roughness,
+			emission: surface.emission,
+		}
+	}
+
+	/// The material of this surface
+	pub fn pbr(&self) -> PbrSurface {
+		PbrSurface {
+			base_color: self.albedo,
+			metallic: self.metallic,
+			roughness: self.roughness,
+			normal: self.normal,
+			emission: self.emission,
+		}
+	}
+
+	/// The BRDF times the cosine term of light arriving from `wi` being reflected towards `wo`
+	pub fn eval(&self, wi: Vec3) -> Vec3 {
+		self.pbr().eval(self.wo, wi)
+	}
+
+	/// Sample the direction of incident light proportional to the BRDF, see [`PbrSurface::sample`]
+	pub fn sample(&self, u: Vec2) -> BrdfSample {
+		self.pbr().sample(self.wo, u)
+	}
+
+	/// The solid angle pdf of [`Self::sample`] returning `wi`
+	pub fn pdf(&self, wi: Vec3) -> f32 {
+		self.pbr().pdf(self.wo, wi)
+	}
+
+	/// The light of `sample` arriving at this surface
+	pub fn incident(
+		&self,
+		scene: &VisiScene,
+		descriptors: &Descriptors,
+		light: &Light,
+		sample: LightSample,
+	) -> IncidentLight {
+		light.incident(scene, descriptors, self.position, sample.uv)
+	}
+
+	/// Unshadowed radiance reflected towards `wo` of `incident` light
+	pub fn radiance(&self, incident: &IncidentLight) -> Vec3 {
+		self.eval(incident.direction) * incident.radiance
+	}
+
+	/// Whether the `other` surface is similar enough to reuse its reservoir, by comparing normals and the distance
+	/// to the camera at `camera_position`.
+	pub fn is_similar(&self, other: &Self, camera_position: Vec3) -> bool {
+		let depth = self.position.distance(camera_position);
+		let other_depth = other.position.distance(camera_position);
+		self.normal.dot(other.normal) >= SIMILAR_NORMAL_THRESHOLD
+			&& f32::abs(depth - other_depth) <= SIMILAR_DEPTH_THRESHOLD * depth
+	}
+
+	pub fn is_light_visible(&self, scene: &VisiScene, descriptors: &Descriptors, incident: &IncidentLight) -> bool {
+		let origin = self.position + self.normal * RAY_EPSILON;
+		scene.is_visible(descriptors, origin, incident.light_position(self.position))
+	}
+
+	/// The target function `p̂` of all DI reservoirs
+	pub fn target_pdf(&self, incident: &IncidentLight) -> f32 {
+		luminance(self.radiance(incident))
+	}
+}
+
+/// minimum cosine between normals of similar surfaces, roughly 25°
+pub const SIMILAR_NORMAL_THRESHOLD: f32 = 0.9;
+/// maximum relative difference in depth of similar surfaces
+pub const SIMILAR_DEPTH_THRESHOLD: f32 = 0.1;
+
+pub fn reservoir_index(pixel: UVec2, viewport_size: UVec2) -> usize {
+	(pixel.y * viewport_size.x + pixel.x) as usize
+}
+
+/// upper limit of [`DiSettings::spatial_neighbors`]
+pub const MAX_SPATIAL_NEIGHBORS: u32 = 8;
+
+pub const DI_WG_SIZE: UVec2 = UVec2::new(8, 8);
+
+const_assert_eq!(DI_WG_SIZE.x, 8);
+const_assert_eq!(DI_WG_SIZE.y, 8);
@@ -0,0 +1,66 @@
+//! Shade each pixel using the light sample selected by its reservoir, plus the light emitted by the surface itself.
+
+use crate::material::pbr::{PbrSurface, surface_index};
+use crate::restir::di::{DI_WG_SIZE, DiReservoir, DiSurface, reservoir_index};
+use crate::visibility::id::GeometryId;
+use crate::visibility::scene::VisiScene;
+use glam::{UVec3, UVec4, Vec3Swizzles, Vec4};
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2d, Image2dU, MutImage, TransientDesc};
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	/// the surfaces resolved by the material shaders
+	pub surfaces: TransientDesc<'a, Buffer<[PbrSurface]>>,
+	pub reservoirs: TransientDesc<'a, Buffer<[DiReservoir]>>,
+	pub output_image: TransientDesc<'a, MutImage<Image2d>>,
+}
+
+#[bindless(compute(threads(8, 8)))]
+pub fn di_shade(
+	#[bindless(descriptors)] descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(workgroup_id)] wg_id: UVec3,
+	#[spirv(local_invocation_id)] inv_id: UVec3,
+) {
+	let scene = param.scene.access(&descriptors).load();
+	let size = scene.camera.viewport_size;
+	let pixel = wg_id.xy() * DI_WG_SIZE + inv_id.xy();
+	if !(pixel.x < size.x && pixel.y < size.y) {
+		return;
+	}
+
+	let packed_geo: UVec4 = param.packed_vertex_image.access(&descriptors).fetch_with_lod(pixel, 0);
+	let geo = GeometryId::from_visibility(packed_geo);
+	let mut color = Vec4::ZERO;
+	if geo.is_clear {
+		let fragment_pos = (pixel.as_vec2() + 0.5) / size.as_vec2();
+		let far = scene.camera.reconstruct_from_depth(fragment_pos, 1.).world_space;
+		let direction = (far - scene.camera.view_from_world.translation()).normalize();
+		color = Vec4::from((scene.environment.radiance(&descriptors, direction), 1.));
+	} else {
+		let tri = scene.load_triangle(&descriptors, pixel, geo);
+		let surface = DiSurface::new(
+			&scene,
+			&tri,
+			param.surfaces.access(&descriptors).load(surface_index(pixel, size)),
+		);
+		let mut radiance = surface.emission;
+		let reservoir = param.reservoirs.access(&descriptors).load(reservoir_index(pixel, size));
+		if !reservoir.is_empty() {
+			let light = reservoir.sample.load_light(&scene, &descriptors);
+			let incident = surface.incident(&scene, &descriptors, &light, reservoir.sample);
+			if surface.is_light_visible(&scene, &descriptors, &incident) {
+				radiance += surface.radiance(&incident) * reservoir.contribution_weight;
+			}
+		}
+		color = Vec4::from((radiance, 1.));
+	}
+
+	unsafe {
+		param.output_image.access(&descriptors).write(pixel, color);
+	}
+}
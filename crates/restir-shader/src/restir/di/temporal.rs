@@ -0,0 +1,132 @@
+//! Temporal reuse: Merge the reservoir of the previous frame into the current one, by reprojecting each pixel into
+//! the previous frame.
+
+use crate::material::pbr::{PbrSurface, surface_index};
+use crate::random::Rng;
+use crate::restir::di::{DI_WG_SIZE, DiReservoir, DiSettings, DiSurface, LightSample, reservoir_index};
+use crate::restir::reservoir::balance_heuristic;
+use crate::visibility::id::GeometryId;
+use crate::visibility::scene::VisiScene;
+use glam::{UVec3, UVec4, Vec3Swizzles};
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2dU, MutBuffer, TransientDesc};
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub prev_scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	/// the surfaces resolved by the material shaders
+	pub surfaces: TransientDesc<'a, Buffer<[PbrSurface]>>,
+	pub prev_packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	pub prev_surfaces: TransientDesc<'a, Buffer<[PbrSurface]>>,
+	pub reservoirs: TransientDesc<'a, MutBuffer<[DiReservoir]>>,
+	pub prev_reservoirs: TransientDesc<'a, Buffer<[DiReservoir]>>,
+	pub settings: DiSettings,
+	pub frame: u32,
+}
+
+#[bindless(compute(threads(8, 8)))]
+pub fn di_temporal(
+	#[bindless(descriptors)] mut descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(workgroup_id)] wg_id: UVec3,
+	#[spirv(local_invocation_id)] inv_id: UVec3,
+) {
+	let scene = param.scene.access(&descriptors).load();
+	let size = scene.camera.viewport_size;
+	let pixel = wg_id.xy() * DI_WG_SIZE + inv_id.xy();
+	if !(pixel.x < size.x && pixel.y < size.y) {
+		return;
+	}
+	let packed_geo: UVec4 = param.packed_vertex_image.access(&descriptors).fetch_with_lod(pixel, 0);
+	let geo = GeometryId::from_visibility(packed_geo);
+	if geo.is_clear || scene.light_count == 0 {
+		return;
+	}
+	let tri = scene.load_triangle(&descriptors, pixel, geo);
+	let surface = DiSurface::new(
+		&scene,
+		&tri,
+		param.surfaces.access(&descriptors).load(surface_index(pixel, size)),
+	);
+
+	// reproject into the previous frame
+	let prev_scene = param.prev_scene.access(&descriptors).load();
+	if prev_scene.light_count != scene.light_count {
+		return;
+	}
+	let prev_camera = prev_scene.camera;
+	let Some(motion) = scene.motion_vector(&tri) else {
+		return;
+	};
+	let prev_pixel = (pixel.as_vec2() + motion + 0.5).floor();
+	if !(prev_pixel.x >= 0. && prev_pixel.y >= 0.) {
+		return;
+	}
+	let prev_pixel = prev_pixel.as_uvec2();
+	let prev_size = prev_camera.viewport_size;
+	if !(prev_pixel.x < prev_size.x && prev_pixel.y < prev_size.y) {
+		return;
+	}
+
+	// reject history of a different surface
+	let prev_packed_geo: UVec4 = param
+		.prev_packed_vertex_image
+		.access(&descriptors)
+		.fetch_with_lod(prev_pixel, 0);
+	let prev_geo = GeometryId::from_visibility(prev_packed_geo);
+	if prev_geo.is_clear || prev_geo != geo {
+		return;
+	}
+	let prev_tri = prev_scene.load_triangle(&descriptors, prev_pixel, prev_geo);
+	let prev_surface = DiSurface::new(
+		&prev_scene,
+		&prev_tri,
+		param
+			.prev_surfaces
+			.access(&descriptors)
+			.load(surface_index(prev_pixel, prev_size)),
+	);
+	if !surface.is_similar(&prev_surface, prev_camera.view_from_world.translation()) {
+		return;
+	}
+
+	let index = reservoir_index(pixel, size);
+	let current = param.reservoirs.access(&mut descriptors).load(index);
+	let mut history = param
+		.prev_reservoirs
+		.access(&descriptors)
+		.load(reservoir_index(prev_pixel, prev_size));
+	history.cap_confidence(param.settings.temporal_confidence_cap * current.confidence);
+
+	// generalized balance heuristic, as the target function changes between frames
+	let target_pdf = |scene: &VisiScene, surface: &DiSurface, sample: LightSample| {
+		let light = sample.load_light(scene, &descriptors);
+		surface.target_pdf(&surface.incident(scene, &descriptors, &light, sample))
+	};
+	let current_pdf = current.target_pdf;
+	let current_prev_pdf = target_pdf(&prev_scene, &prev_surface, current.sample);
+	let history_pdf = target_pdf(&scene, &surface, history.sample);
+	let history_prev_pdf = target_pdf(&prev_scene, &prev_surface, history.sample);
+	let current_mis = balance_heuristic(
+		current.confidence,
+		current_pdf,
+		current.confidence * current_pdf + history.confidence * current_prev_pdf,
+	);
+	let history_mis = balance_heuristic(
+		history.confidence,
+		history_prev_pdf,
+		current.confidence * history_pdf + history.confidence * history_prev_pdf,
+	);
+
+	let mut rng = Rng::new(pixel, param.frame, 1);
+	let mut reservoir = DiReservoir::new();
+	reservoir.merge(&current, current_pdf, current_mis, rng.next_f32());
+	reservoir.merge(&history, history_pdf, history_mis, rng.next_f32());
+	reservoir.finalize();
+	unsafe {
+		param.reservoirs.access(&mut descriptors).store(index, reservoir);
+	}
+}
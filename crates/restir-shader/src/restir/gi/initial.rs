@@ -0,0 +1,87 @@
+//! Initial sample generation: Trace a single cosine distributed ray from the visible surface of each pixel and estimate
+//! the radiance leaving the surface it hits with next event estimation.
+
+use crate::brdf::{ShadingFrame, cosine_hemisphere_pdf, sample_cosine_hemisphere};
+use crate::material::pbr::{PbrSurface, surface_index};
+use crate::random::Rng;
+use crate::reference::{direct_light, hit_surface};
+use crate::restir::di::{DiSurface, reservoir_index};
+use crate::restir::gi::{GI_RNG_SALT, GI_WG_SIZE, GiReservoir, GiSample};
+use crate::utils::ray::{RAY_EPSILON, Ray};
+use crate::visibility::id::GeometryId;
+use crate::visibility::scene::VisiScene;
+use glam::{UVec3, UVec4, Vec2, Vec3Swizzles, Vec4};
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2dU, MutBuffer, TransientDesc};
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	/// the surfaces resolved by the material shaders
+	pub surfaces: TransientDesc<'a, Buffer<[PbrSurface]>>,
+	pub reservoirs: TransientDesc<'a, MutBuffer<[GiReservoir]>>,
+	pub frame: u32,
+}
+
+#[bindless(compute(threads(8, 8)))]
+pub fn gi_initial(
+	#[bindless(descriptors)] mut descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(workgroup_id)] wg_id: UVec3,
+	#[spirv(local_invocation_id)] inv_id: UVec3,
+) {
+	let scene = param.scene.access(&descriptors).load();
+	let size = scene.camera.viewport_size;
+	let pixel = wg_id.xy() * GI_WG_SIZE + inv_id.xy();
+	if !(pixel.x < size.x && pixel.y < size.y) {
+		return;
+	}
+
+	let mut reservoir = GiReservoir::new();
+	let packed_geo: UVec4 = param.packed_vertex_image.access(&descriptors).fetch_with_lod(pixel, 0);
+	let geo = GeometryId::from_visibility(packed_geo);
+	if !geo.is_clear {
+		let tri = scene.load_triangle(&descriptors, pixel, geo);
+		let surface = DiSurface::new(
+			&scene,
+			&tri,
+			param.surfaces.access(&descriptors).load(surface_index(pixel, size)),
+		);
+		let mut rng = Rng::new(pixel, param.frame, GI_RNG_SALT);
+		let u = Vec2::new(rng.next_f32(), rng.next_f32());
+		let wi = ShadingFrame::new(surface.normal).to_world(sample_cosine_hemisphere(u));
+		let source_pdf = cosine_hemisphere_pdf(surface.normal.dot(wi));
+
+		let ray = Ray {
+			origin: surface.position + surface.normal * RAY_EPSILON,
+			direction: wi,
+			t_min: 0.,
+			t_max: f32::INFINITY,
+		};
+		let hit = scene.trace_closest(&descriptors, ray);
+		// rays escaping into the environment are direct light, leaving the reservoir empty
+		if hit.is_hit() && source_pdf > 0. {
+			let hit = hit_surface(&scene, &descriptors, ray, hit);
+			let u = Vec4::new(rng.next_f32(), rng.next_f32(), rng.next_f32(), rng.next_f32());
+			let radiance = direct_light(&scene, &descriptors, &hit, u, false);
+			let sample = GiSample {
+				position: hit.position,
+				normal: hit.normal,
+				radiance: if radiance.is_finite() { radiance } else { Vec3::ZERO },
+			};
+			reservoir.update(sample, sample.target_pdf(&surface), 1. / source_pdf, 1., rng.next_f32());
+		} else {
+			reservoir.confidence = 1.;
+		}
+		reservoir.finalize();
+	}
+
+	unsafe {
+		param
+			.reservoirs
+			.access(&mut descriptors)
+			.store(reservoir_index(pixel, size), reservoir);
+	}
+}
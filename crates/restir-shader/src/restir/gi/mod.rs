@@ -0,0 +1,201 @@
+//! ReSTIR GI, see https://research.nvidia.com/publication/2021-06_restir-gi-path-resampling-real-time-path-tracing
+//!
+//! Each pixel traces a single bounce from its visible surface and stores the surface it hit, together with the
+//! radiance leaving it, as a [`GiSample`]. Reusing a sample at another pixel reconnects that pixel's visible surface to
+//! the same sample point, which changes the solid angle the sample is measured in and thus requires the
+//! [`GiSample::jacobian`] to be applied. Only indirect light is resampled: the radiance of a sample excludes light
+//! arriving directly from the environment, as that is already covered by ReSTIR DI.
+
+use crate::restir::di::DiSurface;
+use crate::restir::reservoir::Reservoir;
+use crate::utils::color::luminance;
+use crate::utils::ray::RAY_EPSILON;
+use crate::visibility::scene::VisiScene;
+use glam::{UVec2, Vec3};
+use rust_gpu_bindless_macros::{BufferStruct, BufferStructPlain};
+use rust_gpu_bindless_shaders::descriptor::Descriptors;
+use static_assertions::const_assert_eq;
+
+pub mod initial;
+pub mod shade;
+pub mod spatial;
+pub mod temporal;
+
+/// A secondary surface hit by a ray leaving the visible surface, selected by a [`GiReservoir`]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, BufferStructPlain)]
+pub struct GiSample {
+	pub position: Vec3,
+	/// normalized shading normal, facing the visible surface the sample was traced from
+	pub normal: Vec3,
+	/// radiance leaving `position` towards the visible surface
+	pub radiance: Vec3,
+}
+
+impl GiSample {
+	/// Normalized direction from `surface` towards this sample
+	pub fn direction(&self, surface: &DiSurface) -> Vec3 {
+		(self.position - surface.position).normalize_or_zero()
+	}
+
+	/// Unshadowed reflected radiance towards the camera, of `surface` lit by this sample.
+	pub fn reflected_radiance(&self, surface: &DiSurface) -> Vec3 {
+		surface.eval(self.direction(surface)) * self.radiance
+	}
+
+	/// The target function `p̂` of all GI reservoirs, in solid angle measure at `surface`
+	pub fn target_pdf(&self, surface: &DiSurface) -> f32 {
+		luminance(self.reflected_radiance(surface))
+	}
+
+	/// The change of measure from solid angle at `surface` to area at the sample point, `cos φ / d²`. It is 0 for
+	/// surfaces behind the sample, as its radiance only leaves towards the side it was traced from.
+	pub fn solid_angle_to_area(&self, surface: &DiSurface) -> f32 {
+		let offset = surface.position - self.position;
+		let distance_squared = offset.length_squared();
+		if distance_squared <= 0. {
+			return 0.;
+		}
+		let cos_phi = f32::max(self.normal.dot(offset), 0.) / distance_squared.sqrt();
+		cos_phi / distance_squared
+	}
+
+	/// The jacobian determinant of the reconnection shift, moving this sample from the solid angle at `from` into
+	/// the solid angle at `to`. Contribution weights are multiplied by it when a reservoir is reused at another
+	/// surface. Returns 0 if the shift is invalid.
+	pub fn jacobian(&self, from: &DiSurface, to: &DiSurface) -> f32 {
+		let from_area = self.solid_angle_to_area(from);
+		let to_area = self.solid_angle_to_area(to);
+		if from_area > 0. && to_area.is_finite() {
+			to_area / from_area
+		} else {
+			0.
+		}
+	}
+
+	/// The target function at `surface` in area measure at the sample point, which allows comparing target functions
+	/// of different pixels in the generalized balance heuristic, as all of them share the same sample point.
+	pub fn area_target_pdf(&self, surface: &DiSurface) -> f32 {
+		self.target_pdf(surface) * self.solid_angle_to_area(surface)
+	}
+
+	pub fn is_visible(&self, scene: &VisiScene, descriptors: &Descriptors, surface: &DiSurface) -> bool {
+		let origin = surface.position + surface.normal * RAY_EPSILON;
+		let target = self.position + self.normal * RAY_EPSILON;
+		scene.is_visible(descriptors, origin, target)
+	}
+}
+
+pub type GiReservoir = Reservoir<GiSample>;
+
+/// Merge `other`, which was resampled at surface `from`, into `reservoir` of surface `to`. `target_pdf` must be the
+/// target function of `other.sample` at `to`.
+pub fn merge_shifted(
+	reservoir: &mut GiReservoir,
+	other: &GiReservoir,
+	from: &DiSurface,
+	to: &DiSurface,
+	target_pdf: f32,
+	mis_weight: f32,
+	rand: f32,
+) {
+	let mut shifted = *other;
+	shifted.contribution_weight *= other.sample.jacobian(from, to);
+	reservoir.merge(&shifted, target_pdf, mis_weight, rand);
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, BufferStruct)]
+pub struct GiSettings {
+	pub temporal_reuse: bool,
+	/// the confidence of the history is capped to this multiple of the current reservoir's confidence
+	pub temporal_confidence_cap: f32,
+	/// amount of spatial reuse passes, 0 disables spatial reuse
+	pub spatial_iterations: u32,
+	/// amount of neighbors merged by each spatial pass, up to [`MAX_SPATIAL_NEIGHBORS`]
+	pub spatial_neighbors: u32,
+	/// radius in pixels in which neighbors are selected
+	pub spatial_radius: f32,
+	/// Trace visibility towards the samples of all neighbors and use the generalized balance heuristic, instead of
+	/// confidence weights that ignore differing target functions.
+	pub spatial_unbiased: bool,
+}
+
+impl Default for GiSettings {
+	fn default() -> Self {
+		Self {
+			temporal_reuse: true,
+			temporal_confidence_cap: 30.,
+			spatial_iterations: 1,
+			spatial_neighbors: 5,
+			spatial_radius: 30.,
+			spatial_unbiased: false,
+		}
+	}
+}
+
+/// added to the salts of all [`Rng`](crate::random::Rng)s, so that GI doesn't reuse the random numbers of DI
+pub const GI_RNG_SALT: u32 = 0x100;
+
+/// upper limit of [`GiSettings::spatial_neighbors`]
+pub const MAX_SPATIAL_NEIGHBORS: u32 = 8;
+
+pub const GI_WG_SIZE: UVec2 = UVec2::new(8, 8);
+
+const_assert_eq!(GI_WG_SIZE.x, 8);
+const_assert_eq!(GI_WG_SIZE.y, 8);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn surface(position: Vec3, normal: Vec3) -> DiSurface {
+		DiSurface {
+			position,
+			normal,
+			wo: normal,
+			albedo: Vec3::splat(0.8),
+			metallic: 0.,
+			roughness: 1.,
+			emission: Vec3::ZERO,
+		}
+	}
+
+	#[test]
+	fn test_jacobian() {
+		let sample = GiSample {
+			position: Vec3::ZERO,
+			normal: Vec3::Y,
+			radiance: Vec3::ONE,
+		};
+		let near = surface(Vec3::new(0., 1., 0.), -Vec3::Y);
+		let far = surface(Vec3::new(0., 2., 0.), -Vec3::Y);
+		assert!((sample.jacobian(&near, &far) - 0.25).abs() < 1e-6);
+		assert!((sample.jacobian(&far, &near) - 4.).abs() < 1e-6);
+		assert_eq!(sample.jacobian(&near, &near), 1.);
+
+		// a grazing angle at the sample point
+		let grazing = surface(Vec3::new(1., 1., 0.), -Vec3::Y);
+		let expected = (1. / 2f32.sqrt()) / 2.;
+		assert!((sample.jacobian(&near, &grazing) - expected).abs() < 1e-6);
+	}
+
+	#[test]
+	fn test_area_target_pdf() {
+		let sample = GiSample {
+			position: Vec3::ZERO,
+			normal: Vec3::Y,
+			radiance: Vec3::new(1., 2., 3.),
+		};
+		let from = surface(Vec3::new(0.5, 1., 0.), -Vec3::Y);
+		let to = surface(Vec3::new(-1., 3., 0.5), Vec3::new(0.3, -1., 0.).normalize());
+		// p̂_to(T(x)) |T'(x)| in solid angle at `from` equals the ratio of area measure target functions
+		let shifted = sample.target_pdf(&to) * sample.jacobian(&from, &to);
+		let expected = sample.area_target_pdf(&to) / sample.solid_angle_to_area(&from);
+		assert!((shifted - expected).abs() < 1e-5, "{shifted} != {expected}");
+
+		let behind = surface(Vec3::new(0., -1., 0.), Vec3::Y);
+		assert_eq!(sample.jacobian(&from, &behind), 0.);
+		assert_eq!(sample.area_target_pdf(&behind), 0.);
+	}
+}
@@ -0,0 +1,67 @@
+//! Add the indirect light of the sample selected by each pixel's reservoir onto the direct light already in the
+//! output image.
+
+use crate::material::pbr::{PbrSurface, surface_index};
+use crate::restir::di::{DiSurface, reservoir_index};
+use crate::restir::gi::{GI_WG_SIZE, GiReservoir};
+use crate::visibility::id::GeometryId;
+use crate::visibility::scene::VisiScene;
+use glam::{UVec3, UVec4, Vec3Swizzles, Vec4};
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2d, Image2dU, MutImage, TransientDesc};
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	/// the surfaces resolved by the material shaders
+	pub surfaces: TransientDesc<'a, Buffer<[PbrSurface]>>,
+	pub reservoirs: TransientDesc<'a, Buffer<[GiReservoir]>>,
+	pub output_image: TransientDesc<'a, MutImage<Image2d>>,
+}
+
+#[bindless(compute(threads(8, 8)))]
+pub fn gi_shade(
+	#[bindless(descriptors)] descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(workgroup_id)] wg_id: UVec3,
+	#[spirv(local_invocation_id)] inv_id: UVec3,
+) {
+	let scene = param.scene.access(&descriptors).load();
+	let size = scene.camera.viewport_size;
+	let pixel = wg_id.xy() * GI_WG_SIZE + inv_id.xy();
+	if !(pixel.x < size.x && pixel.y < size.y) {
+		return;
+	}
+
+	let packed_geo: UVec4 = param.packed_vertex_image.access(&descriptors).fetch_with_lod(pixel, 0);
+	let geo = GeometryId::from_visibility(packed_geo);
+	if geo.is_clear {
+		return;
+	}
+	let reservoir = param.reservoirs.access(&descriptors).load(reservoir_index(pixel, size));
+	if reservoir.is_empty() {
+		return;
+	}
+	let tri = scene.load_triangle(&descriptors, pixel, geo);
+	let surface = DiSurface::new(
+		&scene,
+		&tri,
+		param.surfaces.access(&descriptors).load(surface_index(pixel, size)),
+	);
+	let sample = reservoir.sample;
+	if !sample.is_visible(&scene, &descriptors, &surface) {
+		return;
+	}
+	let indirect = sample.reflected_radiance(&surface) * reservoir.contribution_weight;
+	if !indirect.is_finite() {
+		return;
+	}
+
+	let output_image = param.output_image.access(&descriptors);
+	let color: Vec4 = output_image.read(pixel);
+	unsafe {
+		output_image.write(pixel, color + Vec4::from((indirect, 0.)));
+	}
+}
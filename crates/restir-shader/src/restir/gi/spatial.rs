@@ -0,0 +1,158 @@
+//! Spatial reuse: Merge the reservoirs of randomly selected neighboring pixels into the reservoir of each pixel, by
+//! reconnecting the visible surface of each pixel to the sample points of its neighbors.
+
+use crate::material::pbr::{PbrSurface, surface_index};
+use crate::random::Rng;
+use crate::restir::di::{DiSurface, reservoir_index};
+use crate::restir::gi::{
+	GI_RNG_SALT, GI_WG_SIZE, GiReservoir, GiSample, GiSettings, MAX_SPATIAL_NEIGHBORS, merge_shifted,
+};
+use crate::restir::reservoir::{balance_heuristic, confidence_mis_weight};
+use crate::visibility::id::GeometryId;
+use crate::visibility::scene::VisiScene;
+use core::f32::consts::PI;
+use glam::{UVec3, UVec4, Vec2, Vec3Swizzles};
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2dU, MutBuffer, TransientDesc};
+use spirv_std::num_traits::Float;
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	/// the surfaces resolved by the material shaders
+	pub surfaces: TransientDesc<'a, Buffer<[PbrSurface]>>,
+	pub src_reservoirs: TransientDesc<'a, Buffer<[GiReservoir]>>,
+	pub dst_reservoirs: TransientDesc<'a, MutBuffer<[GiReservoir]>>,
+	pub settings: GiSettings,
+	pub frame: u32,
+	/// index of this spatial pass within the frame
+	pub iteration: u32,
+}
+
+const MAX_RESERVOIRS: usize = MAX_SPATIAL_NEIGHBORS as usize + 1;
+
+#[bindless(compute(threads(8, 8)))]
+pub fn gi_spatial(
+	#[bindless(descriptors)] mut descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(workgroup_id)] wg_id: UVec3,
+	#[spirv(local_invocation_id)] inv_id: UVec3,
+) {
+	let scene = param.scene.access(&descriptors).load();
+	let size = scene.camera.viewport_size;
+	let pixel = wg_id.xy() * GI_WG_SIZE + inv_id.xy();
+	if !(pixel.x < size.x && pixel.y < size.y) {
+		return;
+	}
+
+	let index = reservoir_index(pixel, size);
+	let src_reservoirs = param.src_reservoirs.access(&descriptors);
+	let center = src_reservoirs.load(index);
+	let packed_geo: UVec4 = param.packed_vertex_image.access(&descriptors).fetch_with_lod(pixel, 0);
+	let geo = GeometryId::from_visibility(packed_geo);
+	if geo.is_clear {
+		unsafe {
+			param.dst_reservoirs.access(&mut descriptors).store(index, center);
+		}
+		return;
+	}
+	let tri = scene.load_triangle(&descriptors, pixel, geo);
+	let camera_position = scene.camera.view_from_world.translation();
+
+	// the center pixel is always at index 0, followed by all accepted neighbors
+	let center_surface = param.surfaces.access(&descriptors).load(surface_index(pixel, size));
+	let mut surfaces = [DiSurface::new(&scene, &tri, center_surface); MAX_RESERVOIRS];
+	let mut reservoirs = [center; MAX_RESERVOIRS];
+	let mut count = 1;
+	let mut confidence_sum = center.confidence;
+	let mut rng = Rng::new(pixel, param.frame, GI_RNG_SALT + 2 + param.iteration);
+	let neighbors = u32::min(param.settings.spatial_neighbors, MAX_SPATIAL_NEIGHBORS);
+	for _ in 0..neighbors {
+		// uniformly distributed in a disk around the center pixel
+		let angle = rng.next_f32() * 2. * PI;
+		let radius = param.settings.spatial_radius * rng.next_f32().sqrt();
+		let offset = Vec2::new(angle.cos(), angle.sin()) * radius;
+		let neighbor = (pixel.as_vec2() + offset + 0.5).floor();
+		if !(neighbor.x >= 0. && neighbor.y >= 0.) {
+			continue;
+		}
+		let neighbor = neighbor.as_uvec2();
+		if !(neighbor.x < size.x && neighbor.y < size.y) || neighbor == pixel {
+			continue;
+		}
+
+		let packed_geo: UVec4 = param
+			.packed_vertex_image
+			.access(&descriptors)
+			.fetch_with_lod(neighbor, 0);
+		let neighbor_geo = GeometryId::from_visibility(packed_geo);
+		if neighbor_geo.is_clear {
+			continue;
+		}
+		let neighbor_tri = scene.load_triangle(&descriptors, neighbor, neighbor_geo);
+		let surface = DiSurface::new(
+			&scene,
+			&neighbor_tri,
+			param.surfaces.access(&descriptors).load(surface_index(neighbor, size)),
+		);
+		if !surfaces[0].is_similar(&surface, camera_position) {
+			continue;
+		}
+		surfaces[count] = surface;
+		reservoirs[count] = src_reservoirs.load(reservoir_index(neighbor, size));
+		confidence_sum += reservoirs[count].confidence;
+		count += 1;
+	}
+
+	let unbiased = param.settings.spatial_unbiased;
+	let visible = |surface: &DiSurface, sample: &GiSample, pdf: f32| {
+		if unbiased && pdf > 0. && !sample.is_visible(&scene, &descriptors, surface) {
+			0.
+		} else {
+			pdf
+		}
+	};
+
+	let mut out = GiReservoir::new();
+	for i in 0..count {
+		let reservoir = reservoirs[i];
+		if reservoir.is_empty() {
+			// still contributes its confidence
+			out.confidence += reservoir.confidence;
+			continue;
+		}
+		let sample = reservoir.sample;
+		let center_pdf = visible(&surfaces[0], &sample, sample.target_pdf(&surfaces[0]));
+		let mis_weight = if unbiased {
+			// in area measure of the shared sample point, so that target functions of different pixels are comparable
+			let mut own_pdf = 0.;
+			let mut denominator = 0.;
+			for j in 0..count {
+				let pdf = visible(&surfaces[j], &sample, sample.area_target_pdf(&surfaces[j]));
+				if j == i {
+					own_pdf = pdf;
+				}
+				denominator += reservoirs[j].confidence * pdf;
+			}
+			balance_heuristic(reservoir.confidence, own_pdf, denominator)
+		} else {
+			confidence_mis_weight(reservoir.confidence, confidence_sum)
+		};
+		merge_shifted(
+			&mut out,
+			&reservoir,
+			&surfaces[i],
+			&surfaces[0],
+			center_pdf,
+			mis_weight,
+			rng.next_f32(),
+		);
+	}
+	out.finalize();
+
+	unsafe {
+		param.dst_reservoirs.access(&mut descriptors).store(index, out);
+	}
+}
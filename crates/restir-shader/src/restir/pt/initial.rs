@@ -0,0 +1,63 @@
+//! Initial path generation: Trace a single path of indirect light from the visible surface of each pixel.
+
+use crate::material::pbr::{PbrSurface, surface_index};
+use crate::random::Rng;
+use crate::restir::di::{DiSurface, reservoir_index};
+use crate::restir::pt::{PT_RNG_SALT, PT_WG_SIZE, PtReservoir, PtSettings, generate_path};
+use crate::visibility::id::GeometryId;
+use crate::visibility::scene::VisiScene;
+use glam::{UVec3, UVec4, Vec3Swizzles};
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2dU, MutBuffer, TransientDesc};
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	/// the surfaces resolved by the material shaders
+	pub surfaces: TransientDesc<'a, Buffer<[PbrSurface]>>,
+	pub reservoirs: TransientDesc<'a, MutBuffer<[PtReservoir]>>,
+	pub settings: PtSettings,
+	pub frame: u32,
+}
+
+#[bindless(compute(threads(8, 8)))]
+pub fn pt_initial(
+	#[bindless(descriptors)] mut descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(workgroup_id)] wg_id: UVec3,
+	#[spirv(local_invocation_id)] inv_id: UVec3,
+) {
+	let scene = param.scene.access(&descriptors).load();
+	let size = scene.camera.viewport_size;
+	let pixel = wg_id.xy() * PT_WG_SIZE + inv_id.xy();
+	if !(pixel.x < size.x && pixel.y < size.y) {
+		return;
+	}
+
+	let mut reservoir = PtReservoir::new();
+	let packed_geo: UVec4 = param.packed_vertex_image.access(&descriptors).fetch_with_lod(pixel, 0);
+	let geo = GeometryId::from_visibility(packed_geo);
+	if !geo.is_clear {
+		let tri = scene.load_triangle(&descriptors, pixel, geo);
+		let surface = DiSurface::new(
+			&scene,
+			&tri,
+			param.surfaces.access(&descriptors).load(surface_index(pixel, size)),
+		);
+		let mut rng = Rng::new(pixel, param.frame, PT_RNG_SALT);
+		let seed = rng.next_u32();
+		let path = generate_path(&scene, &descriptors, &surface, seed, &param.settings);
+		// W = 1 / p(x) of a path in primary sample space, where all random numbers are uniformly distributed
+		reservoir.update(path.sample, path.target_pdf(), 1., 1., rng.next_f32());
+		reservoir.finalize();
+	}
+
+	unsafe {
+		param
+			.reservoirs
+			.access(&mut descriptors)
+			.store(reservoir_index(pixel, size), reservoir);
+	}
+}
@@ -0,0 +1,589 @@
+//! ReSTIR PT, see https://research.nvidia.com/publication/2022-07_generalized-resampled-importance-sampling-foundations-restir
+//!
+//! Each pixel traces an entire path of indirect light from its visible surface and resamples it as a whole. Paths are
+//! reused at other pixels with the hybrid shift: The prefix of the path is regenerated from the other pixel's visible
+//! surface by replaying the same random numbers, until it reaches the reconnection vertex. There the shifted path
+//! reconnects to the stored vertex and reuses the radiance gathered by the rest of the base path. Paths without a
+//! reconnection vertex are replayed in their entirety.
+//!
+//! All samples live in primary sample space, the unit hypercube of random numbers consumed by the path, where random
+//! replay has a jacobian of 1. Only the reconnection changes the measure, see [`PtPath::jacobian`]. Like ReSTIR GI,
+//! only indirect light is resampled, ReSTIR DI covers light arriving directly at the visible surface.
+//!
+//! Directions are sampled from the BRDF of each material. The BRDF of the reconnection vertex is evaluated again for
+//! every predecessor it is connected to, so both vertices of the reconnection have to be rough.
+
+use crate::random::Rng;
+use crate::reference::{direct_light, hit_surface, visible_light};
+use crate::restir::di::DiSurface;
+use crate::restir::reservoir::Reservoir;
+use crate::utils::color::luminance;
+use crate::utils::ray::{RAY_EPSILON, Ray};
+use crate::visibility::scene::VisiScene;
+use glam::{UVec2, Vec2, Vec3, Vec4};
+use num_enum::{FromPrimitive, IntoPrimitive};
+use rust_gpu_bindless_macros::{BufferStruct, BufferStructPlain};
+use rust_gpu_bindless_shaders::buffer_content::BufferStructPlain;
+use rust_gpu_bindless_shaders::descriptor::Descriptors;
+use static_assertions::const_assert_eq;
+
+pub mod initial;
+pub mod shade;
+pub mod spatial;
+pub mod temporal;
+
+/// A path selected by a [`PtReservoir`]. Vertices are numbered starting at the visible surface with 1, following the
+/// camera at 0.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, BufferStructPlain)]
+pub struct PtSample {
+	/// the seed of the [`Rng`] generating all random numbers of the path
+	pub seed: u32,
+	/// the index of the reconnection vertex, or 0 if the path has none and is shifted with random replay only
+	pub reconnection_vertex: u32,
+	pub reconnection_position: Vec3,
+	/// normalized shading normal of the reconnection vertex, facing its predecessor
+	pub reconnection_normal: Vec3,
+	/// the material of the reconnection vertex, see [`DiSurface`]
+	pub reconnection_albedo: Vec3,
+	pub reconnection_metallic: f32,
+	pub reconnection_roughness: f32,
+	/// normalized direction from the reconnection vertex towards the light sampled by next event estimation
+	pub reconnection_light_direction: Vec3,
+	/// incident radiance of the light sampled at the reconnection vertex, divided by the probability of selecting it
+	pub reconnection_light_radiance: Vec3,
+	/// normalized direction the reconnection vertex sampled towards its successor
+	pub suffix_direction: Vec3,
+	/// solid angle pdf of the reconnection vertex sampling `suffix_direction`, 0 if the path ended at it
+	pub suffix_pdf: f32,
+	/// radiance arriving at the reconnection vertex from `suffix_direction`, gathered by the rest of the path
+	pub suffix_radiance: Vec3,
+	/// area measure pdf of sampling the reconnection vertex from its predecessor on the path
+	pub reconnection_pdf: f32,
+	/// amount of vertices following the visible surface
+	pub path_length: u32,
+	/// the radiance carried by the path towards the camera, at the pixel owning the reservoir
+	pub radiance: Vec3,
+}
+
+impl PtSample {
+	/// The reconnection vertex, reflecting light towards `wo`
+	pub fn reconnection_surface(&self, wo: Vec3) -> DiSurface {
+		DiSurface {
+			position: self.reconnection_position,
+			normal: self.reconnection_normal,
+			wo,
+			albedo: self.reconnection_albedo,
+			metallic: self.reconnection_metallic,
+			roughness: self.reconnection_roughness,
+			// emissive surfaces following the visible surface are lights reached by next event estimation instead
+			emission: Vec3::ZERO,
+		}
+	}
+
+	/// The radiance `vertex` reflects towards its `wo` of the light sampled at the reconnection vertex and the light
+	/// arriving from the suffix. Also returns the ratio of the pdfs of `vertex` and the reconnection vertex of this path
+	/// sampling `suffix_direction`, which is 0 if `vertex` can't sample it.
+	fn reconnection_radiance(&self, vertex: &DiSurface) -> (Vec3, f32) {
+		let light = vertex.eval(self.reconnection_light_direction) * self.reconnection_light_radiance;
+		if self.suffix_pdf <= 0. || self.suffix_radiance == Vec3::ZERO {
+			return (light, 1.);
+		}
+		let pdf = vertex.pdf(self.suffix_direction);
+		if pdf <= 0. {
+			return (Vec3::ZERO, 0.);
+		}
+		let suffix = vertex.eval(self.suffix_direction) / pdf * self.suffix_radiance;
+		(light + suffix, pdf / self.suffix_pdf)
+	}
+}
+
+pub type PtReservoir = Reservoir<PtSample>;
+
+/// Debug views replacing the shaded image with properties of the selected paths
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, FromPrimitive, IntoPrimitive)]
+pub enum PtDebugView {
+	#[default]
+	None,
+	/// the indirect light only, without the direct light of ReSTIR DI
+	Indirect,
+	/// red for paths shifted by random replay only, otherwise a gradient from green to blue with increasing index
+	ReconnectionVertex,
+	/// path length relative to [`PtSettings::max_bounces`]
+	PathLength,
+	/// confidence relative to [`PtSettings::temporal_confidence_cap`]
+	Confidence,
+}
+
+impl PtDebugView {
+	pub const MAX_VALUE: PtDebugView = PtDebugView::Confidence;
+	pub const LEN: u32 = Self::MAX_VALUE as u32 + 1;
+}
+
+unsafe impl BufferStructPlain for PtDebugView {
+	type Transfer = u32;
+
+	unsafe fn write(self) -> Self::Transfer {
+		<u32 as From<Self>>::from(self)
+	}
+
+	unsafe fn read(from: Self::Transfer) -> Self {
+		<Self as num_enum::FromPrimitive>::from_primitive(from)
+	}
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, BufferStruct)]
+pub struct PtSettings {
+	/// maximum amount of vertices following the visible surface
+	pub max_bounces: u32,
+	/// Minimum distance between a vertex and its predecessor for it to become the reconnection vertex. Shorter edges
+	/// are replayed, as reconnecting them would result in large jacobians.
+	pub reconnection_min_distance: f32,
+	/// Minimum perceptual roughness of both a vertex and its predecessor for it to become the reconnection vertex. The
+	/// BRDFs of smoother surfaces are too narrow to be reconnected towards other directions.
+	pub reconnection_min_roughness: f32,
+	pub temporal_reuse: bool,
+	/// the confidence of the history is capped to this multiple of the current reservoir's confidence
+	pub temporal_confidence_cap: f32,
+	/// amount of spatial reuse passes, 0 disables spatial reuse
+	pub spatial_iterations: u32,
+	/// amount of neighbors merged by each spatial pass, up to [`MAX_SPATIAL_NEIGHBORS`]
+	pub spatial_neighbors: u32,
+	/// radius in pixels in which neighbors are selected
+	pub spatial_radius: f32,
+	/// Use the generalized balance heuristic, which shifts every path into every other neighbor, instead of confidence
+	/// weights that ignore differing target functions.
+	pub spatial_unbiased: bool,
+	pub debug_view: PtDebugView,
+}
+
+impl Default for PtSettings {
+	fn default() -> Self {
+		Self {
+			max_bounces: 3,
+			reconnection_min_distance: 0.1,
+			reconnection_min_roughness: 0.3,
+			temporal_reuse: true,
+			temporal_confidence_cap: 20.,
+			spatial_iterations: 1,
+			spatial_neighbors: 3,
+			spatial_radius: 20.,
+			spatial_unbiased: false,
+			debug_view: PtDebugView::default(),
+		}
+	}
+}
+
+/// Whether `vertex` may become the reconnection vertex following `prev`. Both have to be rough enough for their BRDFs
+/// to be evaluated in other directions and sufficiently far apart.
+pub fn is_reconnectable(prev: &DiSurface, vertex: &DiSurface, settings: &PtSettings) -> bool {
+	prev.roughness >= settings.reconnection_min_roughness
+		&& vertex.roughness >= settings.reconnection_min_roughness
+		&& prev.position.distance_squared(vertex.position)
+			> settings.reconnection_min_distance * settings.reconnection_min_distance
+}
+
+/// A path traced from some visible surface, either newly generated or shifted from a base path
+#[derive(Copy, Clone, Debug)]
+pub struct PtPath {
+	/// the path as seen from the visible surface it was traced from, with its [`PtSample::radiance`]
+	pub sample: PtSample,
+	/// The jacobian determinant of the shift from the base path, in primary sample space:
+	/// `p(ω'_{k-1}) G(x'_{k-1}, x_k) / (p(ω_{k-1}) G(x_{k-1}, x_k))` for the reconnection at vertex `k`, 1 for newly
+	/// generated paths and random replay. 0 if the shift failed.
+	pub jacobian: f32,
+}
+
+impl PtPath {
+	const INVALID: Self = Self {
+		sample: PtSample {
+			seed: 0,
+			reconnection_vertex: 0,
+			reconnection_position: Vec3::ZERO,
+			reconnection_normal: Vec3::ZERO,
+			reconnection_albedo: Vec3::ZERO,
+			reconnection_metallic: 0.,
+			reconnection_roughness: 0.,
+			reconnection_light_direction: Vec3::ZERO,
+			reconnection_light_radiance: Vec3::ZERO,
+			suffix_direction: Vec3::ZERO,
+			suffix_pdf: 0.,
+			suffix_radiance: Vec3::ZERO,
+			reconnection_pdf: 0.,
+			path_length: 0,
+			radiance: Vec3::ZERO,
+		},
+		jacobian: 0.,
+	};
+
+	pub fn is_valid(&self) -> bool {
+		self.jacobian > 0.
+	}
+
+	/// The target function `p̂` of all PT reservoirs
+	pub fn target_pdf(&self) -> f32 {
+		if self.is_valid() {
+			luminance(self.sample.radiance)
+		} else {
+			0.
+		}
+	}
+
+	/// `p̂(T(x)) |T'(x)|`, the target function of the shifted path in the primary sample space of the base path
+	pub fn shifted_target_pdf(&self) -> f32 {
+		self.target_pdf() * self.jacobian
+	}
+
+	/// `reservoir` of the base path, with its sample replaced by this shifted path. Merging it requires
+	/// [`Self::target_pdf`] as the target function.
+	pub fn shift_reservoir(&self, reservoir: &PtReservoir) -> PtReservoir {
+		let mut shifted = *reservoir;
+		shifted.sample = self.sample;
+		shifted.contribution_weight *= self.jacobian;
+		shifted
+	}
+}
+
+/// Generate a new path from the visible surface `primary` using the random numbers of `seed`.
+pub fn generate_path(
+	scene: &VisiScene,
+	descriptors: &Descriptors,
+	primary: &DiSurface,
+	seed: u32,
+	settings: &PtSettings,
+) -> PtPath {
+	trace_path(scene, descriptors, primary, seed, settings, None)
+}
+
+/// Shift the `base` path, which was traced from another visible surface, to start at `primary` instead.
+pub fn shift_path(
+	scene: &VisiScene,
+	descriptors: &Descriptors,
+	primary: &DiSurface,
+	base: &PtSample,
+	settings: &PtSettings,
+) -> PtPath {
+	trace_path(scene, descriptors, primary, base.seed, settings, Some(*base))
+}
+
+fn trace_path(
+	scene: &VisiScene,
+	descriptors: &Descriptors,
+	primary: &DiSurface,
+	seed: u32,
+	settings: &PtSettings,
+	base: Option<PtSample>,
+) -> PtPath {
+	let mut rng = Rng::from_seed(seed);
+	let mut sample = PtSample {
+		seed,
+		..PtSample::default()
+	};
+	let mut surface = *primary;
+	// throughput of the path up to the current vertex, or up to the reconnection vertex once it has been found
+	let mut throughput = Vec3::ONE;
+	// throughput of the suffix following the reconnection vertex
+	let mut suffix_throughput = Vec3::ONE;
+	// direction from the reconnection vertex towards its predecessor
+	let mut reconnection_wo = Vec3::Z;
+	let max_vertex = settings.max_bounces + 1;
+	for vertex in 2..=max_vertex {
+		// always consume the same amount of random numbers per vertex, so that replay stays in sync
+		let u_direction = Vec2::new(rng.next_f32(), rng.next_f32());
+		let u_light = Vec4::new(rng.next_f32(), rng.next_f32(), rng.next_f32(), rng.next_f32());
+
+		if let Some(base) = base {
+			if vertex == base.reconnection_vertex {
+				return reconnect(scene, descriptors, &surface, &base, sample, throughput, settings);
+			}
+		}
+
+		let brdf_sample = surface.sample(u_direction);
+		if !brdf_sample.is_valid() {
+			break;
+		}
+		let wi = brdf_sample.wi;
+		let weight = surface.eval(wi) / brdf_sample.pdf;
+		let in_suffix = sample.reconnection_vertex != 0;
+		if vertex - 1 == sample.reconnection_vertex {
+			// the BRDF of the reconnection vertex is evaluated once the path is complete, or for every predecessor
+			// it is reconnected to
+			sample.suffix_direction = wi;
+			sample.suffix_pdf = brdf_sample.pdf;
+		} else if in_suffix {
+			suffix_throughput *= weight;
+		} else {
+			throughput *= weight;
+		}
+
+		let ray = Ray {
+			origin: surface.position + surface.normal * RAY_EPSILON,
+			direction: wi,
+			t_min: 0.,
+			t_max: f32::INFINITY,
+		};
+		let hit = scene.trace_closest(descriptors, ray);
+		if !hit.is_hit() {
+			// light of the environment is gathered by next event estimation only
+			break;
+		}
+		let next = hit_surface(scene, descriptors, ray, hit);
+		sample.path_length = vertex - 1;
+
+		let cos_next = next.normal.dot(-wi);
+		if !in_suffix && cos_next > 0. && is_reconnectable(&surface, &next, settings) {
+			if base.is_some() {
+				// the shifted path would pick an earlier reconnection vertex, so shifting it back wouldn't return the
+				// base path
+				return PtPath::INVALID;
+			}
+			sample.reconnection_vertex = vertex;
+			sample.reconnection_position = next.position;
+			sample.reconnection_normal = next.normal;
+			sample.reconnection_albedo = next.albedo;
+			sample.reconnection_metallic = next.metallic;
+			sample.reconnection_roughness = next.roughness;
+			sample.reconnection_pdf = brdf_sample.pdf * cos_next / surface.position.distance_squared(next.position);
+			reconnection_wo = next.wo;
+			// only the light is sampled, its reflection depends on the predecessor of the reconnection vertex
+			let light = visible_light(scene, descriptors, &next, u_light);
+			sample.reconnection_light_direction = light.direction;
+			sample.reconnection_light_radiance = light.radiance;
+		} else {
+			let radiance = direct_light(scene, descriptors, &next, u_light, false);
+			if in_suffix {
+				sample.suffix_radiance += suffix_throughput * radiance;
+			} else {
+				sample.radiance += throughput * radiance;
+			}
+		}
+		surface = next;
+	}
+
+	if let Some(base) = base {
+		if base.reconnection_vertex != 0 {
+			// the shifted path ended before reaching the reconnection vertex
+			return PtPath::INVALID;
+		}
+	}
+	if sample.reconnection_vertex != 0 {
+		let (radiance, _) = sample.reconnection_radiance(&sample.reconnection_surface(reconnection_wo));
+		sample.radiance += throughput * radiance;
+	}
+	finish(sample, 1.)
+}
+
+/// Connect the replayed prefix ending at `prev` to the reconnection vertex of `base`
+fn reconnect(
+	scene: &VisiScene,
+	descriptors: &Descriptors,
+	prev: &DiSurface,
+	base: &PtSample,
+	mut sample: PtSample,
+	throughput: Vec3,
+	settings: &PtSettings,
+) -> PtPath {
+	let offset = base.reconnection_position - prev.position;
+	let distance_squared = offset.length_squared();
+	let direction = offset / distance_squared.sqrt();
+	let vertex = base.reconnection_surface(-direction);
+	if base.reconnection_pdf <= 0. || !is_reconnectable(prev, &vertex, settings) {
+		return PtPath::INVALID;
+	}
+	let cos_prev = prev.normal.dot(direction);
+	let cos_vertex = vertex.normal.dot(-direction);
+	if cos_prev <= 0. || cos_vertex <= 0. {
+		return PtPath::INVALID;
+	}
+	let direction_pdf = prev.pdf(direction);
+	if direction_pdf <= 0. {
+		return PtPath::INVALID;
+	}
+	let origin = prev.position + prev.normal * RAY_EPSILON;
+	if !scene.is_visible(descriptors, origin, vertex.position + vertex.normal * RAY_EPSILON) {
+		return PtPath::INVALID;
+	}
+	let (reconnection_radiance, suffix_jacobian) = base.reconnection_radiance(&vertex);
+	if suffix_jacobian <= 0. {
+		return PtPath::INVALID;
+	}
+
+	let reconnection_pdf = direction_pdf * cos_vertex / distance_squared;
+	sample = PtSample {
+		seed: sample.seed,
+		reconnection_pdf,
+		radiance: sample.radiance,
+		..*base
+	};
+	sample.radiance += throughput * prev.eval(direction) / direction_pdf * reconnection_radiance;
+	finish(sample, reconnection_pdf / base.reconnection_pdf * suffix_jacobian)
+}
+
+fn finish(mut sample: PtSample, jacobian: f32) -> PtPath {
+	// a single invalid path would poison all reservoirs it is reused in
+	if !(sample.radiance.is_finite() && jacobian.is_finite()) {
+		return PtPath::INVALID;
+	}
+	if !sample.suffix_radiance.is_finite() {
+		sample.suffix_radiance = Vec3::ZERO;
+	}
+	PtPath { sample, jacobian }
+}
+
+/// added to the salts of all [`Rng`]s, so that PT doesn't reuse the random numbers of DI or GI
+pub const PT_RNG_SALT: u32 = 0x200;
+
+/// upper limit of [`PtSettings::spatial_neighbors`]
+pub const MAX_SPATIAL_NEIGHBORS: u32 = 8;
+
+pub const PT_WG_SIZE: UVec2 = UVec2::new(8, 8);
+
+const_assert_eq!(PT_WG_SIZE.x, 8);
+const_assert_eq!(PT_WG_SIZE.y, 8);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::visibility::scene::tests::single_triangle_scene;
+	use rust_gpu_bindless_shaders::descriptor::CpuDescriptors;
+
+	fn surface(position: Vec3, normal: Vec3) -> DiSurface {
+		DiSurface {
+			position,
+			normal,
+			wo: normal,
+			albedo: Vec3::splat(0.8),
+			metallic: 0.,
+			roughness: 1.,
+			emission: Vec3::ZERO,
+		}
+	}
+
+	/// The radiance a path reconnected from `prev` to the reconnection vertex of `base` carries, and its jacobian
+	fn expected_shift(prev: &DiSurface, base: &PtSample) -> (Vec3, f32) {
+		let offset = base.reconnection_position - prev.position;
+		let direction = offset.normalize();
+		let vertex = base.reconnection_surface(-direction);
+		let suffix_pdf = vertex.pdf(base.suffix_direction);
+		let reflected = vertex.eval(base.reconnection_light_direction) * base.reconnection_light_radiance
+			+ vertex.eval(base.suffix_direction) / suffix_pdf * base.suffix_radiance;
+		let radiance = prev.eval(direction) / prev.pdf(direction) * reflected;
+		let reconnection_pdf = prev.pdf(direction) * vertex.normal.dot(-direction) / offset.length_squared();
+		let jacobian = reconnection_pdf / base.reconnection_pdf * suffix_pdf / base.suffix_pdf;
+		(radiance, jacobian)
+	}
+
+	#[test]
+	fn test_reconnection_shift() {
+		let mut cpu = CpuDescriptors::new();
+		let scene = single_triangle_scene(&mut cpu);
+		let descriptors = cpu.descriptors();
+		let settings = PtSettings::default();
+
+		// in front of the triangle at z = -2, with the reconnection vertex straight above the base path's surface
+		let base_surface = surface(Vec3::new(0., 0., -1.), Vec3::Y);
+		let vertex = surface(Vec3::new(0., 1., -1.), -Vec3::Y);
+		let suffix_direction = Vec3::new(0.3, -1., 0.2).normalize();
+		let mut base = PtSample {
+			seed: 42,
+			reconnection_vertex: 2,
+			reconnection_position: vertex.position,
+			reconnection_normal: vertex.normal,
+			reconnection_albedo: vertex.albedo,
+			reconnection_metallic: vertex.metallic,
+			reconnection_roughness: vertex.roughness,
+			reconnection_light_direction: Vec3::new(-0.5, -1., 0.).normalize(),
+			reconnection_light_radiance: Vec3::new(1., 2., 3.),
+			suffix_direction,
+			suffix_pdf: vertex.pdf(suffix_direction),
+			suffix_radiance: Vec3::new(0.5, 0.2, 0.1),
+			// straight above at a distance of 1
+			reconnection_pdf: base_surface.pdf(Vec3::Y),
+			path_length: 2,
+			radiance: Vec3::ZERO,
+		};
+		base.radiance = expected_shift(&base_surface, &base).0;
+
+		let identity = shift_path(&scene, &descriptors, &base_surface, &base, &settings);
+		assert!((identity.jacobian - 1.).abs() < 1e-5, "{}", identity.jacobian);
+		assert!(identity.sample.radiance.abs_diff_eq(base.radiance, 1e-5));
+
+		// both cosines are 1/√2 and the distance is doubled, and the BRDFs of both vertices see different directions
+		let neighbor = surface(Vec3::new(1., 0., -1.), Vec3::Y);
+		let shifted = shift_path(&scene, &descriptors, &neighbor, &base, &settings);
+		let (radiance, jacobian) = expected_shift(&neighbor, &base);
+		assert!(
+			(shifted.jacobian - jacobian).abs() < 1e-5,
+			"{} != {jacobian}",
+			shifted.jacobian
+		);
+		assert!(shifted.sample.radiance.abs_diff_eq(radiance, 1e-5));
+		assert!(!shifted.sample.radiance.abs_diff_eq(base.radiance, 1e-3));
+		assert!((shifted.target_pdf() - luminance(radiance)).abs() < 1e-5);
+		assert_eq!(shifted.sample.suffix_radiance, base.suffix_radiance);
+
+		// occluded by the triangle
+		let occluded = shift_path(
+			&scene,
+			&descriptors,
+			&surface(Vec3::new(0., 0., -3.), Vec3::Y),
+			&base,
+			&settings,
+		);
+		assert!(!occluded.is_valid());
+		assert_eq!(occluded.target_pdf(), 0.);
+
+		// too close to the reconnection vertex
+		let close = shift_path(
+			&scene,
+			&descriptors,
+			&surface(Vec3::new(0., 0.95, -1.), Vec3::Y),
+			&base,
+			&settings,
+		);
+		assert!(!close.is_valid());
+
+		// too smooth to reconnect from
+		let smooth = DiSurface {
+			roughness: 0.1,
+			..neighbor
+		};
+		assert!(!shift_path(&scene, &descriptors, &smooth, &base, &settings).is_valid());
+	}
+
+	#[test]
+	fn test_is_reconnectable() {
+		let settings = PtSettings::default();
+		let prev = surface(Vec3::ZERO, Vec3::Y);
+		let vertex = surface(Vec3::Y, -Vec3::Y);
+		assert!(is_reconnectable(&prev, &vertex, &settings));
+		let smooth = |surface: DiSurface| DiSurface {
+			roughness: settings.reconnection_min_roughness / 2.,
+			..surface
+		};
+		assert!(!is_reconnectable(&smooth(prev), &vertex, &settings));
+		assert!(!is_reconnectable(&prev, &smooth(vertex), &settings));
+	}
+
+	#[test]
+	fn test_random_replay() {
+		let mut cpu = CpuDescriptors::new();
+		let scene = single_triangle_scene(&mut cpu);
+		let descriptors = cpu.descriptors();
+		let settings = PtSettings::default();
+
+		// all rays leaving the triangle escape into the environment, so paths end without a reconnection vertex
+		let primary = surface(Vec3::new(0., 0., -2.), Vec3::Z);
+		let path = generate_path(&scene, &descriptors, &primary, 7, &settings);
+		assert!(path.is_valid());
+		assert_eq!(path.sample.reconnection_vertex, 0);
+		assert_eq!(path.sample.path_length, 0);
+
+		let neighbor = surface(Vec3::new(0.5, 0., -2.), Vec3::Z);
+		let shifted = shift_path(&scene, &descriptors, &neighbor, &path.sample, &settings);
+		assert_eq!(shifted.jacobian, 1.);
+		assert_eq!(shifted.sample.seed, path.sample.seed);
+	}
+}
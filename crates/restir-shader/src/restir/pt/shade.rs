@@ -0,0 +1,84 @@
+//! Add the indirect light of the path selected by each pixel's reservoir onto the direct light already in the output
+//! image, or replace it with one of the [`PtDebugView`]s.
+
+use crate::restir::di::reservoir_index;
+use crate::restir::pt::{PT_WG_SIZE, PtDebugView, PtReservoir, PtSettings};
+use crate::visibility::id::GeometryId;
+use crate::visibility::scene::VisiScene;
+use glam::{UVec3, UVec4, Vec3, Vec3Swizzles, Vec4};
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, Image, Image2d, Image2dU, MutImage, TransientDesc};
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub scene: TransientDesc<'a, Buffer<VisiScene>>,
+	pub packed_vertex_image: TransientDesc<'a, Image<Image2dU>>,
+	pub reservoirs: TransientDesc<'a, Buffer<[PtReservoir]>>,
+	pub output_image: TransientDesc<'a, MutImage<Image2d>>,
+	pub settings: PtSettings,
+}
+
+#[bindless(compute(threads(8, 8)))]
+pub fn pt_shade(
+	#[bindless(descriptors)] descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(workgroup_id)] wg_id: UVec3,
+	#[spirv(local_invocation_id)] inv_id: UVec3,
+) {
+	let scene = param.scene.access(&descriptors).load();
+	let size = scene.camera.viewport_size;
+	let pixel = wg_id.xy() * PT_WG_SIZE + inv_id.xy();
+	if !(pixel.x < size.x && pixel.y < size.y) {
+		return;
+	}
+
+	let packed_geo: UVec4 = param.packed_vertex_image.access(&descriptors).fetch_with_lod(pixel, 0);
+	let geo = GeometryId::from_visibility(packed_geo);
+	let debug_view = param.settings.debug_view;
+	if geo.is_clear && debug_view == PtDebugView::None {
+		return;
+	}
+	let reservoir = param.reservoirs.access(&descriptors).load(reservoir_index(pixel, size));
+	// the radiance of the path was already evaluated at this pixel while resampling
+	let indirect = if reservoir.is_empty() {
+		Vec3::ZERO
+	} else {
+		reservoir.sample.radiance * reservoir.contribution_weight
+	};
+
+	let output_image = param.output_image.access(&descriptors);
+	let color = match debug_view {
+		PtDebugView::None => {
+			let color: Vec4 = output_image.read(pixel);
+			if !indirect.is_finite() {
+				return;
+			}
+			color + Vec4::from((indirect, 0.))
+		}
+		PtDebugView::Indirect => Vec4::from((indirect, 1.)),
+		PtDebugView::ReconnectionVertex => {
+			let vertex = reservoir.sample.reconnection_vertex;
+			let color = if reservoir.is_empty() {
+				Vec3::ZERO
+			} else if vertex == 0 {
+				Vec3::X
+			} else {
+				let t = (vertex - 2) as f32 / u32::max(param.settings.max_bounces.saturating_sub(1), 1) as f32;
+				Vec3::Y.lerp(Vec3::Z, t)
+			};
+			Vec4::from((color, 1.))
+		}
+		PtDebugView::PathLength => {
+			let t = reservoir.sample.path_length as f32 / u32::max(param.settings.max_bounces, 1) as f32;
+			Vec4::from((Vec3::splat(t), 1.))
+		}
+		PtDebugView::Confidence => {
+			let t = reservoir.confidence / f32::max(param.settings.temporal_confidence_cap, 1.);
+			Vec4::from((Vec3::splat(f32::min(t, 1.)), 1.))
+		}
+	};
+	unsafe {
+		output_image.write(pixel, color);
+	}
+}
@@ -0,0 +1,243 @@
+use rust_gpu_bindless_macros::BufferStructPlain;
+
+/// A weighted reservoir holding a single `sample` selected out of a stream of candidates.
+///
+/// All weights follow generalized resampled importance sampling (GRIS), see
+/// https://research.nvidia.com/publication/2022-07_generalized-resampled-importance-sampling-foundations-restir:
+/// Every candidate `x_i` is streamed in with a resampling weight `w_i = m_i(x_i) * p̂(x_i) * W_i`, with `m_i` being its
+/// MIS weight, `p̂` the target function at the pixel owning this reservoir and `W_i` the contribution weight of the
+/// candidate. After [`Self::finalize`], `f(y) * contribution_weight` is an unbiased estimate of the integral of `f`, as
+/// long as all MIS weights of a sample sum up to one and `p̂` is non-zero wherever `f` is.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, BufferStructPlain)]
+pub struct Reservoir<S> {
+	/// the selected sample
+	pub sample: S,
+	/// the target function `p̂(y)` of the selected sample
+	pub target_pdf: f32,
+	/// sum of all resampling weights `w_i`
+	pub weight_sum: f32,
+	/// confidence weight, the amount of candidates this reservoir represents, often referred to as `M`
+	pub confidence: f32,
+	/// unbiased contribution weight `W` of the selected sample, only valid after [`Self::finalize`]
+	pub contribution_weight: f32,
+}
+
+impl<S: Copy + Default> Reservoir<S> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Stream a new candidate into this reservoir, returns true if it got selected.
+	///
+	/// For initial candidates sampled from some source pdf `p(x)`, `contribution_weight` is `1 / p(x)` and
+	/// `mis_weight` is usually `1 / M` with `M` being the total amount of candidates generated.
+	pub fn update(&mut self, sample: S, target_pdf: f32, contribution_weight: f32, mis_weight: f32, rand: f32) -> bool {
+		self.confidence += 1.;
+		self.stream(sample, target_pdf, mis_weight * target_pdf * contribution_weight, rand)
+	}
+
+	/// Merge the finalized `other` reservoir into this one, returns true if its sample got selected.
+	///
+	/// `target_pdf` must be the target function of `other.sample` evaluated at the pixel owning **this** reservoir. See
+	/// [`confidence_mis_weight`] and [`balance_heuristic`] for the `mis_weight`.
+	pub fn merge(&mut self, other: &Self, target_pdf: f32, mis_weight: f32, rand: f32) -> bool {
+		self.confidence += other.confidence;
+		self.stream(
+			other.sample,
+			target_pdf,
+			mis_weight * target_pdf * other.contribution_weight,
+			rand,
+		)
+	}
+
+	fn stream(&mut self, sample: S, target_pdf: f32, weight: f32, rand: f32) -> bool {
+		if weight.is_nan() || weight <= 0. {
+			return false;
+		}
+		self.weight_sum += weight;
+		if rand * self.weight_sum < weight {
+			self.sample = sample;
+			self.target_pdf = target_pdf;
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Calculate the unbiased contribution weight `W = weight_sum / p̂(y)` once all candidates have been streamed in.
+	pub fn finalize(&mut self) {
+		self.contribution_weight = if self.target_pdf > 0. {
+			self.weight_sum / self.target_pdf
+		} else {
+			0.
+		};
+	}
+
+	/// Limit the confidence weight to `max_confidence`, to prevent an old reservoir from dominating all future merges.
+	/// As the contribution weight is already normalized, this does not affect the current estimate.
+	pub fn cap_confidence(&mut self, max_confidence: f32) {
+		self.confidence = f32::min(self.confidence, max_confidence);
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.weight_sum <= 0.
+	}
+}
+
+/// MIS weight of a reservoir with confidence `confidence` merged with others totalling `confidence_sum`, the
+/// generalization of the `1 / M` weights from the original ReSTIR paper. Only unbiased if all merged reservoirs share the
+/// same target function, use [`balance_heuristic`] otherwise.
+pub fn confidence_mis_weight(confidence: f32, confidence_sum: f32) -> f32 {
+	if confidence_sum > 0. {
+		confidence / confidence_sum
+	} else {
+		0.
+	}
+}
+
+/// Generalized balance heuristic `m_i(y) = c_i p̂_i(y) / Σ_j c_j p̂_j(y)` for a sample `y` taken from reservoir `i`, with
+/// `c_j` being the confidence of reservoir `j` and `p̂_j` the target function at the pixel of reservoir `j`.
+pub fn balance_heuristic(confidence: f32, target_pdf: f32, confidence_target_pdf_sum: f32) -> f32 {
+	if confidence_target_pdf_sum > 0. {
+		confidence * target_pdf / confidence_target_pdf_sum
+	} else {
+		0.
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::restir::reservoir::{Reservoir, balance_heuristic, confidence_mis_weight};
+
+	/// xorshift, good enough for tests
+	struct TestRng(u32);
+
+	impl TestRng {
+		fn next(&mut self) -> f32 {
+			self.0 ^= self.0 << 13;
+			self.0 ^= self.0 >> 17;
+			self.0 ^= self.0 << 5;
+			(self.0 >> 8) as f32 / (1 << 24) as f32
+		}
+	}
+
+	/// integrand, integral over [0, 1] is 1.5
+	fn f(x: f32) -> f32 {
+		3. * x * x + 0.5
+	}
+
+	const INTEGRAL: f32 = 1.5;
+	const TRIALS: u32 = 100_000;
+
+	/// RIS over `candidates` uniform samples in [0, 1] with target function `target`
+	fn ris(rng: &mut TestRng, candidates: u32, target: impl Fn(f32) -> f32) -> Reservoir<f32> {
+		let mut reservoir = Reservoir::new();
+		for _ in 0..candidates {
+			let x = rng.next();
+			reservoir.update(x, target(x), 1., 1. / candidates as f32, rng.next());
+		}
+		reservoir.finalize();
+		reservoir
+	}
+
+	#[test]
+	fn test_ris_unbiased() {
+		let mut rng = TestRng(0x1234_5678);
+		let mut sum = 0.;
+		for _ in 0..TRIALS {
+			let r = ris(&mut rng, 8, |x| x + 0.1);
+			sum += f(r.sample) * r.contribution_weight;
+		}
+		let estimate = sum / TRIALS as f32;
+		assert!((estimate - INTEGRAL).abs() < 0.02, "estimate {estimate}");
+	}
+
+	#[test]
+	fn test_ris_selects_proportional_to_target() {
+		let mut rng = TestRng(0xdead_beef);
+		let mut upper_half = 0;
+		for _ in 0..TRIALS {
+			let r = ris(&mut rng, 64, |x| if x < 0.5 { 1. } else { 3. });
+			upper_half += (r.sample >= 0.5) as u32;
+		}
+		let ratio = upper_half as f32 / TRIALS as f32;
+		assert!((ratio - 0.75).abs() < 0.02, "ratio {ratio}");
+	}
+
+	#[test]
+	fn test_merge_confidence_unbiased() {
+		let mut rng = TestRng(0xcafe_f00d);
+		let target = |x: f32| x + 0.1;
+		let mut sum = 0.;
+		for _ in 0..TRIALS {
+			let a = ris(&mut rng, 4, target);
+			let b = ris(&mut rng, 12, target);
+			let confidence_sum = a.confidence + b.confidence;
+			let mut merged = Reservoir::new();
+			merged.merge(
+				&a,
+				target(a.sample),
+				confidence_mis_weight(a.confidence, confidence_sum),
+				rng.next(),
+			);
+			merged.merge(
+				&b,
+				target(b.sample),
+				confidence_mis_weight(b.confidence, confidence_sum),
+				rng.next(),
+			);
+			merged.finalize();
+			assert_eq!(merged.confidence, 16.);
+			sum += f(merged.sample) * merged.contribution_weight;
+		}
+		let estimate = sum / TRIALS as f32;
+		assert!((estimate - INTEGRAL).abs() < 0.02, "estimate {estimate}");
+	}
+
+	#[test]
+	fn test_merge_balance_heuristic_unbiased() {
+		let mut rng = TestRng(0x0bad_cafe);
+		// the neighbor's domain can't produce any samples in [0, 0.5)
+		let target_a = |x: f32| x + 0.1;
+		let target_b = |x: f32| if x < 0.5 { 0. } else { 1. - x + 0.1 };
+		let mut sum = 0.;
+		for _ in 0..TRIALS {
+			let a = ris(&mut rng, 4, target_a);
+			let b = ris(&mut rng, 4, target_b);
+			let denominator = |y: f32| a.confidence * target_a(y) + b.confidence * target_b(y);
+			let mut merged = Reservoir::new();
+			let mis_a = balance_heuristic(a.confidence, target_a(a.sample), denominator(a.sample));
+			merged.merge(&a, target_a(a.sample), mis_a, rng.next());
+			let mis_b = balance_heuristic(b.confidence, target_b(b.sample), denominator(b.sample));
+			merged.merge(&b, target_a(b.sample), mis_b, rng.next());
+			merged.finalize();
+			sum += f(merged.sample) * merged.contribution_weight;
+		}
+		let estimate = sum / TRIALS as f32;
+		assert!((estimate - INTEGRAL).abs() < 0.02, "estimate {estimate}");
+	}
+
+	#[test]
+	fn test_cap_confidence() {
+		let mut rng = TestRng(0x4242_4242);
+		let mut r = ris(&mut rng, 32, |x| x + 0.1);
+		let contribution_weight = r.contribution_weight;
+		r.cap_confidence(20.);
+		assert_eq!(r.confidence, 20.);
+		assert_eq!(r.contribution_weight, contribution_weight);
+		r.cap_confidence(40.);
+		assert_eq!(r.confidence, 20.);
+	}
+
+	#[test]
+	fn test_empty() {
+		let mut r = Reservoir::<f32>::new();
+		assert!(r.is_empty());
+		r.update(0.5, 0., 1., 1., 0.5);
+		r.finalize();
+		assert!(r.is_empty());
+		assert_eq!(r.confidence, 1.);
+		assert_eq!(r.contribution_weight, 0.);
+	}
+}
@@ -0,0 +1,6 @@
+use glam::{Vec3, vec3};
+
+/// Relative luminance of a linear Rec. 709 color
+pub fn luminance(color: Vec3) -> f32 {
+	color.dot(vec3(0.2126, 0.7152, 0.0722))
+}
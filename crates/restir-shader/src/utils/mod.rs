@@ -1,3 +1,5 @@
 pub mod affine;
 pub mod affine_transform;
+pub mod color;
+pub mod ray;
 pub mod view_range;
@@ -0,0 +1,141 @@
+use glam::{Affine3A, Vec3};
+
+/// Offset applied to the start and end of rays between two surfaces, to prevent self-intersection.
+pub const RAY_EPSILON: f32 = 1e-3;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Ray {
+	pub origin: Vec3,
+	/// not necessarily normalized, `t` is measured in multiples of it
+	pub direction: Vec3,
+	pub t_min: f32,
+	pub t_max: f32,
+}
+
+impl Ray {
+	/// A ray from `from` to `to`, excluding both end points
+	pub fn between(from: Vec3, to: Vec3) -> Self {
+		let direction = to - from;
+		let epsilon = RAY_EPSILON / direction.length();
+		Self {
+			origin: from,
+			direction,
+			t_min: epsilon,
+			t_max: 1. - epsilon,
+		}
+	}
+
+	/// Transform this ray, keeping `t` of all intersections intact
+	pub fn transform(&self, transform: Affine3A) -> Self {
+		Self {
+			origin: transform.transform_point3(self.origin),
+			direction: transform.transform_vector3(self.direction),
+			t_min: self.t_min,
+			t_max: self.t_max,
+		}
+	}
+
+	/// Möller-Trumbore ray triangle intersection, returns `t` of the intersection or [`f32::INFINITY`] if the
+	/// triangle was missed. Triangles are hit from both sides.
+	pub fn intersect_triangle(&self, p: [Vec3; 3]) -> f32 {
+		let e1 = p[1] - p[0];
+		let e2 = p[2] - p[0];
+		let pvec = self.direction.cross(e2);
+		let det = e1.dot(pvec);
+		if f32::abs(det) < 1e-12 {
+			return f32::INFINITY;
+		}
+		let inv_det = 1. / det;
+		let tvec = self.origin - p[0];
+		let u = tvec.dot(pvec) * inv_det;
+		if !(0. ..=1.).contains(&u) {
+			return f32::INFINITY;
+		}
+		let qvec = tvec.cross(e1);
+		let v = self.direction.dot(qvec) * inv_det;
+		if v < 0. || u + v > 1. {
+			return f32::INFINITY;
+		}
+		let t = e2.dot(qvec) * inv_det;
+		if self.t_min <= t && t <= self.t_max {
+			t
+		} else {
+			f32::INFINITY
+		}
+	}
+
+	pub fn hits_triangle(&self, p: [Vec3; 3]) -> bool {
+		self.intersect_triangle(p) != f32::INFINITY
+	}
+
+	/// Slab test against the axis aligned box from `min` to `max`, returns `t` where the ray enters the box, clamped to
+	/// `t_min`, or [`f32::INFINITY`] if the box was missed.
+	pub fn intersect_aabb(&self, min: Vec3, max: Vec3) -> f32 {
+		let inv_direction = self.direction.recip();
+		let t0 = (min - self.origin) * inv_direction;
+		let t1 = (max - self.origin) * inv_direction;
+		let t_near = f32::max(t0.min(t1).max_element(), self.t_min);
+		let t_far = f32::min(t0.max(t1).min_element(), self.t_max);
+		if t_near <= t_far { t_near } else { f32::INFINITY }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::utils::ray::Ray;
+	use glam::{Affine3A, Vec3, vec3};
+
+	const TRIANGLE: [Vec3; 3] = [vec3(-1., -1., 0.), vec3(1., -1., 0.), vec3(0., 1., 0.)];
+
+	#[test]
+	fn test_hit() {
+		let ray = Ray::between(vec3(0., 0., 2.), vec3(0., 0., -2.));
+		assert!((ray.intersect_triangle(TRIANGLE) - 0.5).abs() < 1e-6);
+		let reversed = Ray::between(vec3(0., 0., -2.), vec3(0., 0., 2.));
+		assert!(reversed.hits_triangle(TRIANGLE), "backfaces are hit");
+	}
+
+	#[test]
+	fn test_miss() {
+		let outside = Ray::between(vec3(2., 0., 2.), vec3(2., 0., -2.));
+		assert!(!outside.hits_triangle(TRIANGLE));
+		let parallel = Ray::between(vec3(-2., 0., 0.), vec3(2., 0., 0.));
+		assert!(!parallel.hits_triangle(TRIANGLE));
+	}
+
+	#[test]
+	fn test_t_range() {
+		let short = Ray::between(vec3(0., 0., 2.), vec3(0., 0., 1.));
+		assert!(!short.hits_triangle(TRIANGLE), "ends before the triangle");
+		let behind = Ray::between(vec3(0., 0., -1.), vec3(0., 0., -2.));
+		assert!(!behind.hits_triangle(TRIANGLE), "starts behind the triangle");
+		let on_surface = Ray::between(vec3(0., 0., 0.), vec3(0., 0., 2.));
+		assert!(!on_surface.hits_triangle(TRIANGLE), "starts on the triangle");
+	}
+
+	#[test]
+	fn test_aabb() {
+		let (min, max) = (vec3(-1., -1., -1.), vec3(1., 1., 1.));
+		let ray = Ray::between(vec3(0.5, 0., 3.), vec3(0.5, 0., -3.));
+		assert!((ray.intersect_aabb(min, max) - 1. / 3.).abs() < 1e-6);
+		let inside = Ray::between(vec3(0., 0., 0.), vec3(0., 0., 3.));
+		assert_eq!(inside.intersect_aabb(min, max), inside.t_min, "starts inside the box");
+		let outside = Ray::between(vec3(2., 0., 3.), vec3(2., 0., -3.));
+		assert_eq!(outside.intersect_aabb(min, max), f32::INFINITY);
+		let short = Ray::between(vec3(0., 0., 3.), vec3(0., 0., 2.));
+		assert_eq!(short.intersect_aabb(min, max), f32::INFINITY, "ends before the box");
+		let flat = (vec3(-1., -1., 0.), vec3(1., 1., 0.));
+		assert!(
+			(ray.intersect_aabb(flat.0, flat.1) - 0.5).abs() < 1e-6,
+			"boxes of axis aligned triangles"
+		);
+	}
+
+	#[test]
+	fn test_transform() {
+		let ray = Ray::between(vec3(10., 0., 2.), vec3(10., 0., -2.));
+		let local_from_world = Affine3A::from_translation(vec3(10., 0., 0.)).inverse();
+		let t = ray.transform(local_from_world).intersect_triangle(TRIANGLE);
+		assert!((t - 0.5).abs() < 1e-6);
+	}
+}
@@ -77,6 +77,24 @@ impl BarycentricDeriv {
 	}
 }
 
+/// An attribute interpolated at a pixel, with its screen space derivatives towards the next pixel in x and y
+#[derive(Copy, Clone, Debug)]
+pub struct Interpolated<V> {
+	pub value: V,
+	pub ddx: V,
+	pub ddy: V,
+}
+
+impl BarycentricDeriv {
+	pub fn interpolate<V: Copy + Mul<f32, Output = V> + Add<V, Output = V>>(&self, attr: [V; 3]) -> Interpolated<V> {
+		Interpolated {
+			value: self.lambda.interpolate(attr),
+			ddx: self.ddx.interpolate(attr),
+			ddy: self.ddy.interpolate(attr),
+		}
+	}
+}
+
 impl Barycentric {
 	pub fn interpolate<V: Copy + Mul<f32, Output = V> + Add<V, Output = V>>(&self, attr: [V; 3]) -> V {
 		attr[0] * self.x + attr[1] * self.y + attr[2] * self.z
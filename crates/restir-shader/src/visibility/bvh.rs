@@ -0,0 +1,155 @@
+//! A bounding volume hierarchy for tracing rays in compute shaders, without requiring hardware ray tracing. Each
+//! [`VisiModel`](crate::visibility::scene::VisiModel) has a BVH over its triangles and the
+//! [`VisiScene`](crate::visibility::scene::VisiScene) a BVH over its instances.
+
+use crate::utils::ray::Ray;
+use glam::Vec3;
+use rust_gpu_bindless_macros::{BufferStruct, BufferStructPlain};
+use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, StrongDesc};
+
+/// Maximum depth of any leaf, which bounds the traversal stack
+pub const BVH_MAX_DEPTH: u32 = 32;
+
+/// A node of the BVH, bounding all primitives below it
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, BufferStructPlain)]
+pub struct BvhNode {
+	pub bounds_min: Vec3,
+	pub bounds_max: Vec3,
+	/// inner node: 0
+	/// leaf: the number of primitives
+	pub primitive_count: u32,
+	/// inner node: the index of the left child, directly followed by the right child
+	/// leaf: the index of the first primitive in [`Bvh::primitives`]
+	pub index: u32,
+}
+
+impl BvhNode {
+	/// The root of a BVH without any primitives
+	pub const EMPTY: Self = Self {
+		bounds_min: Vec3::INFINITY,
+		bounds_max: Vec3::NEG_INFINITY,
+		primitive_count: 0,
+		index: 0,
+	};
+
+	pub fn is_leaf(&self) -> bool {
+		self.primitive_count > 0
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.bounds_min.cmpgt(self.bounds_max).any()
+	}
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct BvhHit {
+	/// `t` of the hit along the ray, or [`f32::INFINITY`] if nothing was hit
+	pub t: f32,
+	pub primitive: u32,
+}
+
+impl BvhHit {
+	pub const MISS: Self = Self {
+		t: f32::INFINITY,
+		primitive: 0,
+	};
+
+	pub fn is_hit(&self) -> bool {
+		self.t != f32::INFINITY
+	}
+}
+
+/// Trace `ray` through a BVH, visiting the nearer child first. `load_node` fetches a node with the root at index 0 and
+/// `load_primitive` an entry of the primitive list of the leaves. `intersect` is called with a primitive and the ray,
+/// shortened to the closest hit so far, and returns `t` of the hit or [`f32::INFINITY`] if it was missed.
+///
+/// With `any_hit`, returns the first hit found instead of the closest.
+pub fn traverse_bvh(
+	load_node: impl Fn(u32) -> BvhNode,
+	load_primitive: impl Fn(u32) -> u32,
+	ray: Ray,
+	any_hit: bool,
+	mut intersect: impl FnMut(u32, Ray) -> f32,
+) -> BvhHit {
+	let mut ray = ray;
+	let mut hit = BvhHit::MISS;
+	let mut node = load_node(0);
+	if node.is_empty() || ray.intersect_aabb(node.bounds_min, node.bounds_max) == f32::INFINITY {
+		return hit;
+	}
+
+	// holds at most one sibling per level above the current node
+	let mut stack = [0; BVH_MAX_DEPTH as usize];
+	let mut stack_len = 0;
+	loop {
+		if node.is_leaf() {
+			for i in node.index..node.index + node.primitive_count {
+				let primitive = load_primitive(i);
+				let t = intersect(primitive, ray);
+				if t != f32::INFINITY {
+					hit = BvhHit { t, primitive };
+					if any_hit {
+						return hit;
+					}
+					ray.t_max = t;
+				}
+			}
+		} else {
+			let left = load_node(node.index);
+			let right = load_node(node.index + 1);
+			let t_left = ray.intersect_aabb(left.bounds_min, left.bounds_max);
+			let t_right = ray.intersect_aabb(right.bounds_min, right.bounds_max);
+			let (near, t_near, far_index, t_far) = if t_left <= t_right {
+				(left, t_left, node.index + 1, t_right)
+			} else {
+				(right, t_right, node.index, t_left)
+			};
+			if t_near != f32::INFINITY {
+				if t_far != f32::INFINITY {
+					stack[stack_len] = far_index;
+					stack_len += 1;
+				}
+				node = near;
+				continue;
+			}
+		}
+
+		if stack_len == 0 {
+			break;
+		}
+		stack_len -= 1;
+		node = load_node(stack[stack_len]);
+	}
+	hit
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, BufferStruct)]
+pub struct Bvh {
+	/// all nodes, always contains at least the root
+	pub nodes: StrongDesc<Buffer<[BvhNode]>>,
+	/// The primitives of all leaves. Contains a single unused entry if there are none, as buffers can't be empty.
+	pub primitives: StrongDesc<Buffer<[u32]>>,
+}
+
+impl Bvh {
+	/// Trace `ray` through this BVH, see [`traverse_bvh`]
+	pub fn traverse(
+		&self,
+		descriptors: &Descriptors,
+		ray: Ray,
+		any_hit: bool,
+		intersect: impl FnMut(u32, Ray) -> f32,
+	) -> BvhHit {
+		let nodes = self.nodes.access(descriptors);
+		let primitives = self.primitives.access(descriptors);
+		traverse_bvh(
+			|i| nodes.load(i as usize),
+			|i| primitives.load(i as usize),
+			ray,
+			any_hit,
+			intersect,
+		)
+	}
+}
@@ -1,11 +1,17 @@
 use core::error::Error;
 use core::fmt::{Debug, Display, Formatter};
+use glam::{UVec2, UVec4, Vec4Swizzles};
 use rust_gpu_bindless_macros::BufferStructPlain;
 use static_assertions::const_assert_eq;
 
+/// Bits of the triangle id in a [`PackedGeometryId`], models are split to not exceed them
 pub const TRIANGLE_BITS: u32 = 20;
+/// Bits of the instance id in a [`PackedGeometryId`], scenes with more instances require a [`WideGeometryId`]
 pub const INSTANCE_BITS: u32 = 12;
 
+/// The largest instance or triangle id, `!0` is reserved to mark cleared pixels of a [`WideGeometryId`]
+pub const MAX_ID: u32 = !0 - 1;
+
 const TRIANGLE_MASK: u32 = (1 << TRIANGLE_BITS) - 1;
 const INSTANCE_MASK: u32 = (1 << INSTANCE_BITS) - 1;
 
@@ -30,18 +36,14 @@ impl Error for TriangleIdOutOfRange {}
 
 impl Display for TriangleIdOutOfRange {
 	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-		write!(
-			f,
-			"Triangle id can't fit into {TRIANGLE_BITS} bits (max {TRIANGLE_MASK})"
-		)
+		write!(f, "Triangle id can't be larger than {MAX_ID}")
 	}
 }
 
 impl TriangleId {
-	/// Creates a new `TriangleId`. Returns `None` if the version is too large to be represented by [`TRIANGLE_BITS`]
-	/// bits.
+	/// Creates a new `TriangleId`. Returns `None` if the id is larger than [`MAX_ID`].
 	pub const fn new(id: u32) -> Result<Self, TriangleIdOutOfRange> {
-		if id == id & TRIANGLE_MASK {
+		if id <= MAX_ID {
 			unsafe { Ok(Self::new_unchecked(id)) }
 		} else {
 			Err(TriangleIdOutOfRange)
@@ -74,18 +76,14 @@ impl Error for InstanceIdOutOfRange {}
 
 impl Display for InstanceIdOutOfRange {
 	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-		write!(
-			f,
-			"Instance id can't fit into {INSTANCE_BITS} bits (max {INSTANCE_MASK})"
-		)
+		write!(f, "Instance id can't be larger than {MAX_ID}")
 	}
 }
 
 impl InstanceId {
-	/// Creates a new `Instance`. Returns `None` if the version is too large to be represented by [`INSTANCE_BITS`]
-	/// bits.
+	/// Creates a new `Instance`. Returns `None` if the id is larger than [`MAX_ID`].
 	pub const fn new(id: u32) -> Result<Self, InstanceIdOutOfRange> {
-		if id == id & INSTANCE_MASK {
+		if id <= MAX_ID {
 			unsafe { Ok(Self::new_unchecked(id)) }
 		} else {
 			Err(InstanceIdOutOfRange)
@@ -116,11 +114,29 @@ pub struct GeometryId {
 }
 
 impl GeometryId {
+	pub const CLEAR: Self = PackedGeometryId::CLEAR.unpack();
+
+	/// Truncates ids that exceed [`INSTANCE_BITS`] or [`TRIANGLE_BITS`]
 	pub const fn pack(&self) -> PackedGeometryId {
 		PackedGeometryId::new(self.instance_id, self.triangle_id)
 	}
+
+	pub const fn pack_wide(&self) -> WideGeometryId {
+		WideGeometryId::new(self.instance_id, self.triangle_id)
+	}
+
+	/// Decode a texel of the visibility buffer, which may either be in the `R32_UINT` format of [`PackedGeometryId`]
+	/// or the `R32G32_UINT` format of [`WideGeometryId`].
+	pub fn from_visibility(texel: UVec4) -> Self {
+		if texel.y == 0 {
+			PackedGeometryId::from_u32(texel.x).unpack()
+		} else {
+			WideGeometryId(texel.xy()).unpack()
+		}
+	}
 }
 
+/// Instance and triangle ids packed into 32 bits, limited to [`INSTANCE_BITS`] and [`TRIANGLE_BITS`] respectively
 #[repr(transparent)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, BufferStructPlain)]
 pub struct PackedGeometryId(u32);
@@ -162,3 +178,73 @@ impl Debug for PackedGeometryId {
 		f.debug_tuple("PackedGeometryId").field(&self.unpack()).finish()
 	}
 }
+
+/// Instance and triangle ids with 32 bits each, stored in a `R32G32_UINT` visibility buffer. The triangle id is
+/// offset by one, so that the second channel can't be 0 and [`GeometryId::from_visibility`] can distinguish it from a
+/// `R32_UINT` [`PackedGeometryId`], whose second channel always reads as 0.
+#[repr(transparent)]
+#[derive(Copy, Clone, Hash, Eq, PartialEq)]
+pub struct WideGeometryId(UVec2);
+const_assert_eq!(size_of::<WideGeometryId>(), 8);
+
+impl WideGeometryId {
+	pub const CLEAR: Self = Self(UVec2::splat(!0));
+
+	pub const fn new(instance_id: InstanceId, triangle_id: TriangleId) -> Self {
+		Self(UVec2::new(instance_id.0, triangle_id.0 + 1))
+	}
+
+	pub const fn unpack(&self) -> GeometryId {
+		GeometryId {
+			instance_id: InstanceId(self.0.x),
+			triangle_id: TriangleId(self.0.y.wrapping_sub(1)),
+			is_clear: self.is_clear(),
+		}
+	}
+
+	/// [`MAX_ID`] excludes `!0` as an instance id
+	pub const fn is_clear(&self) -> bool {
+		self.0.x == Self::CLEAR.0.x
+	}
+
+	pub const fn to_uvec2(&self) -> UVec2 {
+		self.0
+	}
+}
+
+impl Debug for WideGeometryId {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		f.debug_tuple("WideGeometryId").field(&self.unpack()).finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn geo(instance_id: u32, triangle_id: u32) -> GeometryId {
+		GeometryId {
+			instance_id: InstanceId::new(instance_id).unwrap(),
+			triangle_id: TriangleId::new(triangle_id).unwrap(),
+			is_clear: false,
+		}
+	}
+
+	#[test]
+	fn test_from_visibility() {
+		let r32 = |packed: PackedGeometryId| UVec4::new(packed.to_u32(), 0, 0, 1);
+		let r32g32 = |wide: WideGeometryId| UVec4::from((wide.to_uvec2(), 0, 1));
+
+		for geo in [geo(0, 0), geo(42, 1337), geo(INSTANCE_MASK, TRIANGLE_MASK - 1)] {
+			assert_eq!(GeometryId::from_visibility(r32(geo.pack())), geo);
+			assert_eq!(GeometryId::from_visibility(r32g32(geo.pack_wide())), geo);
+		}
+		let wide = geo(MAX_ID, MAX_ID);
+		assert_eq!(GeometryId::from_visibility(r32g32(wide.pack_wide())), wide);
+
+		assert!(GeometryId::from_visibility(r32(PackedGeometryId::CLEAR)).is_clear);
+		assert!(GeometryId::from_visibility(r32g32(WideGeometryId::CLEAR)).is_clear);
+		assert!(InstanceId::new(!0).is_err());
+		assert!(TriangleId::new(!0).is_err());
+	}
+}
@@ -1,4 +1,6 @@
 pub mod barycentric;
+pub mod bvh;
 pub mod id;
+pub mod output;
 pub mod raster;
 pub mod scene;
@@ -0,0 +1,39 @@
+//! Copy the linear radiance of a frame into the output image, where debug views may be drawn on top of it, while the
+//! radiance image itself stays untouched for dumping it.
+
+use glam::{UVec2, UVec3, Vec3Swizzles, Vec4};
+use rust_gpu_bindless_macros::{BufferStruct, bindless};
+use rust_gpu_bindless_shaders::descriptor::{Descriptors, Image2d, MutImage, TransientDesc};
+use static_assertions::const_assert_eq;
+
+#[repr(C)]
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	/// RGBA32F linear radiance rendered this frame
+	pub radiance: TransientDesc<'a, MutImage<Image2d>>,
+	pub output_image: TransientDesc<'a, MutImage<Image2d>>,
+	pub size: UVec2,
+}
+
+pub const OUTPUT_WG_SIZE: UVec2 = UVec2::new(8, 8);
+
+const_assert_eq!(OUTPUT_WG_SIZE.x, 8);
+const_assert_eq!(OUTPUT_WG_SIZE.y, 8);
+
+#[bindless(compute(threads(8, 8)))]
+pub fn visi_output_copy(
+	#[bindless(descriptors)] descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(workgroup_id)] wg_id: UVec3,
+	#[spirv(local_invocation_id)] inv_id: UVec3,
+) {
+	let pixel = wg_id.xy() * OUTPUT_WG_SIZE + inv_id.xy();
+	if !(pixel.x < param.size.x && pixel.y < param.size.y) {
+		return;
+	}
+
+	let color: Vec4 = param.radiance.access(&descriptors).read(pixel);
+	unsafe {
+		param.output_image.access(&descriptors).write(pixel, color);
+	}
+}
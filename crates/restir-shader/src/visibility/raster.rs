@@ -1,4 +1,4 @@
-use crate::visibility::id::{InstanceId, PackedGeometryId, TriangleId};
+use crate::visibility::id::{InstanceId, PackedGeometryId, TriangleId, WideGeometryId};
 use crate::visibility::scene::{VisiModel, VisiScene};
 use glam::Vec4;
 use rust_gpu_bindless_macros::{BufferStruct, bindless};
@@ -43,3 +43,15 @@ pub fn visibility_frag(
 	let triangle_id = unsafe { TriangleId::new_unchecked(primitive_id) };
 	*out_packed_geometry_id = PackedGeometryId::new(vtx_instance_id, triangle_id);
 }
+
+/// [`visibility_frag`] for `R32G32_UINT` visibility buffers, supporting scenes with more instances
+#[bindless(fragment())]
+pub fn visibility_frag_wide(
+	#[bindless(param)] _param: &Param<'static>,
+	#[spirv(flat, primitive_id)] primitive_id: u32,
+	#[spirv(flat)] vtx_instance_id: InstanceId,
+	out_wide_geometry_id: &mut WideGeometryId,
+) {
+	let triangle_id = unsafe { TriangleId::new_unchecked(primitive_id) };
+	*out_wide_geometry_id = WideGeometryId::new(vtx_instance_id, triangle_id);
+}
@@ -1,9 +1,17 @@
 use crate::camera::Camera;
+use crate::light::Light;
+use crate::light::alias::AliasEntry;
+use crate::light::environment::EnvironmentMap;
+use crate::light::tree::LightTree;
+use crate::material::pbr::{PbrMaterial, PbrSurface};
+use crate::material::system::MaterialType;
 use crate::utils::affine_transform::AffineTransform;
-use crate::visibility::barycentric::BarycentricDeriv;
+use crate::utils::ray::Ray;
+use crate::visibility::barycentric::{Barycentric, BarycentricDeriv, Interpolated};
+use crate::visibility::bvh::Bvh;
 use crate::visibility::id::{GeometryId, InstanceId, TriangleId};
 use core::ops::{Deref, DerefMut};
-use glam::{UVec2, Vec3};
+use glam::{UVec2, Vec2, Vec3, Vec4, Vec4Swizzles};
 use rust_gpu_bindless_macros::BufferStruct;
 use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, StrongDesc};
 
@@ -11,7 +19,21 @@ use rust_gpu_bindless_shaders::descriptor::{Buffer, Descriptors, StrongDesc};
 #[derive(Copy, Clone, Debug, BufferStruct)]
 pub struct VisiScene {
 	pub instances: StrongDesc<Buffer<[VisiInstance]>>,
+	/// BVH over the world space bounds of all `instances`
+	pub bvh: Bvh,
+	/// Contains a single unused entry if there are no lights, as buffers can't be empty. Use `light_count` instead of
+	/// the buffer length.
+	pub lights: StrongDesc<Buffer<[Light]>>,
+	pub light_count: u32,
+	/// alias table over all `lights`, proportional to their emitted power
+	pub light_alias_table: StrongDesc<Buffer<[AliasEntry]>>,
+	pub light_tree: LightTree,
+	/// indexed by the [`VisiInstanceInfo::material_id`] of each instance
+	pub materials: StrongDesc<Buffer<[PbrMaterial]>>,
+	pub environment: EnvironmentMap,
 	pub camera: Camera,
+	/// the camera of the previous frame, or `camera` if there is none
+	pub prev_camera: Camera,
 }
 
 #[repr(C)]
@@ -23,6 +45,7 @@ pub struct VisiTriangle {
 	pub model: VisiModel,
 	pub indices: VisiIndices,
 	pub vertices: [VisiVertex; 3],
+	pub attributes: [VisiVertexAttributes; 3],
 	pub barycentric: BarycentricDeriv,
 }
 
@@ -31,7 +54,61 @@ impl VisiScene {
 		self.instances.access(descriptors).load(instance_id.to_usize())
 	}
 
+	/// The material of instances with `material_id`
+	pub fn load_material(&self, descriptors: &Descriptors, material_id: u32) -> PbrMaterial {
+		self.materials.access(descriptors).load(material_id as usize)
+	}
+
+	/// The surface of `tri` with the material of its instance, as the material shaders resolve it
+	pub fn load_surface(&self, descriptors: &Descriptors, tri: &VisiTriangle) -> PbrSurface {
+		match tri.instance.material_type {
+			MaterialType::Pbr => self
+				.load_material(descriptors, tri.instance.material_id)
+				.surface(descriptors, tri),
+		}
+	}
+
 	pub fn load_triangle(&self, descriptors: &Descriptors, pixel: UVec2, geo: GeometryId) -> VisiTriangle {
+		self.load_triangle_with(descriptors, pixel, geo, |instance, vertices| {
+			let clip_pos_fn = |i: usize| {
+				self.camera
+					.transform_vertex(instance.world_from_local, vertices[i].0)
+					.clip_space
+			};
+			let clip_pos = [clip_pos_fn(0), clip_pos_fn(1), clip_pos_fn(2)];
+			let viewport = self.camera.viewport_size.as_vec2();
+			let pixel_ndc = pixel.as_vec2() / viewport * 2. - 1.;
+			BarycentricDeriv::calculate_from(clip_pos[0], clip_pos[1], clip_pos[2], pixel_ndc, viewport)
+		})
+	}
+
+	/// The triangle hit by `ray`. There is no pixel footprint to derive texture coordinate derivatives from, so they
+	/// are zero and textures are sampled at their full resolution.
+	pub fn load_hit_triangle(&self, descriptors: &Descriptors, ray: Ray, hit: SceneHit) -> VisiTriangle {
+		self.load_triangle_with(descriptors, UVec2::ZERO, hit.geo, |instance, vertices| {
+			let world_from_local = instance.world_from_local.affine;
+			let p = vertices.map(|v| world_from_local.transform_point3(v.0));
+			let e1 = p[1] - p[0];
+			let e2 = p[2] - p[0];
+			let cross = e1.cross(e2);
+			let to_hit = ray.origin + ray.direction * hit.t - p[0];
+			let u = to_hit.cross(e2).dot(cross) / cross.length_squared();
+			let v = e1.cross(to_hit).dot(cross) / cross.length_squared();
+			BarycentricDeriv {
+				lambda: Barycentric(Vec3::new(1. - u - v, u, v)),
+				ddx: Barycentric(Vec3::ZERO),
+				ddy: Barycentric(Vec3::ZERO),
+			}
+		})
+	}
+
+	fn load_triangle_with(
+		&self,
+		descriptors: &Descriptors,
+		pixel: UVec2,
+		geo: GeometryId,
+		barycentric: impl FnOnce(&VisiInstance, &[VisiVertex; 3]) -> BarycentricDeriv,
+	) -> VisiTriangle {
 		let instance = self.load_instance(descriptors, geo.instance_id);
 		let model = instance.model.access(descriptors).load();
 		let indices = model.load_indices(descriptors, geo.triangle_id);
@@ -40,15 +117,12 @@ impl VisiScene {
 			model.load_vertex(descriptors, indices[1]),
 			model.load_vertex(descriptors, indices[2]),
 		];
-		let clip_pos_fn = |i: usize| {
-			self.camera
-				.transform_vertex(instance.world_from_local, vertices[i].0)
-				.clip_space
-		};
-		let clip_pos = [clip_pos_fn(0), clip_pos_fn(1), clip_pos_fn(2)];
-		let viewport = self.camera.viewport_size.as_vec2();
-		let pixel_ndc = pixel.as_vec2() / viewport * 2. - 1.;
-		let barycentric = BarycentricDeriv::calculate_from(clip_pos[0], clip_pos[1], clip_pos[2], pixel_ndc, viewport);
+		let attributes = [
+			model.load_attributes(descriptors, indices[0]),
+			model.load_attributes(descriptors, indices[1]),
+			model.load_attributes(descriptors, indices[2]),
+		];
+		let barycentric = barycentric(&instance, &vertices);
 		VisiTriangle {
 			pixel,
 			geo,
@@ -56,9 +130,155 @@ impl VisiScene {
 			model,
 			indices,
 			vertices,
+			attributes,
 			barycentric,
 		}
 	}
+
+	/// Screen space motion in pixels of the surface of `tri` since the previous frame, pointing from its position in
+	/// this frame to where it was in the previous frame. `None` if it was behind the previous camera.
+	pub fn motion_vector(&self, tri: &VisiTriangle) -> Option<Vec2> {
+		let local_position = tri.local_position();
+		let clip = self
+			.camera
+			.transform_vertex(tri.instance.world_from_local, local_position)
+			.clip_space;
+		let prev_clip = self
+			.prev_camera
+			.transform_vertex(tri.instance.prev_world_from_local, local_position)
+			.clip_space;
+		if clip.w <= 0. || prev_clip.w <= 0. {
+			return None;
+		}
+		let screen = (clip.xy() / clip.w + 1.) / 2. * self.camera.viewport_size.as_vec2();
+		let prev_screen = (prev_clip.xy() / prev_clip.w + 1.) / 2. * self.prev_camera.viewport_size.as_vec2();
+		Some(prev_screen - screen)
+	}
+
+	/// The closest triangle hit by the world space `ray`
+	pub fn trace_closest(&self, descriptors: &Descriptors, ray: Ray) -> SceneHit {
+		self.trace(descriptors, ray, false)
+	}
+
+	/// Whether `ray` hits any triangle, which is cheaper than finding the closest one
+	pub fn trace_any(&self, descriptors: &Descriptors, ray: Ray) -> bool {
+		self.trace(descriptors, ray, true).is_hit()
+	}
+
+	/// Whether the straight line between `from` and `to` is unoccluded by any triangle in the scene.
+	pub fn is_visible(&self, descriptors: &Descriptors, from: Vec3, to: Vec3) -> bool {
+		!self.trace_any(descriptors, Ray::between(from, to))
+	}
+
+	fn trace(&self, descriptors: &Descriptors, ray: Ray, any_hit: bool) -> SceneHit {
+		let instances = self.instances.access(descriptors);
+		let mut triangle_id = 0;
+		let hit = self.bvh.traverse(descriptors, ray, any_hit, |instance_index, ray| {
+			let instance = instances.load(instance_index as usize);
+			let local_ray = ray.transform(instance.world_from_local.affine.inverse());
+			let model = instance.model.access(descriptors).load();
+			let triangles = model.triangles.access(descriptors);
+			let hit = model
+				.bvh
+				.traverse(descriptors, local_ray, any_hit, |triangle_index, local_ray| {
+					let indices = triangles.load(triangle_index as usize);
+					local_ray.intersect_triangle(indices.map(|i| model.load_vertex(descriptors, i).0))
+				});
+			if hit.is_hit() {
+				triangle_id = hit.primitive;
+			}
+			hit.t
+		});
+
+		if hit.is_hit() {
+			// ids are bounds checked when the scene is built, and not packed as they may exceed `PackedGeometryId`
+			let geo = unsafe {
+				GeometryId {
+					instance_id: InstanceId::new_unchecked(hit.primitive),
+					triangle_id: TriangleId::new_unchecked(triangle_id),
+					is_clear: false,
+				}
+			};
+			SceneHit { t: hit.t, geo }
+		} else {
+			SceneHit {
+				t: f32::INFINITY,
+				geo: GeometryId::CLEAR,
+			}
+		}
+	}
+}
+
+/// The result of tracing a ray through a [`VisiScene`]
+#[derive(Copy, Clone, Debug)]
+pub struct SceneHit {
+	/// `t` of the hit along the ray, or [`f32::INFINITY`] if nothing was hit
+	pub t: f32,
+	/// the triangle that was hit, or [`GeometryId::is_clear`] if nothing was hit
+	pub geo: GeometryId,
+}
+
+impl SceneHit {
+	pub fn is_hit(&self) -> bool {
+		!self.geo.is_clear
+	}
+}
+
+impl VisiTriangle {
+	pub fn world_vertex_positions(&self) -> [Vec3; 3] {
+		let world_from_local = self.instance.world_from_local.affine;
+		[
+			world_from_local.transform_point3(self.vertices[0].0),
+			world_from_local.transform_point3(self.vertices[1].0),
+			world_from_local.transform_point3(self.vertices[2].0),
+		]
+	}
+
+	pub fn local_position(&self) -> Vec3 {
+		self.barycentric.lambda.interpolate(self.vertices.map(|v| v.0))
+	}
+
+	pub fn world_position(&self) -> Vec3 {
+		self.barycentric.lambda.interpolate(self.world_vertex_positions())
+	}
+
+	/// normalized geometric normal in world space, following the counter-clockwise winding order
+	pub fn world_geometric_normal(&self) -> Vec3 {
+		let p = self.world_vertex_positions();
+		(p[1] - p[0]).cross(p[2] - p[0]).normalize()
+	}
+
+	/// normalized interpolated vertex normal in world space, or the geometric normal if it is degenerate
+	pub fn world_normal(&self) -> Vec3 {
+		let normal = self.barycentric.lambda.interpolate(self.attributes.map(|a| a.normal));
+		let normal = (self.instance.world_from_local.normal * normal).normalize_or_zero();
+		if normal == Vec3::ZERO {
+			self.world_geometric_normal()
+		} else {
+			normal
+		}
+	}
+
+	/// normalized interpolated tangent in world space, with `w` being the sign of the bitangent
+	/// `cross(normal, tangent) * w`
+	pub fn world_tangent(&self) -> Vec4 {
+		let tangent = self
+			.barycentric
+			.lambda
+			.interpolate(self.attributes.map(|a| a.tangent.xyz()));
+		let tangent = self
+			.instance
+			.world_from_local
+			.affine
+			.transform_vector3(tangent)
+			.normalize_or_zero();
+		Vec4::from((tangent, self.attributes[0].tangent.w))
+	}
+
+	/// interpolated texture coordinates and their screen space derivatives, for selecting mip levels
+	pub fn tex_coord(&self) -> Interpolated<Vec2> {
+		self.barycentric.interpolate(self.attributes.map(|a| a.tex_coord))
+	}
 }
 
 #[repr(C)]
@@ -86,6 +306,25 @@ impl DerefMut for VisiInstance {
 #[derive(Copy, Clone, Debug, BufferStruct)]
 pub struct VisiInstanceInfo {
 	pub world_from_local: AffineTransform,
+	/// `world_from_local` of the previous frame, for computing [`VisiScene::motion_vector`]
+	pub prev_world_from_local: AffineTransform,
+	/// selects the material shader of all pixels covered by this instance, see
+	/// [`classify`](crate::material::system::classify)
+	pub material_type: MaterialType,
+	/// index into the materials of `material_type`, [`VisiScene::materials`] for [`MaterialType::Pbr`]
+	pub material_id: u32,
+}
+
+impl VisiInstanceInfo {
+	/// An instance with a [`MaterialType::Pbr`] material that hasn't moved since the previous frame
+	pub fn new(world_from_local: AffineTransform, material_id: u32) -> Self {
+		Self {
+			world_from_local,
+			prev_world_from_local: world_from_local,
+			material_type: MaterialType::Pbr,
+			material_id,
+		}
+	}
 }
 
 #[repr(C)]
@@ -93,6 +332,10 @@ pub struct VisiInstanceInfo {
 pub struct VisiModel {
 	pub triangles: StrongDesc<Buffer<[VisiIndices]>>,
 	pub vertices: StrongDesc<Buffer<[VisiVertex]>>,
+	/// attributes of each of the `vertices`, separate so rasterization only needs to read positions
+	pub attributes: StrongDesc<Buffer<[VisiVertexAttributes]>>,
+	/// BVH over all `triangles`
+	pub bvh: Bvh,
 }
 
 #[repr(C)]
@@ -121,8 +364,206 @@ impl VisiModel {
 	pub fn load_vertex(&self, descriptors: &Descriptors, vertex_id: u32) -> VisiVertex {
 		self.vertices.access(descriptors).load(vertex_id as usize)
 	}
+
+	pub fn load_attributes(&self, descriptors: &Descriptors, vertex_id: u32) -> VisiVertexAttributes {
+		self.attributes.access(descriptors).load(vertex_id as usize)
+	}
 }
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, BufferStruct)]
 pub struct VisiVertex(pub Vec3);
+
+/// Shading attributes of a [`VisiVertex`]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, BufferStruct)]
+pub struct VisiVertexAttributes {
+	/// normalized vertex normal in model space
+	pub normal: Vec3,
+	/// normalized tangent in model space, with `w` being the sign of the bitangent `cross(normal, tangent) * w`
+	pub tangent: Vec4,
+	pub tex_coord: Vec2,
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+	use crate::light::tree::LightTreeNode;
+	use crate::visibility::bvh::BvhNode;
+	use core::f32::consts::FRAC_PI_2;
+	use glam::{Affine3A, vec3};
+	use rust_gpu_bindless_shaders::descriptor::{CpuDescriptors, CpuImage, CpuSampler};
+
+	pub const VIEWPORT: UVec2 = UVec2::splat(16);
+	const DEPTH: f32 = 2.;
+
+	/// A BVH with only a root leaf containing a single primitive
+	fn single_leaf_bvh(cpu: &mut CpuDescriptors, bounds_min: Vec3, bounds_max: Vec3) -> Bvh {
+		Bvh {
+			nodes: cpu.alloc_slice([BvhNode {
+				bounds_min,
+				bounds_max,
+				primitive_count: 1,
+				index: 0,
+			}]),
+			primitives: cpu.alloc_slice([0]),
+		}
+	}
+
+	/// A camera at the origin looking down -Z, with the entire viewport covered by a single triangle `DEPTH` away
+	pub fn single_triangle_scene(cpu: &mut CpuDescriptors) -> VisiScene {
+		let vertices = [
+			vec3(-100., -100., -DEPTH),
+			vec3(100., -100., -DEPTH),
+			vec3(0., 100., -DEPTH),
+		];
+		let (bounds_min, bounds_max) = (vec3(-100., -100., -DEPTH), vec3(100., 100., -DEPTH));
+		let model = VisiModel {
+			triangles: cpu.alloc_slice([VisiIndices([0, 1, 2])]),
+			vertices: cpu.alloc_slice(vertices.map(VisiVertex)),
+			attributes: cpu.alloc_slice([VisiVertexAttributes::default(); 3]),
+			bvh: single_leaf_bvh(cpu, bounds_min, bounds_max),
+		};
+		let instance = VisiInstance {
+			model: cpu.alloc_buffer(model),
+			info: VisiInstanceInfo::new(AffineTransform::new(Affine3A::IDENTITY), 0),
+		};
+		let camera =
+			Camera::new_perspective_rh_y_flip(VIEWPORT, FRAC_PI_2, 0.1, 100., AffineTransform::new(Affine3A::IDENTITY));
+		let material = diffuse_material(cpu, Vec3::splat(0.8));
+		VisiScene {
+			instances: cpu.alloc_slice([instance]),
+			bvh: single_leaf_bvh(cpu, bounds_min, bounds_max),
+			lights: cpu.alloc_slice([Light::directional(Vec3::Z, Vec3::ZERO)]),
+			light_count: 0,
+			light_alias_table: cpu.alloc_slice([AliasEntry::default()]),
+			light_tree: LightTree {
+				nodes: cpu.alloc_slice([LightTreeNode::default()]),
+				bit_trails: cpu.alloc_slice([0]),
+				infinite_lights: cpu.alloc_slice([0]),
+				infinite_light_count: 0,
+			},
+			materials: cpu.alloc_slice([material]),
+			environment: EnvironmentMap {
+				image: cpu.alloc_image(CpuImage::new(UVec2::ONE, [Vec4::ZERO])),
+				marginal_cdf: cpu.alloc_slice([0., 1.]),
+				conditional_cdf: cpu.alloc_slice([0., 1.]),
+				size: UVec2::ONE,
+			},
+			camera,
+			prev_camera: camera,
+		}
+	}
+
+	/// A rough dielectric material of a single color without any textures
+	pub fn diffuse_material(cpu: &mut CpuDescriptors, base_color: Vec3) -> PbrMaterial {
+		let white = cpu.alloc_image(CpuImage::new(UVec2::ONE, [Vec4::ONE]));
+		PbrMaterial {
+			base_color: white,
+			metallic: white,
+			roughness: white,
+			normal: cpu.alloc_image(CpuImage::new(UVec2::ONE, [Vec4::new(0.5, 0.5, 1., 1.)])),
+			emissive: white,
+			sampler: cpu.alloc_sampler(CpuSampler::default()),
+			base_color_factor: Vec4::from((base_color, 1.)),
+			emissive_factor: Vec3::ZERO,
+			metallic_factor: 0.,
+			roughness_factor: 1.,
+			normal_scale: 1.,
+		}
+	}
+
+	fn geo() -> GeometryId {
+		GeometryId {
+			instance_id: InstanceId::new(0).unwrap(),
+			triangle_id: TriangleId::new(0).unwrap(),
+			is_clear: false,
+		}
+	}
+
+	#[test]
+	fn test_load_triangle() {
+		let mut cpu = CpuDescriptors::new();
+		let scene = single_triangle_scene(&mut cpu);
+		let descriptors = cpu.descriptors();
+
+		let center = scene.load_triangle(&descriptors, VIEWPORT / 2, geo());
+		assert_eq!(*center.indices, [0, 1, 2]);
+		assert!(center.world_position().distance(vec3(0., 0., -DEPTH)) < 1e-4);
+		assert!(center.world_normal().distance(Vec3::Z) < 1e-6);
+		let lambda = center.barycentric.lambda.0;
+		assert!((lambda.x + lambda.y + lambda.z - 1.).abs() < 1e-6);
+
+		// a fov of 90° spans the viewport over [-DEPTH, DEPTH]
+		let corner = scene.load_triangle(&descriptors, UVec2::ZERO, geo()).world_position();
+		assert!((corner.x + DEPTH).abs() < 1e-4);
+		assert!((corner.y.abs() - DEPTH).abs() < 1e-4);
+		assert!((corner.z + DEPTH).abs() < 1e-4);
+	}
+
+	#[test]
+	fn test_load_hit_triangle() {
+		let mut cpu = CpuDescriptors::new();
+		let scene = single_triangle_scene(&mut cpu);
+		let descriptors = cpu.descriptors();
+
+		let ray = Ray {
+			origin: vec3(1., 0.5, 0.),
+			direction: -Vec3::Z,
+			t_min: 0.,
+			t_max: f32::INFINITY,
+		};
+		let hit = scene.trace_closest(&descriptors, ray);
+		let tri = scene.load_hit_triangle(&descriptors, ray, hit);
+		assert_eq!(tri.geo, hit.geo);
+		assert!(tri.world_position().distance(vec3(1., 0.5, -DEPTH)) < 1e-4);
+		let lambda = tri.barycentric.lambda.0;
+		assert!((lambda.element_sum() - 1.).abs() < 1e-6);
+		// without a pixel footprint the texture coordinates have no derivatives
+		assert_eq!(tri.tex_coord().ddx, Vec2::ZERO);
+	}
+
+	#[test]
+	fn test_motion_vector() {
+		let mut cpu = CpuDescriptors::new();
+		let mut scene = single_triangle_scene(&mut cpu);
+		let descriptors = cpu.descriptors();
+		let mut tri = scene.load_triangle(&descriptors, VIEWPORT / 2, geo());
+		assert!(scene.motion_vector(&tri).unwrap().length() < 1e-4);
+
+		// the instance moved by 1 along +X, which spans 4 pixels at `DEPTH`
+		tri.instance.info.prev_world_from_local = AffineTransform::new(Affine3A::from_translation(vec3(-1., 0., 0.)));
+		let motion = scene.motion_vector(&tri).unwrap();
+		assert!(motion.distance(Vec2::new(-4., 0.)) < 1e-3, "{motion}");
+
+		// the camera moved behind the surface
+		scene.prev_camera.view_from_world = AffineTransform::new(Affine3A::from_translation(vec3(0., 0., -2. * DEPTH)));
+		assert!(scene.motion_vector(&tri).is_none());
+	}
+
+	#[test]
+	fn test_is_visible() {
+		let mut cpu = CpuDescriptors::new();
+		let scene = single_triangle_scene(&mut cpu);
+		let descriptors = cpu.descriptors();
+		assert!(scene.is_visible(&descriptors, Vec3::ZERO, vec3(0., 0., -DEPTH + 1.)));
+		assert!(!scene.is_visible(&descriptors, Vec3::ZERO, vec3(0., 0., -DEPTH - 1.)));
+		assert!(!scene.is_visible(&descriptors, vec3(1., 1., 0.), vec3(-1., 0., -DEPTH - 1.)));
+	}
+
+	#[test]
+	fn test_trace_closest() {
+		let mut cpu = CpuDescriptors::new();
+		let scene = single_triangle_scene(&mut cpu);
+		let descriptors = cpu.descriptors();
+
+		let hit = scene.trace_closest(&descriptors, Ray::between(Vec3::ZERO, vec3(0., 0., -2. * DEPTH)));
+		assert!(hit.is_hit());
+		assert_eq!(hit.geo, geo());
+		assert!((hit.t - 0.5).abs() < 1e-6);
+
+		let miss = scene.trace_closest(&descriptors, Ray::between(Vec3::ZERO, vec3(0., 0., DEPTH)));
+		assert!(!miss.is_hit());
+		assert_eq!(miss.t, f32::INFINITY);
+	}
+}
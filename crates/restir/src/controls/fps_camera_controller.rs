@@ -55,6 +55,14 @@ impl FpsCameraController {
 		Self::default()
 	}
 
+	/// Move the camera to the position and view direction of `world_from_camera`, ignoring any roll
+	pub fn look_from(&mut self, world_from_camera: Affine3A) {
+		let forward = world_from_camera.transform_vector3(Vec3::NEG_Z).normalize();
+		self.position = Vec3::from(world_from_camera.translation);
+		self.rotation_yaw = f32::atan2(-forward.x, -forward.z);
+		self.rotation_pitch = f32::asin(forward.y.clamp(-1., 1.));
+	}
+
 	pub fn handle_input(&mut self, event: &Event<()>, focus: bool) {
 		match event {
 			Event::WindowEvent {
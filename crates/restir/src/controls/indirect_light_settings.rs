@@ -0,0 +1,54 @@
+use crate::controls::restir_gi_settings::RestirGiSettings;
+use crate::controls::restir_pt_settings::RestirPtSettings;
+use crate::visibility::renderer::IndirectLight;
+use egui::Ui;
+
+/// The variant of [`IndirectLight`] selected in the ui
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum IndirectLightMode {
+	#[default]
+	None,
+	Gi,
+	Pt,
+}
+
+impl IndirectLightMode {
+	pub const ALL: [IndirectLightMode; 3] = [IndirectLightMode::None, IndirectLightMode::Gi, IndirectLightMode::Pt];
+}
+
+#[derive(Debug, Default)]
+pub struct IndirectLightSettings {
+	pub mode: IndirectLightMode,
+	pub gi: RestirGiSettings,
+	pub pt: RestirPtSettings,
+}
+
+impl IndirectLightSettings {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn get(&self) -> IndirectLight {
+		match self.mode {
+			IndirectLightMode::None => IndirectLight::None,
+			IndirectLightMode::Gi => IndirectLight::Gi(self.gi.get()),
+			IndirectLightMode::Pt => IndirectLight::Pt(self.pt.get()),
+		}
+	}
+
+	pub fn ui(&mut self, ui: &mut Ui) {
+		ui.strong("Indirect Light:");
+		egui::ComboBox::from_id_salt(concat!(file!(), line!()))
+			.selected_text(format!("{:?}", self.mode))
+			.show_ui(ui, |ui| {
+				for x in IndirectLightMode::ALL {
+					ui.selectable_value(&mut self.mode, x, format!("{:?}", x));
+				}
+			});
+		match self.mode {
+			IndirectLightMode::None => (),
+			IndirectLightMode::Gi => self.gi.ui(ui),
+			IndirectLightMode::Pt => self.pt.ui(ui),
+		}
+	}
+}
@@ -2,4 +2,9 @@ pub mod app_focus;
 pub mod delta_time;
 pub mod fps_camera_controller;
 pub mod fps_ui;
+pub mod indirect_light_settings;
+pub mod render_mode_settings;
+pub mod restir_di_settings;
+pub mod restir_gi_settings;
+pub mod restir_pt_settings;
 pub mod visi_debug_selector;
@@ -0,0 +1,55 @@
+use crate::visibility::renderer::RenderMode;
+use egui::Ui;
+use restir_shader::random::SamplerType;
+use restir_shader::reference::ReferenceSettings;
+
+#[derive(Debug, Default)]
+pub struct RenderModeSettings {
+	pub mode: RenderMode,
+	pub reference: ReferenceSettings,
+}
+
+impl RenderModeSettings {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn mode(&self) -> RenderMode {
+		self.mode
+	}
+
+	pub fn reference(&self) -> ReferenceSettings {
+		self.reference
+	}
+
+	pub fn ui(&mut self, ui: &mut Ui) {
+		ui.strong("Render Mode:");
+		egui::ComboBox::from_id_salt(concat!(file!(), line!()))
+			.selected_text(format!("{:?}", self.mode))
+			.show_ui(ui, |ui| {
+				for x in RenderMode::ALL {
+					ui.selectable_value(&mut self.mode, x, format!("{:?}", x));
+				}
+			});
+		let reference_enabled = self.mode == RenderMode::Reference;
+		ui.add_enabled(
+			reference_enabled,
+			egui::Slider::new(&mut self.reference.max_bounces, 1..=16).text("max bounces"),
+		);
+		ui.add_enabled(
+			reference_enabled,
+			egui::Slider::new(&mut self.reference.max_samples, 0..=65536)
+				.logarithmic(true)
+				.text("max samples (0 = unlimited)"),
+		);
+		ui.add_enabled_ui(reference_enabled, |ui| {
+			egui::ComboBox::from_label("sampler")
+				.selected_text(format!("{:?}", self.reference.sampler))
+				.show_ui(ui, |ui| {
+					for x in (0..SamplerType::LEN).map(SamplerType::from) {
+						ui.selectable_value(&mut self.reference.sampler, x, format!("{:?}", x));
+					}
+				});
+		});
+	}
+}
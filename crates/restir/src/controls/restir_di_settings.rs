@@ -0,0 +1,48 @@
+use egui::Ui;
+use restir_shader::restir::di::{DiSettings, LightSampling, MAX_SPATIAL_NEIGHBORS};
+
+#[derive(Debug, Default)]
+pub struct RestirDiSettings {
+	pub s: DiSettings,
+}
+
+impl RestirDiSettings {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn get(&self) -> DiSettings {
+		self.s
+	}
+
+	pub fn ui(&mut self, ui: &mut Ui) {
+		ui.strong("ReSTIR DI:");
+		ui.add(egui::Slider::new(&mut self.s.initial_candidates, 1..=128).text("initial candidates"));
+		egui::ComboBox::from_label("light sampling")
+			.selected_text(format!("{:?}", self.s.light_sampling))
+			.show_ui(ui, |ui| {
+				for x in (0..LightSampling::LEN).map(LightSampling::from) {
+					ui.selectable_value(&mut self.s.light_sampling, x, format!("{:?}", x));
+				}
+			});
+		ui.checkbox(&mut self.s.temporal_reuse, "temporal reuse");
+		ui.add_enabled(
+			self.s.temporal_reuse,
+			egui::Slider::new(&mut self.s.temporal_confidence_cap, 1. ..=50.).text("temporal M-cap"),
+		);
+		ui.add(egui::Slider::new(&mut self.s.spatial_iterations, 0..=4).text("spatial iterations"));
+		let spatial_enabled = self.s.spatial_iterations > 0;
+		ui.add_enabled(
+			spatial_enabled,
+			egui::Slider::new(&mut self.s.spatial_neighbors, 1..=MAX_SPATIAL_NEIGHBORS).text("spatial neighbors"),
+		);
+		ui.add_enabled(
+			spatial_enabled,
+			egui::Slider::new(&mut self.s.spatial_radius, 1. ..=100.).text("spatial radius"),
+		);
+		ui.add_enabled(
+			spatial_enabled,
+			egui::Checkbox::new(&mut self.s.spatial_unbiased, "unbiased spatial reuse"),
+		);
+	}
+}
@@ -0,0 +1,40 @@
+use egui::Ui;
+use restir_shader::restir::gi::{GiSettings, MAX_SPATIAL_NEIGHBORS};
+
+#[derive(Debug, Default)]
+pub struct RestirGiSettings {
+	pub s: GiSettings,
+}
+
+impl RestirGiSettings {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn get(&self) -> GiSettings {
+		self.s
+	}
+
+	pub fn ui(&mut self, ui: &mut Ui) {
+		ui.strong("ReSTIR GI:");
+		ui.checkbox(&mut self.s.temporal_reuse, "temporal reuse");
+		ui.add_enabled(
+			self.s.temporal_reuse,
+			egui::Slider::new(&mut self.s.temporal_confidence_cap, 1. ..=50.).text("temporal M-cap"),
+		);
+		ui.add(egui::Slider::new(&mut self.s.spatial_iterations, 0..=4).text("spatial iterations"));
+		let spatial_enabled = self.s.spatial_iterations > 0;
+		ui.add_enabled(
+			spatial_enabled,
+			egui::Slider::new(&mut self.s.spatial_neighbors, 1..=MAX_SPATIAL_NEIGHBORS).text("spatial neighbors"),
+		);
+		ui.add_enabled(
+			spatial_enabled,
+			egui::Slider::new(&mut self.s.spatial_radius, 1. ..=100.).text("spatial radius"),
+		);
+		ui.add_enabled(
+			spatial_enabled,
+			egui::Checkbox::new(&mut self.s.spatial_unbiased, "unbiased spatial reuse"),
+		);
+	}
+}
@@ -0,0 +1,50 @@
+use egui::Ui;
+use restir_shader::restir::pt::{MAX_SPATIAL_NEIGHBORS, PtDebugView, PtSettings};
+
+#[derive(Debug, Default)]
+pub struct RestirPtSettings {
+	pub s: PtSettings,
+}
+
+impl RestirPtSettings {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn get(&self) -> PtSettings {
+		self.s
+	}
+
+	pub fn ui(&mut self, ui: &mut Ui) {
+		ui.strong("ReSTIR PT:");
+		ui.add(egui::Slider::new(&mut self.s.max_bounces, 1..=8).text("max bounces"));
+		ui.add(egui::Slider::new(&mut self.s.reconnection_min_distance, 0. ..=1.).text("min reconnection distance"));
+		ui.add(egui::Slider::new(&mut self.s.reconnection_min_roughness, 0. ..=1.).text("min reconnection roughness"));
+		ui.checkbox(&mut self.s.temporal_reuse, "temporal reuse");
+		ui.add_enabled(
+			self.s.temporal_reuse,
+			egui::Slider::new(&mut self.s.temporal_confidence_cap, 1. ..=50.).text("temporal M-cap"),
+		);
+		ui.add(egui::Slider::new(&mut self.s.spatial_iterations, 0..=4).text("spatial iterations"));
+		let spatial_enabled = self.s.spatial_iterations > 0;
+		ui.add_enabled(
+			spatial_enabled,
+			egui::Slider::new(&mut self.s.spatial_neighbors, 1..=MAX_SPATIAL_NEIGHBORS).text("spatial neighbors"),
+		);
+		ui.add_enabled(
+			spatial_enabled,
+			egui::Slider::new(&mut self.s.spatial_radius, 1. ..=100.).text("spatial radius"),
+		);
+		ui.add_enabled(
+			spatial_enabled,
+			egui::Checkbox::new(&mut self.s.spatial_unbiased, "unbiased spatial reuse"),
+		);
+		egui::ComboBox::from_label("PT debug view")
+			.selected_text(format!("{:?}", self.s.debug_view))
+			.show_ui(ui, |ui| {
+				for x in (0..PtDebugView::LEN).map(PtDebugView::from) {
+					ui.selectable_value(&mut self.s.debug_view, x, format!("{:?}", x));
+				}
+			});
+	}
+}
@@ -1,9 +1,13 @@
 use rust_gpu_bindless::platform::ash::Debuggers;
 
 pub mod controls;
+pub mod light;
 pub mod main_loop;
 pub mod material;
 pub mod model;
+pub mod random;
+pub mod reference;
+pub mod restir;
 pub mod shader;
 pub mod visibility;
 
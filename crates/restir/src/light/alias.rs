@@ -0,0 +1,97 @@
+use restir_shader::light::alias::AliasEntry;
+
+/// Build an alias table using Vose's method, to be sampled with
+/// [`sample_alias`](restir_shader::light::alias::sample_alias). If all weights are zero or any is not finite, all
+/// entries are sampled uniformly instead.
+pub fn build_alias_table(weights: &[f32]) -> Vec<AliasEntry> {
+	let len = weights.len();
+	let sum = weights.iter().map(|w| f32::max(*w, 0.) as f64).sum::<f64>();
+	let weights = if sum > 0. && sum.is_finite() {
+		weights
+			.iter()
+			.map(|w| f32::max(*w, 0.) as f64 / sum)
+			.collect::<Vec<_>>()
+	} else {
+		vec![1. / len as f64; len]
+	};
+
+	// probabilities scaled by len, so that the average is 1
+	let mut scaled = weights.iter().map(|p| p * len as f64).collect::<Vec<_>>();
+	let (mut small, mut large): (Vec<_>, Vec<_>) = (0..len).partition(|i| scaled[*i] < 1.);
+	let mut table = (0..len)
+		.map(|i| AliasEntry {
+			threshold: 1.,
+			alias: i as u32,
+			pdf: weights[i] as f32,
+			alias_pdf: weights[i] as f32,
+		})
+		.collect::<Vec<_>>();
+	while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+		small.pop();
+		table[s].threshold = scaled[s] as f32;
+		table[s].alias = l as u32;
+		table[s].alias_pdf = weights[l] as f32;
+		scaled[l] -= 1. - scaled[s];
+		if scaled[l] < 1. {
+			large.pop();
+			small.push(l);
+		}
+	}
+	// any remaining entries are only left over due to rounding errors and always select themselves
+	table
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use restir_shader::light::alias::sample_alias;
+
+	fn histogram(table: &[AliasEntry], samples: u32) -> Vec<f64> {
+		let mut histogram = vec![0.; table.len()];
+		for i in 0..samples {
+			let u = (i as f32 + 0.5) / samples as f32;
+			let (index, pdf) = sample_alias(table.len() as u32, u, |i| table[i as usize]);
+			assert_eq!(pdf, table[index as usize].pdf);
+			histogram[index as usize] += 1. / samples as f64;
+		}
+		histogram
+	}
+
+	fn assert_distribution(weights: &[f32], expected: &[f64]) {
+		let table = build_alias_table(weights);
+		for (entry, expected) in table.iter().zip(expected) {
+			assert!((entry.pdf as f64 - expected).abs() < 1e-6, "{table:?}");
+		}
+		let histogram = histogram(&table, 1 << 20);
+		for (i, (actual, expected)) in histogram.iter().zip(expected).enumerate() {
+			assert!(
+				(actual - expected).abs() < 1e-3,
+				"entry {i}: expected {expected} got {actual}, histogram {histogram:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn test_proportional() {
+		let weights = [1., 2., 3., 4., 0., 10.];
+		let sum = weights.iter().sum::<f32>() as f64;
+		let expected = weights.iter().map(|w| *w as f64 / sum).collect::<Vec<_>>();
+		assert_distribution(&weights, &expected);
+	}
+
+	#[test]
+	fn test_single_dominant() {
+		assert_distribution(&[0., 0., 5., 0.], &[0., 0., 1., 0.]);
+	}
+
+	#[test]
+	fn test_uniform_fallback() {
+		assert_distribution(&[0., 0., 0., 0.], &[0.25; 4]);
+		assert_distribution(&[1., f32::INFINITY], &[0.5; 2]);
+	}
+
+	#[test]
+	fn test_empty() {
+		assert!(build_alias_table(&[]).is_empty());
+	}
+}
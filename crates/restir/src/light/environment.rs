@@ -0,0 +1,279 @@
+use glam::{UVec2, Vec3};
+use restir_shader::light::environment::EnvironmentMap;
+use restir_shader::utils::color::luminance;
+use rust_gpu_bindless::descriptor::{
+	Bindless, BindlessBufferCreateInfo, BindlessBufferUsage, BindlessImageCreateInfo, BindlessImageUsage, Buffer,
+	Extent, Format, Image, Image2d, RCDesc, RCDescExt,
+};
+use rust_gpu_bindless::pipeline::{MutBufferAccessExt, MutImageAccessExt, TransferRead, TransferWrite};
+use std::f32::consts::PI;
+use std::path::Path;
+
+/// An equirectangular environment map uploaded to the GPU, see [`EnvironmentMap`]
+#[derive(Clone)]
+pub struct VisiCpuEnvironment {
+	pub image: RCDesc<Image<Image2d>>,
+	pub marginal_cdf: RCDesc<Buffer<[f32]>>,
+	pub conditional_cdf: RCDesc<Buffer<[f32]>>,
+	pub size: UVec2,
+	/// radiance averaged over the sphere of directions, zero if the environment emits no light
+	pub average_radiance: Vec3,
+}
+
+impl VisiCpuEnvironment {
+	/// Upload an environment map of `size` with `texels` of linear radiance in row-major order, starting with the top
+	/// row at +Y.
+	pub async fn new(bindless: &Bindless, size: UVec2, texels: &[Vec3], name: &str) -> anyhow::Result<Self> {
+		if size.x == 0 || size.y == 0 || texels.len() != (size.x * size.y) as usize {
+			anyhow::bail!("environment map {name} of size {size} has {} texels", texels.len());
+		}
+		let (marginal_cdf, conditional_cdf) = build_environment_cdf(size, texels);
+		let average_radiance = average_radiance(size, texels);
+
+		let image = bindless
+			.execute(|cmd| {
+				let staging = bindless.buffer().alloc_from_iter(
+					&BindlessBufferCreateInfo {
+						usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::TRANSFER_SRC,
+						allocation_scheme: Default::default(),
+						name: &format!("{name} staging"),
+					},
+					texels.iter().flat_map(|texel| texel.extend(1.).to_array()),
+				)?;
+				let image = bindless.image().alloc::<Image2d>(&BindlessImageCreateInfo {
+					format: Format::R32G32B32A32_SFLOAT,
+					extent: Extent::from([size.x, size.y]),
+					mip_levels: 1,
+					array_layers: 1,
+					samples: Default::default(),
+					usage: BindlessImageUsage::TRANSFER_DST | BindlessImageUsage::SAMPLED,
+					allocation_scheme: Default::default(),
+					name,
+					..BindlessImageCreateInfo::default()
+				})?;
+				let image = image.access_dont_care::<TransferWrite>(cmd)?;
+				let staging = staging.access::<TransferRead>(cmd)?;
+				cmd.copy_buffer_to_image(&staging, &image)?;
+				Ok(image.into_shared())
+			})?
+			.await;
+
+		let alloc_cdf = |cdf: Vec<f32>, name: &str| {
+			bindless.buffer().alloc_shared_from_iter(
+				&BindlessBufferCreateInfo {
+					usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+					allocation_scheme: Default::default(),
+					name,
+				},
+				cdf,
+			)
+		};
+		Ok(Self {
+			image,
+			marginal_cdf: alloc_cdf(marginal_cdf, &format!("{name} marginal cdf"))?,
+			conditional_cdf: alloc_cdf(conditional_cdf, &format!("{name} conditional cdf"))?,
+			size,
+			average_radiance,
+		})
+	}
+
+	/// An environment emitting the same `radiance` from all directions, use [`Vec3::ZERO`] for no environment
+	pub async fn constant(bindless: &Bindless, radiance: Vec3) -> anyhow::Result<Self> {
+		Self::new(bindless, UVec2::ONE, &[radiance], "constant environment").await
+	}
+
+	/// Load an equirectangular Radiance `.hdr` image, with all radiance multiplied by `intensity`
+	pub async fn load_hdr(bindless: &Bindless, path: &Path, intensity: f32) -> anyhow::Result<Self> {
+		let hdr = image::open(path)?.into_rgb32f();
+		let size = UVec2::new(hdr.width(), hdr.height());
+		let texels = hdr
+			.pixels()
+			.map(|p| Vec3::from_array(p.0) * intensity)
+			.collect::<Vec<_>>();
+		Self::new(bindless, size, &texels, &path.to_string_lossy()).await
+	}
+
+	/// A simple sky fading from a blue zenith to a bright horizon above a dark ground, for scenes without an `.hdr`
+	pub async fn sky(bindless: &Bindless) -> anyhow::Result<Self> {
+		let size = UVec2::new(64, 32);
+		let zenith = Vec3::new(0.05, 0.1, 0.25);
+		let horizon = Vec3::new(0.25, 0.27, 0.3);
+		let ground = Vec3::new(0.03, 0.027, 0.024);
+		let texels = (0..size.y)
+			.flat_map(|y| {
+				let elevation = 1. - 2. * (y as f32 + 0.5) / size.y as f32;
+				let radiance = if elevation > 0. {
+					horizon.lerp(zenith, elevation.sqrt())
+				} else {
+					ground
+				};
+				(0..size.x).map(move |_| radiance)
+			})
+			.collect::<Vec<_>>();
+		Self::new(bindless, size, &texels, "sky").await
+	}
+
+	pub fn to_gpu(&self) -> EnvironmentMap {
+		EnvironmentMap {
+			image: self.image.to_strong(),
+			marginal_cdf: self.marginal_cdf.to_strong(),
+			conditional_cdf: self.conditional_cdf.to_strong(),
+			size: self.size,
+		}
+	}
+}
+
+/// `sin(theta)` at the center of row `y`, the relative solid angle covered by texels of that row
+fn row_sin_theta(size: UVec2, y: u32) -> f32 {
+	f32::sin(PI * (y as f32 + 0.5) / size.y as f32)
+}
+
+/// Radiance of an equirectangular map averaged over the sphere of directions
+pub fn average_radiance(size: UVec2, texels: &[Vec3]) -> Vec3 {
+	let (sum, weight) =
+		texels
+			.chunks_exact(size.x as usize)
+			.enumerate()
+			.fold((Vec3::ZERO, 0.), |(sum, weight), (y, row)| {
+				let sin_theta = row_sin_theta(size, y as u32);
+				let row_sum = row.iter().copied().sum::<Vec3>();
+				(sum + row_sum * sin_theta, weight + sin_theta * row.len() as f32)
+			});
+	if weight > 0. { sum / weight } else { Vec3::ZERO }
+}
+
+/// Build the marginal and conditional CDF of an equirectangular map, see [`EnvironmentMap`] for their layout. Texels are
+/// weighted by their luminance and the solid angle they cover. Rows or maps without any light are sampled uniformly.
+pub fn build_environment_cdf(size: UVec2, texels: &[Vec3]) -> (Vec<f32>, Vec<f32>) {
+	let mut conditional = Vec::with_capacity(((size.x + 1) * size.y) as usize);
+	let mut row_weights = Vec::with_capacity(size.y as usize);
+	for (y, row) in texels.chunks_exact(size.x as usize).enumerate() {
+		let sin_theta = row_sin_theta(size, y as u32) as f64;
+		let weights = row
+			.iter()
+			.map(|texel| f32::max(luminance(*texel), 0.) as f64 * sin_theta)
+			.collect::<Vec<_>>();
+		let row_weight = weights.iter().sum::<f64>();
+		row_weights.push(row_weight);
+		conditional.extend(cdf_from_weights(&weights, row_weight));
+	}
+	let total = row_weights.iter().sum::<f64>();
+	(cdf_from_weights(&row_weights, total), conditional)
+}
+
+/// `weights.len() + 1` CDF entries from 0 to 1, uniform if `sum` is not positive
+fn cdf_from_weights(weights: &[f64], sum: f64) -> Vec<f32> {
+	let uniform = !(sum > 0. && sum.is_finite());
+	let mut accum = 0.;
+	let mut cdf = Vec::with_capacity(weights.len() + 1);
+	cdf.push(0.);
+	for weight in weights {
+		accum += if uniform {
+			1. / weights.len() as f64
+		} else {
+			weight / sum
+		};
+		cdf.push(accum as f32);
+	}
+	// exactly 1, regardless of rounding errors
+	*cdf.last_mut().unwrap() = 1.;
+	cdf
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use glam::Vec2;
+	use restir_shader::light::environment::{
+		environment_cdf_pdf, equirect_direction, equirect_uv, sample_environment_cdf,
+	};
+
+	fn random_texels(size: UVec2) -> Vec<Vec3> {
+		(0..size.x * size.y)
+			.map(|i| {
+				// some texels are black
+				let value = (i * 7919 % 13) as f32 * (i % 5).min(1) as f32;
+				Vec3::new(value, value * 0.5, value * 0.25)
+			})
+			.collect()
+	}
+
+	fn histogram(size: UVec2, texels: &[Vec3], samples: u32) -> (Vec<f64>, Vec<f32>) {
+		let (marginal, conditional) = build_environment_cdf(size, texels);
+		let marginal = |i: u32| marginal[i as usize];
+		let conditional = |i: u32| conditional[i as usize];
+		let mut histogram = vec![0.; texels.len()];
+		let mut pdfs = vec![0.; texels.len()];
+		for y in 0..samples {
+			for x in 0..samples {
+				let u = (Vec2::new(x as f32, y as f32) + 0.5) / samples as f32;
+				let (uv, pdf) = sample_environment_cdf(size, u, marginal, conditional);
+				assert_eq!(pdf, environment_cdf_pdf(size, uv, marginal, conditional));
+				assert!(pdf > 0., "sampled {uv} with zero pdf");
+				let texel = (uv * size.as_vec2()).as_uvec2().min(size - 1);
+				let index = (texel.y * size.x + texel.x) as usize;
+				histogram[index] += 1. / (samples * samples) as f64;
+				pdfs[index] = pdf;
+			}
+		}
+		(histogram, pdfs)
+	}
+
+	#[test]
+	fn test_proportional_to_luminance() {
+		let size = UVec2::new(16, 8);
+		let texels = random_texels(size);
+		let weights = texels
+			.iter()
+			.enumerate()
+			.map(|(i, t)| luminance(*t) as f64 * row_sin_theta(size, i as u32 / size.x) as f64)
+			.collect::<Vec<_>>();
+		let sum = weights.iter().sum::<f64>();
+
+		let (histogram, pdfs) = histogram(size, &texels, 1024);
+		for (i, (actual, weight)) in histogram.iter().zip(&weights).enumerate() {
+			let expected = weight / sum;
+			assert!(
+				(actual - expected).abs() < 1e-3,
+				"texel {i}: expected {expected} got {actual}"
+			);
+			if *actual > 0. {
+				// pdf over the uv square is the probability of the texel divided by its area
+				let pdf = expected * (size.x * size.y) as f64;
+				assert!(
+					(pdfs[i] as f64 - pdf).abs() < 1e-3 * pdf,
+					"texel {i}: expected pdf {pdf}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn test_black_is_uniform() {
+		let size = UVec2::new(4, 4);
+		let (histogram, pdfs) = histogram(size, &[Vec3::ZERO; 16], 256);
+		for (actual, pdf) in histogram.iter().zip(&pdfs) {
+			assert!((actual - 1. / 16.).abs() < 1e-3, "{histogram:?}");
+			assert!((pdf - 1.).abs() < 1e-5);
+		}
+	}
+
+	#[test]
+	fn test_average_radiance() {
+		let size = UVec2::new(8, 4);
+		let radiance = Vec3::new(1., 2., 3.);
+		let average = average_radiance(size, &vec![radiance; 32]);
+		assert!(average.abs_diff_eq(radiance, 1e-5), "{average}");
+		assert_eq!(average_radiance(size, &[Vec3::ZERO; 32]), Vec3::ZERO);
+	}
+
+	#[test]
+	fn test_equirect_roundtrip() {
+		for uv in [Vec2::new(0.1, 0.2), Vec2::new(0.75, 0.5), Vec2::new(0.5, 0.9)] {
+			let direction = equirect_direction(uv);
+			assert!(direction.is_normalized());
+			assert!(equirect_uv(direction).abs_diff_eq(uv, 1e-5), "{uv}");
+		}
+		assert!(equirect_direction(Vec2::new(0.3, 0.)).abs_diff_eq(Vec3::Y, 1e-5));
+	}
+}
@@ -0,0 +1,4 @@
+pub mod alias;
+pub mod environment;
+pub mod power;
+pub mod tree;
@@ -0,0 +1,21 @@
+use restir_shader::light::{Light, LightType};
+use restir_shader::utils::color::luminance;
+use std::f32::consts::PI;
+
+/// The total power emitted by a light, as luminance. Used as weights to select lights proportional to their power.
+///
+/// Directional and environment lights are assumed to illuminate a disk the size of the scene with `scene_radius`.
+/// `triangle_area` is the world space area of emissive triangles and ignored for all other lights.
+pub fn light_power(light: &Light, scene_radius: f32, triangle_area: f32) -> f32 {
+	let emission = luminance(light.emission);
+	match light.light_type {
+		LightType::Point => 4. * PI * emission,
+		// approximates the smooth falloff with a hard cone halfway between the inner and outer angle
+		LightType::Spot => 2. * PI * (1. - (light.cos_inner + light.cos_outer) / 2.) * emission,
+		LightType::Directional => PI * scene_radius * scene_radius * emission,
+		// lambertian emitter, only emitting from its front face
+		LightType::EmissiveTriangle => PI * triangle_area * emission,
+		// the average radiance arriving from all directions of the sphere, as in pbrt
+		LightType::Environment => 4. * PI * PI * scene_radius * scene_radius * emission,
+	}
+}
@@ -0,0 +1,252 @@
+use glam::{Quat, Vec3};
+use restir_shader::light::tree::{LIGHT_TREE_MAX_DEPTH, LIGHT_TREE_NO_TRAIL, LightTreeNode};
+use restir_shader::light::{Light, LightType};
+use std::f32::consts::PI;
+
+/// Spatial and directional bounds of the light emitted by one or many lights
+#[derive(Copy, Clone, Debug)]
+pub struct LightBounds {
+	pub min: Vec3,
+	pub max: Vec3,
+	/// axis of the cone bounding the normals of all emitters
+	pub axis: Vec3,
+	/// cosine of the angle of the cone bounding the normals around the `axis`
+	pub cos_theta_o: f32,
+	/// cosine of the angle around each normal light is emitted into
+	pub cos_theta_e: f32,
+	pub power: f32,
+}
+
+impl LightBounds {
+	/// Bounds of a point or spot light, infinite lights can't be bounded
+	pub fn delta(light: &Light, power: f32) -> Option<Self> {
+		let (axis, cos_theta_o, cos_theta_e) = match light.light_type {
+			LightType::Point => (Vec3::Z, -1., 0.),
+			LightType::Spot => (
+				light.direction,
+				light.cos_inner,
+				f32::cos(f32::acos(light.cos_outer) - f32::acos(light.cos_inner)),
+			),
+			LightType::Directional | LightType::EmissiveTriangle | LightType::Environment => return None,
+		};
+		Some(Self {
+			min: light.position,
+			max: light.position,
+			axis,
+			cos_theta_o,
+			cos_theta_e,
+			power,
+		})
+	}
+
+	/// Bounds of a one-sided emissive triangle at world space `positions`
+	pub fn triangle(positions: [Vec3; 3], power: f32) -> Self {
+		let [a, b, c] = positions;
+		Self {
+			min: a.min(b).min(c),
+			max: a.max(b).max(c),
+			axis: (b - a).cross(c - a).normalize_or_zero(),
+			cos_theta_o: 1.,
+			cos_theta_e: 0.,
+			power,
+		}
+	}
+
+	pub fn centroid(&self) -> Vec3 {
+		(self.min + self.max) / 2.
+	}
+
+	pub fn union(&self, other: &Self) -> Self {
+		let (axis, cos_theta_o) = cone_union(self.axis, self.cos_theta_o, other.axis, other.cos_theta_o);
+		Self {
+			min: self.min.min(other.min),
+			max: self.max.max(other.max),
+			axis,
+			cos_theta_o,
+			cos_theta_e: f32::min(self.cos_theta_e, other.cos_theta_e),
+			power: self.power + other.power,
+		}
+	}
+
+	fn node(&self, is_leaf: bool, index: u32) -> LightTreeNode {
+		LightTreeNode {
+			bounds_min: self.min,
+			bounds_max: self.max,
+			axis: self.axis,
+			cos_theta_o: self.cos_theta_o,
+			cos_theta_e: self.cos_theta_e,
+			power: self.power,
+			is_leaf,
+			index,
+		}
+	}
+}
+
+/// The smallest cone containing both cones `a` and `b`
+fn cone_union(axis_a: Vec3, cos_a: f32, axis_b: Vec3, cos_b: f32) -> (Vec3, f32) {
+	let theta_a = f32::acos(cos_a.clamp(-1., 1.));
+	let theta_b = f32::acos(cos_b.clamp(-1., 1.));
+	let theta_d = axis_a.angle_between(axis_b);
+	if f32::min(theta_d + theta_b, PI) <= theta_a {
+		return (axis_a, cos_a);
+	}
+	if f32::min(theta_d + theta_a, PI) <= theta_b {
+		return (axis_b, cos_b);
+	}
+
+	let theta_o = (theta_a + theta_d + theta_b) / 2.;
+	let rotation_axis = axis_a.cross(axis_b);
+	if theta_o >= PI || rotation_axis.length_squared() == 0. {
+		return (axis_a, -1.);
+	}
+	let axis = Quat::from_axis_angle(rotation_axis.normalize(), theta_o - theta_a) * axis_a;
+	(axis, f32::cos(theta_o))
+}
+
+/// A light tree ready to be uploaded, see [`restir_shader::light::tree`]
+#[derive(Clone, Debug)]
+pub struct CpuLightTree {
+	/// all nodes, with the root at index 0
+	pub nodes: Vec<LightTreeNode>,
+	/// the bit trail of each light
+	pub bit_trails: Vec<u32>,
+}
+
+/// Build a light tree over all lights with `Some` bounds and positive power, by recursively splitting the lights at the
+/// median along the largest axis of their centroids.
+pub fn build_light_tree(lights: &[Option<LightBounds>]) -> anyhow::Result<CpuLightTree> {
+	let mut bit_trails = vec![LIGHT_TREE_NO_TRAIL; lights.len()];
+	let mut bounded = lights
+		.iter()
+		.enumerate()
+		.filter_map(|(i, bounds)| bounds.filter(|b| b.power > 0.).map(|b| (i as u32, b)))
+		.collect::<Vec<_>>();
+
+	let mut nodes = vec![LightTreeNode::default()];
+	if !bounded.is_empty() {
+		build_node(&mut nodes, &mut bit_trails, &mut bounded, 0, 0, 0)?;
+	}
+	Ok(CpuLightTree { nodes, bit_trails })
+}
+
+fn build_node(
+	nodes: &mut Vec<LightTreeNode>,
+	bit_trails: &mut [u32],
+	lights: &mut [(u32, LightBounds)],
+	node_index: usize,
+	bit_trail: u32,
+	depth: u32,
+) -> anyhow::Result<()> {
+	let bounds = lights[1..].iter().fold(lights[0].1, |acc, (_, b)| acc.union(b));
+	if let [(light_index, _)] = lights {
+		bit_trails[*light_index as usize] = bit_trail;
+		nodes[node_index] = bounds.node(true, *light_index);
+		return Ok(());
+	}
+	if depth >= LIGHT_TREE_MAX_DEPTH {
+		anyhow::bail!("light tree exceeds max depth of {LIGHT_TREE_MAX_DEPTH}");
+	}
+
+	let (centroid_min, centroid_max) = lights
+		.iter()
+		.fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), (_, b)| {
+			(min.min(b.centroid()), max.max(b.centroid()))
+		});
+	let axis = (centroid_max - centroid_min).max_position();
+	let mid = lights.len() / 2;
+	lights.select_nth_unstable_by(mid, |(_, a), (_, b)| a.centroid()[axis].total_cmp(&b.centroid()[axis]));
+
+	let child = nodes.len();
+	nodes.push(LightTreeNode::default());
+	nodes.push(LightTreeNode::default());
+	let (left, right) = lights.split_at_mut(mid);
+	build_node(nodes, bit_trails, left, child, bit_trail, depth + 1)?;
+	build_node(nodes, bit_trails, right, child + 1, bit_trail | (1 << depth), depth + 1)?;
+	nodes[node_index] = bounds.node(false, child as u32);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use restir_shader::light::tree::{light_tree_pdf, sample_light_tree};
+	use restir_shader::random::Rng;
+
+	fn random_triangles(count: u32) -> Vec<Option<LightBounds>> {
+		let mut rng = Rng::new(Default::default(), 0, 0);
+		let mut point = || Vec3::new(rng.next_f32(), rng.next_f32(), rng.next_f32()) * 10. - 5.;
+		(0..count)
+			.map(|i| {
+				let a = point();
+				let offset = point() / 10.;
+				let positions = [a, a + offset, a + offset.cross(Vec3::Y)];
+				// some lights are not part of the tree
+				(i % 7 != 0).then(|| LightBounds::triangle(positions, (i % 5) as f32))
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_pdf_matches_traversal() {
+		let lights = random_triangles(100);
+		let tree = build_light_tree(&lights).unwrap();
+		let load = |i: u32| tree.nodes[i as usize];
+		let position = Vec3::new(0.5, -6., 0.2);
+		let normal = Vec3::Y;
+
+		let pdfs = tree
+			.bit_trails
+			.iter()
+			.map(|trail| light_tree_pdf(load, position, normal, *trail) as f64)
+			.collect::<Vec<_>>();
+		let pdf_sum = pdfs.iter().sum::<f64>();
+		assert!((pdf_sum - 1.).abs() < 1e-4, "pdfs should sum to 1: {pdf_sum}");
+
+		let samples = 1 << 20;
+		let mut histogram = vec![0.; lights.len()];
+		for i in 0..samples {
+			let u = (i as f32 + 0.5) / samples as f32;
+			let (light_index, pdf) = sample_light_tree(load, position, normal, u);
+			let expected = pdfs[light_index as usize];
+			assert!(
+				(pdf as f64 - expected).abs() <= 1e-5 * expected,
+				"sampled pdf {pdf} should match {expected} of light {light_index}"
+			);
+			histogram[light_index as usize] += 1. / samples as f64;
+		}
+		for (i, (actual, expected)) in histogram.iter().zip(&pdfs).enumerate() {
+			assert!(
+				(actual - expected).abs() < 1e-3,
+				"light {i}: expected {expected} got {actual}"
+			);
+		}
+	}
+
+	#[test]
+	fn test_excluded_lights() {
+		let lights = random_triangles(20);
+		let tree = build_light_tree(&lights).unwrap();
+		for (bounds, trail) in lights.iter().zip(&tree.bit_trails) {
+			let in_tree = bounds.is_some_and(|b| b.power > 0.);
+			assert_eq!(in_tree, *trail != LIGHT_TREE_NO_TRAIL);
+		}
+	}
+
+	#[test]
+	fn test_empty() {
+		let tree = build_light_tree(&[None]).unwrap();
+		assert_eq!(tree.nodes.len(), 1);
+		let (_, pdf) = sample_light_tree(|i| tree.nodes[i as usize], Vec3::ZERO, Vec3::Y, 0.5);
+		assert_eq!(pdf, 0.);
+	}
+
+	#[test]
+	fn test_cone_union() {
+		let (axis, cos) = cone_union(Vec3::X, 1., Vec3::Y, 1.);
+		assert!(axis.abs_diff_eq(Vec3::new(1., 1., 0.).normalize(), 1e-5), "{axis}");
+		assert!((cos - f32::cos(PI / 4.)).abs() < 1e-5);
+
+		let (_, cos) = cone_union(Vec3::X, 1., Vec3::NEG_X, 1.);
+		assert_eq!(cos, -1.);
+	}
+}
@@ -2,15 +2,29 @@ use crate::controls::app_focus::AppFocus;
 use crate::controls::delta_time::DeltaTimer;
 use crate::controls::fps_camera_controller::FpsCameraController;
 use crate::controls::fps_ui::FpsUi;
+use crate::controls::indirect_light_settings::IndirectLightSettings;
+use crate::controls::render_mode_settings::RenderModeSettings;
+use crate::controls::restir_di_settings::RestirDiSettings;
 use crate::controls::visi_debug_selector::VisiDebugSettings;
 use crate::debugger;
+use crate::light::environment::VisiCpuEnvironment;
+use crate::material::pbr::VisiCpuPbrMaterial;
 use crate::model::VisiCpuModel;
-use crate::visibility::renderer::{VisiPipelines, VisiPipelinesFormat, VisiRenderInfo};
-use crate::visibility::scene::VisiCpuSceneAccum;
+use crate::model::gltf::{GltfCamera, GltfScene};
+use crate::model::obj::ObjScene;
+use crate::model::parametized::CUBE_BOTTOM_TRIANGLES;
+use crate::visibility::gpu_scene::VisiGpuScene;
+use crate::visibility::output_dump::OutputDump;
+use crate::visibility::renderer::{IndirectLight, VisiPipelines, VisiPipelinesFormat, VisiRenderInfo};
+use crate::visibility::scene::VisiCpuLight;
 use egui::{Context, Pos2};
 use glam::{Affine3A, UVec3, Vec3, Vec3Swizzles, Vec4};
 use restir_shader::camera::Camera;
+use restir_shader::light::Light;
+use restir_shader::material::debug::DebugType;
+use restir_shader::restir::pt::PtDebugView;
 use restir_shader::utils::affine_transform::AffineTransform;
+use restir_shader::visibility::id::TriangleId;
 use restir_shader::visibility::scene::VisiInstanceInfo;
 use rust_gpu_bindless::descriptor::{BindlessImageUsage, BindlessInstance, DescriptorCounts, ImageDescExt};
 use rust_gpu_bindless::pipeline::{
@@ -26,6 +40,7 @@ use rust_gpu_bindless_winit::ash::{
 use rust_gpu_bindless_winit::event_loop::{EventLoopExecutor, event_loop_init};
 use rust_gpu_bindless_winit::window_ref::WindowRef;
 use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::mpsc::Receiver;
 use winit::dpi::PhysicalSize;
@@ -75,6 +90,7 @@ pub async fn main_loop(event_loop: EventLoopExecutor, events: Receiver<Event<()>
 		)
 	};
 
+	let mut output_dump = OutputDump::from_env()?;
 	let mut swapchain = unsafe {
 		let bindless2 = bindless.clone();
 		AshSwapchain::new(&bindless, &event_loop, window.clone(), move |surface, _| {
@@ -89,8 +105,11 @@ pub async fn main_loop(event_loop: EventLoopExecutor, events: Receiver<Event<()>
 	.await?;
 	let swapchain_format = swapchain.params().format;
 
-	let visi_format = VisiPipelinesFormat::new(&bindless, swapchain_format);
-	let visi_pipelines = VisiPipelines::new(&bindless, visi_format)?;
+	let mut visi_format = VisiPipelinesFormat::new(&bindless, swapchain_format);
+	if std::env::var_os("RESTIR_WIDE_IDS").is_some() {
+		visi_format = visi_format.with_wide_ids();
+	}
+	let visi_pipelines = VisiPipelines::new(&bindless, visi_format).await?;
 	let mut visi_renderer = visi_pipelines.new_renderer();
 
 	let egui_renderer = EguiRenderer::new(bindless.clone());
@@ -104,12 +123,76 @@ pub async fn main_loop(event_loop: EventLoopExecutor, events: Receiver<Event<()>
 	};
 
 	let model_cube = crate::model::parametized::cube(&bindless, Affine3A::default())?;
+	let (gltf_scene, obj_scene) = match std::env::var_os("RESTIR_SCENE").map(PathBuf::from) {
+		Some(path) if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("obj")) => {
+			(None, Some(ObjScene::load(&bindless, &path)?))
+		}
+		Some(path) => (Some(GltfScene::load(&bindless, &path)?), None),
+		None => (None, None),
+	};
+	let gltf_camera = gltf_scene.as_ref().and_then(|scene| scene.cameras.first().copied());
+	let environment = match std::env::var_os("RESTIR_ENVIRONMENT") {
+		Some(path) => VisiCpuEnvironment::load_hdr(&bindless, Path::new(&path), 1.).await?,
+		None => VisiCpuEnvironment::sky(&bindless).await?,
+	};
+	// `.obj` scenes bring their own materials, glTF scenes use the first material for all instances
+	let pbr_materials = match &obj_scene {
+		Some(obj_scene) => obj_scene.pbr_materials(&bindless).await?,
+		None => vec![
+			VisiCpuPbrMaterial::from_factors(&bindless, Vec4::new(0.8, 0.8, 0.8, 1.), 0., 0.6, Vec3::ZERO).await?,
+			VisiCpuPbrMaterial::from_factors(&bindless, Vec4::new(1., 0.78, 0.34, 1.), 1., 0.3, Vec3::ZERO).await?,
+		],
+	};
+
+	let mut gpu_scene = VisiGpuScene::new(&bindless);
+	if let Some(gltf_scene) = &gltf_scene {
+		gltf_scene.push(&mut gpu_scene);
+	} else if let Some(obj_scene) = &obj_scene {
+		obj_scene.push(&mut gpu_scene, Affine3A::IDENTITY)?;
+	} else {
+		let mut add_model_at = |model: &VisiCpuModel, at: Vec3, material_id: u32| {
+			gpu_scene.add(
+				model,
+				VisiInstanceInfo::new(AffineTransform::new(Affine3A::from_translation(at)), material_id),
+			)
+		};
+		add_model_at(&model_cube, Vec3::new(0., 0., -6.), 0);
+		add_model_at(&model_cube, Vec3::new(4., 0., -2.), 1);
+		let light_cube = add_model_at(&model_cube, Vec3::new(0., 3., -3.), 0);
+		add_model_at(&model_cube, Vec3::new(-4., 0., -4.), 1);
+
+		gpu_scene.add_light(Light::point(Vec3::new(3., 3., -5.), Vec3::new(30., 15., 5.)));
+		gpu_scene.add_light(Light::point(Vec3::new(-3., -2., -1.), Vec3::new(5., 10., 30.)));
+		gpu_scene.add_light(Light::spot(
+			Vec3::new(2., -2., -1.),
+			Vec3::new(0., 0.3, -1.),
+			Vec3::new(5., 50., 5.),
+			0.2,
+			0.4,
+		));
+		gpu_scene.add_light(Light::directional(Vec3::new(-1., -2., -1.), Vec3::splat(0.1)));
+		for triangle_id in CUBE_BOTTOM_TRIANGLES {
+			gpu_scene.add_light(VisiCpuLight::EmissiveTriangle {
+				instance: light_cube,
+				triangle_id: TriangleId::new(triangle_id)?,
+				radiance: Vec3::splat(2.),
+			});
+		}
+	}
+
+	gpu_scene.set_materials(pbr_materials);
 
 	let mut delta_timer = DeltaTimer::new();
 	let mut app_focus = AppFocus::new(event_loop.clone(), window);
 	let mut camera_controls = FpsCameraController::default();
+	if let Some(gltf_camera) = &gltf_camera {
+		camera_controls.look_from(gltf_camera.world_from_camera);
+	}
 	let mut fps_ui = FpsUi::new();
 	let mut visi_debug_settings = VisiDebugSettings::new();
+	let mut restir_di_settings = RestirDiSettings::new();
+	let mut indirect_light_settings = IndirectLightSettings::new();
+	let mut render_mode_settings = RenderModeSettings::new();
 
 	'outer: loop {
 		{
@@ -135,41 +218,31 @@ pub async fn main_loop(event_loop: EventLoopExecutor, events: Receiver<Event<()>
 			swapchain.acquire_image(None).await?
 		};
 
-		let render_info;
+		let camera;
 		{
 			profiling::scope!("update");
 			let delta_time = delta_timer.next();
 			fps_ui.update(delta_time);
 
 			let out_extent = UVec3::from(swapchain_image.extent()).xy();
-			let fov_y = 90.;
-			let camera = Camera::new_perspective_rh_y_flip(
-				out_extent,
-				fov_y / 360. * 2. * PI,
-				0.01,
-				1000.,
-				AffineTransform::new(camera_controls.update(delta_time)),
-			);
-
-			let mut accum = VisiCpuSceneAccum::new();
-			let mut add_model_at = |model: &VisiCpuModel, at: Vec3| {
-				accum.push(
-					model,
-					VisiInstanceInfo {
-						world_from_local: AffineTransform::new(Affine3A::from_translation(at)),
-					},
-				);
+			let camera_transform = camera_controls.update(delta_time);
+			camera = match &gltf_camera {
+				Some(gltf_camera) => GltfCamera {
+					world_from_camera: camera_transform,
+					..*gltf_camera
+				}
+				.to_camera(out_extent),
+				None => {
+					let fov_y = 90.;
+					Camera::new_perspective_rh_y_flip(
+						out_extent,
+						fov_y / 360. * 2. * PI,
+						0.01,
+						1000.,
+						AffineTransform::new(camera_transform),
+					)
+				}
 			};
-			add_model_at(&model_cube, Vec3::new(0., 0., -6.));
-			add_model_at(&model_cube, Vec3::new(4., 0., -2.));
-			add_model_at(&model_cube, Vec3::new(0., 3., -3.));
-			add_model_at(&model_cube, Vec3::new(-4., 0., -4.));
-			let scene = accum.finish(&bindless, camera)?;
-
-			render_info = VisiRenderInfo {
-				scene,
-				debug_settings: visi_debug_settings.get(),
-			}
 		}
 
 		let egui_output = {
@@ -179,13 +252,19 @@ pub async fn main_loop(event_loop: EventLoopExecutor, events: Receiver<Event<()>
 					.fixed_pos(Pos2::new(0., 0.))
 					.hscroll(true)
 					.show(ctx, |ui| {
+						render_mode_settings.ui(ui);
+						ui.separator();
 						visi_debug_settings.ui(ui);
+						ui.separator();
+						restir_di_settings.ui(ui);
+						ui.separator();
+						indirect_light_settings.ui(ui);
 					});
 				fps_ui.ui(ctx);
 			})?
 		};
 
-		let swapchain_image = {
+		let (swapchain_image, pending_dump) = {
 			profiling::scope!("render");
 			bindless.execute(|cmd| {
 				let mut output_image = swapchain_image.access_dont_care::<TransferWrite>(cmd)?;
@@ -194,7 +273,27 @@ pub async fn main_loop(event_loop: EventLoopExecutor, events: Receiver<Event<()>
 					clear_value: Vec4::ZERO,
 				})?;
 				let mut output_image = output_image.transition::<StorageReadWrite>()?;
-				visi_renderer.render(cmd, &mut output_image, render_info).unwrap();
+				let scene = gpu_scene.upload(cmd, camera, &environment).unwrap();
+				let mut debug_settings = visi_debug_settings.get();
+				let mut indirect_light = indirect_light_settings.get();
+				if output_dump.is_some() {
+					// the PT debug views would replace the dumped radiance, and the overlay would hide it in the window
+					debug_settings.debug_type = DebugType::None;
+					if let IndirectLight::Pt(settings) = &mut indirect_light {
+						settings.debug_view = PtDebugView::None;
+					}
+				}
+				let render_info = VisiRenderInfo {
+					scene,
+					debug_settings,
+					di_settings: restir_di_settings.get(),
+					indirect_light,
+					render_mode: render_mode_settings.mode(),
+					reference_settings: render_mode_settings.reference(),
+				};
+				let pending_dump = visi_renderer
+					.render(cmd, &mut output_image, render_info, output_dump.as_mut())
+					.unwrap();
 				let mut output_image = output_image.transition::<ColorAttachment>()?;
 				egui_output
 					.draw(
@@ -208,7 +307,7 @@ pub async fn main_loop(event_loop: EventLoopExecutor, events: Receiver<Event<()>
 						},
 					)
 					.unwrap();
-				Ok(output_image.transition::<Present>()?.into_desc())
+				Ok((output_image.transition::<Present>()?.into_desc(), pending_dump))
 			})?
 		};
 
@@ -216,8 +315,15 @@ pub async fn main_loop(event_loop: EventLoopExecutor, events: Receiver<Event<()>
 			profiling::scope!("swapchain image present");
 			swapchain.present_image(swapchain_image)?;
 		}
+		if let (Some(output_dump), Some(pending_dump)) = (&mut output_dump, pending_dump) {
+			profiling::scope!("output dump");
+			output_dump.write(pending_dump).await?;
+		}
 		profiling::finish_frame!();
 	}
 
+	if let Some(output_dump) = output_dump {
+		output_dump.finish()?;
+	}
 	Ok(())
 }
@@ -1,13 +1,13 @@
-use crate::material::system::material_pipeline::MaterialPipeline;
+use crate::material::system::image_pipeline::MaterialImagePipeline;
 use restir_shader::material::debug::DebugSettings;
 use rust_gpu_bindless::descriptor::Bindless;
 use std::ops::Deref;
 
-pub struct VisiDebugPipeline(pub MaterialPipeline<DebugSettings>);
+pub struct VisiDebugPipeline(pub MaterialImagePipeline<DebugSettings>);
 
 impl VisiDebugPipeline {
 	pub fn new(bindless: &Bindless) -> anyhow::Result<Self> {
-		Ok(Self(MaterialPipeline::new(
+		Ok(Self(MaterialImagePipeline::new(
 			bindless,
 			crate::shader::material::debug::debug_material::image::new(),
 		)?))
@@ -15,7 +15,7 @@ impl VisiDebugPipeline {
 }
 
 impl Deref for VisiDebugPipeline {
-	type Target = MaterialPipeline<DebugSettings>;
+	type Target = MaterialImagePipeline<DebugSettings>;
 
 	fn deref(&self) -> &Self::Target {
 		&self.0
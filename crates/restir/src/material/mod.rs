@@ -1,2 +1,4 @@
 pub mod debug;
+pub mod pbr;
+pub mod resolve;
 pub mod system;
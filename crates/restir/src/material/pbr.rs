@@ -0,0 +1,296 @@
+use crate::material::system::material_pipeline::MaterialPipeline;
+use glam::{UVec2, Vec3, Vec4, Vec4Swizzles};
+use restir_shader::material::debug::DebugSettings;
+use restir_shader::material::pbr::PbrMaterial;
+use rust_gpu_bindless::descriptor::{
+	AddressMode, Bindless, BindlessBufferCreateInfo, BindlessBufferUsage, BindlessImageCreateInfo, BindlessImageUsage,
+	BindlessSamplerCreateInfo, Buffer, Extent, Filter, Format, Image, Image2d, RCDesc, RCDescExt, Sampler,
+};
+use rust_gpu_bindless::pipeline::{MutBufferAccessExt, MutImageAccessExt, TransferRead, TransferWrite};
+use std::ops::Deref;
+
+pub struct VisiPbrPipeline(pub MaterialPipeline<DebugSettings>);
+
+impl VisiPbrPipeline {
+	pub fn new(bindless: &Bindless) -> anyhow::Result<Self> {
+		Ok(Self(MaterialPipeline::new(
+			bindless,
+			crate::shader::material::pbr::pbr_material::image::new(),
+			crate::shader::material::pbr::pbr_material::tiles::new(),
+		)?))
+	}
+}
+
+impl Deref for VisiPbrPipeline {
+	type Target = MaterialPipeline<DebugSettings>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+/// The textures and factors of a [`PbrMaterial`] kept alive on the CPU
+#[derive(Clone)]
+pub struct VisiCpuPbrMaterial {
+	pub base_color: RCDesc<Image<Image2d>>,
+	pub metallic: RCDesc<Image<Image2d>>,
+	pub roughness: RCDesc<Image<Image2d>>,
+	pub normal: RCDesc<Image<Image2d>>,
+	pub emissive: RCDesc<Image<Image2d>>,
+	pub sampler: RCDesc<Sampler>,
+	pub base_color_factor: Vec4,
+	pub emissive_factor: Vec3,
+	pub metallic_factor: f32,
+	pub roughness_factor: f32,
+	pub normal_scale: f32,
+}
+
+/// Color textures are sRGB encoded, so sampling them returns linear values
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TextureEncoding {
+	Srgb,
+	Linear,
+}
+
+impl TextureEncoding {
+	pub fn format(&self) -> Format {
+		match self {
+			TextureEncoding::Srgb => Format::R8G8B8A8_SRGB,
+			TextureEncoding::Linear => Format::R8G8B8A8_UNORM,
+		}
+	}
+}
+
+impl VisiCpuPbrMaterial {
+	/// A material with 1x1 textures, so only the factors and the vertex normal are used
+	pub async fn from_factors(
+		bindless: &Bindless,
+		base_color_factor: Vec4,
+		metallic_factor: f32,
+		roughness_factor: f32,
+		emissive_factor: Vec3,
+	) -> anyhow::Result<Self> {
+		let white = upload_texture(bindless, UVec2::ONE, &[[255; 4]], TextureEncoding::Linear, "pbr white").await?;
+		let flat_normal = upload_texture(
+			bindless,
+			UVec2::ONE,
+			&[[128, 128, 255, 255]],
+			TextureEncoding::Linear,
+			"pbr flat normal",
+		)
+		.await?;
+		Ok(Self {
+			base_color: white.clone(),
+			metallic: white.clone(),
+			roughness: white.clone(),
+			normal: flat_normal,
+			emissive: white,
+			sampler: default_sampler(bindless)?,
+			base_color_factor,
+			emissive_factor,
+			metallic_factor,
+			roughness_factor,
+			normal_scale: 1.,
+		})
+	}
+
+	pub fn to_gpu(&self) -> PbrMaterial {
+		PbrMaterial {
+			base_color: self.base_color.to_strong(),
+			metallic: self.metallic.to_strong(),
+			roughness: self.roughness.to_strong(),
+			normal: self.normal.to_strong(),
+			emissive: self.emissive.to_strong(),
+			sampler: self.sampler.to_strong(),
+			base_color_factor: self.base_color_factor,
+			emissive_factor: self.emissive_factor,
+			metallic_factor: self.metallic_factor,
+			roughness_factor: self.roughness_factor,
+			normal_scale: self.normal_scale,
+		}
+	}
+}
+
+/// Upload `materials` into the buffer of [`VisiScene::materials`](restir_shader::visibility::scene::VisiScene), which
+/// keeps all their textures alive
+pub fn upload_materials(
+	bindless: &Bindless,
+	materials: &[VisiCpuPbrMaterial],
+) -> anyhow::Result<RCDesc<Buffer<[PbrMaterial]>>> {
+	if materials.is_empty() {
+		anyhow::bail!("a scene needs at least one material");
+	}
+	Ok(bindless.buffer().alloc_shared_from_iter(
+		&BindlessBufferCreateInfo {
+			usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+			allocation_scheme: Default::default(),
+			name: "Materials",
+		},
+		materials.iter().map(VisiCpuPbrMaterial::to_gpu),
+	)?)
+}
+
+/// Trilinear sampler repeating textures, as expected by most models
+pub fn default_sampler(bindless: &Bindless) -> anyhow::Result<RCDesc<Sampler>> {
+	Ok(bindless.sampler().alloc(&BindlessSamplerCreateInfo {
+		min_filter: Filter::Linear,
+		mag_filter: Filter::Linear,
+		mipmap_mode: Filter::Linear,
+		address_mode_u: AddressMode::Repeat,
+		address_mode_v: AddressMode::Repeat,
+		address_mode_w: AddressMode::Repeat,
+		..BindlessSamplerCreateInfo::default()
+	})?)
+}
+
+/// Upload an RGBA8 texture of `size` with `texels` in row-major order, along with its full mip chain down to 1x1
+pub async fn upload_texture(
+	bindless: &Bindless,
+	size: UVec2,
+	texels: &[[u8; 4]],
+	encoding: TextureEncoding,
+	name: &str,
+) -> anyhow::Result<RCDesc<Image<Image2d>>> {
+	if size.x == 0 || size.y == 0 || texels.len() != (size.x * size.y) as usize {
+		anyhow::bail!("texture {name} of size {size} has {} texels", texels.len());
+	}
+	let mips = generate_mips(size, texels, encoding);
+	Ok(bindless
+		.execute(|cmd| {
+			let image = bindless.image().alloc::<Image2d>(&BindlessImageCreateInfo {
+				format: encoding.format(),
+				extent: Extent::from([size.x, size.y]),
+				mip_levels: mips.len() as u32,
+				array_layers: 1,
+				samples: Default::default(),
+				usage: BindlessImageUsage::TRANSFER_DST | BindlessImageUsage::SAMPLED,
+				allocation_scheme: Default::default(),
+				name,
+				..BindlessImageCreateInfo::default()
+			})?;
+			let image = image.access_dont_care::<TransferWrite>(cmd)?;
+			for (mip_level, mip) in mips.iter().enumerate() {
+				let staging = bindless.buffer().alloc_from_iter(
+					&BindlessBufferCreateInfo {
+						usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::TRANSFER_SRC,
+						allocation_scheme: Default::default(),
+						name: &format!("{name} staging mip {mip_level}"),
+					},
+					mip.iter().map(|texel| u32::from_le_bytes(*texel)),
+				)?;
+				let staging = staging.access::<TransferRead>(cmd)?;
+				cmd.copy_buffer_to_image_mip(&staging, &image, mip_level as u32)?;
+			}
+			Ok(image.into_shared())
+		})?
+		.await)
+}
+
+/// The amount of mip levels of a full mip chain of `size` down to 1x1
+pub fn mip_levels(size: UVec2) -> u32 {
+	u32::BITS - size.max_element().leading_zeros()
+}
+
+/// Generate all mip levels of `texels` of `size` by repeatedly averaging 2x2 texels, starting with `texels` as mip 0.
+/// The color of sRGB textures is averaged in linear space, alpha is always linear. Odd sizes round down and clamp the
+/// last texel.
+pub fn generate_mips(size: UVec2, texels: &[[u8; 4]], encoding: TextureEncoding) -> Vec<Vec<[u8; 4]>> {
+	let decode = |texel: [u8; 4]| {
+		let v = Vec4::from_array(texel.map(|c| c as f32 / 255.));
+		match encoding {
+			TextureEncoding::Srgb => Vec4::from((v.xyz().map(srgb_to_linear), v.w)),
+			TextureEncoding::Linear => v,
+		}
+	};
+	let encode = |v: Vec4| {
+		let v = match encoding {
+			TextureEncoding::Srgb => Vec4::from((v.xyz().map(linear_to_srgb), v.w)),
+			TextureEncoding::Linear => v,
+		};
+		v.to_array().map(|c| (c.clamp(0., 1.) * 255.).round() as u8)
+	};
+
+	let mut mips = Vec::with_capacity(mip_levels(size) as usize);
+	mips.push(texels.to_vec());
+	let mut prev_size = size;
+	let mut prev = texels.iter().copied().map(decode).collect::<Vec<_>>();
+	while prev_size != UVec2::ONE {
+		let size = (prev_size / 2).max(UVec2::ONE);
+		let max = prev_size - 1;
+		let mip = (0..size.y)
+			.flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+			.map(|texel| {
+				let sum = [UVec2::new(0, 0), UVec2::new(1, 0), UVec2::new(0, 1), UVec2::new(1, 1)]
+					.map(|offset| (texel * 2 + offset).min(max))
+					.map(|src| prev[(src.y * prev_size.x + src.x) as usize])
+					.into_iter()
+					.sum::<Vec4>();
+				sum / 4.
+			})
+			.collect::<Vec<_>>();
+		mips.push(mip.iter().copied().map(encode).collect());
+		prev = mip;
+		prev_size = size;
+	}
+	mips
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+	if c <= 0.04045 {
+		c / 12.92
+	} else {
+		((c + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+	if c <= 0.0031308 {
+		c * 12.92
+	} else {
+		1.055 * c.powf(1. / 2.4) - 0.055
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_mip_levels() {
+		assert_eq!(mip_levels(UVec2::ONE), 1);
+		assert_eq!(mip_levels(UVec2::new(2, 1)), 2);
+		assert_eq!(mip_levels(UVec2::new(256, 256)), 9);
+		assert_eq!(mip_levels(UVec2::new(300, 17)), 9);
+	}
+
+	#[test]
+	fn test_generate_mips() {
+		let texels = [
+			[0, 0, 0, 0],
+			[255, 255, 255, 255],
+			[0, 0, 0, 0],
+			[255, 255, 255, 255],
+			[0; 4],
+			[255; 4],
+		];
+		let size = UVec2::new(3, 2);
+		let linear = generate_mips(size, &texels, TextureEncoding::Linear);
+		assert_eq!(linear.len(), mip_levels(size) as usize);
+		assert_eq!(linear[0], texels);
+		// the 2x2 block at the origin is half black, half white
+		assert_eq!(linear[1], [[128; 4]]);
+
+		// averaged in linear space, so the sRGB encoded color is brighter than half while alpha is not
+		let srgb = generate_mips(size, &texels, TextureEncoding::Srgb);
+		assert_eq!(srgb[1], [[188, 188, 188, 128]]);
+	}
+
+	#[test]
+	fn test_generate_mips_odd() {
+		// the last column is clamped instead of wrapped, so the 1x1 mip of the 3x1 texture only sees the first two
+		let texels = [[0; 4], [0; 4], [255; 4]];
+		let mips = generate_mips(UVec2::new(3, 1), &texels, TextureEncoding::Linear);
+		assert_eq!(mips.len(), 2);
+		assert_eq!(mips[1], [[0; 4]]);
+	}
+}
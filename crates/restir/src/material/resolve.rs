@@ -0,0 +1,62 @@
+use crate::material::debug::VisiDebugPipeline;
+use crate::material::pbr::VisiPbrPipeline;
+use crate::material::system::classify_pipeline::{MaterialClassifyBuffers, MaterialClassifyPipeline};
+use crate::visibility::scene::VisiCpuScene;
+use restir_shader::material::debug::DebugSettings;
+use restir_shader::material::pbr::PbrSurface;
+use restir_shader::material::system::MaterialType;
+use rust_gpu_bindless::descriptor::{Bindless, Image, Image2d, Image2dU, MutBuffer, MutImage, TransientDesc};
+use rust_gpu_bindless::pipeline::Recording;
+
+pub struct VisiMaterialPipelines {
+	pub classify: MaterialClassifyPipeline,
+	pub debug: VisiDebugPipeline,
+	pub pbr: VisiPbrPipeline,
+}
+
+impl VisiMaterialPipelines {
+	pub fn new(bindless: &Bindless) -> anyhow::Result<Self> {
+		Ok(Self {
+			classify: MaterialClassifyPipeline::new(bindless)?,
+			debug: VisiDebugPipeline::new(bindless)?,
+			pbr: VisiPbrPipeline::new(bindless)?,
+		})
+	}
+
+	/// Resolve the surface of every pixel into `surfaces`, running each material shader only over the tiles containing
+	/// its [`MaterialType`]. Returns `buffers` to be reused by the next frame.
+	pub fn resolve(
+		&self,
+		cmd: &mut Recording,
+		scene: &VisiCpuScene,
+		packed_vertex_image: TransientDesc<Image<Image2dU>>,
+		surfaces: TransientDesc<MutBuffer<[PbrSurface]>>,
+		buffers: MaterialClassifyBuffers,
+	) -> anyhow::Result<MaterialClassifyBuffers> {
+		let classification = self.classify.classify(cmd, scene, packed_vertex_image, buffers)?;
+		for tiles in &classification.material_tiles {
+			match tiles.material_type {
+				MaterialType::Pbr => self
+					.pbr
+					.tiles
+					.dispatch(cmd, scene, tiles, packed_vertex_image, surfaces)?,
+			}
+		}
+		Ok(classification.into_buffers())
+	}
+
+	/// Preview the material of every pixel lit without shadows, see
+	/// [`DebugType::Materials`](restir_shader::material::debug::DebugType::Materials)
+	pub fn preview(
+		&self,
+		cmd: &mut Recording,
+		scene: &VisiCpuScene,
+		packed_vertex_image: TransientDesc<Image<Image2dU>>,
+		output_image: TransientDesc<MutImage<Image2d>>,
+		settings: DebugSettings,
+	) -> anyhow::Result<()> {
+		self.pbr
+			.image
+			.dispatch(cmd, scene, packed_vertex_image, output_image, settings)
+	}
+}
@@ -0,0 +1,175 @@
+use crate::visibility::scene::VisiCpuScene;
+use restir_shader::material::system::classify::{MATERIAL_CLASSIFY_WG_SIZE, material_tile_count};
+use restir_shader::material::system::{MaterialType, classify, tile_list};
+use rust_gpu_bindless::descriptor::{
+	Bindless, BindlessAllocationScheme, BindlessBufferCreateInfo, BindlessBufferUsage, Buffer, Extent, Image, Image2dU,
+	MutBuffer, MutDesc, RCDesc, RCDescExt, TransientDesc,
+};
+use rust_gpu_bindless::pipeline::{
+	BindlessComputePipeline, IndirectCommandRead, MutBufferAccess, MutBufferAccessExt, Recording, ShaderRead,
+	ShaderReadWrite, TransferWrite,
+};
+
+/// The tiles containing some material type, to be dispatched over with a
+/// [`MaterialTilePipeline`](crate::material::system::tile_pipeline::MaterialTilePipeline)
+pub struct MaterialTiles<'a> {
+	pub material_type: MaterialType,
+	pub indirect: MutBufferAccess<'a, [u32; 3], IndirectCommandRead>,
+	pub tiles: MutBufferAccess<'a, [u32], ShaderRead>,
+}
+
+/// The buffers [`MaterialClassifyPipeline::classify`] writes into, which are kept by each renderer and only
+/// reallocated if the extent changes
+pub struct MaterialClassifyBuffers {
+	pub extent: Extent,
+	/// the material type mask of each tile
+	pub tile_materials: MutDesc<MutBuffer<[u32]>>,
+	/// the dispatch_indirect arguments of each [`MaterialType`]
+	pub indirect: Vec<MutDesc<MutBuffer<[u32; 3]>>>,
+	/// the tiles containing each [`MaterialType`]
+	pub tiles: Vec<MutDesc<MutBuffer<[u32]>>>,
+}
+
+impl MaterialClassifyBuffers {
+	pub fn new(bindless: &Bindless, extent: Extent) -> anyhow::Result<Self> {
+		let tile_count = material_tile_count([extent.width, extent.height].into());
+		let tile_count = (tile_count.x * tile_count.y) as usize;
+		let alloc_tiles = |name: &str| {
+			bindless.buffer().alloc_slice::<u32>(
+				&BindlessBufferCreateInfo {
+					usage: BindlessBufferUsage::STORAGE_BUFFER,
+					allocation_scheme: BindlessAllocationScheme::Dedicated,
+					name,
+				},
+				tile_count,
+			)
+		};
+		let material_types = (0..MaterialType::LEN).map(MaterialType::from);
+		Ok(Self {
+			extent,
+			tile_materials: alloc_tiles("material tile masks")?,
+			indirect: material_types
+				.clone()
+				.map(|material_type| {
+					bindless.buffer().alloc_sized::<[u32; 3]>(&BindlessBufferCreateInfo {
+						usage: BindlessBufferUsage::STORAGE_BUFFER
+							| BindlessBufferUsage::INDIRECT_BUFFER
+							| BindlessBufferUsage::TRANSFER_DST,
+						allocation_scheme: Default::default(),
+						name: &format!("material indirect {material_type:?}"),
+					})
+				})
+				.collect::<Result<_, _>>()?,
+			tiles: material_types
+				.map(|material_type| alloc_tiles(&format!("material tiles {material_type:?}")))
+				.collect::<Result<_, _>>()?,
+		})
+	}
+}
+
+/// The result of [`MaterialClassifyPipeline::classify`]
+pub struct MaterialClassification<'a> {
+	pub extent: Extent,
+	pub tile_materials: MutBufferAccess<'a, [u32], ShaderRead>,
+	/// the tiles of each [`MaterialType`]
+	pub material_tiles: Vec<MaterialTiles<'a>>,
+}
+
+impl MaterialClassification<'_> {
+	/// Return the buffers to be reused by the next frame
+	pub fn into_buffers(self) -> MaterialClassifyBuffers {
+		let (indirect, tiles) = self
+			.material_tiles
+			.into_iter()
+			.map(|tiles| (tiles.indirect.into_desc(), tiles.tiles.into_desc()))
+			.unzip();
+		MaterialClassifyBuffers {
+			extent: self.extent,
+			tile_materials: self.tile_materials.into_desc(),
+			indirect,
+			tiles,
+		}
+	}
+}
+
+pub struct MaterialClassifyPipeline {
+	classify: BindlessComputePipeline<classify::Param<'static>>,
+	tile_list: BindlessComputePipeline<tile_list::Param<'static>>,
+	/// copied into the dispatch_indirect arguments before counting any tiles
+	indirect_reset: RCDesc<Buffer<[u32; 3]>>,
+}
+
+impl MaterialClassifyPipeline {
+	pub fn new(bindless: &Bindless) -> anyhow::Result<Self> {
+		Ok(Self {
+			classify: bindless
+				.create_compute_pipeline(crate::shader::material::system::classify::material_classify::new())?,
+			tile_list: bindless
+				.create_compute_pipeline(crate::shader::material::system::tile_list::material_tile_list::new())?,
+			indirect_reset: bindless.buffer().alloc_shared_from_data(
+				&BindlessBufferCreateInfo {
+					usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::TRANSFER_SRC,
+					allocation_scheme: Default::default(),
+					name: "material indirect reset",
+				},
+				[0u32, 1, 1],
+			)?,
+		})
+	}
+
+	/// Classify the tiles of the screen by the `material_type` of the instances they cover, returning the tiles of each
+	/// [`MaterialType`]. `buffers` must have been allocated for the viewport of `scene`.
+	pub fn classify<'a>(
+		&self,
+		cmd: &mut Recording<'a>,
+		scene: &VisiCpuScene,
+		packed_vertex_image: TransientDesc<Image<Image2dU>>,
+		buffers: MaterialClassifyBuffers,
+	) -> anyhow::Result<MaterialClassification<'a>> {
+		let tile_count = material_tile_count(scene.camera.viewport_size);
+		let tile_count = tile_count.x * tile_count.y;
+		let group_counts = [tile_count.div_ceil(MATERIAL_CLASSIFY_WG_SIZE), 1, 1];
+
+		// every tile writes its mask, so there is no need to preserve the previous contents
+		let tile_materials = unsafe { buffers.tile_materials.access_as_undefined::<ShaderReadWrite>(cmd)? };
+		cmd.dispatch(
+			&self.classify,
+			group_counts,
+			classify::Param {
+				scene: scene.scene.to_transient(cmd),
+				packed_vertex_image,
+				tile_materials: tile_materials.to_mut_transient()?,
+			},
+		)?;
+		let tile_materials = tile_materials.transition::<ShaderRead>()?;
+
+		let mut material_tiles = Vec::with_capacity(MaterialType::LEN as usize);
+		for (material_type, (indirect, tiles)) in buffers.indirect.into_iter().zip(buffers.tiles).enumerate() {
+			let material_type = MaterialType::from(material_type as u32);
+			let indirect = unsafe { indirect.access_as_undefined::<TransferWrite>(cmd)? };
+			cmd.copy_buffer_to_buffer(&self.indirect_reset, &indirect)?;
+			let indirect = indirect.transition::<ShaderReadWrite>()?;
+			let tiles = unsafe { tiles.access_as_undefined::<ShaderReadWrite>(cmd)? };
+			cmd.dispatch(
+				&self.tile_list,
+				group_counts,
+				tile_list::Param {
+					tile_materials: tile_materials.to_transient()?,
+					indirect: indirect.to_mut_transient()?,
+					tiles: tiles.to_mut_transient()?,
+					material_type,
+				},
+			)?;
+			material_tiles.push(MaterialTiles {
+				material_type,
+				indirect: indirect.transition::<IndirectCommandRead>()?,
+				tiles: tiles.transition::<ShaderRead>()?,
+			});
+		}
+		Ok(MaterialClassification {
+			extent: buffers.extent,
+			tile_materials,
+			material_tiles,
+		})
+	}
+}
@@ -23,7 +23,7 @@ impl<T: BufferStruct> MaterialImagePipeline<T> {
 	pub fn dispatch(
 		&self,
 		cmd: &mut Recording,
-		scene: VisiCpuScene,
+		scene: &VisiCpuScene,
 		packed_vertex_image: TransientDesc<Image<Image2dU>>,
 		output_image: TransientDesc<MutImage<Image2d>>,
 		param: T,
@@ -1,21 +1,26 @@
 use crate::material::system::image_pipeline::MaterialImagePipeline;
-use restir_shader::material::system::image_shader::Param;
+use crate::material::system::tile_pipeline::MaterialTilePipeline;
+use restir_shader::material::system::{image_shader, tile_shader};
 use rust_gpu_bindless::descriptor::Bindless;
 use rust_gpu_bindless_shaders::buffer_content::BufferStruct;
 use rust_gpu_bindless_shaders::shader::BindlessShader;
 use rust_gpu_bindless_shaders::shader_type::ComputeShader;
 
+/// The pipelines of a [`material_shader`](restir_shader::material_shader)
 pub struct MaterialPipeline<T: BufferStruct> {
 	pub image: MaterialImagePipeline<T>,
+	pub tiles: MaterialTilePipeline,
 }
 
 impl<T: BufferStruct> MaterialPipeline<T> {
 	pub fn new(
 		bindless: &Bindless,
-		image: &impl BindlessShader<ShaderType = ComputeShader, ParamConstant = Param<'static, T>>,
+		image: &impl BindlessShader<ShaderType = ComputeShader, ParamConstant = image_shader::Param<'static, T>>,
+		tiles: &impl BindlessShader<ShaderType = ComputeShader, ParamConstant = tile_shader::Param<'static>>,
 	) -> anyhow::Result<Self> {
 		Ok(Self {
 			image: MaterialImagePipeline::new(bindless, image)?,
+			tiles: MaterialTilePipeline::new(bindless, tiles)?,
 		})
 	}
 }
@@ -1,2 +1,4 @@
+pub mod classify_pipeline;
 pub mod image_pipeline;
 pub mod material_pipeline;
+pub mod tile_pipeline;
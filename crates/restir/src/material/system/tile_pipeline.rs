@@ -0,0 +1,47 @@
+use crate::material::system::classify_pipeline::MaterialTiles;
+use crate::visibility::scene::VisiCpuScene;
+use restir_shader::material::pbr::PbrSurface;
+use restir_shader::material::system::tile_shader::Param;
+use rust_gpu_bindless::descriptor::{Bindless, Image, Image2dU, MutBuffer, RCDescExt, TransientDesc};
+use rust_gpu_bindless::pipeline::{BindlessComputePipeline, Recording};
+use rust_gpu_bindless_shaders::shader::BindlessShader;
+use rust_gpu_bindless_shaders::shader_type::ComputeShader;
+
+pub struct MaterialTilePipeline {
+	pipeline: BindlessComputePipeline<Param<'static>>,
+}
+
+impl MaterialTilePipeline {
+	pub fn new(
+		bindless: &Bindless,
+		shader: &impl BindlessShader<ShaderType = ComputeShader, ParamConstant = Param<'static>>,
+	) -> anyhow::Result<Self> {
+		Ok(Self {
+			pipeline: bindless.create_compute_pipeline(shader)?,
+		})
+	}
+
+	/// Resolve the surfaces of all pixels of instances with the material type of `tiles`, only dispatching over the
+	/// tiles containing it
+	pub fn dispatch(
+		&self,
+		cmd: &mut Recording,
+		scene: &VisiCpuScene,
+		tiles: &MaterialTiles,
+		packed_vertex_image: TransientDesc<Image<Image2dU>>,
+		surfaces: TransientDesc<MutBuffer<[PbrSurface]>>,
+	) -> anyhow::Result<()> {
+		cmd.dispatch_indirect(
+			&self.pipeline,
+			&tiles.indirect,
+			Param {
+				scene: scene.scene.to_transient(cmd),
+				packed_vertex_image,
+				surfaces,
+				tiles: tiles.tiles.to_transient()?,
+				material_type: tiles.material_type,
+			},
+		)?;
+		Ok(())
+	}
+}
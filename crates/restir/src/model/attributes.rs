@@ -0,0 +1,269 @@
+use glam::{Vec2, Vec3, Vec4};
+use restir_shader::visibility::scene::{VisiIndices, VisiVertex, VisiVertexAttributes};
+
+/// How normals are computed for models without any
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ComputedNormals {
+	/// the geometric normal of each triangle, duplicating all vertices shared between triangles
+	Flat,
+	/// area weighted average of the normals of all triangles sharing a vertex
+	#[default]
+	Smooth,
+}
+
+/// Per-vertex attributes of a model, attributes that are `None` are computed
+#[derive(Clone, Debug, Default)]
+pub struct VisiCpuAttributes {
+	pub normals: Option<Vec<Vec3>>,
+	pub tex_coords: Option<Vec<Vec2>>,
+	/// tangents with `w` being the sign of the bitangent, computed from `tex_coords` if missing
+	pub tangents: Option<Vec<Vec4>>,
+	pub computed_normals: ComputedNormals,
+}
+
+impl VisiCpuAttributes {
+	/// No attributes with flat normals
+	pub fn flat() -> Self {
+		Self {
+			computed_normals: ComputedNormals::Flat,
+			..Self::default()
+		}
+	}
+}
+
+/// Compute all missing `attributes` of a mesh. Returns the vertices and indices, which only differ from the inputs if
+/// flat normals were computed, and the attributes of each vertex.
+pub fn compute_attributes(
+	vertices: Vec<VisiVertex>,
+	indices: Vec<VisiIndices>,
+	attributes: VisiCpuAttributes,
+) -> anyhow::Result<(Vec<VisiVertex>, Vec<VisiIndices>, Vec<VisiVertexAttributes>)> {
+	let VisiCpuAttributes {
+		normals,
+		tex_coords,
+		tangents,
+		computed_normals,
+	} = attributes;
+	let len = vertices.len();
+	for (name, attr_len) in [
+		("normals", normals.as_ref().map(Vec::len)),
+		("tex_coords", tex_coords.as_ref().map(Vec::len)),
+		("tangents", tangents.as_ref().map(Vec::len)),
+	] {
+		if attr_len.is_some_and(|attr_len| attr_len != len) {
+			anyhow::bail!("{len} vertices have {} {name}", attr_len.unwrap());
+		}
+	}
+	if let Some(index) = indices.iter().flat_map(|i| i.0).find(|i| *i as usize >= len) {
+		anyhow::bail!("index {index} is out of bounds of {len} vertices");
+	}
+
+	let (vertices, indices, tex_coords, tangents) = if normals.is_none() && computed_normals == ComputedNormals::Flat {
+		(
+			unweld(&vertices, &indices),
+			(0..indices.len() as u32)
+				.map(|i| VisiIndices([i * 3, i * 3 + 1, i * 3 + 2]))
+				.collect::<Vec<_>>(),
+			tex_coords.map(|attr| unweld(&attr, &indices)),
+			tangents.map(|attr| unweld(&attr, &indices)),
+		)
+	} else {
+		(vertices, indices, tex_coords, tangents)
+	};
+
+	let normals = normals.unwrap_or_else(|| {
+		// unwelded vertices of flat normals only have a single triangle
+		let mut normals = vec![Vec3::ZERO; vertices.len()];
+		for triangle in &indices {
+			let [a, b, c] = triangle.map(|i| vertices[i as usize].0);
+			// length is twice the triangle's area
+			let normal = (b - a).cross(c - a);
+			for i in triangle.0 {
+				normals[i as usize] += normal;
+			}
+		}
+		normals.iter().map(|n| n.normalize_or_zero()).collect()
+	});
+	let tangents = tangents.unwrap_or_else(|| compute_tangents(&vertices, &indices, &normals, tex_coords.as_deref()));
+	let tex_coords = tex_coords.unwrap_or_else(|| vec![Vec2::ZERO; vertices.len()]);
+
+	let attributes = (0..vertices.len())
+		.map(|i| VisiVertexAttributes {
+			normal: normals[i],
+			tangent: tangents[i],
+			tex_coord: tex_coords[i],
+		})
+		.collect();
+	Ok((vertices, indices, attributes))
+}
+
+/// One copy of `attr` for each corner of all triangles
+fn unweld<T: Copy>(attr: &[T], indices: &[VisiIndices]) -> Vec<T> {
+	indices.iter().flat_map(|i| i.0).map(|i| attr[i as usize]).collect()
+}
+
+/// Tangents following the direction of increasing u of `tex_coords`, see "Computing Tangent Space Basis Vectors for an
+/// Arbitrary Mesh" by Lengyel. Vertices without texture coordinates or degenerate mappings get an arbitrary tangent.
+fn compute_tangents(
+	vertices: &[VisiVertex],
+	indices: &[VisiIndices],
+	normals: &[Vec3],
+	tex_coords: Option<&[Vec2]>,
+) -> Vec<Vec4> {
+	let mut tangents = vec![Vec3::ZERO; vertices.len()];
+	let mut bitangents = vec![Vec3::ZERO; vertices.len()];
+	if let Some(tex_coords) = tex_coords {
+		for triangle in indices {
+			let [p0, p1, p2] = triangle.map(|i| vertices[i as usize].0);
+			let [t0, t1, t2] = triangle.map(|i| tex_coords[i as usize]);
+			let (e1, e2) = (p1 - p0, p2 - p0);
+			let (d1, d2) = (t1 - t0, t2 - t0);
+			let det = d1.x * d2.y - d2.x * d1.y;
+			if det == 0. {
+				continue;
+			}
+			let tangent = (e1 * d2.y - e2 * d1.y) / det;
+			let bitangent = (e2 * d1.x - e1 * d2.x) / det;
+			for i in triangle.0 {
+				tangents[i as usize] += tangent;
+				bitangents[i as usize] += bitangent;
+			}
+		}
+	}
+
+	(0..vertices.len())
+		.map(|i| {
+			let normal = normals[i];
+			// Gram-Schmidt orthogonalize against the normal
+			let tangent = (tangents[i] - normal * normal.dot(tangents[i])).normalize_or_zero();
+			if tangent == Vec3::ZERO {
+				return Vec4::from((normal.any_orthonormal_vector(), 1.));
+			}
+			let w = if normal.cross(tangent).dot(bitangents[i]) < 0. {
+				-1.
+			} else {
+				1.
+			};
+			Vec4::from((tangent, w))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use glam::Vec4Swizzles;
+
+	/// two quads at a right angle, sharing an edge along the x-axis
+	fn bent_quad() -> (Vec<VisiVertex>, Vec<VisiIndices>) {
+		let vertices = [
+			Vec3::new(0., 0., 1.),
+			Vec3::new(1., 0., 1.),
+			Vec3::new(1., 0., 0.),
+			Vec3::new(0., 0., 0.),
+			Vec3::new(0., 1., 0.),
+			Vec3::new(1., 1., 0.),
+		]
+		.map(VisiVertex)
+		.to_vec();
+		let indices = vec![
+			VisiIndices([0, 1, 2]),
+			VisiIndices([2, 3, 0]),
+			VisiIndices([3, 2, 5]),
+			VisiIndices([5, 4, 3]),
+		];
+		(vertices, indices)
+	}
+
+	#[test]
+	fn test_smooth_normals() {
+		let (vertices, indices) = bent_quad();
+		let (out_vertices, out_indices, attributes) =
+			compute_attributes(vertices.clone(), indices.clone(), VisiCpuAttributes::default()).unwrap();
+		assert_eq!(out_vertices.len(), vertices.len());
+		assert_eq!(out_indices.len(), indices.len());
+		// vertices on the bend are shared by two triangles of one face and one of the other
+		assert!(attributes[0].normal.abs_diff_eq(Vec3::Y, 1e-6));
+		assert!(
+			attributes[2]
+				.normal
+				.abs_diff_eq(Vec3::new(0., 2., 1.).normalize(), 1e-6)
+		);
+		assert!(
+			attributes[3]
+				.normal
+				.abs_diff_eq(Vec3::new(0., 1., 2.).normalize(), 1e-6)
+		);
+		assert!(attributes[4].normal.abs_diff_eq(Vec3::Z, 1e-6));
+	}
+
+	#[test]
+	fn test_flat_normals() {
+		let (vertices, indices) = bent_quad();
+		let (out_vertices, out_indices, attributes) =
+			compute_attributes(vertices, indices, VisiCpuAttributes::flat()).unwrap();
+		assert_eq!(out_vertices.len(), 12);
+		assert_eq!(out_indices[3].0, [9, 10, 11]);
+		assert_eq!(out_vertices[9].0, Vec3::new(1., 1., 0.));
+		for (i, attribute) in attributes.iter().enumerate() {
+			let expected = if i < 6 { Vec3::Y } else { Vec3::Z };
+			assert!(
+				attribute.normal.abs_diff_eq(expected, 1e-6),
+				"vertex {i}: {}",
+				attribute.normal
+			);
+		}
+	}
+
+	#[test]
+	fn test_tangents_follow_tex_coords() {
+		let (vertices, indices) = bent_quad();
+		// u along +x, v along the bend
+		let tex_coords = vec![
+			Vec2::new(0., 0.),
+			Vec2::new(1., 0.),
+			Vec2::new(1., 1.),
+			Vec2::new(0., 1.),
+			Vec2::new(0., 2.),
+			Vec2::new(1., 2.),
+		];
+		let (_, _, attributes) = compute_attributes(
+			vertices,
+			indices,
+			VisiCpuAttributes {
+				tex_coords: Some(tex_coords),
+				..VisiCpuAttributes::default()
+			},
+		)
+		.unwrap();
+		for attribute in &attributes {
+			assert!(
+				attribute.tangent.xyz().abs_diff_eq(Vec3::X, 1e-6),
+				"{}",
+				attribute.tangent
+			);
+			assert_eq!(attribute.tangent.w, 1.);
+		}
+	}
+
+	#[test]
+	fn test_tangents_without_tex_coords() {
+		let (vertices, indices) = bent_quad();
+		let (_, _, attributes) = compute_attributes(vertices, indices, VisiCpuAttributes::default()).unwrap();
+		for attribute in &attributes {
+			let tangent = attribute.tangent.xyz();
+			assert!(tangent.is_normalized());
+			assert!(tangent.dot(attribute.normal).abs() < 1e-6);
+		}
+	}
+
+	#[test]
+	fn test_mismatched_len() {
+		let (vertices, indices) = bent_quad();
+		let attributes = VisiCpuAttributes {
+			normals: Some(vec![Vec3::Y; 2]),
+			..VisiCpuAttributes::default()
+		};
+		assert!(compute_attributes(vertices, indices, attributes).is_err());
+	}
+}
@@ -0,0 +1,177 @@
+use crate::model::VisiCpuModel;
+use crate::model::attributes::{ComputedNormals, VisiCpuAttributes};
+use crate::visibility::scene::VisiScenePush;
+use glam::{Affine3A, Mat4, UVec2, Vec2, Vec3, Vec4};
+use gltf::camera::Projection;
+use gltf::khr_lights_punctual::Kind;
+use gltf::mesh::Mode;
+use restir_shader::camera::Camera;
+use restir_shader::light::Light;
+use restir_shader::utils::affine_transform::AffineTransform;
+use restir_shader::visibility::scene::{VisiIndices, VisiInstanceInfo, VisiVertex};
+use rust_gpu_bindless::descriptor::Bindless;
+use std::path::Path;
+
+/// Far plane of cameras with an infinite projection
+const DEFAULT_Z_FAR: f32 = 1000.;
+
+/// The default scene of a glTF file, with all meshes uploaded as [`VisiCpuModel`]s
+pub struct GltfScene {
+	/// the models of each glTF mesh, one per primitive, or multiple if a primitive has too many triangles
+	pub meshes: Vec<Vec<VisiCpuModel>>,
+	pub instances: Vec<GltfInstance>,
+	pub cameras: Vec<GltfCamera>,
+	/// lights from `KHR_lights_punctual`, in world space
+	pub lights: Vec<Light>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct GltfInstance {
+	/// index into [`GltfScene::meshes`]
+	pub mesh: usize,
+	pub world_from_local: Affine3A,
+}
+
+/// A perspective camera of a glTF scene, looking along -Z
+#[derive(Copy, Clone, Debug)]
+pub struct GltfCamera {
+	/// camera transform without any scale
+	pub world_from_camera: Affine3A,
+	pub fov_y: f32,
+	pub z_near: f32,
+	pub z_far: f32,
+}
+
+impl GltfCamera {
+	pub fn to_camera(&self, viewport_size: UVec2) -> Camera {
+		Camera::new_perspective_rh_y_flip(
+			viewport_size,
+			self.fov_y,
+			self.z_near,
+			self.z_far,
+			AffineTransform::new(self.world_from_camera),
+		)
+	}
+}
+
+impl GltfScene {
+	/// Load a `.gltf` or `.glb` file. Only triangle primitives are imported, images and materials are ignored.
+	///
+	/// Light intensities of `KHR_lights_punctual` are photometric, but are used as is like any other emission.
+	pub fn load(bindless: &Bindless, path: &Path) -> anyhow::Result<Self> {
+		let gltf::Gltf { document, blob } = gltf::Gltf::open(path)?;
+		let buffers = gltf::import_buffers(&document, path.parent(), blob)?;
+
+		let meshes = document
+			.meshes()
+			.map(|mesh| {
+				let mut models = Vec::new();
+				for primitive in mesh.primitives() {
+					// points and lines can't be rasterized into the visibility buffer
+					if primitive.mode() != Mode::Triangles {
+						continue;
+					}
+					let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+					let vertices = reader
+						.read_positions()
+						.ok_or_else(|| anyhow::anyhow!("mesh {:?} has no positions", mesh.name()))?
+						.map(|p| VisiVertex(Vec3::from_array(p)))
+						.collect::<Vec<_>>();
+					let indices = match reader.read_indices() {
+						Some(indices) => indices.into_u32().collect::<Vec<_>>(),
+						None => (0..vertices.len() as u32).collect(),
+					};
+					let indices = indices
+						.as_chunks::<3>()
+						.0
+						.iter()
+						.map(|i| VisiIndices(*i))
+						.collect::<Vec<_>>();
+					let attributes = VisiCpuAttributes {
+						normals: reader.read_normals().map(|n| n.map(Vec3::from_array).collect()),
+						tex_coords: reader
+							.read_tex_coords(0)
+							.map(|t| t.into_f32().map(Vec2::from_array).collect()),
+						tangents: reader.read_tangents().map(|t| t.map(Vec4::from_array).collect()),
+						// the spec requires flat normals for primitives without any
+						computed_normals: ComputedNormals::Flat,
+					};
+					models.extend(VisiCpuModel::new_split(bindless, vertices, indices, attributes)?);
+				}
+				Ok(models)
+			})
+			.collect::<anyhow::Result<Vec<_>>>()?;
+
+		let mut scene = Self {
+			meshes,
+			instances: Vec::new(),
+			cameras: Vec::new(),
+			lights: Vec::new(),
+		};
+		if let Some(gltf_scene) = document.default_scene().or_else(|| document.scenes().next()) {
+			for node in gltf_scene.nodes() {
+				scene.visit_node(&node, Affine3A::IDENTITY);
+			}
+		}
+		Ok(scene)
+	}
+
+	fn visit_node(&mut self, node: &gltf::Node, parent: Affine3A) {
+		let world_from_local = parent * Affine3A::from_mat4(Mat4::from_cols_array_2d(&node.transform().matrix()));
+		let position = world_from_local.transform_point3(Vec3::ZERO);
+		let forward = world_from_local.transform_vector3(Vec3::NEG_Z).normalize_or_zero();
+
+		if let Some(mesh) = node.mesh() {
+			self.instances.push(GltfInstance {
+				mesh: mesh.index(),
+				world_from_local,
+			});
+		}
+		if let Some(Projection::Perspective(perspective)) = node.camera().map(|camera| camera.projection()) {
+			let (_, rotation, translation) = world_from_local.to_scale_rotation_translation();
+			self.cameras.push(GltfCamera {
+				world_from_camera: Affine3A::from_rotation_translation(rotation, translation),
+				fov_y: perspective.yfov(),
+				z_near: perspective.znear(),
+				z_far: perspective.zfar().unwrap_or(DEFAULT_Z_FAR),
+			});
+		}
+		if let Some(light) = node.light() {
+			let emission = Vec3::from_array(light.color()) * light.intensity();
+			self.lights.push(match light.kind() {
+				Kind::Directional => Light::directional(forward, emission),
+				Kind::Point => Light::point(position, emission),
+				Kind::Spot {
+					inner_cone_angle,
+					outer_cone_angle,
+				} => Light::spot(position, forward, emission, inner_cone_angle, outer_cone_angle),
+			});
+		}
+
+		for child in node.children() {
+			self.visit_node(&child, world_from_local);
+		}
+	}
+
+	/// Push all instances and lights of this scene into `scene`, returning the handles of the instances of each
+	/// [`GltfInstance`]
+	pub fn push<S: VisiScenePush>(&self, scene: &mut S) -> Vec<Vec<S::Instance>> {
+		for light in &self.lights {
+			scene.push_light(*light);
+		}
+		self.instances
+			.iter()
+			.map(|instance| {
+				self.meshes[instance.mesh]
+					.iter()
+					.map(|model| {
+						scene.push(
+							model,
+							VisiInstanceInfo::new(AffineTransform::new(instance.world_from_local), 0),
+						)
+					})
+					.collect()
+			})
+			.collect()
+	}
+}
@@ -1,5 +1,8 @@
+pub mod attributes;
+pub mod gltf;
 #[allow(clippy::module_inception)]
 mod model;
+pub mod obj;
 pub mod parametized;
 
 pub use model::*;
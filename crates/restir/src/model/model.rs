@@ -1,23 +1,79 @@
-use restir_shader::visibility::scene::{VisiIndices, VisiModel, VisiVertex};
+use crate::model::attributes::{VisiCpuAttributes, compute_attributes};
+use crate::visibility::bvh::build_bvh;
+use glam::Vec3;
+use restir_shader::visibility::id::TRIANGLE_BITS;
+use restir_shader::visibility::scene::{VisiIndices, VisiModel, VisiVertex, VisiVertexAttributes};
 use rust_gpu_bindless::__private::static_assertions::const_assert_eq;
 use rust_gpu_bindless::descriptor::{
 	Bindless, BindlessBufferCreateInfo, BindlessBufferUsage, Buffer, DescBufferLenExt, RCDesc, RCDescExt,
 };
+use rustc_hash::FxHashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
-#[derive(Clone, Eq, PartialEq, Hash)]
+#[derive(Clone)]
 pub struct VisiCpuModel {
 	pub model: RCDesc<Buffer<VisiModel>>,
 	pub indices: RCDesc<Buffer<[u32]>>,
 	/// Use this instead of `indices.len()`. Silly len repr in bindless strikes again.
 	pub indices_count: u32,
+	/// CPU copy of the triangles, for building light sampling structures
+	pub cpu_triangles: Arc<[VisiIndices]>,
+	/// CPU copy of the vertices, for building light sampling structures
+	pub cpu_vertices: Arc<[VisiVertex]>,
+	/// local space AABB as `[min, max]`
+	pub bounds: [Vec3; 2],
+}
+
+/// Models are identified by their [`VisiModel`] buffer
+impl PartialEq for VisiCpuModel {
+	fn eq(&self, other: &Self) -> bool {
+		self.model == other.model
+	}
+}
+
+impl Eq for VisiCpuModel {}
+
+impl Hash for VisiCpuModel {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.model.hash(state);
+	}
 }
 
 impl VisiCpuModel {
+	/// Create a model, computing all `attributes` that are missing
 	pub fn new(
 		bindless: &Bindless,
 		vertices: impl ExactSizeIterator<Item = VisiVertex>,
 		indices: impl ExactSizeIterator<Item = VisiIndices>,
+		attributes: VisiCpuAttributes,
+	) -> anyhow::Result<Self> {
+		let (vertices, indices, attributes) = compute_attributes(vertices.collect(), indices.collect(), attributes)?;
+		Self::upload(bindless, vertices, indices, attributes)
+	}
+
+	fn upload(
+		bindless: &Bindless,
+		vertices: Vec<VisiVertex>,
+		indices: Vec<VisiIndices>,
+		attributes: Vec<VisiVertexAttributes>,
 	) -> anyhow::Result<Self> {
+		let cpu_triangles = Arc::<[_]>::from(indices);
+		let cpu_vertices = Arc::<[_]>::from(vertices);
+		let bounds = cpu_vertices
+			.iter()
+			.fold([Vec3::INFINITY, Vec3::NEG_INFINITY], |[min, max], v| {
+				[min.min(v.0), max.max(v.0)]
+			});
+		let triangle_bounds = cpu_triangles
+			.iter()
+			.map(|t| {
+				let [a, b, c] = t.map(|i| cpu_vertices[i as usize].0);
+				[a.min(b).min(c), a.max(b).max(c)]
+			})
+			.collect::<Vec<_>>();
+		let bvh = build_bvh(&triangle_bounds).upload(bindless)?;
+
 		let triangles = bindless.buffer().alloc_shared_from_iter(
 			&BindlessBufferCreateInfo {
 				usage: BindlessBufferUsage::MAP_WRITE
@@ -26,7 +82,7 @@ impl VisiCpuModel {
 				allocation_scheme: Default::default(),
 				name: "visi model indices",
 			},
-			indices,
+			cpu_triangles.iter().copied(),
 		)?;
 
 		let vertices = bindless.buffer().alloc_shared_from_iter(
@@ -35,7 +91,16 @@ impl VisiCpuModel {
 				allocation_scheme: Default::default(),
 				name: "visi model vertices",
 			},
-			vertices,
+			cpu_vertices.iter().copied(),
+		)?;
+
+		let attributes = bindless.buffer().alloc_shared_from_iter(
+			&BindlessBufferCreateInfo {
+				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+				allocation_scheme: Default::default(),
+				name: "visi model attributes",
+			},
+			attributes,
 		)?;
 
 		let model = bindless.buffer().alloc_shared_from_data(
@@ -47,6 +112,8 @@ impl VisiCpuModel {
 			VisiModel {
 				triangles: triangles.to_strong(),
 				vertices: vertices.to_strong(),
+				attributes: attributes.to_strong(),
+				bvh: bvh.to_gpu(),
 			},
 		)?;
 
@@ -58,6 +125,114 @@ impl VisiCpuModel {
 			model,
 			indices,
 			indices_count,
+			cpu_triangles,
+			cpu_vertices,
+			bounds,
+		})
+	}
+
+	/// Create models from a mesh of any size, split into multiple models if it has more triangles than a
+	/// [`PackedGeometryId`] can address. Returns no models if the mesh has no triangles.
+	///
+	/// [`PackedGeometryId`]: restir_shader::visibility::id::PackedGeometryId
+	pub fn new_split(
+		bindless: &Bindless,
+		vertices: Vec<VisiVertex>,
+		indices: Vec<VisiIndices>,
+		attributes: VisiCpuAttributes,
+	) -> anyhow::Result<Vec<Self>> {
+		let (vertices, indices, attributes) = compute_attributes(vertices, indices, attributes)?;
+		let vertices = vertices.into_iter().zip(attributes).collect::<Vec<_>>();
+		split_mesh(&vertices, &indices, 1 << TRIANGLE_BITS)?
+			.into_iter()
+			.map(|(vertices, indices)| {
+				let (vertices, attributes) = vertices.into_iter().unzip();
+				Self::upload(bindless, vertices, indices, attributes)
+			})
+			.collect()
+	}
+}
+
+/// Split a mesh into chunks of at most `max_triangles`, each only containing the vertices its triangles reference.
+pub fn split_mesh<V: Copy>(
+	vertices: &[V],
+	indices: &[VisiIndices],
+	max_triangles: usize,
+) -> anyhow::Result<Vec<(Vec<V>, Vec<VisiIndices>)>> {
+	if let Some(index) = indices.iter().flat_map(|i| i.0).find(|i| *i as usize >= vertices.len()) {
+		anyhow::bail!("index {index} is out of bounds of {} vertices", vertices.len());
+	}
+	if indices.len() <= max_triangles {
+		return Ok(if indices.is_empty() {
+			Vec::new()
+		} else {
+			vec![(vertices.to_vec(), indices.to_vec())]
+		});
+	}
+
+	Ok(indices
+		.chunks(max_triangles)
+		.map(|chunk| {
+			let mut remap = FxHashMap::default();
+			let mut chunk_vertices = Vec::new();
+			let chunk_indices = chunk
+				.iter()
+				.map(|triangle| {
+					VisiIndices(triangle.map(|i| {
+						*remap.entry(i).or_insert_with(|| {
+							chunk_vertices.push(vertices[i as usize]);
+							chunk_vertices.len() as u32 - 1
+						})
+					}))
+				})
+				.collect();
+			(chunk_vertices, chunk_indices)
 		})
+		.collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn strip(triangles: u32) -> (Vec<VisiVertex>, Vec<VisiIndices>) {
+		let vertices = (0..triangles + 2)
+			.map(|i| VisiVertex(Vec3::new(i as f32, (i % 2) as f32, 0.)))
+			.collect();
+		let indices = (0..triangles).map(|i| VisiIndices([i, i + 1, i + 2])).collect();
+		(vertices, indices)
+	}
+
+	fn positions(vertices: &[VisiVertex], indices: &[VisiIndices]) -> Vec<[Vec3; 3]> {
+		indices.iter().map(|t| t.map(|i| vertices[i as usize].0)).collect()
+	}
+
+	#[test]
+	fn test_split_keeps_triangles() {
+		let (vertices, indices) = strip(11);
+		let chunks = split_mesh(&vertices, &indices, 4).unwrap();
+		assert_eq!(chunks.iter().map(|(_, i)| i.len()).collect::<Vec<_>>(), [4, 4, 3]);
+		let split = chunks
+			.iter()
+			.flat_map(|(vertices, indices)| positions(vertices, indices))
+			.collect::<Vec<_>>();
+		assert_eq!(split, positions(&vertices, &indices));
+		// only referenced vertices are copied
+		assert_eq!(chunks[2].0.len(), 5);
+	}
+
+	#[test]
+	fn test_no_split() {
+		let (vertices, indices) = strip(3);
+		let chunks = split_mesh(&vertices, &indices, 3).unwrap();
+		assert_eq!(chunks.len(), 1);
+		assert_eq!(chunks[0].0.len(), vertices.len());
+		assert!(split_mesh(&vertices, &[], 3).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_out_of_bounds() {
+		let (vertices, _) = strip(1);
+		assert!(split_mesh(&vertices, &[VisiIndices([0, 1, 3])], 4).is_err());
 	}
 }
@@ -0,0 +1,506 @@
+use crate::material::pbr::VisiCpuPbrMaterial;
+use crate::model::VisiCpuModel;
+use crate::model::attributes::{ComputedNormals, VisiCpuAttributes};
+use crate::visibility::scene::{VisiCpuLight, VisiScenePush};
+use anyhow::Context;
+use glam::{Affine3A, Vec2, Vec3, Vec4};
+use restir_shader::material::pbr::DIELECTRIC_F0;
+use restir_shader::utils::affine_transform::AffineTransform;
+use restir_shader::visibility::id::TriangleId;
+use restir_shader::visibility::scene::{VisiIndices, VisiInstanceInfo, VisiVertex};
+use rust_gpu_bindless::descriptor::Bindless;
+use rustc_hash::FxHashMap;
+use std::path::Path;
+
+/// The parameters of a `.mtl` material relevant for rendering, all other parameters and textures are ignored. Rendered
+/// as the metallic-roughness material of [`Self::pbr_factors`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjMaterial {
+	pub name: String,
+	/// `Kd`
+	pub diffuse: Vec3,
+	/// `Ks`
+	pub specular: Vec3,
+	/// `Ns`, the phong exponent
+	pub shininess: f32,
+	/// `Ke`, radiance emitted by all triangles using this material
+	pub emission: Vec3,
+}
+
+/// The metallic-roughness parameters approximating an [`ObjMaterial`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ObjPbrFactors {
+	pub base_color: Vec3,
+	pub metallic: f32,
+	pub roughness: f32,
+}
+
+impl ObjMaterial {
+	pub fn new(name: String) -> Self {
+		Self {
+			name,
+			diffuse: Vec3::splat(0.8),
+			specular: Vec3::ZERO,
+			shininess: 0.,
+			emission: Vec3::ZERO,
+		}
+	}
+
+	/// Convert the diffuse and specular colors to metallic-roughness the same way glTF converts its
+	/// specular-glossiness materials, see `KHR_materials_pbrSpecularGlossiness`. The roughness matches the width of
+	/// the phong lobe with exponent `Ns`, using `alpha = sqrt(2 / (Ns + 2))` from "Microfacet Models for Refraction
+	/// through Rough Surfaces" by Walter et al.
+	pub fn pbr_factors(&self) -> ObjPbrFactors {
+		let specular = self.specular.clamp(Vec3::ZERO, Vec3::ONE);
+		let one_minus_specular = 1. - specular.max_element();
+		let diffuse = self.diffuse.max(Vec3::ZERO);
+		let metallic = solve_metallic(
+			perceived_brightness(diffuse),
+			perceived_brightness(specular),
+			one_minus_specular,
+		);
+		let from_diffuse = diffuse * one_minus_specular / (1. - DIELECTRIC_F0) / f32::max(1. - metallic, 1e-4);
+		let from_specular = (specular - DIELECTRIC_F0 * (1. - metallic)) / f32::max(metallic, 1e-4);
+		let base_color = from_diffuse.lerp(from_specular, metallic * metallic);
+
+		let alpha = f32::sqrt(2. / (f32::max(self.shininess, 0.) + 2.));
+		ObjPbrFactors {
+			base_color: base_color.clamp(Vec3::ZERO, Vec3::ONE),
+			metallic,
+			// alpha is the square of the perceptual roughness
+			roughness: alpha.sqrt(),
+		}
+	}
+}
+
+fn perceived_brightness(c: Vec3) -> f32 {
+	f32::sqrt(0.299 * c.x * c.x + 0.587 * c.y * c.y + 0.114 * c.z * c.z)
+}
+
+/// The metalness reproducing the perceived brightness of `diffuse` and `specular`
+fn solve_metallic(diffuse: f32, specular: f32, one_minus_specular: f32) -> f32 {
+	if specular < DIELECTRIC_F0 {
+		return 0.;
+	}
+	let a = DIELECTRIC_F0;
+	let b = diffuse * one_minus_specular / (1. - DIELECTRIC_F0) + specular - 2. * DIELECTRIC_F0;
+	let c = DIELECTRIC_F0 - specular;
+	let d = f32::max(b * b - 4. * a * c, 0.);
+	((-b + d.sqrt()) / (2. * a)).clamp(0., 1.)
+}
+
+/// All triangles of an `.obj` file using the same material, with only the vertices they reference
+#[derive(Clone, Debug, Default)]
+pub struct ObjGroup {
+	/// index into [`ObjMesh::materials`], `None` if no or an unknown material was used
+	pub material: Option<usize>,
+	pub vertices: Vec<Vec3>,
+	/// normal of each vertex, `None` if any face of this group has no normals
+	pub normals: Option<Vec<Vec3>>,
+	/// texture coordinates of each vertex, `None` if any face of this group has none
+	pub tex_coords: Option<Vec<Vec2>>,
+	pub triangles: Vec<[u32; 3]>,
+	/// index of each `.obj` `v/vt/vn` combination within `vertices`
+	remap: FxHashMap<ObjCorner, u32>,
+}
+
+/// Indices of the position, texture coordinate and normal of a face's corner
+type ObjCorner = (u32, Option<u32>, Option<u32>);
+
+/// The geometry of an `.obj` file grouped by material
+#[derive(Clone, Debug, Default)]
+pub struct ObjMesh {
+	pub materials: Vec<ObjMaterial>,
+	pub groups: Vec<ObjGroup>,
+}
+
+/// Parse an `.obj` file, polygons are triangulated as fans. `load_mtl` is called with the path of each `mtllib`.
+pub fn parse_obj(source: &str, mut load_mtl: impl FnMut(&str) -> anyhow::Result<String>) -> anyhow::Result<ObjMesh> {
+	let mut mesh = ObjMesh::default();
+	let mut positions = Vec::new();
+	let mut tex_coords = Vec::new();
+	let mut normals = Vec::new();
+	let mut group_of_material = FxHashMap::default();
+	let mut current_material = None;
+	let mut face = Vec::new();
+
+	for (line_nr, line) in source.lines().enumerate() {
+		let line = line.split('#').next().unwrap().trim();
+		let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+		let rest = rest.trim();
+		(|| -> anyhow::Result<()> {
+			match keyword {
+				"v" => positions.push(parse_vec3(rest)?),
+				"vn" => normals.push(parse_vec3(rest)?),
+				"vt" => {
+					let mut values = rest.split_whitespace().map(|v| v.parse::<f32>());
+					let u = values.next().ok_or_else(|| anyhow::anyhow!("expected u"))??;
+					let v = values.next().transpose()?.unwrap_or(0.);
+					// obj has its origin in the bottom left
+					tex_coords.push(Vec2::new(u, 1. - v));
+				}
+				"f" => {
+					face.clear();
+					for corner in rest.split_whitespace() {
+						let mut indices = corner.split('/');
+						let position = resolve_index(indices.next(), positions.len())?
+							.ok_or_else(|| anyhow::anyhow!("face corner {corner} has no position"))?;
+						let tex_coord = resolve_index(indices.next(), tex_coords.len())?;
+						let normal = resolve_index(indices.next(), normals.len())?;
+						face.push((position, tex_coord, normal));
+					}
+					if face.len() < 3 {
+						anyhow::bail!("face has less than 3 vertices");
+					}
+
+					let group = *group_of_material.entry(current_material).or_insert_with(|| {
+						mesh.groups.push(ObjGroup {
+							material: current_material,
+							normals: Some(Vec::new()),
+							tex_coords: Some(Vec::new()),
+							..ObjGroup::default()
+						});
+						mesh.groups.len() - 1
+					});
+					let group = &mut mesh.groups[group];
+					let mut local = |corner: ObjCorner| {
+						*group.remap.entry(corner).or_insert_with(|| {
+							let (position, tex_coord, normal) = corner;
+							group.vertices.push(positions[position as usize]);
+							group.tex_coords = group.tex_coords.take().zip(tex_coord).map(|(mut t, i)| {
+								t.push(tex_coords[i as usize]);
+								t
+							});
+							group.normals = group.normals.take().zip(normal).map(|(mut n, i)| {
+								n.push(normals[i as usize]);
+								n
+							});
+							group.vertices.len() as u32 - 1
+						})
+					};
+					let first = local(face[0]);
+					for edge in face[1..].windows(2) {
+						let triangle = [first, local(edge[0]), local(edge[1])];
+						group.triangles.push(triangle);
+					}
+				}
+				"usemtl" => current_material = mesh.materials.iter().position(|m| m.name == rest),
+				"mtllib" => mesh.materials.extend(parse_mtl(&load_mtl(rest)?)?),
+				// groups, smoothing groups and everything else
+				_ => (),
+			}
+			Ok(())
+		})()
+		.with_context(|| format!("obj line {}: {line}", line_nr + 1))?;
+	}
+	Ok(mesh)
+}
+
+/// Parse all materials of a `.mtl` file
+pub fn parse_mtl(source: &str) -> anyhow::Result<Vec<ObjMaterial>> {
+	let mut materials: Vec<ObjMaterial> = Vec::new();
+	for (line_nr, line) in source.lines().enumerate() {
+		let line = line.split('#').next().unwrap().trim();
+		let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+		let rest = rest.trim();
+		(|| -> anyhow::Result<()> {
+			if keyword == "newmtl" {
+				materials.push(ObjMaterial::new(rest.to_string()));
+				return Ok(());
+			}
+			let Some(material) = materials.last_mut() else {
+				return Ok(());
+			};
+			match keyword {
+				"Kd" => material.diffuse = parse_vec3(rest)?,
+				"Ks" => material.specular = parse_vec3(rest)?,
+				"Ke" => material.emission = parse_vec3(rest)?,
+				"Ns" => material.shininess = rest.parse()?,
+				_ => (),
+			}
+			Ok(())
+		})()
+		.with_context(|| format!("mtl line {}: {line}", line_nr + 1))?;
+	}
+	Ok(materials)
+}
+
+/// Resolve a 1-based or negative relative `index` into an array with `len` elements, `None` if `index` is missing
+fn resolve_index(index: Option<&str>, len: usize) -> anyhow::Result<Option<u32>> {
+	let Some(index) = index.filter(|i| !i.is_empty()) else {
+		return Ok(None);
+	};
+	let index = index.parse::<i64>()?;
+	let resolved = match index {
+		1.. => index - 1,
+		..0 => len as i64 + index,
+		0 => anyhow::bail!("index 0 is invalid"),
+	};
+	if !(0..len as i64).contains(&resolved) {
+		anyhow::bail!("index {index} is out of bounds of {len} elements");
+	}
+	Ok(Some(resolved as u32))
+}
+
+fn parse_vec3(s: &str) -> anyhow::Result<Vec3> {
+	let values = s
+		.split_whitespace()
+		.map(|v| v.parse::<f32>())
+		.collect::<Result<Vec<_>, _>>()?;
+	match values[..] {
+		// `Kd 0.5` is a grey color
+		[v] => Ok(Vec3::splat(v)),
+		// ignore the optional w of vertices
+		[x, y, z, ..] => Ok(Vec3::new(x, y, z)),
+		_ => anyhow::bail!("expected 3 values"),
+	}
+}
+
+/// A `.obj` file uploaded as [`VisiCpuModel`]s
+pub struct ObjScene {
+	pub materials: Vec<ObjMaterial>,
+	pub models: Vec<ObjModel>,
+}
+
+pub struct ObjModel {
+	pub model: VisiCpuModel,
+	/// index into [`ObjScene::materials`]
+	pub material: Option<usize>,
+}
+
+impl ObjScene {
+	/// One material per [`ObjMaterial`] in the same order, followed by a default material for models without any. Its
+	/// index is the material id of the instances [`Self::push`] creates.
+	pub async fn pbr_materials(&self, bindless: &Bindless) -> anyhow::Result<Vec<VisiCpuPbrMaterial>> {
+		let default = ObjMaterial::new("default".to_string());
+		let base = VisiCpuPbrMaterial::from_factors(bindless, Vec4::ONE, 0., 1., Vec3::ZERO).await?;
+		Ok(self
+			.materials
+			.iter()
+			.chain([&default])
+			.map(|material| {
+				let factors = material.pbr_factors();
+				VisiCpuPbrMaterial {
+					base_color_factor: Vec4::from((factors.base_color, 1.)),
+					metallic_factor: factors.metallic,
+					roughness_factor: factors.roughness,
+					emissive_factor: material.emission,
+					..base.clone()
+				}
+			})
+			.collect())
+	}
+
+	/// The material id of `model`, an index into [`Self::pbr_materials`]
+	pub fn material_id(&self, model: &ObjModel) -> u32 {
+		model.material.unwrap_or(self.materials.len()) as u32
+	}
+
+	/// Load an `.obj` file and the `.mtl` files it references
+	pub fn load(bindless: &Bindless, path: &Path) -> anyhow::Result<Self> {
+		let source = std::fs::read_to_string(path).with_context(|| format!("reading {path:?}"))?;
+		let dir = path.parent().unwrap_or(Path::new(""));
+		let mesh = parse_obj(&source, |mtl| {
+			let mtl = dir.join(mtl);
+			std::fs::read_to_string(&mtl).with_context(|| format!("reading {mtl:?}"))
+		})?;
+
+		let mut models = Vec::new();
+		for group in mesh.groups {
+			let vertices = group.vertices.into_iter().map(VisiVertex).collect();
+			let indices = group.triangles.into_iter().map(VisiIndices).collect();
+			let attributes = VisiCpuAttributes {
+				normals: group.normals,
+				tex_coords: group.tex_coords,
+				tangents: None,
+				computed_normals: ComputedNormals::Smooth,
+			};
+			for model in VisiCpuModel::new_split(bindless, vertices, indices, attributes)? {
+				models.push(ObjModel {
+					model,
+					material: group.material,
+				});
+			}
+		}
+		Ok(Self {
+			materials: mesh.materials,
+			models,
+		})
+	}
+
+	/// Push an instance of all models into `scene`, with every triangle of emissive materials as a light
+	pub fn push<S: VisiScenePush>(
+		&self,
+		scene: &mut S,
+		world_from_local: Affine3A,
+	) -> anyhow::Result<Vec<S::Instance>> {
+		self.models
+			.iter()
+			.map(|model| {
+				let instance = scene.push(
+					&model.model,
+					VisiInstanceInfo::new(AffineTransform::new(world_from_local), self.material_id(model)),
+				);
+				let emission = model.material.map_or(Vec3::ZERO, |m| self.materials[m].emission);
+				if emission != Vec3::ZERO {
+					for triangle_id in 0..model.model.cpu_triangles.len() as u32 {
+						scene.push_light(VisiCpuLight::EmissiveTriangle {
+							instance: instance.clone(),
+							triangle_id: TriangleId::new(triangle_id)?,
+							radiance: emission,
+						});
+					}
+				}
+				Ok(instance)
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const CORNELL_MTL: &str = "
+newmtl white
+Kd 0.7 0.7 0.7
+Ks 0.1
+Ns 10
+newmtl light
+Kd 0
+Ke 17 12 4
+";
+
+	const CORNELL_OBJ: &str = "
+mtllib cornell.mtl
+# floor
+v -1 0 -1
+v 1 0 -1
+v 1 0 1
+v -1 0 1
+vn 0 1 0
+vt 0.5 0.25
+usemtl white
+f 1//1 2//1 3//1 4//1
+# light
+v -0.25 1.9 -0.25
+v 0.25 1.9 -0.25
+v 0.25 1.9 0.25
+usemtl light
+f -3/1/1 -2/1/1 -1/1/1
+usemtl white
+f 1//1 3//1 5//1
+";
+
+	fn parse() -> ObjMesh {
+		parse_obj(CORNELL_OBJ, |mtl| {
+			assert_eq!(mtl, "cornell.mtl");
+			Ok(CORNELL_MTL.to_string())
+		})
+		.unwrap()
+	}
+
+	#[test]
+	fn test_materials() {
+		let mesh = parse();
+		assert_eq!(
+			mesh.materials,
+			[
+				ObjMaterial {
+					name: "white".to_string(),
+					diffuse: Vec3::splat(0.7),
+					specular: Vec3::splat(0.1),
+					shininess: 10.,
+					emission: Vec3::ZERO,
+				},
+				ObjMaterial {
+					name: "light".to_string(),
+					diffuse: Vec3::ZERO,
+					specular: Vec3::ZERO,
+					shininess: 0.,
+					emission: Vec3::new(17., 12., 4.),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn test_pbr_factors() {
+		let diffuse = ObjMaterial {
+			diffuse: Vec3::new(0.2, 0.5, 0.7),
+			..ObjMaterial::new("diffuse".to_string())
+		};
+		let factors = diffuse.pbr_factors();
+		assert_eq!(factors.metallic, 0.);
+		// without any specular the dielectric fresnel is compensated
+		assert!(
+			factors
+				.base_color
+				.abs_diff_eq(diffuse.diffuse / (1. - DIELECTRIC_F0), 1e-6)
+		);
+		// Ns 0 is a uniform lobe
+		assert_eq!(factors.roughness, 1.);
+
+		let gold = ObjMaterial {
+			diffuse: Vec3::ZERO,
+			specular: Vec3::new(1., 0.78, 0.34),
+			shininess: 1000.,
+			..ObjMaterial::new("gold".to_string())
+		};
+		let factors = gold.pbr_factors();
+		assert!((factors.metallic - 1.).abs() < 1e-3, "{factors:?}");
+		assert!(factors.base_color.abs_diff_eq(gold.specular, 1e-3), "{factors:?}");
+		assert!(factors.roughness < 0.25, "{factors:?}");
+
+		// some specular on a white diffuse surface stays mostly dielectric
+		let white = &parse().materials[0];
+		let factors = white.pbr_factors();
+		assert!(factors.metallic < 0.1, "{factors:?}");
+		assert!(factors.roughness > 0.5 && factors.roughness < 1., "{factors:?}");
+	}
+
+	#[test]
+	fn test_groups_by_material() {
+		let mesh = parse();
+		assert_eq!(mesh.groups.len(), 2);
+
+		let white = &mesh.groups[0];
+		assert_eq!(white.material, Some(0));
+		// the quad is triangulated as a fan, the last face reuses the floor's vertices
+		assert_eq!(white.triangles, [[0, 1, 2], [0, 2, 3], [0, 2, 4]]);
+		assert_eq!(white.vertices.len(), 5);
+		assert_eq!(white.vertices[4], Vec3::new(-0.25, 1.9, -0.25));
+
+		let light = &mesh.groups[1];
+		assert_eq!(light.material, Some(1));
+		assert_eq!(light.triangles, [[0, 1, 2]]);
+		assert_eq!(light.vertices[2], Vec3::new(0.25, 1.9, 0.25));
+	}
+
+	#[test]
+	fn test_attributes() {
+		let mesh = parse();
+		let [white, light] = &mesh.groups[..] else { panic!() };
+		assert_eq!(white.normals, Some(vec![Vec3::Y; 5]));
+		assert_eq!(white.tex_coords, None);
+		assert_eq!(light.normals, Some(vec![Vec3::Y; 3]));
+		assert_eq!(light.tex_coords, Some(vec![Vec2::new(0.5, 0.75); 3]));
+
+		// a position used with and without a normal results in two vertices
+		let mesh = parse_obj("v 0 0 0\nvn 0 0 1\nf 1//1 1//1 1//1\nf 1 1 1", |_| unreachable!()).unwrap();
+		assert_eq!(mesh.groups[0].vertices.len(), 2);
+		assert_eq!(mesh.groups[0].normals, None);
+	}
+
+	#[test]
+	fn test_errors() {
+		let no_mtl = |_: &str| -> anyhow::Result<String> { unreachable!() };
+		assert!(parse_obj("v 0 0 0\nf 1 2 3", no_mtl).is_err());
+		assert!(parse_obj("v 0 0 0\nv 1 0 0\nf 1 2", no_mtl).is_err());
+		assert!(parse_obj("v 0 0\n", no_mtl).is_err());
+		assert!(parse_obj("v 0 0 0\nf 1/1 1/1 1/1", no_mtl).is_err());
+		// unknown materials are not an error
+		let mesh = parse_obj("v 0 0 0\nusemtl missing\nf 1 1 1", no_mtl).unwrap();
+		assert_eq!(mesh.groups[0].material, None);
+	}
+}
@@ -1,8 +1,12 @@
 use crate::model::VisiCpuModel;
+use crate::model::attributes::VisiCpuAttributes;
 use glam::{Affine3A, Vec3};
 use restir_shader::visibility::scene::{VisiIndices, VisiVertex};
 use rust_gpu_bindless::descriptor::Bindless;
 
+/// The triangle ids of the bottom face of a [`cube`], facing -Y
+pub const CUBE_BOTTOM_TRIANGLES: [u32; 2] = [8, 9];
+
 pub fn cube(bindless: &Bindless, transform: Affine3A) -> anyhow::Result<VisiCpuModel> {
 	// from https://en.wikibooks.org/wiki/OpenGL_Programming/Modern_OpenGL_Tutorial_05
 	#[rustfmt::skip]
@@ -46,5 +50,5 @@ pub fn cube(bindless: &Bindless, transform: Affine3A) -> anyhow::Result<VisiCpuM
 		.iter()
 		.map(|pos| VisiVertex(transform.transform_point3(Vec3::from_array(*pos))));
 	let indices = indices.as_chunks::<3>().0.iter().map(|i| VisiIndices(*i));
-	VisiCpuModel::new(bindless, vertices, indices)
+	VisiCpuModel::new(bindless, vertices, indices, VisiCpuAttributes::flat())
 }
@@ -0,0 +1,212 @@
+use crate::material::pbr::{TextureEncoding, upload_texture};
+use glam::UVec2;
+use restir_shader::random::Rng;
+use restir_shader::random::blue_noise::{BLUE_NOISE_SIZE, BlueNoise};
+use rust_gpu_bindless::descriptor::{Bindless, Image, Image2d, RCDesc, RCDescExt};
+
+/// Standard deviation of the gaussian used to measure clustering, as suggested by Ulichney
+const SIGMA: f32 = 1.5;
+
+/// The blue noise texture uploaded to the GPU, see [`BlueNoise`]
+#[derive(Clone)]
+pub struct VisiCpuBlueNoise {
+	pub image: RCDesc<Image<Image2d>>,
+}
+
+impl VisiCpuBlueNoise {
+	pub async fn new(bindless: &Bindless) -> anyhow::Result<Self> {
+		let channels: [Vec<u32>; 4] = std::array::from_fn(|seed| generate_blue_noise(BLUE_NOISE_SIZE, seed as u32));
+		let texel_count = (BLUE_NOISE_SIZE * BLUE_NOISE_SIZE) as usize;
+		let texels = (0..texel_count)
+			.map(|i| {
+				channels
+					.each_ref()
+					.map(|ranks| (ranks[i] as usize * 256 / texel_count) as u8)
+			})
+			.collect::<Vec<_>>();
+		let image = upload_texture(
+			bindless,
+			UVec2::splat(BLUE_NOISE_SIZE),
+			&texels,
+			TextureEncoding::Linear,
+			"blue noise",
+		)
+		.await?;
+		Ok(Self { image })
+	}
+
+	pub fn to_gpu(&self) -> BlueNoise {
+		BlueNoise {
+			image: self.image.to_strong(),
+		}
+	}
+}
+
+/// Generate a tileable `size`² blue noise pattern using the void-and-cluster method, see "The void-and-cluster method
+/// for dither array generation" by Ulichney. Returns the rank of each pixel in row-major order, which is a
+/// permutation of `0..size²`.
+pub fn generate_blue_noise(size: u32, seed: u32) -> Vec<u32> {
+	let mut pattern = Pattern::new(size);
+	let pixel_count = pattern.ones.len() as u32;
+
+	// initial binary pattern: random points, relaxed by moving the tightest cluster into the largest void until stable
+	let mut rng = Rng::new(UVec2::ZERO, 0, seed);
+	let initial_count = (pixel_count / 10).max(1);
+	let mut placed = 0;
+	while placed < initial_count {
+		let i = rng.next_u32() as usize % pattern.ones.len();
+		if !pattern.ones[i] {
+			pattern.toggle(i);
+			placed += 1;
+		}
+	}
+	for _ in 0..pixel_count {
+		let cluster = pattern.tightest_cluster();
+		pattern.toggle(cluster);
+		let void = pattern.largest_void();
+		pattern.toggle(void);
+		if void == cluster {
+			break;
+		}
+	}
+
+	// rank the initial points by repeatedly removing the tightest cluster
+	let mut ranks = vec![0; pixel_count as usize];
+	let initial = pattern.clone();
+	for rank in (0..initial_count).rev() {
+		let cluster = pattern.tightest_cluster();
+		pattern.toggle(cluster);
+		ranks[cluster] = rank;
+	}
+
+	// rank all other points by repeatedly filling the largest void
+	let mut pattern = initial;
+	for rank in initial_count..pixel_count {
+		let void = pattern.largest_void();
+		pattern.toggle(void);
+		ranks[void] = rank;
+	}
+	ranks
+}
+
+/// A toroidal binary pattern and the gaussian weighted density of set pixels around each pixel
+#[derive(Clone)]
+struct Pattern {
+	size: u32,
+	ones: Vec<bool>,
+	energy: Vec<f32>,
+	/// gaussian weight by the wrapped offset between two pixels
+	kernel: Vec<f32>,
+}
+
+impl Pattern {
+	fn new(size: u32) -> Self {
+		let pixel_count = (size * size) as usize;
+		let kernel = (0..pixel_count as u32)
+			.map(|i| {
+				let wrap = |d: u32| {
+					let d = d as f32;
+					d.min(size as f32 - d)
+				};
+				let (dx, dy) = (wrap(i % size), wrap(i / size));
+				f32::exp(-(dx * dx + dy * dy) / (2. * SIGMA * SIGMA))
+			})
+			.collect();
+		Self {
+			size,
+			ones: vec![false; pixel_count],
+			energy: vec![0.; pixel_count],
+			kernel,
+		}
+	}
+
+	fn toggle(&mut self, i: usize) {
+		self.ones[i] = !self.ones[i];
+		let sign = if self.ones[i] { 1. } else { -1. };
+		let size = self.size;
+		let (x, y) = (i as u32 % size, i as u32 / size);
+		for (j, energy) in self.energy.iter_mut().enumerate() {
+			let (jx, jy) = (j as u32 % size, j as u32 / size);
+			let offset = (jy + size - y) % size * size + (jx + size - x) % size;
+			*energy += sign * self.kernel[offset as usize];
+		}
+	}
+
+	/// The set pixel with the most set pixels around it
+	fn tightest_cluster(&self) -> usize {
+		self.extreme(true, |a, b| a > b)
+	}
+
+	/// The unset pixel with the least set pixels around it
+	fn largest_void(&self) -> usize {
+		self.extreme(false, |a, b| a < b)
+	}
+
+	fn extreme(&self, set: bool, better: fn(f32, f32) -> bool) -> usize {
+		let mut best = None;
+		for (i, &energy) in self.energy.iter().enumerate() {
+			if self.ones[i] == set && best.is_none_or(|(_, best)| better(energy, best)) {
+				best = Some((i, energy));
+			}
+		}
+		best.expect("pattern is neither empty nor full").0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SIZE: u32 = 32;
+
+	#[test]
+	fn test_permutation() {
+		let mut ranks = generate_blue_noise(SIZE, 0);
+		ranks.sort_unstable();
+		assert!(ranks.into_iter().eq(0..SIZE * SIZE));
+	}
+
+	/// Variance of the averages of 4x4 blocks, relative to the variance expected for independent values. Blue noise
+	/// lacks low frequencies, so its blocks average out much better than the 1 of white noise.
+	fn block_variance(values: &[f32]) -> f32 {
+		const BLOCK: u32 = 4;
+		let mean = values.iter().sum::<f32>() / values.len() as f32;
+		let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / values.len() as f32;
+		let blocks = SIZE / BLOCK;
+		let mut block_variance = 0.;
+		for by in 0..blocks {
+			for bx in 0..blocks {
+				let mut sum = 0.;
+				for y in by * BLOCK..(by + 1) * BLOCK {
+					for x in bx * BLOCK..(bx + 1) * BLOCK {
+						sum += values[(y * SIZE + x) as usize];
+					}
+				}
+				let block_mean = sum / (BLOCK * BLOCK) as f32;
+				block_variance += (block_mean - mean) * (block_mean - mean);
+			}
+		}
+		block_variance / (blocks * blocks) as f32 / (variance / (BLOCK * BLOCK) as f32)
+	}
+
+	#[test]
+	fn test_blue_noise_lacks_low_frequencies() {
+		for seed in 0..3 {
+			let blue = generate_blue_noise(SIZE, seed)
+				.into_iter()
+				.map(|r| r as f32)
+				.collect::<Vec<_>>();
+			let mut rng = Rng::new(UVec2::ZERO, 0, seed);
+			let white = (0..SIZE * SIZE).map(|_| rng.next_f32()).collect::<Vec<_>>();
+			let blue = block_variance(&blue);
+			let white = block_variance(&white);
+			assert!(white > 0.5, "seed {seed}: white noise {white}");
+			assert!(blue < 0.3, "seed {seed}: blue noise {blue}");
+		}
+	}
+
+	#[test]
+	fn test_seeds_differ() {
+		assert_ne!(generate_blue_noise(SIZE, 0), generate_blue_noise(SIZE, 1));
+	}
+}
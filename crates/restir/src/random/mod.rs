@@ -0,0 +1 @@
+pub mod blue_noise;
@@ -0,0 +1,31 @@
+use glam::UVec2;
+use restir_shader::reference::REFERENCE_WG_SIZE;
+use restir_shader::reference::path_trace::Param;
+use rust_gpu_bindless::descriptor::Bindless;
+use rust_gpu_bindless::pipeline::{BindlessComputePipeline, Recording};
+
+pub struct ReferencePathTracePipeline {
+	pipeline: BindlessComputePipeline<Param<'static>>,
+}
+
+impl ReferencePathTracePipeline {
+	pub fn new(bindless: &Bindless) -> anyhow::Result<Self> {
+		Ok(Self {
+			pipeline: bindless
+				.create_compute_pipeline(crate::shader::reference::path_trace::reference_path_trace::new())?,
+		})
+	}
+
+	pub fn dispatch(&self, cmd: &mut Recording, size: UVec2, param: Param) -> anyhow::Result<()> {
+		cmd.dispatch(
+			&self.pipeline,
+			[
+				size.x.div_ceil(REFERENCE_WG_SIZE.x),
+				size.y.div_ceil(REFERENCE_WG_SIZE.y),
+				1,
+			],
+			param,
+		)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,33 @@
+use crate::restir::pass::ScreenPassPipeline;
+use restir_shader::restir::di::{DI_WG_SIZE, initial, shade, spatial, temporal};
+use rust_gpu_bindless::descriptor::Bindless;
+
+pub struct DiPipelines {
+	pub initial: ScreenPassPipeline<initial::Param<'static>>,
+	pub temporal: ScreenPassPipeline<temporal::Param<'static>>,
+	pub spatial: ScreenPassPipeline<spatial::Param<'static>>,
+	pub shade: ScreenPassPipeline<shade::Param<'static>>,
+}
+
+impl DiPipelines {
+	pub fn new(bindless: &Bindless) -> anyhow::Result<Self> {
+		Ok(Self {
+			initial: ScreenPassPipeline::new(
+				bindless,
+				crate::shader::restir::di::initial::di_initial::new(),
+				DI_WG_SIZE,
+			)?,
+			temporal: ScreenPassPipeline::new(
+				bindless,
+				crate::shader::restir::di::temporal::di_temporal::new(),
+				DI_WG_SIZE,
+			)?,
+			spatial: ScreenPassPipeline::new(
+				bindless,
+				crate::shader::restir::di::spatial::di_spatial::new(),
+				DI_WG_SIZE,
+			)?,
+			shade: ScreenPassPipeline::new(bindless, crate::shader::restir::di::shade::di_shade::new(), DI_WG_SIZE)?,
+		})
+	}
+}
@@ -0,0 +1,33 @@
+use crate::restir::pass::ScreenPassPipeline;
+use restir_shader::restir::gi::{GI_WG_SIZE, initial, shade, spatial, temporal};
+use rust_gpu_bindless::descriptor::Bindless;
+
+pub struct GiPipelines {
+	pub initial: ScreenPassPipeline<initial::Param<'static>>,
+	pub temporal: ScreenPassPipeline<temporal::Param<'static>>,
+	pub spatial: ScreenPassPipeline<spatial::Param<'static>>,
+	pub shade: ScreenPassPipeline<shade::Param<'static>>,
+}
+
+impl GiPipelines {
+	pub fn new(bindless: &Bindless) -> anyhow::Result<Self> {
+		Ok(Self {
+			initial: ScreenPassPipeline::new(
+				bindless,
+				crate::shader::restir::gi::initial::gi_initial::new(),
+				GI_WG_SIZE,
+			)?,
+			temporal: ScreenPassPipeline::new(
+				bindless,
+				crate::shader::restir::gi::temporal::gi_temporal::new(),
+				GI_WG_SIZE,
+			)?,
+			spatial: ScreenPassPipeline::new(
+				bindless,
+				crate::shader::restir::gi::spatial::gi_spatial::new(),
+				GI_WG_SIZE,
+			)?,
+			shade: ScreenPassPipeline::new(bindless, crate::shader::restir::gi::shade::gi_shade::new(), GI_WG_SIZE)?,
+		})
+	}
+}
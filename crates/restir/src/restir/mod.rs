@@ -0,0 +1,4 @@
+pub mod di;
+pub mod gi;
+pub mod pass;
+pub mod pt;
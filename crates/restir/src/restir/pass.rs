@@ -0,0 +1,34 @@
+use glam::UVec2;
+use rust_gpu_bindless::descriptor::Bindless;
+use rust_gpu_bindless::pipeline::{BindlessComputePipeline, Recording};
+use rust_gpu_bindless_shaders::buffer_content::BufferStruct;
+use rust_gpu_bindless_shaders::shader::BindlessShader;
+use rust_gpu_bindless_shaders::shader_type::ComputeShader;
+
+/// A compute shader invoked once per pixel of the screen, in workgroups of `wg_size` pixels
+pub struct ScreenPassPipeline<T: BufferStruct> {
+	pipeline: BindlessComputePipeline<T>,
+	wg_size: UVec2,
+}
+
+impl<T: BufferStruct> ScreenPassPipeline<T> {
+	pub fn new(
+		bindless: &Bindless,
+		shader: &impl BindlessShader<ShaderType = ComputeShader, ParamConstant = T>,
+		wg_size: UVec2,
+	) -> anyhow::Result<Self> {
+		Ok(Self {
+			pipeline: bindless.create_compute_pipeline(shader)?,
+			wg_size,
+		})
+	}
+
+	pub fn dispatch(&self, cmd: &mut Recording, size: UVec2, param: T) -> anyhow::Result<()> {
+		cmd.dispatch(
+			&self.pipeline,
+			[size.x.div_ceil(self.wg_size.x), size.y.div_ceil(self.wg_size.y), 1],
+			param,
+		)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,33 @@
+use crate::restir::pass::ScreenPassPipeline;
+use restir_shader::restir::pt::{PT_WG_SIZE, initial, shade, spatial, temporal};
+use rust_gpu_bindless::descriptor::Bindless;
+
+pub struct PtPipelines {
+	pub initial: ScreenPassPipeline<initial::Param<'static>>,
+	pub temporal: ScreenPassPipeline<temporal::Param<'static>>,
+	pub spatial: ScreenPassPipeline<spatial::Param<'static>>,
+	pub shade: ScreenPassPipeline<shade::Param<'static>>,
+}
+
+impl PtPipelines {
+	pub fn new(bindless: &Bindless) -> anyhow::Result<Self> {
+		Ok(Self {
+			initial: ScreenPassPipeline::new(
+				bindless,
+				crate::shader::restir::pt::initial::pt_initial::new(),
+				PT_WG_SIZE,
+			)?,
+			temporal: ScreenPassPipeline::new(
+				bindless,
+				crate::shader::restir::pt::temporal::pt_temporal::new(),
+				PT_WG_SIZE,
+			)?,
+			spatial: ScreenPassPipeline::new(
+				bindless,
+				crate::shader::restir::pt::spatial::pt_spatial::new(),
+				PT_WG_SIZE,
+			)?,
+			shade: ScreenPassPipeline::new(bindless, crate::shader::restir::pt::shade::pt_shade::new(), PT_WG_SIZE)?,
+		})
+	}
+}
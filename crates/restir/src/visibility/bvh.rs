@@ -0,0 +1,403 @@
+use glam::Vec3;
+use restir_shader::visibility::bvh::{BVH_MAX_DEPTH, Bvh, BvhNode};
+use rust_gpu_bindless::descriptor::{
+	Bindless, BindlessBufferCreateInfo, BindlessBufferUsage, Buffer, RCDesc, RCDescExt,
+};
+
+/// Number of buckets the centroids are binned into, to evaluate the surface area heuristic
+const SAH_BINS: usize = 16;
+/// Cost of traversing an inner node relative to intersecting a primitive
+const SAH_TRAVERSAL_COST: f32 = 1.;
+/// Leaves with more primitives are always split
+const MAX_LEAF_PRIMITIVES: usize = 8;
+
+/// A BVH ready to be uploaded, see [`restir_shader::visibility::bvh`]
+#[derive(Clone, Debug)]
+pub struct CpuBvh {
+	/// all nodes, with the root at index 0
+	pub nodes: Vec<BvhNode>,
+	/// primitives referenced by the leaves, may be empty
+	pub primitives: Vec<u32>,
+}
+
+/// The buffers of a [`CpuBvh`] uploaded to the GPU
+#[derive(Clone)]
+pub struct VisiCpuBvh {
+	pub nodes: RCDesc<Buffer<[BvhNode]>>,
+	pub primitives: RCDesc<Buffer<[u32]>>,
+}
+
+impl CpuBvh {
+	pub fn upload(&self, bindless: &Bindless) -> anyhow::Result<VisiCpuBvh> {
+		let nodes = bindless.buffer().alloc_shared_from_iter(
+			&BindlessBufferCreateInfo {
+				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+				allocation_scheme: Default::default(),
+				name: "BVH nodes",
+			},
+			self.nodes.iter().copied(),
+		)?;
+		let primitives = bindless.buffer().alloc_shared_from_iter(
+			&BindlessBufferCreateInfo {
+				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+				allocation_scheme: Default::default(),
+				name: "BVH primitives",
+			},
+			// buffers can't be empty
+			if self.primitives.is_empty() {
+				vec![0]
+			} else {
+				self.primitives.clone()
+			},
+		)?;
+		Ok(VisiCpuBvh { nodes, primitives })
+	}
+}
+
+impl CpuBvh {
+	/// Recompute the bounds of all nodes from the new `bounds` of the primitives, keeping the tree structure. Much
+	/// cheaper than a rebuild, but traversal gets slower the further the primitives moved since the last build.
+	pub fn refit(&mut self, bounds: &[[Vec3; 2]]) {
+		if self.primitives.is_empty() {
+			return;
+		}
+		// children are always placed after their parent
+		for node_index in (0..self.nodes.len()).rev() {
+			let node = self.nodes[node_index];
+			let node_bounds = if node.is_leaf() {
+				self.primitives[node.index as usize..][..node.primitive_count as usize]
+					.iter()
+					.fold(Aabb::EMPTY, |acc, p| {
+						let [min, max] = bounds[*p as usize];
+						acc.union(&Aabb { min, max })
+					})
+			} else {
+				[node.index, node.index + 1].iter().fold(Aabb::EMPTY, |acc, child| {
+					let child = self.nodes[*child as usize];
+					acc.union(&Aabb {
+						min: child.bounds_min,
+						max: child.bounds_max,
+					})
+				})
+			};
+			self.nodes[node_index].bounds_min = node_bounds.min;
+			self.nodes[node_index].bounds_max = node_bounds.max;
+		}
+	}
+}
+
+impl VisiCpuBvh {
+	pub fn to_gpu(&self) -> Bvh {
+		Bvh {
+			nodes: self.nodes.to_strong(),
+			primitives: self.primitives.to_strong(),
+		}
+	}
+}
+
+/// An axis aligned box, empty if any component of `min` is larger than `max`
+#[derive(Copy, Clone, Debug)]
+struct Aabb {
+	min: Vec3,
+	max: Vec3,
+}
+
+impl Aabb {
+	const EMPTY: Self = Self {
+		min: Vec3::INFINITY,
+		max: Vec3::NEG_INFINITY,
+	};
+
+	fn union(&self, other: &Self) -> Self {
+		Self {
+			min: self.min.min(other.min),
+			max: self.max.max(other.max),
+		}
+	}
+
+	fn grow(&self, p: Vec3) -> Self {
+		Self {
+			min: self.min.min(p),
+			max: self.max.max(p),
+		}
+	}
+
+	fn centroid(&self) -> Vec3 {
+		(self.min + self.max) / 2.
+	}
+
+	fn surface_area(&self) -> f32 {
+		let d = (self.max - self.min).max(Vec3::ZERO);
+		2. * (d.x * d.y + d.y * d.z + d.z * d.x)
+	}
+}
+
+/// Build a BVH over primitives with the AABBs `bounds` as `[min, max]`, by recursively splitting them according to the
+/// binned surface area heuristic, see "On fast Construction of SAH-based Bounding Volume Hierarchies" by Wald.
+pub fn build_bvh(bounds: &[[Vec3; 2]]) -> CpuBvh {
+	let bounds = bounds
+		.iter()
+		.map(|[min, max]| Aabb { min: *min, max: *max })
+		.collect::<Vec<_>>();
+	let mut primitives = (0..bounds.len() as u32).collect::<Vec<_>>();
+	let mut nodes = vec![BvhNode::EMPTY];
+	if !primitives.is_empty() {
+		build_node(&mut nodes, &bounds, &mut primitives, 0, 0, 0);
+	}
+	CpuBvh { nodes, primitives }
+}
+
+fn build_node(
+	nodes: &mut Vec<BvhNode>,
+	bounds: &[Aabb],
+	primitives: &mut [u32],
+	first_primitive: u32,
+	node_index: usize,
+	depth: u32,
+) {
+	let node_bounds = primitives
+		.iter()
+		.fold(Aabb::EMPTY, |acc, p| acc.union(&bounds[*p as usize]));
+	let leaf = BvhNode {
+		bounds_min: node_bounds.min,
+		bounds_max: node_bounds.max,
+		primitive_count: primitives.len() as u32,
+		index: first_primitive,
+	};
+	if primitives.len() == 1 || depth >= BVH_MAX_DEPTH {
+		nodes[node_index] = leaf;
+		return;
+	}
+
+	let mid = match find_sah_split(bounds, primitives, &node_bounds) {
+		Some(mid) => mid,
+		None if primitives.len() <= MAX_LEAF_PRIMITIVES => {
+			nodes[node_index] = leaf;
+			return;
+		}
+		// all centroids coincide, any split is as good as another
+		None => primitives.len() / 2,
+	};
+
+	let child = nodes.len();
+	nodes.push(BvhNode::EMPTY);
+	nodes.push(BvhNode::EMPTY);
+	let (left, right) = primitives.split_at_mut(mid);
+	build_node(nodes, bounds, left, first_primitive, child, depth + 1);
+	build_node(nodes, bounds, right, first_primitive + mid as u32, child + 1, depth + 1);
+	nodes[node_index] = BvhNode {
+		bounds_min: node_bounds.min,
+		bounds_max: node_bounds.max,
+		primitive_count: 0,
+		index: child as u32,
+	};
+}
+
+/// Partition `primitives` along the cheapest split of all axes and return the index of the first primitive of the
+/// right half, or None if no split is cheaper than a leaf, unless the leaf would be too large
+fn find_sah_split(bounds: &[Aabb], primitives: &mut [u32], node_bounds: &Aabb) -> Option<usize> {
+	let centroid_bounds = primitives
+		.iter()
+		.fold(Aabb::EMPTY, |acc, p| acc.grow(bounds[*p as usize].centroid()));
+	let extent = centroid_bounds.max - centroid_bounds.min;
+	let bin_of = |p: u32, axis: usize| {
+		let offset = (bounds[p as usize].centroid()[axis] - centroid_bounds.min[axis]) / extent[axis];
+		usize::min((offset * SAH_BINS as f32) as usize, SAH_BINS - 1)
+	};
+
+	// (cost, axis, last bin of the left half)
+	let mut best: Option<(f32, usize, usize)> = None;
+	for axis in 0..3 {
+		if extent[axis] <= 0. {
+			continue;
+		}
+		let mut bins = [(Aabb::EMPTY, 0); SAH_BINS];
+		for p in primitives.iter() {
+			let bin = &mut bins[bin_of(*p, axis)];
+			bin.0 = bin.0.union(&bounds[*p as usize]);
+			bin.1 += 1;
+		}
+
+		// area times primitive count of everything right of each split, swept from the right
+		let mut right_cost = [0.; SAH_BINS];
+		let mut acc = (Aabb::EMPTY, 0);
+		for split in (1..SAH_BINS).rev() {
+			acc = (acc.0.union(&bins[split].0), acc.1 + bins[split].1);
+			right_cost[split - 1] = acc.0.surface_area() * acc.1 as f32;
+		}
+		let mut acc = (Aabb::EMPTY, 0);
+		for split in 0..SAH_BINS - 1 {
+			acc = (acc.0.union(&bins[split].0), acc.1 + bins[split].1);
+			if acc.1 == 0 || acc.1 == primitives.len() {
+				continue;
+			}
+			let cost = acc.0.surface_area() * acc.1 as f32 + right_cost[split];
+			if best.is_none_or(|(best, _, _)| cost < best) {
+				best = Some((cost, axis, split));
+			}
+		}
+	}
+
+	let (cost, axis, split) = best?;
+	let area = node_bounds.surface_area();
+	let split_cost = SAH_TRAVERSAL_COST + if area > 0. { cost / area } else { 0. };
+	if primitives.len() <= MAX_LEAF_PRIMITIVES && primitives.len() as f32 <= split_cost {
+		return None;
+	}
+
+	let mut mid = 0;
+	for i in 0..primitives.len() {
+		if bin_of(primitives[i], axis) <= split {
+			primitives.swap(i, mid);
+			mid += 1;
+		}
+	}
+	Some(mid)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use restir_shader::random::Rng;
+	use restir_shader::utils::ray::Ray;
+	use restir_shader::visibility::bvh::traverse_bvh;
+
+	fn random_triangles(count: u32, seed: u32) -> Vec<[Vec3; 3]> {
+		let mut rng = Rng::new(Default::default(), 0, seed);
+		let mut point = || Vec3::new(rng.next_f32(), rng.next_f32(), rng.next_f32()) * 10. - 5.;
+		(0..count)
+			.map(|_| {
+				let a = point();
+				[a, a + point() / 10., a + point() / 10.]
+			})
+			.collect()
+	}
+
+	fn triangle_bounds(triangles: &[[Vec3; 3]]) -> Vec<[Vec3; 2]> {
+		triangles
+			.iter()
+			.map(|[a, b, c]| [a.min(*b).min(*c), a.max(*b).max(*c)])
+			.collect()
+	}
+
+	/// Leaf depth and whether every node bounds all primitives below it
+	fn check_node(bvh: &CpuBvh, bounds: &[[Vec3; 2]], node: u32, depth: u32, leaves: &mut Vec<u32>) {
+		let node = bvh.nodes[node as usize];
+		let contains = |[min, max]: [Vec3; 2]| node.bounds_min.cmple(min).all() && max.cmple(node.bounds_max).all();
+		if node.is_leaf() {
+			assert!(depth <= BVH_MAX_DEPTH);
+			for p in &bvh.primitives[node.index as usize..][..node.primitive_count as usize] {
+				assert!(contains(bounds[*p as usize]));
+				leaves.push(*p);
+			}
+		} else {
+			for child in [node.index, node.index + 1] {
+				let child_node = bvh.nodes[child as usize];
+				assert!(contains([child_node.bounds_min, child_node.bounds_max]));
+				check_node(bvh, bounds, child, depth + 1, leaves);
+			}
+		}
+	}
+
+	#[test]
+	fn test_structure() {
+		let bounds = triangle_bounds(&random_triangles(1000, 0));
+		let bvh = build_bvh(&bounds);
+		let mut leaves = Vec::new();
+		check_node(&bvh, &bounds, 0, 0, &mut leaves);
+		leaves.sort_unstable();
+		assert!(
+			leaves.into_iter().eq(0..bounds.len() as u32),
+			"every primitive is in exactly one leaf"
+		);
+	}
+
+	#[test]
+	fn test_coincident_primitives() {
+		let bounds = vec![[Vec3::ZERO, Vec3::ONE]; 1000];
+		let bvh = build_bvh(&bounds);
+		let mut leaves = Vec::new();
+		check_node(&bvh, &bounds, 0, 0, &mut leaves);
+		assert_eq!(leaves.len(), bounds.len());
+		assert!(
+			bvh.nodes
+				.iter()
+				.all(|n| n.primitive_count as usize <= MAX_LEAF_PRIMITIVES)
+		);
+	}
+
+	fn trace(bvh: &CpuBvh, triangles: &[[Vec3; 3]], ray: Ray, any_hit: bool) -> (f32, u32) {
+		let hit = traverse_bvh(
+			|i| bvh.nodes[i as usize],
+			|i| bvh.primitives[i as usize],
+			ray,
+			any_hit,
+			|p, ray| ray.intersect_triangle(triangles[p as usize]),
+		);
+		(hit.t, hit.primitive)
+	}
+
+	#[test]
+	fn test_traversal_matches_brute_force() {
+		let triangles = random_triangles(500, 1);
+		let bvh = build_bvh(&triangle_bounds(&triangles));
+		let mut rng = Rng::new(Default::default(), 0, 2);
+		let mut point = || Vec3::new(rng.next_f32(), rng.next_f32(), rng.next_f32()) * 14. - 7.;
+		let mut hits = 0;
+		for _ in 0..1000 {
+			let ray = Ray::between(point(), point());
+			let expected = triangles
+				.iter()
+				.enumerate()
+				.map(|(i, t)| (ray.intersect_triangle(*t), i as u32))
+				.min_by(|a, b| a.0.total_cmp(&b.0))
+				.unwrap();
+
+			let closest = trace(&bvh, &triangles, ray, false);
+			assert_eq!(closest.0, expected.0);
+			if expected.0 != f32::INFINITY {
+				hits += 1;
+				assert_eq!(closest.1, expected.1);
+			}
+			let any = trace(&bvh, &triangles, ray, true);
+			assert_eq!(any.0 != f32::INFINITY, expected.0 != f32::INFINITY);
+		}
+		assert!(hits > 100, "too few rays hit anything to be meaningful: {hits}");
+	}
+
+	#[test]
+	fn test_refit() {
+		let triangles = random_triangles(500, 3);
+		let mut bvh = build_bvh(&triangle_bounds(&triangles));
+		let moved = triangles
+			.iter()
+			.enumerate()
+			.map(|(i, t)| t.map(|v| v + Vec3::new(i as f32 % 7., 0., -(i as f32 % 3.))))
+			.collect::<Vec<_>>();
+		let bounds = triangle_bounds(&moved);
+		bvh.refit(&bounds);
+		let mut leaves = Vec::new();
+		check_node(&bvh, &bounds, 0, 0, &mut leaves);
+		assert_eq!(leaves.len(), bounds.len());
+
+		let ray = Ray::between(
+			moved[42][0] + Vec3::Z,
+			(moved[42][0] + moved[42][1] + moved[42][2]) / 3.,
+		);
+		let expected = moved
+			.iter()
+			.map(|t| ray.intersect_triangle(*t))
+			.min_by(f32::total_cmp)
+			.unwrap();
+		assert_eq!(trace(&bvh, &moved, ray, false).0, expected);
+	}
+
+	#[test]
+	fn test_empty() {
+		let bvh = build_bvh(&[]);
+		assert_eq!(bvh.nodes.len(), 1);
+		let ray = Ray::between(Vec3::NEG_ONE, Vec3::ONE);
+		let (t, _) = trace(&bvh, &[], ray, false);
+		assert_eq!(t, f32::INFINITY);
+	}
+}
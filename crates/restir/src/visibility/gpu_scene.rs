@@ -0,0 +1,624 @@
+use crate::light::environment::VisiCpuEnvironment;
+use crate::material::pbr::{VisiCpuPbrMaterial, upload_materials};
+use crate::model::VisiCpuModel;
+use crate::visibility::bvh::{CpuBvh, build_bvh};
+use crate::visibility::gpu_slots::{VisiGpuInstance, VisiGpuSlotChanges, VisiGpuSlots};
+use crate::visibility::scene::{
+	VisiCpuDraw, VisiCpuLight, VisiCpuLights, VisiCpuScene, VisiScenePush, delta_light, emissive_triangle_light,
+	scene_radius, world_bounds,
+};
+use glam::Vec3;
+use restir_shader::camera::Camera;
+use restir_shader::material::pbr::PbrMaterial;
+use restir_shader::utils::affine_transform::AffineTransform;
+use restir_shader::visibility::bvh::{Bvh, BvhNode};
+use restir_shader::visibility::id::InstanceId;
+use restir_shader::visibility::scene::{VisiInstance, VisiInstanceInfo, VisiScene};
+use rust_gpu_bindless::descriptor::{
+	Bindless, BindlessBufferCreateInfo, BindlessBufferUsage, Buffer, MutBuffer, MutDesc, MutDescExt, RCDesc, RCDescExt,
+};
+use rust_gpu_bindless::pipeline::{
+	BufferSliceCopy, GeneralRead, MutBufferAccessExt, Recording, TransferRead, TransferWrite,
+};
+use rust_gpu_bindless_shaders::buffer_content::{BufferContent, BufferStruct};
+
+/// A scene persisting across frames, unlike [`VisiCpuSceneAccum`](crate::visibility::scene::VisiCpuSceneAccum).
+/// Instances are kept in slots indexed by their [`InstanceId`] and written into persistent buffers, of which
+/// [`Self::upload`] only updates the slots that changed. Moving instances refits the BVH, while the BVH and draws are
+/// only rebuilt if instances are added or removed, and the lights only if they or their instances changed.
+pub struct VisiGpuScene {
+	bindless: Bindless,
+	slots: VisiGpuSlots<VisiCpuModel>,
+	lights: Vec<VisiCpuLight<VisiGpuInstance>>,
+	/// whether the lights must be rebuilt
+	lights_changed: bool,
+	materials: Vec<VisiCpuPbrMaterial>,
+	/// whether the materials must be uploaded again
+	materials_changed: bool,
+	buffers: VisiGpuSceneBuffers,
+}
+
+impl VisiGpuScene {
+	pub fn new(bindless: &Bindless) -> Self {
+		Self {
+			bindless: bindless.clone(),
+			slots: VisiGpuSlots::new(),
+			lights: Vec::new(),
+			lights_changed: true,
+			materials: Vec::new(),
+			materials_changed: true,
+			buffers: VisiGpuSceneBuffers::new(),
+		}
+	}
+
+	pub fn add(&mut self, model: &VisiCpuModel, info: VisiInstanceInfo) -> VisiGpuInstance {
+		self.slots.add(model.clone(), info)
+	}
+
+	/// Remove an instance together with all of its emissive triangles
+	pub fn remove(&mut self, instance: VisiGpuInstance) -> anyhow::Result<()> {
+		self.slots.remove(instance)?;
+		self.lights.retain(|light| match light {
+			VisiCpuLight::Light(_) => true,
+			VisiCpuLight::EmissiveTriangle { instance: i, .. } => *i != instance,
+		});
+		self.lights_changed = true;
+		Ok(())
+	}
+
+	/// Move an instance. Its `prev_world_from_local` is updated to the transform it had at the last upload.
+	pub fn update_transform(
+		&mut self,
+		instance: VisiGpuInstance,
+		world_from_local: AffineTransform,
+	) -> anyhow::Result<()> {
+		self.slots.update_transform(instance, world_from_local)
+	}
+
+	pub fn add_light(&mut self, light: impl Into<VisiCpuLight<VisiGpuInstance>>) {
+		self.lights.push(light.into());
+		self.lights_changed = true;
+	}
+
+	/// Replace all materials, indexed by the [`VisiInstanceInfo::material_id`] of each instance
+	pub fn set_materials(&mut self, materials: Vec<VisiCpuPbrMaterial>) {
+		self.materials = materials;
+		self.materials_changed = true;
+	}
+
+	/// Record uploading all changes since the last upload into `cmd`, before the commands rendering the returned scene.
+	/// The buffers alternate between frames, so the previous frame's scene stays untouched for temporal reuse. If
+	/// recording fails, all changes are kept for the next upload.
+	pub fn upload(
+		&mut self,
+		cmd: &mut Recording<'_>,
+		camera: Camera,
+		environment: &VisiCpuEnvironment,
+	) -> anyhow::Result<VisiCpuScene> {
+		// verify no oob in shaders later
+		InstanceId::new(self.slots.len())?;
+
+		let Self {
+			bindless,
+			slots,
+			lights,
+			lights_changed,
+			materials,
+			materials_changed,
+			buffers,
+		} = self;
+		slots.upload(|slots, changes| {
+			let mut changed = *materials_changed || changes.structure_changed || !changes.moved_slots.is_empty();
+			if *materials_changed {
+				buffers.materials = Some(upload_materials(bindless, materials)?);
+			}
+			buffers.update_tlas(slots, changes);
+			let moved_emissive = lights.iter().any(|light| match light {
+				VisiCpuLight::Light(_) => false,
+				VisiCpuLight::EmissiveTriangle { instance, .. } => {
+					changes.moved_slots.binary_search(&instance.index()).is_ok()
+				}
+			});
+			let rebuild_lights = *lights_changed
+				|| moved_emissive
+				|| changes.structure_changed
+				|| buffers.environment_radiance != environment.average_radiance;
+			if rebuild_lights {
+				buffers.lights = Some(build_lights(bindless, slots, lights, environment)?);
+				changed = true;
+			}
+			if changed {
+				buffers.version += 1;
+			}
+			let scene = buffers.write(bindless, cmd, slots, changes, camera, environment)?;
+			// clear the changes only once the scene was written, so a failed upload retries them
+			*materials_changed = false;
+			*lights_changed = false;
+			if rebuild_lights {
+				buffers.environment_radiance = environment.average_radiance;
+			}
+			Ok(scene)
+		})
+	}
+}
+
+impl VisiScenePush for VisiGpuScene {
+	type Instance = VisiGpuInstance;
+
+	fn push(&mut self, model: &VisiCpuModel, instance: VisiInstanceInfo) -> VisiGpuInstance {
+		self.add(model, instance)
+	}
+
+	fn push_light(&mut self, light: impl Into<VisiCpuLight<VisiGpuInstance>>) {
+		self.add_light(light);
+	}
+}
+
+/// Everything derived from the slots and lights of a [`VisiGpuScene`]
+struct VisiGpuSceneBuffers {
+	/// BVH over all instances, with the slots of the instances as primitives
+	tlas: CpuBvh,
+	/// incremented whenever the nodes of `tlas` change
+	tlas_nodes_version: u64,
+	/// incremented whenever the primitives of `tlas` change
+	tlas_primitives_version: u64,
+	/// incremented whenever any instance, light or material changes, see [`VisiCpuScene::version`]
+	version: u64,
+	lights: Option<VisiCpuLights>,
+	environment_radiance: Vec3,
+	materials: Option<RCDesc<Buffer<[PbrMaterial]>>>,
+	draws: Vec<VisiCpuDraw>,
+	frames: [VisiGpuSceneFrame; 2],
+	/// the index into `frames` written by the next upload
+	frame: usize,
+	prev_camera: Option<Camera>,
+}
+
+impl VisiGpuSceneBuffers {
+	fn new() -> Self {
+		Self {
+			tlas: build_bvh(&[]),
+			tlas_nodes_version: 0,
+			tlas_primitives_version: 0,
+			version: 0,
+			lights: None,
+			environment_radiance: Vec3::ZERO,
+			materials: None,
+			draws: Vec::new(),
+			frames: [VisiGpuSceneFrame::new(), VisiGpuSceneFrame::new()],
+			frame: 0,
+			prev_camera: None,
+		}
+	}
+
+	/// Rebuild the BVH and draws if instances were added or removed, or refit the BVH if instances moved
+	fn update_tlas(&mut self, slots: &VisiGpuSlots<VisiCpuModel>, changes: &VisiGpuSlotChanges) {
+		if changes.structure_changed {
+			self.tlas = build_tlas(slots, world_bounds);
+			self.tlas_nodes_version += 1;
+			self.tlas_primitives_version += 1;
+
+			self.draws = slots
+				.runs()
+				.into_iter()
+				.map(|run| VisiCpuDraw {
+					model: run.model,
+					instance_start: run.instance_start,
+					instance_count: run.instance_count,
+				})
+				.collect();
+		} else if !changes.moved_slots.is_empty() {
+			// removed slots are never referenced by the BVH
+			let slot_bounds = (0..slots.len())
+				.map(|index| {
+					slots.slot(index).map_or([Vec3::ZERO; 2], |slot| {
+						world_bounds(&slot.model, slot.info.world_from_local)
+					})
+				})
+				.collect::<Vec<_>>();
+			self.tlas.refit(&slot_bounds);
+			self.tlas_nodes_version += 1;
+		}
+	}
+
+	/// Record writing all changes into the buffers of the current frame, and return its scene
+	fn write(
+		&mut self,
+		bindless: &Bindless,
+		cmd: &mut Recording<'_>,
+		slots: &VisiGpuSlots<VisiCpuModel>,
+		changes: &VisiGpuSlotChanges,
+		camera: Camera,
+		environment: &VisiCpuEnvironment,
+	) -> anyhow::Result<VisiCpuScene> {
+		for frame in &mut self.frames {
+			frame.stale_slots.extend_from_slice(&changes.dirty_slots);
+		}
+		let frame = &mut self.frames[self.frame];
+
+		// a newly allocated buffer has none of the slots written
+		let mut stale_slots = if frame.instances.is_allocated() {
+			frame.stale_slots.clone()
+		} else {
+			slots.live_slots().map(|(index, _)| index).collect()
+		};
+		stale_slots.sort_unstable();
+		stale_slots.dedup();
+		// removed slots are never read by the GPU
+		stale_slots.retain(|index| slots.slot(*index).is_some());
+		let staging = stale_slots
+			.iter()
+			.flat_map(|index| slots.slot(*index))
+			.map(|slot| VisiInstance {
+				model: slot.model.model.to_strong(),
+				info: slot.info,
+			})
+			.collect();
+		let instances = frame.instances.write(
+			bindless,
+			cmd,
+			slots.len() as usize,
+			staging,
+			&copy_regions(&stale_slots),
+		)?;
+		frame.stale_slots.clear();
+
+		let tlas_nodes = match frame.tlas_nodes.shared() {
+			Some(nodes) if frame.tlas_nodes_version == Some(self.tlas_nodes_version) => nodes,
+			_ => {
+				frame.tlas_nodes_version = None;
+				let len = self.tlas.nodes.len();
+				let (staging, regions) = full_write(&self.tlas.nodes);
+				let nodes = frame.tlas_nodes.write(bindless, cmd, len, staging, &regions)?;
+				frame.tlas_nodes_version = Some(self.tlas_nodes_version);
+				nodes
+			}
+		};
+		let tlas_primitives = match frame.tlas_primitives.shared() {
+			Some(primitives) if frame.tlas_primitives_version == Some(self.tlas_primitives_version) => primitives,
+			_ => {
+				frame.tlas_primitives_version = None;
+				let len = self.tlas.primitives.len();
+				let (staging, regions) = full_write(&self.tlas.primitives);
+				let primitives = frame.tlas_primitives.write(bindless, cmd, len, staging, &regions)?;
+				frame.tlas_primitives_version = Some(self.tlas_primitives_version);
+				primitives
+			}
+		};
+
+		let lights = self.lights.as_ref().expect("lights are built before writing");
+		let materials = self.materials.as_ref().expect("materials are uploaded before writing");
+		let prev_camera = self.prev_camera.unwrap_or(camera);
+		let scene = frame.write_scene(
+			bindless,
+			cmd,
+			VisiScene {
+				instances: instances.to_strong(),
+				bvh: Bvh {
+					nodes: tlas_nodes.to_strong(),
+					primitives: tlas_primitives.to_strong(),
+				},
+				lights: lights.lights.to_strong(),
+				light_count: lights.count,
+				light_alias_table: lights.alias_table.to_strong(),
+				light_tree: lights.light_tree(),
+				materials: materials.to_strong(),
+				environment: environment.to_gpu(),
+				camera,
+				prev_camera,
+			},
+		)?;
+		self.prev_camera = Some(camera);
+		self.frame = (self.frame + 1) % self.frames.len();
+
+		Ok(VisiCpuScene {
+			draws: self.draws.clone(),
+			instance_total_count: slots.len(),
+			camera,
+			version: self.version,
+			scene,
+			referenced: vec![
+				instances.into_any(),
+				tlas_nodes.into_any(),
+				tlas_primitives.into_any(),
+				lights.lights.clone().into_any(),
+				lights.alias_table.clone().into_any(),
+				lights.tree_nodes.clone().into_any(),
+				lights.tree_bit_trails.clone().into_any(),
+				lights.tree_infinite_lights.clone().into_any(),
+				materials.clone().into_any(),
+			],
+		})
+	}
+}
+
+/// Build the BVH over all live instances of `slots`, with `bounds` computing the world space bounds of each. Its
+/// primitives are the slots of the instances, which are their InstanceIds.
+fn build_tlas<M>(slots: &VisiGpuSlots<M>, bounds: impl Fn(&M, AffineTransform) -> [Vec3; 2]) -> CpuBvh {
+	let live_slots = slots.live_slots().collect::<Vec<_>>();
+	let instance_bounds = live_slots
+		.iter()
+		.map(|(_, slot)| bounds(&slot.model, slot.info.world_from_local))
+		.collect::<Vec<_>>();
+
+	// primitives index `instance_bounds`, but the GPU expects them to be InstanceIds
+	let mut tlas = build_bvh(&instance_bounds);
+	for primitive in &mut tlas.primitives {
+		*primitive = live_slots[*primitive as usize].0;
+	}
+	tlas
+}
+
+fn build_lights(
+	bindless: &Bindless,
+	slots: &VisiGpuSlots<VisiCpuModel>,
+	lights: &[VisiCpuLight<VisiGpuInstance>],
+	environment: &VisiCpuEnvironment,
+) -> anyhow::Result<VisiCpuLights> {
+	let instance_bounds = slots
+		.live_slots()
+		.map(|(_, slot)| world_bounds(&slot.model, slot.info.world_from_local))
+		.collect::<Vec<_>>();
+	let scene_radius = scene_radius(&instance_bounds);
+	let lights = lights
+		.iter()
+		.map(|light| {
+			Ok(match light {
+				VisiCpuLight::Light(light) => delta_light(*light, scene_radius),
+				VisiCpuLight::EmissiveTriangle {
+					instance,
+					triangle_id,
+					radiance,
+				} => {
+					let slot = slots.get(*instance)?;
+					emissive_triangle_light(
+						&slot.model,
+						InstanceId::new(instance.index())?,
+						slot.info.world_from_local,
+						*triangle_id,
+						*radiance,
+						scene_radius,
+					)?
+				}
+			})
+		})
+		.collect::<anyhow::Result<Vec<_>>>()?;
+	VisiCpuLights::new(bindless, lights, environment, scene_radius)
+}
+
+/// The buffers a single frame writes its [`VisiScene`] into. The scene of the previous frame is read by temporal
+/// reuse, so two frames alternate.
+struct VisiGpuSceneFrame {
+	scene: Option<(MutDesc<MutBuffer<VisiScene>>, RCDesc<Buffer<VisiScene>>)>,
+	instances: VisiGpuBuffer<VisiInstance>,
+	/// slots changed since `instances` was last written, may contain duplicates and removed slots
+	stale_slots: Vec<u32>,
+	tlas_nodes: VisiGpuBuffer<BvhNode>,
+	/// the [`VisiGpuSceneBuffers::tlas_nodes_version`] last written into `tlas_nodes`
+	tlas_nodes_version: Option<u64>,
+	tlas_primitives: VisiGpuBuffer<u32>,
+	/// the [`VisiGpuSceneBuffers::tlas_primitives_version`] last written into `tlas_primitives`
+	tlas_primitives_version: Option<u64>,
+}
+
+impl VisiGpuSceneFrame {
+	fn new() -> Self {
+		Self {
+			scene: None,
+			instances: VisiGpuBuffer::new("Instances"),
+			stale_slots: Vec::new(),
+			tlas_nodes: VisiGpuBuffer::new("BVH nodes"),
+			tlas_nodes_version: None,
+			tlas_primitives: VisiGpuBuffer::new("BVH primitives"),
+			tlas_primitives_version: None,
+		}
+	}
+
+	fn write_scene(
+		&mut self,
+		bindless: &Bindless,
+		cmd: &mut Recording<'_>,
+		scene: VisiScene,
+	) -> anyhow::Result<RCDesc<Buffer<VisiScene>>> {
+		let buffer = match self.scene.take() {
+			Some((buffer, _)) => buffer,
+			None => bindless.buffer().alloc_sized::<VisiScene>(&BindlessBufferCreateInfo {
+				usage: BindlessBufferUsage::STORAGE_BUFFER | BindlessBufferUsage::TRANSFER_DST,
+				allocation_scheme: Default::default(),
+				name: "Scene",
+			})?,
+		};
+		let staging = bindless.buffer().alloc_from_data(
+			&BindlessBufferCreateInfo {
+				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::TRANSFER_SRC,
+				allocation_scheme: Default::default(),
+				name: "Scene staging",
+			},
+			scene,
+		)?;
+		let staging = staging.access::<TransferRead>(cmd)?;
+		let buffer = buffer.access::<TransferWrite>(cmd)?;
+		cmd.copy_buffer_to_buffer(&staging, &buffer)?;
+		let buffer = buffer.transition::<GeneralRead>()?.into_desc();
+		let shared = shared_view(&buffer);
+		self.scene = Some((buffer, shared.clone()));
+		Ok(shared)
+	}
+}
+
+/// A buffer persisting across frames, which is written in place and only reallocated when it has to grow
+struct VisiGpuBuffer<T: BufferStruct> {
+	name: &'static str,
+	/// `None` if it was never written or the last write failed
+	buffer: Option<(MutDesc<MutBuffer<[T]>>, RCDesc<Buffer<[T]>>)>,
+}
+
+impl<T: BufferStruct> VisiGpuBuffer<T> {
+	fn new(name: &'static str) -> Self {
+		Self { name, buffer: None }
+	}
+
+	fn is_allocated(&self) -> bool {
+		self.buffer.is_some()
+	}
+
+	fn shared(&self) -> Option<RCDesc<Buffer<[T]>>> {
+		Some(self.buffer.as_ref()?.1.clone())
+	}
+
+	/// Record copying the `regions` of `staging` into this buffer, which must fit `len` elements afterward. If it has
+	/// to grow, its previous contents are copied over, so `regions` only need to contain what changed.
+	fn write(
+		&mut self,
+		bindless: &Bindless,
+		cmd: &mut Recording<'_>,
+		len: usize,
+		staging: Vec<T>,
+		regions: &[BufferSliceCopy],
+	) -> anyhow::Result<RCDesc<Buffer<[T]>>> {
+		// buffers can't be empty
+		let len = len.max(1);
+		let buffer = match self.buffer.take() {
+			Some((buffer, _)) if buffer.inner_slot().len >= len => buffer.access::<TransferWrite>(cmd)?,
+			prev => {
+				let prev_len = prev.as_ref().map_or(0, |(prev, _)| prev.inner_slot().len);
+				let buffer = bindless.buffer().alloc_slice::<T>(
+					&BindlessBufferCreateInfo {
+						usage: BindlessBufferUsage::STORAGE_BUFFER
+							| BindlessBufferUsage::TRANSFER_SRC
+							| BindlessBufferUsage::TRANSFER_DST,
+						allocation_scheme: Default::default(),
+						name: self.name,
+					},
+					len.max(prev_len * 2),
+				)?;
+				let buffer = buffer.access::<TransferWrite>(cmd)?;
+				if let Some((prev, _)) = prev {
+					let prev = prev.access::<TransferRead>(cmd)?;
+					cmd.copy_buffer_to_buffer_slice_regions(&prev, &buffer, &[full_region(prev_len)])?;
+				}
+				buffer
+			}
+		};
+		if !regions.is_empty() {
+			let staging = bindless.buffer().alloc_from_iter(
+				&BindlessBufferCreateInfo {
+					usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::TRANSFER_SRC,
+					allocation_scheme: Default::default(),
+					name: self.name,
+				},
+				staging,
+			)?;
+			let staging = staging.access::<TransferRead>(cmd)?;
+			cmd.copy_buffer_to_buffer_slice_regions(&staging, &buffer, regions)?;
+		}
+		let buffer = buffer.transition::<GeneralRead>()?.into_desc();
+		let shared = shared_view(&buffer);
+		self.buffer = Some((buffer, shared.clone()));
+		Ok(shared)
+	}
+}
+
+/// A shared view of a mutable buffer, to be referenced by other buffers
+fn shared_view<T: BufferContent + ?Sized>(buffer: &MutDesc<MutBuffer<T>>) -> RCDesc<Buffer<T>> {
+	// Safety: all accesses happen on a single queue, and every write transitions the buffer back to `GeneralRead`,
+	// whose barriers order it against all reads through the view recorded before or after it.
+	unsafe { RCDesc::new(buffer.rc_slot().clone()) }
+}
+
+fn full_region(len: usize) -> BufferSliceCopy {
+	BufferSliceCopy {
+		src_start: 0,
+		dst_start: 0,
+		len,
+	}
+}
+
+/// The staging data and regions to write all of `data`. Neither staging buffers nor copies may be empty, so empty
+/// `data` has no regions, e.g. the primitives of a scene without instances.
+fn full_write<T: Copy>(data: &[T]) -> (Vec<T>, Vec<BufferSliceCopy>) {
+	if data.is_empty() {
+		(Vec::new(), Vec::new())
+	} else {
+		(data.to_vec(), vec![full_region(data.len())])
+	}
+}
+
+/// The regions to copy from a staging buffer containing the `slots` in order. `slots` must be sorted and
+/// deduplicated.
+fn copy_regions(slots: &[u32]) -> Vec<BufferSliceCopy> {
+	let mut regions: Vec<BufferSliceCopy> = Vec::new();
+	for (staging_index, index) in slots.iter().enumerate() {
+		let index = *index as usize;
+		match regions.last_mut() {
+			Some(region) if region.dst_start + region.len == index => region.len += 1,
+			_ => regions.push(BufferSliceCopy {
+				src_start: staging_index,
+				dst_start: index,
+				len: 1,
+			}),
+		}
+	}
+	regions
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use glam::Affine3A;
+
+	fn region(src_start: usize, dst_start: usize, len: usize) -> BufferSliceCopy {
+		BufferSliceCopy {
+			src_start,
+			dst_start,
+			len,
+		}
+	}
+
+	#[test]
+	fn test_copy_regions() {
+		assert_eq!(
+			copy_regions(&[1, 2, 3, 6, 9, 10]),
+			[region(0, 1, 3), region(3, 6, 1), region(4, 9, 2)]
+		);
+		assert_eq!(copy_regions(&[]), []);
+		assert_eq!(copy_regions(&[0, 1, 2]), [region(0, 0, 3)]);
+		assert_eq!(copy_regions(&[0, 4]), [region(0, 0, 1), region(1, 4, 1)]);
+	}
+
+	#[test]
+	fn test_upload_without_instances() -> anyhow::Result<()> {
+		let mut slots = VisiGpuSlots::new();
+		let instances = [0., 1., 2.].map(|x| {
+			slots.add(
+				(),
+				VisiInstanceInfo::new(
+					AffineTransform::new(Affine3A::from_translation(Vec3::new(x, 0., 0.))),
+					0,
+				),
+			)
+		});
+		let bounds = |_: &(), transform: AffineTransform| {
+			let center = transform.translation();
+			[center - 0.5, center + 0.5]
+		};
+		let tlas = slots.upload(|slots, _| Ok(build_tlas(slots, bounds)))?;
+		assert_eq!(tlas.primitives.len(), 3);
+		assert_eq!(full_write(&tlas.primitives).1, [region(0, 0, 3)]);
+
+		for instance in instances {
+			slots.remove(instance)?;
+		}
+		let tlas = slots.upload(|slots, changes| {
+			assert!(changes.structure_changed);
+			Ok(build_tlas(slots, bounds))
+		})?;
+		assert!(tlas.primitives.is_empty());
+		let (staging, regions) = full_write(&tlas.primitives);
+		assert!(staging.is_empty() && regions.is_empty());
+		let (staging, regions) = full_write(&tlas.nodes);
+		assert!(!staging.is_empty());
+		assert!(regions.iter().all(|region| region.len > 0));
+		Ok(())
+	}
+}
@@ -0,0 +1,393 @@
+use restir_shader::utils::affine_transform::AffineTransform;
+use restir_shader::visibility::scene::VisiInstanceInfo;
+use std::mem;
+
+/// A stable handle to an instance of a [`VisiGpuScene`]. Its index is the [`InstanceId`] of the instance, which is
+/// reused by the next added instance once it is removed. The generation tells the handles of a reused slot apart.
+///
+/// [`VisiGpuScene`]: crate::visibility::gpu_scene::VisiGpuScene
+/// [`InstanceId`]: restir_shader::visibility::id::InstanceId
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct VisiGpuInstance {
+	index: u32,
+	generation: u32,
+}
+
+impl VisiGpuInstance {
+	pub fn index(&self) -> u32 {
+		self.index
+	}
+}
+
+/// An instance of the model `M`
+pub struct VisiGpuSlot<M> {
+	pub model: M,
+	pub info: VisiInstanceInfo,
+	/// whether the instance was moved since the last upload
+	moved: bool,
+}
+
+struct VisiGpuSlotEntry<M> {
+	/// incremented whenever the instance is removed, invalidating all of its handles
+	generation: u32,
+	slot: Option<VisiGpuSlot<M>>,
+}
+
+/// Consecutive slots of the same model, drawn together
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VisiGpuSlotRun<M> {
+	pub model: M,
+	pub instance_start: u32,
+	pub instance_count: u32,
+}
+
+/// Everything that changed since the last upload of [`VisiGpuSlots`]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VisiGpuSlotChanges {
+	/// sorted and deduplicated slots to upload, never contains removed slots
+	pub dirty_slots: Vec<u32>,
+	/// sorted and deduplicated slots moved since the last upload
+	pub moved_slots: Vec<u32>,
+	/// whether instances were added or removed
+	pub structure_changed: bool,
+}
+
+/// The slot bookkeeping of a [`VisiGpuScene`], indexed by [`InstanceId`]. Tracks which slots must be uploaded and
+/// when the `prev_world_from_local` of moved instances must catch up with their `world_from_local`.
+///
+/// [`VisiGpuScene`]: crate::visibility::gpu_scene::VisiGpuScene
+/// [`InstanceId`]: restir_shader::visibility::id::InstanceId
+pub struct VisiGpuSlots<M> {
+	entries: Vec<VisiGpuSlotEntry<M>>,
+	/// slots of removed instances, reused by the next added instances
+	free_slots: Vec<u32>,
+	/// slots to upload, may contain duplicates and removed slots
+	dirty_slots: Vec<u32>,
+	/// slots moved since the last upload
+	moved_slots: Vec<u32>,
+	/// slots moved before the last upload, whose `prev_world_from_local` must catch up once they stop moving
+	prev_moved_slots: Vec<u32>,
+	structure_changed: bool,
+}
+
+impl<M> Default for VisiGpuSlots<M> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<M> VisiGpuSlots<M> {
+	pub fn new() -> Self {
+		Self {
+			entries: Vec::new(),
+			free_slots: Vec::new(),
+			dirty_slots: Vec::new(),
+			moved_slots: Vec::new(),
+			prev_moved_slots: Vec::new(),
+			structure_changed: true,
+		}
+	}
+
+	/// The amount of slots, including those of removed instances
+	pub fn len(&self) -> u32 {
+		self.entries.len() as u32
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	pub fn add(&mut self, model: M, info: VisiInstanceInfo) -> VisiGpuInstance {
+		let slot = VisiGpuSlot {
+			model,
+			info,
+			moved: false,
+		};
+		let index = match self.free_slots.pop() {
+			Some(index) => {
+				self.entries[index as usize].slot = Some(slot);
+				index
+			}
+			None => {
+				self.entries.push(VisiGpuSlotEntry {
+					generation: 0,
+					slot: Some(slot),
+				});
+				self.entries.len() as u32 - 1
+			}
+		};
+		self.dirty_slots.push(index);
+		self.structure_changed = true;
+		VisiGpuInstance {
+			index,
+			generation: self.entries[index as usize].generation,
+		}
+	}
+
+	/// Remove an instance and return its slot. Its handle and all copies of it become invalid.
+	pub fn remove(&mut self, instance: VisiGpuInstance) -> anyhow::Result<VisiGpuSlot<M>> {
+		self.get(instance)?;
+		let entry = &mut self.entries[instance.index as usize];
+		entry.generation = entry.generation.wrapping_add(1);
+		self.free_slots.push(instance.index);
+		self.structure_changed = true;
+		Ok(entry.slot.take().unwrap())
+	}
+
+	/// Move an instance. Its `prev_world_from_local` is updated to the transform it had at the last upload.
+	pub fn update_transform(
+		&mut self,
+		instance: VisiGpuInstance,
+		world_from_local: AffineTransform,
+	) -> anyhow::Result<()> {
+		let slot = self.slot_mut(instance)?;
+		let first_move = !slot.moved;
+		if first_move {
+			slot.moved = true;
+			slot.info.prev_world_from_local = slot.info.world_from_local;
+		}
+		slot.info.world_from_local = world_from_local;
+		if first_move {
+			self.moved_slots.push(instance.index);
+		}
+		self.dirty_slots.push(instance.index);
+		Ok(())
+	}
+
+	pub fn get(&self, instance: VisiGpuInstance) -> anyhow::Result<&VisiGpuSlot<M>> {
+		self.entries
+			.get(instance.index as usize)
+			.filter(|entry| entry.generation == instance.generation)
+			.and_then(|entry| entry.slot.as_ref())
+			.ok_or_else(|| anyhow::anyhow!("{instance:?} was removed"))
+	}
+
+	fn slot_mut(&mut self, instance: VisiGpuInstance) -> anyhow::Result<&mut VisiGpuSlot<M>> {
+		self.entries
+			.get_mut(instance.index as usize)
+			.filter(|entry| entry.generation == instance.generation)
+			.and_then(|entry| entry.slot.as_mut())
+			.ok_or_else(|| anyhow::anyhow!("{instance:?} was removed"))
+	}
+
+	/// The slot at `index`, or `None` if its instance was removed
+	pub fn slot(&self, index: u32) -> Option<&VisiGpuSlot<M>> {
+		self.entries.get(index as usize)?.slot.as_ref()
+	}
+
+	/// All slots with an instance and their index
+	pub fn live_slots(&self) -> impl Iterator<Item = (u32, &VisiGpuSlot<M>)> {
+		self.entries
+			.iter()
+			.enumerate()
+			.filter_map(|(index, entry)| Some((index as u32, entry.slot.as_ref()?)))
+	}
+
+	/// Call `upload` with all changes since the last successful upload. Instances that stopped moving get their
+	/// `prev_world_from_local` caught up with their `world_from_local` first. If `upload` fails, all changes are kept
+	/// for the next upload.
+	pub fn upload<R>(
+		&mut self,
+		upload: impl FnOnce(&Self, &VisiGpuSlotChanges) -> anyhow::Result<R>,
+	) -> anyhow::Result<R> {
+		// instances that stopped moving must not keep the motion of their last move
+		for index in &self.prev_moved_slots {
+			if let Some(slot) = &mut self.entries[*index as usize].slot
+				&& !slot.moved
+			{
+				slot.info.prev_world_from_local = slot.info.world_from_local;
+				self.dirty_slots.push(*index);
+			}
+		}
+
+		let mut dirty_slots = self.dirty_slots.clone();
+		dirty_slots.sort_unstable();
+		dirty_slots.dedup();
+		// removed slots are never read by the GPU
+		dirty_slots.retain(|index| self.slot(*index).is_some());
+		let mut moved_slots = self.moved_slots.clone();
+		moved_slots.sort_unstable();
+		let changes = VisiGpuSlotChanges {
+			dirty_slots,
+			moved_slots,
+			structure_changed: self.structure_changed,
+		};
+		let result = upload(self, &changes)?;
+
+		for index in &self.moved_slots {
+			if let Some(slot) = &mut self.entries[*index as usize].slot {
+				slot.moved = false;
+			}
+		}
+		self.prev_moved_slots = mem::take(&mut self.moved_slots);
+		self.dirty_slots.clear();
+		self.structure_changed = false;
+		Ok(result)
+	}
+}
+
+impl<M: Clone + PartialEq> VisiGpuSlots<M> {
+	/// Consecutive slots of the same model merged into runs, skipping removed slots
+	pub fn runs(&self) -> Vec<VisiGpuSlotRun<M>> {
+		let mut runs: Vec<VisiGpuSlotRun<M>> = Vec::new();
+		for (index, slot) in self.live_slots() {
+			match runs.last_mut() {
+				Some(run) if run.model == slot.model && run.instance_start + run.instance_count == index => {
+					run.instance_count += 1
+				}
+				_ => runs.push(VisiGpuSlotRun {
+					model: slot.model.clone(),
+					instance_start: index,
+					instance_count: 1,
+				}),
+			}
+		}
+		runs
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use glam::{Affine3A, Vec3};
+
+	fn info(x: f32) -> VisiInstanceInfo {
+		VisiInstanceInfo::new(transform(x), 0)
+	}
+
+	fn transform(x: f32) -> AffineTransform {
+		AffineTransform::new(Affine3A::from_translation(Vec3::new(x, 0., 0.)))
+	}
+
+	fn changes(slots: &mut VisiGpuSlots<u32>) -> VisiGpuSlotChanges {
+		slots.upload(|_, changes| Ok(changes.clone())).unwrap()
+	}
+
+	fn run(model: u32, instance_start: u32, instance_count: u32) -> VisiGpuSlotRun<u32> {
+		VisiGpuSlotRun {
+			model,
+			instance_start,
+			instance_count,
+		}
+	}
+
+	#[test]
+	fn test_add_remove_reuse() -> anyhow::Result<()> {
+		let mut slots = VisiGpuSlots::new();
+		let a = slots.add(0, info(0.));
+		let b = slots.add(0, info(1.));
+		let c = slots.add(1, info(2.));
+		assert_eq!([a.index(), b.index(), c.index()], [0, 1, 2]);
+		assert_eq!(
+			changes(&mut slots),
+			VisiGpuSlotChanges {
+				dirty_slots: vec![0, 1, 2],
+				moved_slots: vec![],
+				structure_changed: true,
+			}
+		);
+		assert_eq!(changes(&mut slots), VisiGpuSlotChanges::default());
+
+		slots.remove(b)?;
+		assert!(slots.remove(b).is_err());
+		assert!(slots.get(b).is_err());
+		assert_eq!(slots.live_slots().map(|(index, _)| index).collect::<Vec<_>>(), [0, 2]);
+		assert_eq!(
+			changes(&mut slots),
+			VisiGpuSlotChanges {
+				structure_changed: true,
+				..Default::default()
+			}
+		);
+
+		// the slot is reused, but the old handle stays invalid
+		let d = slots.add(2, info(3.));
+		assert_eq!(d.index(), b.index());
+		assert_ne!(d, b);
+		assert!(slots.get(b).is_err());
+		assert!(slots.update_transform(b, transform(4.)).is_err());
+		assert_eq!(slots.get(d)?.model, 2);
+		assert_eq!(slots.len(), 3);
+		assert_eq!(changes(&mut slots).dirty_slots, [1]);
+
+		// removed slots are not uploaded
+		slots.update_transform(a, transform(5.))?;
+		slots.remove(a)?;
+		assert_eq!(changes(&mut slots).dirty_slots, []);
+		Ok(())
+	}
+
+	#[test]
+	fn test_prev_moved_catch_up() -> anyhow::Result<()> {
+		let mut slots = VisiGpuSlots::new();
+		let a = slots.add(0, info(0.));
+		let b = slots.add(0, info(0.));
+		changes(&mut slots);
+
+		// moving twice before an upload keeps the transform of the last upload as the previous one
+		slots.update_transform(a, transform(1.))?;
+		slots.update_transform(a, transform(2.))?;
+		let moved = changes(&mut slots);
+		assert_eq!(moved.dirty_slots, [0]);
+		assert_eq!(moved.moved_slots, [0]);
+		assert!(!moved.structure_changed);
+		assert_eq!(slots.get(a)?.info.prev_world_from_local.affine, transform(0.).affine);
+		assert_eq!(slots.get(a)?.info.world_from_local.affine, transform(2.).affine);
+
+		// still moving
+		slots.update_transform(a, transform(3.))?;
+		assert_eq!(changes(&mut slots).dirty_slots, [0]);
+		assert_eq!(slots.get(a)?.info.prev_world_from_local.affine, transform(2.).affine);
+
+		// stopped moving, so the previous transform must catch up once
+		let stopped = changes(&mut slots);
+		assert_eq!(stopped.dirty_slots, [0]);
+		assert_eq!(stopped.moved_slots, []);
+		assert_eq!(slots.get(a)?.info.prev_world_from_local.affine, transform(3.).affine);
+		assert_eq!(changes(&mut slots), VisiGpuSlotChanges::default());
+		assert_eq!(slots.get(b)?.info.prev_world_from_local.affine, transform(0.).affine);
+		Ok(())
+	}
+
+	#[test]
+	fn test_failed_upload_keeps_changes() -> anyhow::Result<()> {
+		let mut slots = VisiGpuSlots::<u32>::new();
+		let a = slots.add(0, info(0.));
+		changes(&mut slots);
+
+		slots.update_transform(a, transform(1.))?;
+		let failed = slots.upload(|_, changes| -> anyhow::Result<()> {
+			assert_eq!(changes.dirty_slots, [0]);
+			Err(anyhow::anyhow!("upload failed"))
+		});
+		assert!(failed.is_err());
+
+		let retried = changes(&mut slots);
+		assert_eq!(retried.dirty_slots, [0]);
+		assert_eq!(retried.moved_slots, [0]);
+		assert_eq!(slots.get(a)?.info.prev_world_from_local.affine, transform(0.).affine);
+		assert_eq!(slots.get(a)?.info.world_from_local.affine, transform(1.).affine);
+		Ok(())
+	}
+
+	#[test]
+	fn test_runs_around_holes() -> anyhow::Result<()> {
+		let mut slots = VisiGpuSlots::new();
+		let instances = [0, 0, 0, 1, 1, 0].map(|model| slots.add(model, info(0.)));
+		assert_eq!(slots.runs(), [run(0, 0, 3), run(1, 3, 2), run(0, 5, 1)]);
+
+		slots.remove(instances[1])?;
+		slots.remove(instances[4])?;
+		assert_eq!(slots.runs(), [run(0, 0, 1), run(0, 2, 1), run(1, 3, 1), run(0, 5, 1)]);
+
+		// filling the holes merges the runs again, a different model splits them
+		slots.add(1, info(0.));
+		slots.add(0, info(0.));
+		assert_eq!(slots.runs(), [run(0, 0, 3), run(1, 3, 2), run(0, 5, 1)]);
+
+		slots.remove(instances[0])?;
+		slots.add(1, info(0.));
+		assert_eq!(slots.runs(), [run(1, 0, 1), run(0, 1, 2), run(1, 3, 2), run(0, 5, 1)]);
+		Ok(())
+	}
+}
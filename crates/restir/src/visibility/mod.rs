@@ -1,3 +1,8 @@
+pub mod bvh;
+pub mod gpu_scene;
+pub mod gpu_slots;
+pub mod output;
+pub mod output_dump;
 pub mod raster;
 pub mod renderer;
 pub mod scene;
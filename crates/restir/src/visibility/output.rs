@@ -0,0 +1,26 @@
+use glam::UVec2;
+use restir_shader::visibility::output::{OUTPUT_WG_SIZE, Param};
+use rust_gpu_bindless::descriptor::Bindless;
+use rust_gpu_bindless::pipeline::{BindlessComputePipeline, Recording};
+
+pub struct VisiOutputCopyPipeline {
+	pipeline: BindlessComputePipeline<Param<'static>>,
+}
+
+impl VisiOutputCopyPipeline {
+	pub fn new(bindless: &Bindless) -> anyhow::Result<Self> {
+		Ok(Self {
+			pipeline: bindless.create_compute_pipeline(crate::shader::visibility::output::visi_output_copy::new())?,
+		})
+	}
+
+	pub fn dispatch(&self, cmd: &mut Recording, param: Param) -> anyhow::Result<()> {
+		let size = param.size;
+		cmd.dispatch(
+			&self.pipeline,
+			[size.x.div_ceil(OUTPUT_WG_SIZE.x), size.y.div_ceil(OUTPUT_WG_SIZE.y), 1],
+			param,
+		)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,168 @@
+use anyhow::Context;
+use image::{Rgb, Rgb32FImage};
+use rust_gpu_bindless::descriptor::{
+	Bindless, BindlessBufferCreateInfo, BindlessBufferUsage, Extent, Format, Image2d, ImageDescExt, MutBuffer, MutDesc,
+	MutDescBufferExt,
+};
+use rust_gpu_bindless::pipeline::{
+	HostAccess, MutBufferAccessExt, MutImageAccess, Recording, TransferRead, TransferWrite,
+};
+use std::path::PathBuf;
+use std::sync::mpsc::{SyncSender, sync_channel};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Dumps the output of the renderer as `.exr` images, to compare them against a reference with `restir-compare`.
+/// The file names contain the frame index and the milliseconds since the first frame, excluding the time spent on
+/// dumping, for convergence-vs-time plots.
+/// The linear radiance is dumped unclamped, as the renderer wrote it before drawing any debug view on top of it.
+pub struct OutputDump {
+	dir: PathBuf,
+	/// only every `interval`th frame is dumped
+	interval: u32,
+	frame: u32,
+	start: Option<Instant>,
+	/// time the frame loop spent on dumping, which is excluded from the milliseconds in the file names
+	dump_time: Duration,
+	/// encodes and saves the images, so the disk doesn't stall the frame loop
+	writer: Option<OutputDumpWriter>,
+}
+
+struct OutputDumpWriter {
+	sender: SyncSender<OutputDumpImage>,
+	thread: JoinHandle<anyhow::Result<()>>,
+}
+
+/// how many images may wait for the writer before the frame loop blocks on it, which counts as dump time
+const WRITER_QUEUE_LEN: usize = 4;
+
+impl OutputDump {
+	/// Enabled by setting `RESTIR_DUMP` to the output directory, `RESTIR_DUMP_INTERVAL` optionally dumps only every
+	/// n-th frame
+	pub fn from_env() -> anyhow::Result<Option<Self>> {
+		let Some(dir) = std::env::var_os("RESTIR_DUMP").map(PathBuf::from) else {
+			return Ok(None);
+		};
+		let interval = match std::env::var("RESTIR_DUMP_INTERVAL") {
+			Ok(interval) => interval
+				.parse::<u32>()
+				.with_context(|| format!("RESTIR_DUMP_INTERVAL {interval:?}"))?
+				.max(1),
+			Err(_) => 1,
+		};
+		std::fs::create_dir_all(&dir).with_context(|| format!("creating {dir:?}"))?;
+		let (sender, receiver) = sync_channel::<OutputDumpImage>(WRITER_QUEUE_LEN);
+		let thread = std::thread::Builder::new()
+			.name("output dump".into())
+			.spawn(move || receiver.into_iter().try_for_each(OutputDumpImage::save))?;
+		Ok(Some(Self {
+			dir,
+			interval,
+			frame: 0,
+			start: None,
+			dump_time: Duration::ZERO,
+			writer: Some(OutputDumpWriter { sender, thread }),
+		}))
+	}
+
+	/// Record copying the RGBA32F `radiance` of this frame into a host readable buffer, if this frame is dumped
+	pub fn record(
+		&mut self,
+		bindless: &Bindless,
+		cmd: &mut Recording<'_>,
+		radiance: &MutImageAccess<'_, Image2d, TransferRead>,
+	) -> anyhow::Result<Option<PendingOutputDump>> {
+		let frame = self.frame;
+		self.frame += 1;
+		let elapsed = self
+			.start
+			.get_or_insert_with(Instant::now)
+			.elapsed()
+			.saturating_sub(self.dump_time);
+		if frame % self.interval != 0 {
+			return Ok(None);
+		}
+
+		let format = radiance.format();
+		if format != Format::R32G32B32A32_SFLOAT {
+			anyhow::bail!("Dumping radiance images of format {format:?} is not supported");
+		}
+		let extent = radiance.extent();
+		let texels = bindless.buffer().alloc_slice::<[f32; 4]>(
+			&BindlessBufferCreateInfo {
+				usage: BindlessBufferUsage::MAP_READ | BindlessBufferUsage::TRANSFER_DST,
+				allocation_scheme: Default::default(),
+				name: "output dump",
+			},
+			(extent.width * extent.height) as usize,
+		)?;
+		let texels = texels.access::<TransferWrite>(cmd)?;
+		// the 16 byte texels of the format fit the buffer exactly
+		unsafe { cmd.copy_image_to_buffer(radiance, &texels)? };
+		Ok(Some(PendingOutputDump {
+			texels: texels.transition::<HostAccess>()?.into_desc(),
+			extent,
+			path: self.dir.join(format!("frame_{frame:06}_{}ms.exr", elapsed.as_millis())),
+		}))
+	}
+
+	/// Wait for the frame of `pending` to finish and hand its image to the writer thread
+	pub async fn write(&mut self, pending: PendingOutputDump) -> anyhow::Result<()> {
+		let start = Instant::now();
+		let texels = pending.texels.mapped().await?.read_iter().collect::<Vec<_>>();
+		let image = OutputDumpImage {
+			texels,
+			extent: pending.extent,
+			path: pending.path,
+		};
+		let writer = self.writer.as_ref().context("output dump writer already failed")?;
+		if writer.sender.send(image).is_err() {
+			// the writer only stops receiving if it failed
+			return self.join_writer();
+		}
+		self.dump_time += start.elapsed();
+		Ok(())
+	}
+
+	/// Wait for the writer thread to save all images
+	pub fn finish(mut self) -> anyhow::Result<()> {
+		self.join_writer()
+	}
+
+	fn join_writer(&mut self) -> anyhow::Result<()> {
+		let writer = self.writer.take().context("output dump writer already failed")?;
+		drop(writer.sender);
+		writer
+			.thread
+			.join()
+			.map_err(|_| anyhow::anyhow!("output dump writer panicked"))?
+	}
+}
+
+/// The output of a frame being copied, handed to [`OutputDump::write`] once the frame finished
+pub struct PendingOutputDump {
+	texels: MutDesc<MutBuffer<[[f32; 4]]>>,
+	extent: Extent,
+	path: PathBuf,
+}
+
+/// The output of a frame read back from the GPU, waiting to be saved by the writer thread
+struct OutputDumpImage {
+	texels: Vec<[f32; 4]>,
+	extent: Extent,
+	path: PathBuf,
+}
+
+impl OutputDumpImage {
+	fn save(self) -> anyhow::Result<()> {
+		let Extent { width, height, .. } = self.extent;
+		let image = Rgb32FImage::from_fn(width, height, |x, y| {
+			let [r, g, b, _] = self.texels[(y * width + x) as usize];
+			Rgb([r, g, b])
+		});
+		image
+			.save(&self.path)
+			.with_context(|| format!("writing {:?}", self.path))?;
+		Ok(())
+	}
+}
@@ -16,25 +16,35 @@ pub struct VisiRasterPipeline {
 
 impl VisiRasterPipeline {
 	pub fn new(bindless: &Bindless, format: VisiPipelinesFormat) -> anyhow::Result<Self> {
-		Ok(Self {
-			pipeline: bindless.create_graphics_pipeline(
-				&format.to_render_pass_format(),
-				&GraphicsPipelineCreateInfo {
-					input_assembly_state: PipelineInputAssemblyStateCreateInfo::default()
-						.topology(PrimitiveTopology::TRIANGLE_LIST),
-					rasterization_state: PipelineRasterizationStateCreateInfo::default().line_width(1.0),
-					depth_stencil_state: PipelineDepthStencilStateCreateInfo::default()
-						.depth_test_enable(true)
-						.depth_write_enable(true)
-						.depth_compare_op(CompareOp::LESS),
-					color_blend_state: PipelineColorBlendStateCreateInfo::default().attachments(&[
-						PipelineColorBlendAttachmentState::default().color_write_mask(ColorComponentFlags::RGBA),
-					]),
-				},
+		let render_pass_format = format.to_render_pass_format();
+		let create_info = GraphicsPipelineCreateInfo {
+			input_assembly_state: PipelineInputAssemblyStateCreateInfo::default()
+				.topology(PrimitiveTopology::TRIANGLE_LIST),
+			rasterization_state: PipelineRasterizationStateCreateInfo::default().line_width(1.0),
+			depth_stencil_state: PipelineDepthStencilStateCreateInfo::default()
+				.depth_test_enable(true)
+				.depth_write_enable(true)
+				.depth_compare_op(CompareOp::LESS),
+			color_blend_state: PipelineColorBlendStateCreateInfo::default().attachments(&[
+				PipelineColorBlendAttachmentState::default().color_write_mask(ColorComponentFlags::RGBA),
+			]),
+		};
+		let pipeline = if format.wide_ids() {
+			bindless.create_graphics_pipeline(
+				&render_pass_format,
+				&create_info,
+				crate::shader::visibility::raster::visibility_vert::new(),
+				crate::shader::visibility::raster::visibility_frag_wide::new(),
+			)?
+		} else {
+			bindless.create_graphics_pipeline(
+				&render_pass_format,
+				&create_info,
 				crate::shader::visibility::raster::visibility_vert::new(),
 				crate::shader::visibility::raster::visibility_frag::new(),
-			)?,
-		})
+			)?
+		};
+		Ok(Self { pipeline })
 	}
 
 	pub fn draw(
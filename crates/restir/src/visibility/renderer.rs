@@ -1,17 +1,37 @@
-use crate::material::debug::VisiDebugPipeline;
+use crate::material::resolve::VisiMaterialPipelines;
+use crate::material::system::classify_pipeline::MaterialClassifyBuffers;
+use crate::random::blue_noise::VisiCpuBlueNoise;
+use crate::reference::path_trace::ReferencePathTracePipeline;
+use crate::restir::di::DiPipelines;
+use crate::restir::gi::GiPipelines;
+use crate::restir::pt::PtPipelines;
+use crate::visibility::output::VisiOutputCopyPipeline;
+use crate::visibility::output_dump::{OutputDump, PendingOutputDump};
 use crate::visibility::raster::VisiRasterPipeline;
 use crate::visibility::scene::VisiCpuScene;
 use anyhow::anyhow;
 use glam::UVec4;
-use restir_shader::material::debug::DebugSettings;
+use restir_shader::camera::Camera;
+use restir_shader::material::debug::{DebugSettings, DebugType};
+use restir_shader::material::pbr::PbrSurface;
+use restir_shader::reference::ReferenceSettings;
+use restir_shader::reference::path_trace;
+use restir_shader::restir::di::{DiReservoir, DiSettings, initial, shade, spatial, temporal};
+use restir_shader::restir::gi::{GiReservoir, GiSettings};
+use restir_shader::restir::pt::{PtReservoir, PtSettings};
+use restir_shader::restir::{gi, pt};
+use restir_shader::visibility::id::{INSTANCE_BITS, MAX_ID};
+use restir_shader::visibility::output;
 use rust_gpu_bindless::descriptor::{
-	Bindless, BindlessAllocationScheme, BindlessImageCreateInfo, BindlessImageUsage, Extent, Format, Image2d, Image2dU,
-	ImageDescExt, MutDesc, MutImage, RCDescExt,
+	Bindless, BindlessAllocationScheme, BindlessBufferCreateInfo, BindlessBufferUsage, BindlessImageCreateInfo,
+	BindlessImageUsage, Extent, Format, Image2d, Image2dU, ImageDescExt, MutBuffer, MutDesc, MutImage, RCDescExt,
 };
 use rust_gpu_bindless::pipeline::{
-	ColorAttachment, DepthStencilAttachment, ImageAccessType, LoadOp, MutImageAccess, MutImageAccessExt, Recording,
-	RenderPassFormat, RenderingAttachment, RenderingAttachmentImage, SampledRead, StorageReadWrite, StoreOp,
+	ColorAttachment, DepthStencilAttachment, ImageAccessType, LoadOp, MutBufferAccess, MutBufferAccessExt,
+	MutImageAccess, MutImageAccessExt, Recording, RenderPassFormat, RenderingAttachment, RenderingAttachmentImage,
+	SampledRead, ShaderRead, ShaderReadWrite, StorageReadWrite, StoreOp, TransferRead,
 };
+use rust_gpu_bindless_shaders::buffer_content::BufferStruct;
 use smallvec::SmallVec;
 use std::sync::Arc;
 
@@ -31,6 +51,30 @@ impl VisiPipelinesFormat {
 		}
 	}
 
+	/// Use a `R32G32_UINT` visibility buffer of [`WideGeometryId`]s, which supports scenes with more than
+	/// `1 << INSTANCE_BITS` instances at the cost of twice the memory.
+	///
+	/// [`WideGeometryId`]: restir_shader::visibility::id::WideGeometryId
+	pub fn with_wide_ids(self) -> Self {
+		Self {
+			visi: Format::R32G32_UINT,
+			..self
+		}
+	}
+
+	pub fn wide_ids(&self) -> bool {
+		self.visi == Format::R32G32_UINT
+	}
+
+	/// The most instances a scene may contain to be rendered with this visibility buffer format
+	pub fn max_instance_count(&self) -> u32 {
+		if self.wide_ids() {
+			MAX_ID + 1
+		} else {
+			1 << INSTANCE_BITS
+		}
+	}
+
 	pub fn to_render_pass_format(&self) -> RenderPassFormat {
 		RenderPassFormat {
 			color_attachments: SmallVec::from_slice(&[self.visi]),
@@ -43,16 +87,34 @@ pub struct VisiPipelines {
 	bindless: Bindless,
 	format: VisiPipelinesFormat,
 	raster_pipeline: VisiRasterPipeline,
-	debug_pipeline: VisiDebugPipeline,
+	materials: VisiMaterialPipelines,
+	di_pipelines: DiPipelines,
+	gi_pipelines: GiPipelines,
+	pt_pipelines: PtPipelines,
+	reference_path_trace: ReferencePathTracePipeline,
+	output_copy: VisiOutputCopyPipeline,
+	blue_noise: VisiCpuBlueNoise,
 }
 
 impl VisiPipelines {
-	pub fn new(bindless: &Bindless, format: VisiPipelinesFormat) -> anyhow::Result<Arc<Self>> {
+	pub async fn new(bindless: &Bindless, format: VisiPipelinesFormat) -> anyhow::Result<Arc<Self>> {
+		if !matches!(format.visi, Format::R32_UINT | Format::R32G32_UINT) {
+			return Err(anyhow!(
+				"Visibility buffer format must be R32_UINT or R32G32_UINT, but was {:?}",
+				format.visi
+			));
+		}
 		Ok(Arc::new(Self {
 			bindless: bindless.clone(),
 			format,
 			raster_pipeline: VisiRasterPipeline::new(bindless, format)?,
-			debug_pipeline: VisiDebugPipeline::new(bindless)?,
+			materials: VisiMaterialPipelines::new(bindless)?,
+			di_pipelines: DiPipelines::new(bindless)?,
+			gi_pipelines: GiPipelines::new(bindless)?,
+			pt_pipelines: PtPipelines::new(bindless)?,
+			reference_path_trace: ReferencePathTracePipeline::new(bindless)?,
+			output_copy: VisiOutputCopyPipeline::new(bindless)?,
+			blue_noise: VisiCpuBlueNoise::new(bindless).await?,
 		}))
 	}
 
@@ -64,50 +126,216 @@ impl VisiPipelines {
 pub struct VisiRenderer {
 	pub pipeline: Arc<VisiPipelines>,
 	resources: Option<VisiRendererResources>,
+	frame: u32,
 }
 
 pub struct VisiRendererResources {
 	pub extent: Extent,
 	pub packed_vertex_image: MutDesc<MutImage<Image2dU>>,
+	pub prev_packed_vertex_image: MutDesc<MutImage<Image2dU>>,
 	pub depth: MutDesc<MutImage<Image2d>>,
+	pub di_reservoirs: MutDesc<MutBuffer<[DiReservoir]>>,
+	pub prev_di_reservoirs: MutDesc<MutBuffer<[DiReservoir]>>,
+	/// the surface of each pixel, resolved by the material shaders
+	pub surfaces: MutDesc<MutBuffer<[PbrSurface]>>,
+	pub prev_surfaces: MutDesc<MutBuffer<[PbrSurface]>>,
+	pub material_classify: MaterialClassifyBuffers,
+	/// the scene of the previous frame, including its camera, if `prev_*` contain valid history
+	pub prev_scene: Option<VisiCpuScene>,
+	/// only allocated while ReSTIR GI is enabled
+	pub gi: Option<IndirectReservoirs<GiReservoir>>,
+	/// only allocated while ReSTIR PT is enabled
+	pub pt: Option<IndirectReservoirs<PtReservoir>>,
+	/// RGBA32F running mean of all samples of the reference path tracer
+	pub reference_accumulation: MutDesc<MutImage<Image2d>>,
+	/// what `reference_accumulation` contains, if it contains valid history
+	pub reference_history: Option<ReferenceHistory>,
+	/// RGBA32F linear radiance of the frame, copied into the output image before any debug view is drawn on top
+	pub radiance: MutDesc<MutImage<Image2d>>,
+}
+
+/// The reservoirs of one of the optional [`IndirectLight`] pass chains
+pub struct IndirectReservoirs<R: BufferStruct> {
+	pub reservoirs: MutDesc<MutBuffer<[R]>>,
+	pub prev_reservoirs: MutDesc<MutBuffer<[R]>>,
+	/// whether `prev_reservoirs` were written by the previous frame, in addition to
+	/// [`VisiRendererResources::prev_scene`] being present
+	pub has_history: bool,
+}
+
+impl<R: BufferStruct> IndirectReservoirs<R> {
+	pub fn new(renderer: &VisiPipelines, extent: Extent, name: &str) -> anyhow::Result<Self> {
+		let alloc_reservoirs = |name: &str| {
+			renderer.bindless.buffer().alloc_slice(
+				&BindlessBufferCreateInfo {
+					usage: BindlessBufferUsage::STORAGE_BUFFER,
+					allocation_scheme: BindlessAllocationScheme::Dedicated,
+					name,
+				},
+				(extent.width * extent.height) as usize,
+			)
+		};
+		Ok(Self {
+			reservoirs: alloc_reservoirs(&format!("{name}_reservoirs"))?,
+			prev_reservoirs: alloc_reservoirs(&format!("prev_{name}_reservoirs"))?,
+			has_history: false,
+		})
+	}
+}
+
+/// The samples averaged by the reference path tracer, which can be accumulated further as long as the camera and
+/// settings stay the same
+#[derive(Copy, Clone, Debug)]
+pub struct ReferenceHistory {
+	pub camera: Camera,
+	/// the [`VisiCpuScene::version`] accumulated
+	pub scene_version: u64,
+	pub settings: ReferenceSettings,
+	pub samples: u32,
+}
+
+impl ReferenceHistory {
+	/// Whether the history can be continued with `scene` and `settings`
+	pub fn is_valid(&self, scene: &VisiCpuScene, settings: &ReferenceSettings) -> bool {
+		let camera = &scene.camera;
+		self.scene_version == scene.version
+			&& self.camera.view_from_world.affine == camera.view_from_world.affine
+			&& self.camera.clip_from_view == camera.clip_from_view
+			&& self.camera.viewport_size == camera.viewport_size
+			&& self.settings == *settings
+	}
 }
 
 impl VisiRendererResources {
 	pub fn new(renderer: &VisiPipelines, extent: Extent) -> anyhow::Result<Self> {
-		let packed_vertex_image = renderer.bindless.image().alloc(&BindlessImageCreateInfo {
-			format: renderer.format.visi,
+		let alloc_packed_vertex_image = |name: &str| {
+			renderer.bindless.image().alloc(&BindlessImageCreateInfo {
+				format: renderer.format.visi,
+				extent,
+				mip_levels: 1,
+				array_layers: 1,
+				samples: Default::default(),
+				usage: BindlessImageUsage::COLOR_ATTACHMENT | BindlessImageUsage::SAMPLED,
+				allocation_scheme: BindlessAllocationScheme::Dedicated,
+				name,
+				..BindlessImageCreateInfo::default()
+			})
+		};
+		let packed_vertex_image = alloc_packed_vertex_image("packed_vertex_image")?;
+		let prev_packed_vertex_image = alloc_packed_vertex_image("prev_packed_vertex_image")?;
+		let depth = renderer.bindless.image().alloc(&BindlessImageCreateInfo {
+			format: renderer.format.depth,
+			extent,
+			mip_levels: 1,
+			array_layers: 1,
+			samples: Default::default(),
+			usage: BindlessImageUsage::DEPTH_STENCIL_ATTACHMENT,
+			allocation_scheme: BindlessAllocationScheme::Dedicated,
+			name: "depth",
+			..BindlessImageCreateInfo::default()
+		})?;
+		let alloc_di_reservoirs = |name: &str| {
+			renderer.bindless.buffer().alloc_slice(
+				&BindlessBufferCreateInfo {
+					usage: BindlessBufferUsage::STORAGE_BUFFER,
+					allocation_scheme: BindlessAllocationScheme::Dedicated,
+					name,
+				},
+				(extent.width * extent.height) as usize,
+			)
+		};
+		let di_reservoirs = alloc_di_reservoirs("di_reservoirs")?;
+		let prev_di_reservoirs = alloc_di_reservoirs("prev_di_reservoirs")?;
+		let alloc_surfaces = |name: &str| {
+			renderer.bindless.buffer().alloc_slice(
+				&BindlessBufferCreateInfo {
+					usage: BindlessBufferUsage::STORAGE_BUFFER,
+					allocation_scheme: BindlessAllocationScheme::Dedicated,
+					name,
+				},
+				(extent.width * extent.height) as usize,
+			)
+		};
+		let surfaces = alloc_surfaces("surfaces")?;
+		let prev_surfaces = alloc_surfaces("prev_surfaces")?;
+		let material_classify = MaterialClassifyBuffers::new(&renderer.bindless, extent)?;
+		let reference_accumulation = renderer.bindless.image().alloc(&BindlessImageCreateInfo {
+			format: Format::R32G32B32A32_SFLOAT,
 			extent,
 			mip_levels: 1,
 			array_layers: 1,
 			samples: Default::default(),
-			usage: BindlessImageUsage::COLOR_ATTACHMENT | BindlessImageUsage::SAMPLED,
+			usage: BindlessImageUsage::STORAGE,
 			allocation_scheme: BindlessAllocationScheme::Dedicated,
-			name: "packed_vertex_image",
+			name: "reference_accumulation",
 			..BindlessImageCreateInfo::default()
 		})?;
-		let depth = renderer.bindless.image().alloc(&BindlessImageCreateInfo {
-			format: renderer.format.depth,
+		let radiance = renderer.bindless.image().alloc(&BindlessImageCreateInfo {
+			format: Format::R32G32B32A32_SFLOAT,
 			extent,
 			mip_levels: 1,
 			array_layers: 1,
 			samples: Default::default(),
-			usage: BindlessImageUsage::DEPTH_STENCIL_ATTACHMENT,
+			usage: BindlessImageUsage::STORAGE | BindlessImageUsage::TRANSFER_SRC,
 			allocation_scheme: BindlessAllocationScheme::Dedicated,
-			name: "depth",
+			name: "radiance",
 			..BindlessImageCreateInfo::default()
 		})?;
 
 		Ok(Self {
 			extent,
 			packed_vertex_image,
+			prev_packed_vertex_image,
 			depth,
+			di_reservoirs,
+			prev_di_reservoirs,
+			surfaces,
+			prev_surfaces,
+			material_classify,
+			prev_scene: None,
+			gi: None,
+			pt: None,
+			reference_accumulation,
+			reference_history: None,
+			radiance,
 		})
 	}
 }
 
+/// What the [`VisiRenderer`] renders into the output image
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum RenderMode {
+	/// real-time ReSTIR DI, optionally followed by one of the [`IndirectLight`] variants
+	#[default]
+	Restir,
+	/// Ground truth by brute force path tracing, progressively accumulated while the camera stands still. See
+	/// [`restir_shader::reference`].
+	Reference,
+}
+
+impl RenderMode {
+	pub const ALL: [RenderMode; 2] = [RenderMode::Restir, RenderMode::Reference];
+}
+
+/// Which ReSTIR variant adds indirect light on top of ReSTIR DI
+#[derive(Copy, Clone, Debug, Default)]
+pub enum IndirectLight {
+	/// direct light only
+	#[default]
+	None,
+	/// ReSTIR GI, see [`restir_shader::restir::gi`]
+	Gi(GiSettings),
+	/// ReSTIR PT, see [`restir_shader::restir::pt`]
+	Pt(PtSettings),
+}
+
 pub struct VisiRenderInfo {
 	pub scene: VisiCpuScene,
 	pub debug_settings: DebugSettings,
+	pub di_settings: DiSettings,
+	pub indirect_light: IndirectLight,
+	pub render_mode: RenderMode,
+	pub reference_settings: ReferenceSettings,
 }
 
 impl VisiRenderer {
@@ -115,16 +343,28 @@ impl VisiRenderer {
 		Self {
 			pipeline,
 			resources: None,
+			frame: 0,
 		}
 	}
 
+	/// Render a frame into `output_image`. If `output_dump` dumps this frame, the linear radiance of the frame is copied
+	/// before any debug view is drawn on top of it, to be written once the frame finished.
 	pub fn render(
 		&mut self,
 		cmd: &mut Recording<'_>,
 		output_image: &mut MutImageAccess<'_, Image2d, StorageReadWrite>,
 		info: VisiRenderInfo,
-	) -> anyhow::Result<()> {
+		output_dump: Option<&mut OutputDump>,
+	) -> anyhow::Result<Option<PendingOutputDump>> {
 		self.image_supported(output_image)?;
+		let max_instance_count = self.pipeline.format.max_instance_count();
+		if info.scene.instance_total_count > max_instance_count {
+			return Err(anyhow!(
+				"Scene has {} instances, but visibility buffer format {:?} only supports {max_instance_count}",
+				info.scene.instance_total_count,
+				self.pipeline.format.visi
+			));
+		}
 		let resources = {
 			let extent = output_image.extent();
 			let resources = if let Some(resources) = self.resources.take() {
@@ -175,19 +415,427 @@ impl VisiRenderer {
 		)?;
 
 		let packed_vertex_image = packed_vertex_image.transition::<SampledRead>()?;
-		self.pipeline.debug_pipeline.image.dispatch(
+		let size = info.scene.camera.viewport_size;
+		let frame = self.frame;
+		self.frame = self.frame.wrapping_add(1);
+
+		if info.render_mode == RenderMode::Reference {
+			let samples = resources
+				.reference_history
+				.filter(|history| history.is_valid(&info.scene, &info.reference_settings))
+				.map_or(0, |history| history.samples);
+			let mut reference_accumulation = resources.reference_accumulation.access::<StorageReadWrite>(cmd)?;
+			let mut radiance = resources.radiance.access_dont_care::<StorageReadWrite>(cmd)?;
+			let param = path_trace::Param {
+				scene: info.scene.scene.to_transient(cmd),
+				packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+				accumulation: reference_accumulation.to_mut_transient(),
+				output_image: radiance.to_mut_transient(),
+				settings: info.reference_settings,
+				blue_noise: self.pipeline.blue_noise.to_gpu(),
+				sample_index: samples,
+			};
+			self.pipeline.reference_path_trace.dispatch(cmd, size, param)?;
+			let (radiance, pending_dump) =
+				self.output(cmd, radiance, output_image, &info, &packed_vertex_image, output_dump)?;
+
+			let max_samples = info.reference_settings.max_samples;
+			self.resources = Some(VisiRendererResources {
+				extent: resources.extent,
+				packed_vertex_image: packed_vertex_image.into_desc(),
+				prev_packed_vertex_image: resources.prev_packed_vertex_image,
+				depth: depth.into_desc(),
+				di_reservoirs: resources.di_reservoirs,
+				prev_di_reservoirs: resources.prev_di_reservoirs,
+				surfaces: resources.surfaces,
+				prev_surfaces: resources.prev_surfaces,
+				material_classify: resources.material_classify,
+				// the reservoirs were not updated and are no longer a valid history
+				prev_scene: None,
+				gi: resources.gi,
+				pt: resources.pt,
+				reference_accumulation: reference_accumulation.into_desc(),
+				reference_history: Some(ReferenceHistory {
+					camera: info.scene.camera,
+					scene_version: info.scene.version,
+					settings: info.reference_settings,
+					samples: if max_samples == 0 {
+						samples.saturating_add(1)
+					} else {
+						u32::min(samples + 1, max_samples)
+					},
+				}),
+				radiance,
+			});
+			return Ok(pending_dump);
+		}
+
+		// the material shaders resolve the surfaces all ReSTIR passes shade, clear pixels are never read
+		let surfaces = unsafe { resources.surfaces.access_as_undefined::<ShaderReadWrite>(cmd)? };
+		let material_classify = self.pipeline.materials.resolve(
 			cmd,
-			info.scene,
+			&info.scene,
 			packed_vertex_image.to_transient_sampled()?,
-			output_image.to_mut_transient(),
-			info.debug_settings,
+			surfaces.to_mut_transient()?,
+			resources.material_classify,
 		)?;
+		let surfaces = surfaces.transition::<ShaderRead>()?;
+		let prev_surfaces = resources.prev_surfaces.access::<ShaderRead>(cmd)?;
 
+		// every pixel writes its reservoir, so there is no need to preserve the previous contents
+		let mut di_reservoirs = unsafe { resources.di_reservoirs.access_as_undefined::<ShaderReadWrite>(cmd)? };
+		let param = initial::Param {
+			scene: info.scene.scene.to_transient(cmd),
+			packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+			surfaces: surfaces.to_transient()?,
+			reservoirs: di_reservoirs.to_mut_transient()?,
+			settings: info.di_settings,
+			frame,
+		};
+		self.pipeline.di_pipelines.initial.dispatch(cmd, size, param)?;
+
+		let prev_packed_vertex_image = resources.prev_packed_vertex_image.access::<SampledRead>(cmd)?;
+		let prev_di_reservoirs = resources.prev_di_reservoirs.access::<ShaderRead>(cmd)?;
+		if let Some(prev_scene) = resources
+			.prev_scene
+			.as_ref()
+			.filter(|_| info.di_settings.temporal_reuse)
+		{
+			// ShaderReadWrite -> ShaderReadWrite would not emit a barrier
+			di_reservoirs = di_reservoirs
+				.transition::<ShaderRead>()?
+				.transition::<ShaderReadWrite>()?;
+			let param = temporal::Param {
+				scene: info.scene.scene.to_transient(cmd),
+				prev_scene: prev_scene.scene.to_transient(cmd),
+				packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+				prev_packed_vertex_image: prev_packed_vertex_image.to_transient_sampled()?,
+				prev_surfaces: prev_surfaces.to_transient()?,
+				reservoirs: di_reservoirs.to_mut_transient()?,
+				prev_reservoirs: prev_di_reservoirs.to_transient()?,
+				settings: info.di_settings,
+				frame,
+			};
+			self.pipeline.di_pipelines.temporal.dispatch(cmd, size, param)?;
+		}
+
+		// spatial reuse ping-pongs between both reservoir buffers, as the history is no longer needed
+		let mut di_reservoirs = di_reservoirs.transition::<ShaderRead>()?;
+		let mut spare_di_reservoirs = prev_di_reservoirs.transition::<ShaderReadWrite>()?;
+		for iteration in 0..info.di_settings.spatial_iterations {
+			let param = spatial::Param {
+				scene: info.scene.scene.to_transient(cmd),
+				packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+				surfaces: surfaces.to_transient()?,
+				src_reservoirs: di_reservoirs.to_transient()?,
+				dst_reservoirs: spare_di_reservoirs.to_mut_transient()?,
+				settings: info.di_settings,
+				frame,
+				iteration,
+			};
+			self.pipeline.di_pipelines.spatial.dispatch(cmd, size, param)?;
+			let dst = spare_di_reservoirs.transition::<ShaderRead>()?;
+			spare_di_reservoirs = di_reservoirs.transition::<ShaderReadWrite>()?;
+			di_reservoirs = dst;
+		}
+
+		// the DI shade writes every pixel, which the indirect light is added onto
+		let mut radiance = resources.radiance.access_dont_care::<StorageReadWrite>(cmd)?;
+		let param = shade::Param {
+			scene: info.scene.scene.to_transient(cmd),
+			packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+			surfaces: surfaces.to_transient()?,
+			reservoirs: di_reservoirs.to_transient()?,
+			output_image: radiance.to_mut_transient(),
+		};
+		self.pipeline.di_pipelines.shade.dispatch(cmd, size, param)?;
+
+		// reservoirs of the variant not in use are freed
+		let (mut gi, mut pt) = (None, None);
+		let prev_scene = resources.prev_scene.as_ref();
+		match info.indirect_light {
+			IndirectLight::None => (),
+			IndirectLight::Gi(settings) => {
+				let reservoirs = match resources.gi {
+					Some(reservoirs) => reservoirs,
+					None => IndirectReservoirs::new(&self.pipeline, resources.extent, "gi")?,
+				};
+				gi = Some(self.render_gi(
+					cmd,
+					&mut radiance,
+					&info,
+					settings,
+					reservoirs,
+					&packed_vertex_image,
+					&prev_packed_vertex_image,
+					&surfaces,
+					&prev_surfaces,
+					prev_scene,
+					frame,
+				)?);
+			}
+			IndirectLight::Pt(settings) => {
+				let reservoirs = match resources.pt {
+					Some(reservoirs) => reservoirs,
+					None => IndirectReservoirs::new(&self.pipeline, resources.extent, "pt")?,
+				};
+				pt = Some(self.render_pt(
+					cmd,
+					&mut radiance,
+					&info,
+					settings,
+					reservoirs,
+					&packed_vertex_image,
+					&prev_packed_vertex_image,
+					&surfaces,
+					&prev_surfaces,
+					prev_scene,
+					frame,
+				)?);
+			}
+		}
+		let (radiance, pending_dump) =
+			self.output(cmd, radiance, output_image, &info, &packed_vertex_image, output_dump)?;
+
+		// this frame becomes the history of the next frame
 		self.resources = Some(VisiRendererResources {
 			extent: resources.extent,
-			packed_vertex_image: packed_vertex_image.into_desc(),
+			packed_vertex_image: prev_packed_vertex_image.into_desc(),
+			prev_packed_vertex_image: packed_vertex_image.into_desc(),
 			depth: depth.into_desc(),
+			di_reservoirs: spare_di_reservoirs.into_desc(),
+			prev_di_reservoirs: di_reservoirs.into_desc(),
+			surfaces: prev_surfaces.into_desc(),
+			prev_surfaces: surfaces.into_desc(),
+			material_classify,
+			prev_scene: Some(info.scene),
+			gi,
+			pt,
+			reference_accumulation: resources.reference_accumulation,
+			reference_history: None,
+			radiance,
 		});
+		Ok(pending_dump)
+	}
+
+	/// The ReSTIR GI pass chain, adding indirect light onto the direct light in `output_image`
+	#[allow(clippy::too_many_arguments)]
+	fn render_gi(
+		&self,
+		cmd: &mut Recording<'_>,
+		output_image: &mut MutImageAccess<'_, Image2d, StorageReadWrite>,
+		info: &VisiRenderInfo,
+		settings: GiSettings,
+		gi: IndirectReservoirs<GiReservoir>,
+		packed_vertex_image: &MutImageAccess<'_, Image2dU, SampledRead>,
+		prev_packed_vertex_image: &MutImageAccess<'_, Image2dU, SampledRead>,
+		surfaces: &MutBufferAccess<'_, [PbrSurface], ShaderRead>,
+		prev_surfaces: &MutBufferAccess<'_, [PbrSurface], ShaderRead>,
+		prev_scene: Option<&VisiCpuScene>,
+		frame: u32,
+	) -> anyhow::Result<IndirectReservoirs<GiReservoir>> {
+		let size = info.scene.camera.viewport_size;
+		// every pixel writes its reservoir, so there is no need to preserve the previous contents
+		let mut reservoirs = unsafe { gi.reservoirs.access_as_undefined::<ShaderReadWrite>(cmd)? };
+		let param = gi::initial::Param {
+			scene: info.scene.scene.to_transient(cmd),
+			packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+			surfaces: surfaces.to_transient()?,
+			reservoirs: reservoirs.to_mut_transient()?,
+			frame,
+		};
+		self.pipeline.gi_pipelines.initial.dispatch(cmd, size, param)?;
+
+		let prev_reservoirs = gi.prev_reservoirs.access::<ShaderRead>(cmd)?;
+		if let Some(prev_scene) = prev_scene.filter(|_| settings.temporal_reuse && gi.has_history) {
+			// ShaderReadWrite -> ShaderReadWrite would not emit a barrier
+			reservoirs = reservoirs.transition::<ShaderRead>()?.transition::<ShaderReadWrite>()?;
+			let param = gi::temporal::Param {
+				scene: info.scene.scene.to_transient(cmd),
+				prev_scene: prev_scene.scene.to_transient(cmd),
+				packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+				prev_packed_vertex_image: prev_packed_vertex_image.to_transient_sampled()?,
+				prev_surfaces: prev_surfaces.to_transient()?,
+				reservoirs: reservoirs.to_mut_transient()?,
+				prev_reservoirs: prev_reservoirs.to_transient()?,
+				settings,
+				frame,
+			};
+			self.pipeline.gi_pipelines.temporal.dispatch(cmd, size, param)?;
+		}
+
+		// spatial reuse ping-pongs between both reservoir buffers, as the history is no longer needed
+		let mut reservoirs = reservoirs.transition::<ShaderRead>()?;
+		let mut spare_reservoirs = prev_reservoirs.transition::<ShaderReadWrite>()?;
+		for iteration in 0..settings.spatial_iterations {
+			let param = gi::spatial::Param {
+				scene: info.scene.scene.to_transient(cmd),
+				packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+				surfaces: surfaces.to_transient()?,
+				src_reservoirs: reservoirs.to_transient()?,
+				dst_reservoirs: spare_reservoirs.to_mut_transient()?,
+				settings,
+				frame,
+				iteration,
+			};
+			self.pipeline.gi_pipelines.spatial.dispatch(cmd, size, param)?;
+			let dst = spare_reservoirs.transition::<ShaderRead>()?;
+			spare_reservoirs = reservoirs.transition::<ShaderReadWrite>()?;
+			reservoirs = dst;
+		}
+
+		let param = gi::shade::Param {
+			scene: info.scene.scene.to_transient(cmd),
+			packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+			surfaces: surfaces.to_transient()?,
+			reservoirs: reservoirs.to_transient()?,
+			output_image: output_image.to_mut_transient(),
+		};
+		self.pipeline.gi_pipelines.shade.dispatch(cmd, size, param)?;
+
+		Ok(IndirectReservoirs {
+			reservoirs: spare_reservoirs.into_desc(),
+			prev_reservoirs: reservoirs.into_desc(),
+			has_history: true,
+		})
+	}
+
+	/// The ReSTIR PT pass chain, adding indirect light onto the direct light in `output_image`
+	#[allow(clippy::too_many_arguments)]
+	fn render_pt(
+		&self,
+		cmd: &mut Recording<'_>,
+		output_image: &mut MutImageAccess<'_, Image2d, StorageReadWrite>,
+		info: &VisiRenderInfo,
+		settings: PtSettings,
+		pt: IndirectReservoirs<PtReservoir>,
+		packed_vertex_image: &MutImageAccess<'_, Image2dU, SampledRead>,
+		prev_packed_vertex_image: &MutImageAccess<'_, Image2dU, SampledRead>,
+		surfaces: &MutBufferAccess<'_, [PbrSurface], ShaderRead>,
+		prev_surfaces: &MutBufferAccess<'_, [PbrSurface], ShaderRead>,
+		prev_scene: Option<&VisiCpuScene>,
+		frame: u32,
+	) -> anyhow::Result<IndirectReservoirs<PtReservoir>> {
+		let size = info.scene.camera.viewport_size;
+		// every pixel writes its reservoir, so there is no need to preserve the previous contents
+		let mut reservoirs = unsafe { pt.reservoirs.access_as_undefined::<ShaderReadWrite>(cmd)? };
+		let param = pt::initial::Param {
+			scene: info.scene.scene.to_transient(cmd),
+			packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+			surfaces: surfaces.to_transient()?,
+			reservoirs: reservoirs.to_mut_transient()?,
+			settings,
+			frame,
+		};
+		self.pipeline.pt_pipelines.initial.dispatch(cmd, size, param)?;
+
+		let prev_reservoirs = pt.prev_reservoirs.access::<ShaderRead>(cmd)?;
+		if let Some(prev_scene) = prev_scene.filter(|_| settings.temporal_reuse && pt.has_history) {
+			// ShaderReadWrite -> ShaderReadWrite would not emit a barrier
+			reservoirs = reservoirs.transition::<ShaderRead>()?.transition::<ShaderReadWrite>()?;
+			let param = pt::temporal::Param {
+				scene: info.scene.scene.to_transient(cmd),
+				prev_scene: prev_scene.scene.to_transient(cmd),
+				packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+				prev_packed_vertex_image: prev_packed_vertex_image.to_transient_sampled()?,
+				prev_surfaces: prev_surfaces.to_transient()?,
+				reservoirs: reservoirs.to_mut_transient()?,
+				prev_reservoirs: prev_reservoirs.to_transient()?,
+				settings,
+				frame,
+			};
+			self.pipeline.pt_pipelines.temporal.dispatch(cmd, size, param)?;
+		}
+
+		// spatial reuse ping-pongs between both reservoir buffers, as the history is no longer needed
+		let mut reservoirs = reservoirs.transition::<ShaderRead>()?;
+		let mut spare_reservoirs = prev_reservoirs.transition::<ShaderReadWrite>()?;
+		for iteration in 0..settings.spatial_iterations {
+			let param = pt::spatial::Param {
+				scene: info.scene.scene.to_transient(cmd),
+				packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+				surfaces: surfaces.to_transient()?,
+				src_reservoirs: reservoirs.to_transient()?,
+				dst_reservoirs: spare_reservoirs.to_mut_transient()?,
+				settings,
+				frame,
+				iteration,
+			};
+			self.pipeline.pt_pipelines.spatial.dispatch(cmd, size, param)?;
+			let dst = spare_reservoirs.transition::<ShaderRead>()?;
+			spare_reservoirs = reservoirs.transition::<ShaderReadWrite>()?;
+			reservoirs = dst;
+		}
+
+		let param = pt::shade::Param {
+			scene: info.scene.scene.to_transient(cmd),
+			packed_vertex_image: packed_vertex_image.to_transient_sampled()?,
+			reservoirs: reservoirs.to_transient()?,
+			output_image: output_image.to_mut_transient(),
+			settings,
+		};
+		self.pipeline.pt_pipelines.shade.dispatch(cmd, size, param)?;
+
+		Ok(IndirectReservoirs {
+			reservoirs: spare_reservoirs.into_desc(),
+			prev_reservoirs: reservoirs.into_desc(),
+			has_history: true,
+		})
+	}
+
+	/// Copy the `radiance` of this frame into `output_image` and draw the debug view on top of it, dumping the radiance
+	/// beforehand if `output_dump` dumps this frame
+	fn output(
+		&self,
+		cmd: &mut Recording<'_>,
+		mut radiance: MutImageAccess<'_, Image2d, StorageReadWrite>,
+		output_image: &mut MutImageAccess<'_, Image2d, StorageReadWrite>,
+		info: &VisiRenderInfo,
+		packed_vertex_image: &MutImageAccess<'_, Image2dU, SampledRead>,
+		output_dump: Option<&mut OutputDump>,
+	) -> anyhow::Result<(MutDesc<MutImage<Image2d>>, Option<PendingOutputDump>)> {
+		let param = output::Param {
+			radiance: radiance.to_mut_transient(),
+			output_image: output_image.to_mut_transient(),
+			size: info.scene.camera.viewport_size,
+		};
+		self.pipeline.output_copy.dispatch(cmd, param)?;
+		let (radiance, pending_dump) = match output_dump {
+			Some(output_dump) => {
+				let radiance = radiance.transition::<TransferRead>()?;
+				let pending_dump = output_dump.record(&self.pipeline.bindless, cmd, &radiance)?;
+				(radiance.into_desc(), pending_dump)
+			}
+			None => (radiance.into_desc(), None),
+		};
+		self.debug_overlay(cmd, output_image, info, packed_vertex_image)?;
+		Ok((radiance, pending_dump))
+	}
+
+	fn debug_overlay(
+		&self,
+		cmd: &mut Recording<'_>,
+		output_image: &mut MutImageAccess<'_, Image2d, StorageReadWrite>,
+		info: &VisiRenderInfo,
+		packed_vertex_image: &MutImageAccess<'_, Image2dU, SampledRead>,
+	) -> anyhow::Result<()> {
+		match info.debug_settings.debug_type {
+			DebugType::None => (),
+			DebugType::Materials => self.pipeline.materials.preview(
+				cmd,
+				&info.scene,
+				packed_vertex_image.to_transient_sampled()?,
+				output_image.to_mut_transient(),
+				info.debug_settings,
+			)?,
+			_ => self.pipeline.materials.debug.dispatch(
+				cmd,
+				&info.scene,
+				packed_vertex_image.to_transient_sampled()?,
+				output_image.to_mut_transient(),
+				info.debug_settings,
+			)?,
+		}
 		Ok(())
 	}
 
@@ -1,14 +1,91 @@
+use crate::light::alias::build_alias_table;
+use crate::light::environment::VisiCpuEnvironment;
+use crate::light::power::light_power;
+use crate::light::tree::{LightBounds, build_light_tree};
+use crate::material::pbr::{VisiCpuPbrMaterial, upload_materials};
 use crate::model::VisiCpuModel;
+use crate::visibility::bvh::build_bvh;
+use glam::Vec3;
 use restir_shader::camera::Camera;
-use restir_shader::visibility::id::InstanceId;
+use restir_shader::light::Light;
+use restir_shader::light::alias::AliasEntry;
+use restir_shader::light::tree::{LIGHT_TREE_NO_TRAIL, LightTree, LightTreeNode};
+use restir_shader::utils::affine_transform::AffineTransform;
+use restir_shader::visibility::id::{InstanceId, TriangleId};
 use restir_shader::visibility::scene::{VisiInstance, VisiInstanceInfo, VisiScene};
 use rust_gpu_bindless::descriptor::{
-	Bindless, BindlessBufferCreateInfo, BindlessBufferUsage, Buffer, RCDesc, RCDescExt,
+	AnyRCDesc, Bindless, BindlessBufferCreateInfo, BindlessBufferUsage, Buffer, RCDesc, RCDescExt,
 };
-use std::collections::HashMap;
+use rustc_hash::FxHashMap;
 
 pub struct VisiCpuSceneAccum {
-	pub instances: HashMap<VisiCpuModel, Vec<VisiInstance>>,
+	/// deterministic hasher, so that pushing the same instances results in the same [`InstanceId`]s across frames
+	pub instances: FxHashMap<VisiCpuModel, Vec<VisiInstance>>,
+	/// instances pushed with [`VisiCpuSceneAccum::push_keyed`]
+	pub keys: FxHashMap<VisiInstanceKey, VisiCpuInstance>,
+	pub lights: Vec<VisiCpuLight>,
+}
+
+/// Identity of an instance chosen by the caller, which must stay the same across frames and be unique within a scene.
+/// Used to find the transform an instance had in the previous frame.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct VisiInstanceKey(pub u64);
+
+/// Handle to an instance pushed into a [`VisiCpuSceneAccum`], its [`InstanceId`] is only known once the scene is
+/// finished.
+#[derive(Clone)]
+pub struct VisiCpuInstance {
+	pub model: VisiCpuModel,
+	/// index within all instances of `model`
+	pub index: u32,
+}
+
+/// A light of a scene, with emissive triangles referencing their instance by the handle `I` of the scene
+#[derive(Clone)]
+pub enum VisiCpuLight<I = VisiCpuInstance> {
+	/// point, spot, directional or environment light
+	Light(Light),
+	EmissiveTriangle {
+		instance: I,
+		triangle_id: TriangleId,
+		radiance: Vec3,
+	},
+}
+
+impl<I> From<Light> for VisiCpuLight<I> {
+	fn from(value: Light) -> Self {
+		Self::Light(value)
+	}
+}
+
+/// A scene instances and lights can be pushed into, either a [`VisiCpuSceneAccum`] that is rebuilt every frame or a
+/// persistent [`VisiGpuScene`].
+///
+/// [`VisiGpuScene`]: crate::visibility::gpu_scene::VisiGpuScene
+pub trait VisiScenePush {
+	/// handle to a pushed instance
+	type Instance: Clone;
+
+	fn push(&mut self, model: &VisiCpuModel, instance: VisiInstanceInfo) -> Self::Instance;
+
+	fn push_light(&mut self, light: impl Into<VisiCpuLight<Self::Instance>>);
+}
+
+/// The camera and instance transforms of the previously finished scene, to fill in [`VisiScene::prev_camera`] and
+/// [`VisiInstanceInfo::prev_world_from_local`] of the next one.
+#[derive(Default)]
+pub struct VisiCpuSceneHistory {
+	camera: Option<Camera>,
+	/// `world_from_local` of all keyed instances
+	world_from_local: FxHashMap<VisiInstanceKey, AffineTransform>,
+	/// the [`VisiCpuScene::version`] of the previously finished scene
+	version: u64,
+}
+
+impl VisiCpuSceneHistory {
+	pub fn new() -> Self {
+		Self::default()
+	}
 }
 
 impl Default for VisiCpuSceneAccum {
@@ -20,19 +97,46 @@ impl Default for VisiCpuSceneAccum {
 impl VisiCpuSceneAccum {
 	pub fn new() -> Self {
 		Self {
-			instances: HashMap::new(),
+			instances: FxHashMap::default(),
+			keys: FxHashMap::default(),
+			lights: Vec::new(),
 		}
 	}
 
-	pub fn push(&mut self, model: &VisiCpuModel, instance: VisiInstanceInfo) {
-		let instance = VisiInstance {
-			model: model.model.to_strong(),
-			info: instance,
-		};
-		self.instances.entry(model.clone()).or_default().push(instance);
+	/// Push an instance with a stable `key`. If the previously finished scene contained an instance with the same key,
+	/// its transform becomes the `prev_world_from_local` of this one. Instances pushed without a key have not moved.
+	pub fn push_keyed(
+		&mut self,
+		key: VisiInstanceKey,
+		model: &VisiCpuModel,
+		instance: VisiInstanceInfo,
+	) -> VisiCpuInstance {
+		let handle = self.push(model, instance);
+		self.keys.insert(key, handle.clone());
+		handle
 	}
 
-	pub fn finish(self, bindless: &Bindless, camera: Camera) -> anyhow::Result<VisiCpuScene> {
+	/// Upload the scene. The `prev_world_from_local` of keyed instances is taken from `history`, which is updated to
+	/// this scene afterward.
+	pub fn finish(
+		mut self,
+		bindless: &Bindless,
+		camera: Camera,
+		environment: &VisiCpuEnvironment,
+		materials: &[VisiCpuPbrMaterial],
+		history: &mut VisiCpuSceneHistory,
+	) -> anyhow::Result<VisiCpuScene> {
+		let mut world_from_local = FxHashMap::default();
+		for (key, handle) in &self.keys {
+			let instance = &mut self.instances.get_mut(&handle.model).unwrap()[handle.index as usize];
+			if let Some(prev) = history.world_from_local.get(key) {
+				instance.info.prev_world_from_local = *prev;
+			}
+			world_from_local.insert(*key, instance.info.world_from_local);
+		}
+		history.world_from_local = world_from_local;
+		let prev_camera = history.camera.replace(camera).unwrap_or(camera);
+
 		let mut instance_data = Vec::with_capacity(self.instances.values().map(|i| i.len()).sum());
 		let draws = self
 			.instances
@@ -52,6 +156,52 @@ impl VisiCpuSceneAccum {
 			.collect::<anyhow::Result<Vec<_>>>()?;
 		let instance_total_count = instance_data.len() as u32;
 
+		// world space AABB of each instance, in the order of `instance_data`
+		let instance_bounds = draws
+			.iter()
+			.flat_map(|draw| {
+				let instances = &instance_data[draw.instance_start as usize..][..draw.instance_count as usize];
+				instances
+					.iter()
+					.map(|instance| world_bounds(&draw.model, instance.info.world_from_local))
+			})
+			.collect::<Vec<_>>();
+		let scene_radius = scene_radius(&instance_bounds);
+
+		let instance_starts = draws
+			.iter()
+			.map(|draw| (draw.model.clone(), draw.instance_start))
+			.collect::<FxHashMap<_, _>>();
+		let lights = self
+			.lights
+			.into_iter()
+			.map(|light| {
+				Ok(match light {
+					VisiCpuLight::Light(light) => delta_light(light, scene_radius),
+					VisiCpuLight::EmissiveTriangle {
+						instance,
+						triangle_id,
+						radiance,
+					} => {
+						let instance_start = instance_starts
+							.get(&instance.model)
+							.ok_or_else(|| anyhow::anyhow!("emissive triangle references an unknown instance"))?;
+						let instance_id = InstanceId::new(instance_start + instance.index)?;
+						let world_from_local = instance_data[instance_id.to_usize()].info.world_from_local;
+						emissive_triangle_light(
+							&instance.model,
+							instance_id,
+							world_from_local,
+							triangle_id,
+							radiance,
+							scene_radius,
+						)?
+					}
+				})
+			})
+			.collect::<anyhow::Result<Vec<_>>>()?;
+		let lights = VisiCpuLights::new(bindless, lights, environment, scene_radius)?;
+
 		let instance_buffer = bindless.buffer().alloc_shared_from_iter(
 			&BindlessBufferCreateInfo {
 				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
@@ -60,6 +210,8 @@ impl VisiCpuSceneAccum {
 			},
 			instance_data.iter().copied(),
 		)?;
+		let tlas = build_bvh(&instance_bounds).upload(bindless)?;
+		let materials = upload_materials(bindless, materials)?;
 		let scene = bindless.buffer().alloc_shared_from_data(
 			&BindlessBufferCreateInfo {
 				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
@@ -68,26 +220,226 @@ impl VisiCpuSceneAccum {
 			},
 			VisiScene {
 				instances: instance_buffer.to_strong(),
+				bvh: tlas.to_gpu(),
+				lights: lights.lights.to_strong(),
+				light_count: lights.count,
+				light_alias_table: lights.alias_table.to_strong(),
+				light_tree: lights.light_tree(),
+				materials: materials.to_strong(),
+				environment: environment.to_gpu(),
 				camera,
+				prev_camera,
 			},
 		)?;
 
+		// the entire scene is pushed again for every finish, so any part of it may have changed
+		history.version += 1;
 		Ok(VisiCpuScene {
 			camera,
+			version: history.version,
 			draws,
 			instance_total_count,
 			scene,
+			referenced: Vec::new(),
+		})
+	}
+}
+
+impl VisiScenePush for VisiCpuSceneAccum {
+	type Instance = VisiCpuInstance;
+
+	fn push(&mut self, model: &VisiCpuModel, instance: VisiInstanceInfo) -> VisiCpuInstance {
+		let instance = VisiInstance {
+			model: model.model.to_strong(),
+			info: instance,
+		};
+		let instances = self.instances.entry(model.clone()).or_default();
+		instances.push(instance);
+		VisiCpuInstance {
+			model: model.clone(),
+			index: instances.len() as u32 - 1,
+		}
+	}
+
+	fn push_light(&mut self, light: impl Into<VisiCpuLight>) {
+		self.lights.push(light.into());
+	}
+}
+
+/// World space AABB of an instance of `model` as `[min, max]`
+pub(crate) fn world_bounds(model: &VisiCpuModel, world_from_local: AffineTransform) -> [Vec3; 2] {
+	let [min, max] = model.bounds;
+	let (center, extent) = ((min + max) / 2., (max - min) / 2.);
+	let world_from_local = world_from_local.affine;
+	let axes = world_from_local.matrix3;
+	let center = world_from_local.transform_point3(center);
+	let extent = Vec3::from(axes.x_axis.abs() * extent.x + axes.y_axis.abs() * extent.y + axes.z_axis.abs() * extent.z);
+	[center - extent, center + extent]
+}
+
+/// Half the diagonal of the AABB around all `instance_bounds`, or 0 if there are none
+pub(crate) fn scene_radius(instance_bounds: &[[Vec3; 2]]) -> f32 {
+	let scene_bounds = instance_bounds
+		.iter()
+		.fold([Vec3::INFINITY, Vec3::NEG_INFINITY], |[min, max], [b_min, b_max]| {
+			[min.min(*b_min), max.max(*b_max)]
+		});
+	if instance_bounds.is_empty() {
+		0.
+	} else {
+		scene_bounds[0].distance(scene_bounds[1]) / 2.
+	}
+}
+
+/// A light together with its power and bounds, from which the light sampling structures are built
+pub(crate) type LightEntry = (Light, f32, Option<LightBounds>);
+
+pub(crate) fn delta_light(light: Light, scene_radius: f32) -> LightEntry {
+	let power = light_power(&light, scene_radius, 0.);
+	(light, power, LightBounds::delta(&light, power))
+}
+
+pub(crate) fn emissive_triangle_light(
+	model: &VisiCpuModel,
+	instance_id: InstanceId,
+	world_from_local: AffineTransform,
+	triangle_id: TriangleId,
+	radiance: Vec3,
+	scene_radius: f32,
+) -> anyhow::Result<LightEntry> {
+	let indices = model
+		.cpu_triangles
+		.get(triangle_id.to_usize())
+		.ok_or_else(|| anyhow::anyhow!("emissive triangle is out of bounds of its model"))?;
+	let world_from_local = world_from_local.affine;
+	let positions = indices.map(|i| world_from_local.transform_point3(model.cpu_vertices[i as usize].0));
+	let [a, b, c] = positions;
+	let area = (b - a).cross(c - a).length() / 2.;
+	let light = Light::emissive_triangle(instance_id, triangle_id, radiance);
+	let power = light_power(&light, scene_radius, area);
+	Ok((light, power, Some(LightBounds::triangle(positions, power))))
+}
+
+/// The lights of a scene and their sampling structures uploaded to the GPU, including the light of the environment
+#[derive(Clone)]
+pub(crate) struct VisiCpuLights {
+	/// padded to a single unused light if there are none, see `count`
+	pub lights: RCDesc<Buffer<[Light]>>,
+	pub count: u32,
+	pub alias_table: RCDesc<Buffer<[AliasEntry]>>,
+	pub tree_nodes: RCDesc<Buffer<[LightTreeNode]>>,
+	pub tree_bit_trails: RCDesc<Buffer<[u32]>>,
+	pub tree_infinite_lights: RCDesc<Buffer<[u32]>>,
+	pub infinite_light_count: u32,
+}
+
+impl VisiCpuLights {
+	pub fn new(
+		bindless: &Bindless,
+		mut lights: Vec<LightEntry>,
+		environment: &VisiCpuEnvironment,
+		scene_radius: f32,
+	) -> anyhow::Result<Self> {
+		if environment.average_radiance != Vec3::ZERO {
+			lights.push(delta_light(
+				Light::environment(environment.average_radiance),
+				scene_radius,
+			));
+		}
+		let light_data = lights.iter().map(|(light, _, _)| *light).collect::<Vec<_>>();
+		let light_powers = lights.iter().map(|(_, power, _)| *power).collect::<Vec<_>>();
+		let light_alias_table = build_alias_table(&light_powers);
+		let light_bounds = lights.iter().map(|(_, _, bounds)| *bounds).collect::<Vec<_>>();
+		let light_tree = build_light_tree(&light_bounds)?;
+		let infinite_lights = (0..light_data.len() as u32)
+			.filter(|i| light_data[*i as usize].is_infinite())
+			.collect::<Vec<_>>();
+		let infinite_light_count = infinite_lights.len() as u32;
+		let light_count = light_data.len() as u32;
+
+		let light_buffer = bindless.buffer().alloc_shared_from_iter(
+			&BindlessBufferCreateInfo {
+				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+				allocation_scheme: Default::default(),
+				name: "Lights",
+			},
+			pad_empty(light_data, Light::directional(Vec3::Z, Vec3::ZERO)),
+		)?;
+		let light_alias_table_buffer = bindless.buffer().alloc_shared_from_iter(
+			&BindlessBufferCreateInfo {
+				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+				allocation_scheme: Default::default(),
+				name: "Light alias table",
+			},
+			pad_empty(light_alias_table, AliasEntry::default()),
+		)?;
+		let light_tree_nodes = bindless.buffer().alloc_shared_from_iter(
+			&BindlessBufferCreateInfo {
+				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+				allocation_scheme: Default::default(),
+				name: "Light tree nodes",
+			},
+			light_tree.nodes.iter().copied(),
+		)?;
+		let light_tree_bit_trails = bindless.buffer().alloc_shared_from_iter(
+			&BindlessBufferCreateInfo {
+				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+				allocation_scheme: Default::default(),
+				name: "Light tree bit trails",
+			},
+			pad_empty(light_tree.bit_trails, LIGHT_TREE_NO_TRAIL),
+		)?;
+		let light_tree_infinite_lights = bindless.buffer().alloc_shared_from_iter(
+			&BindlessBufferCreateInfo {
+				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+				allocation_scheme: Default::default(),
+				name: "Light tree infinite lights",
+			},
+			pad_empty(infinite_lights, 0),
+		)?;
+		Ok(Self {
+			lights: light_buffer,
+			count: light_count,
+			alias_table: light_alias_table_buffer,
+			tree_nodes: light_tree_nodes,
+			tree_bit_trails: light_tree_bit_trails,
+			tree_infinite_lights: light_tree_infinite_lights,
+			infinite_light_count,
 		})
 	}
+
+	pub fn light_tree(&self) -> LightTree {
+		LightTree {
+			nodes: self.tree_nodes.to_strong(),
+			bit_trails: self.tree_bit_trails.to_strong(),
+			infinite_lights: self.tree_infinite_lights.to_strong(),
+			infinite_light_count: self.infinite_light_count,
+		}
+	}
+}
+
+/// Buffers can't be empty, so empty lists get a single unused `entry`
+fn pad_empty<T>(mut list: Vec<T>, entry: T) -> Vec<T> {
+	if list.is_empty() {
+		list.push(entry);
+	}
+	list
 }
 
 pub struct VisiCpuScene {
 	pub draws: Vec<VisiCpuDraw>,
 	pub instance_total_count: u32,
 	pub camera: Camera,
+	/// Scenes with the same version contain the same instances, lights, materials and environment, only the camera may
+	/// differ. Increases whenever any of them change.
+	pub version: u64,
 	pub scene: RCDesc<Buffer<VisiScene>>,
+	/// resources referenced by `scene` without being kept alive by its buffer, which must live as long as the scene
+	/// may be rendered. The models of all instances are kept alive by `draws`.
+	pub referenced: Vec<AnyRCDesc>,
 }
 
+#[derive(Clone)]
 pub struct VisiCpuDraw {
 	pub model: VisiCpuModel,
 	pub instance_start: u32,
@@ -0,0 +1,414 @@
+use crate::backing::range_set::DescriptorIndexIterator;
+use crate::backing::table::{DrainFlushQueue, RcTableSlot, SlotAllocationError, Table, TableInterface, TableSync};
+use crate::descriptor::{
+	Bindless, BindlessAllocationScheme, BindlessBufferUsage, BufferSlot, DescContentCpu, DescTable, DescriptorCounts,
+	RCDesc, RCDescExt, StrongBackingRefs, WeakBindless,
+};
+use crate::platform::BindlessPlatform;
+use glam::Affine3A;
+use parking_lot::Mutex;
+use rust_gpu_bindless_shaders::buffer_content::BufferStruct;
+use rust_gpu_bindless_shaders::descriptor::{AccelerationStructure, Buffer};
+use smallvec::SmallVec;
+use std::fmt::{Debug, Display, Formatter};
+use std::mem::size_of;
+use std::ops::Deref;
+use std::sync::Arc;
+use thiserror::Error;
+
+impl DescContentCpu for AccelerationStructure {
+	type DescTable<P: BindlessPlatform> = AccelerationStructureTable<P>;
+}
+
+impl<P: BindlessPlatform> DescTable<P> for AccelerationStructureTable<P> {
+	type Slot = AccelerationStructureSlot<P>;
+
+	fn get_slot(slot: &RcTableSlot) -> &Self::Slot {
+		slot.try_deref::<AccelerationStructureInterface<P>>().unwrap()
+	}
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum AccelerationStructureType {
+	/// Contains triangle geometry
+	BottomLevel,
+	/// Contains instances of bottom level acceleration structures
+	TopLevel,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum AccelerationStructureBuildMode {
+	/// Build the acceleration structure from scratch
+	#[default]
+	Build,
+	/// Update the previously built acceleration structure in-place with new vertex positions or instance transforms.
+	/// Requires [`BindlessAccelerationStructureFlags::ALLOW_UPDATE`] and the same amount of primitives as the last
+	/// build.
+	Update,
+}
+
+/// The size of an acceleration structure and the scratch memory required to build or update it, in bytes
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct AccelerationStructureBuildSizes {
+	pub size: u64,
+	pub build_scratch_size: u64,
+	pub update_scratch_size: u64,
+}
+
+pub struct AccelerationStructureSlot<P: BindlessPlatform> {
+	pub platform: P::AccelerationStructure,
+	pub ty: AccelerationStructureType,
+	pub flags: BindlessAccelerationStructureFlags,
+	pub sizes: AccelerationStructureBuildSizes,
+	/// The maximum primitive count of each geometry this acceleration structure was sized for. For top level
+	/// acceleration structures, the single entry is the maximum amount of instances.
+	pub max_primitive_counts: SmallVec<[u32; 1]>,
+	/// A top level acceleration structure keeps the bottom level acceleration structures of its last build alive
+	pub strong_refs: Mutex<StrongBackingRefs<P>>,
+	pub debug_name: String,
+}
+
+impl<P: BindlessPlatform> Deref for AccelerationStructureSlot<P> {
+	type Target = P::AccelerationStructure;
+
+	fn deref(&self) -> &Self::Target {
+		&self.platform
+	}
+}
+
+impl<P: BindlessPlatform> AccelerationStructureSlot<P> {
+	pub fn debug_name(&self) -> &str {
+		&self.debug_name
+	}
+
+	/// Verify that this acceleration structure can be built with geometries of `primitive_counts`
+	pub fn validate_build(
+		&self,
+		ty: AccelerationStructureType,
+		primitive_counts: impl ExactSizeIterator<Item = u32>,
+		mode: AccelerationStructureBuildMode,
+	) -> Result<(), AccelerationStructureBuildError> {
+		if self.ty != ty {
+			return Err(AccelerationStructureBuildError::WrongType {
+				name: self.debug_name.clone(),
+				expected: ty,
+			});
+		}
+		if mode == AccelerationStructureBuildMode::Update
+			&& !self.flags.contains(BindlessAccelerationStructureFlags::ALLOW_UPDATE)
+		{
+			return Err(AccelerationStructureBuildError::UpdateNotAllowed {
+				name: self.debug_name.clone(),
+			});
+		}
+		if primitive_counts.len() != self.max_primitive_counts.len() {
+			return Err(AccelerationStructureBuildError::GeometryCountMismatch {
+				name: self.debug_name.clone(),
+				expected: self.max_primitive_counts.len(),
+				actual: primitive_counts.len(),
+			});
+		}
+		for (count, max) in primitive_counts.zip(self.max_primitive_counts.iter().copied()) {
+			if count > max {
+				return Err(AccelerationStructureBuildError::TooManyPrimitives {
+					name: self.debug_name.clone(),
+					max,
+					actual: count,
+				});
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Triangle geometry of a bottom level acceleration structure
+pub struct BlasTriangles<'a, P: BindlessPlatform> {
+	/// Each vertex must start with its position as three `f32`. Requires
+	/// [`BindlessBufferUsage::ACCELERATION_STRUCTURE_BUILD_INPUT`].
+	pub vertices: &'a BufferSlot<P>,
+	pub vertex_stride: u64,
+	pub vertex_count: u32,
+	/// Three `u32` indices per triangle. Requires [`BindlessBufferUsage::ACCELERATION_STRUCTURE_BUILD_INPUT`].
+	pub indices: &'a BufferSlot<P>,
+	pub triangle_count: u32,
+	/// Opaque geometry may skip any-hit processing
+	pub opaque: bool,
+}
+
+impl<P: BindlessPlatform> Copy for BlasTriangles<'_, P> {}
+
+impl<P: BindlessPlatform> Clone for BlasTriangles<'_, P> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<'a, P: BindlessPlatform> BlasTriangles<'a, P> {
+	/// Opaque triangles of all `indices`, each vertex `V` must start with its position as three `f32`.
+	pub fn new<V: BufferStruct>(vertices: &'a RCDesc<P, Buffer<[V]>>, indices: &'a RCDesc<P, Buffer<[u32]>>) -> Self {
+		let vertices = vertices.inner_slot();
+		let indices = indices.inner_slot();
+		Self {
+			vertices,
+			vertex_stride: size_of::<V::Transfer>() as u64,
+			vertex_count: vertices.len as u32,
+			indices,
+			triangle_count: indices.len as u32 / 3,
+			opaque: true,
+		}
+	}
+
+	pub fn validate(&self) -> Result<(), AccelerationStructureBuildError> {
+		for buffer in [self.vertices, self.indices] {
+			if !buffer
+				.usage
+				.contains(BindlessBufferUsage::ACCELERATION_STRUCTURE_BUILD_INPUT)
+			{
+				return Err(AccelerationStructureBuildError::MissingBuildInputUsage {
+					name: buffer.debug_name.clone(),
+				});
+			}
+		}
+		Ok(())
+	}
+}
+
+/// An instance of a bottom level acceleration structure within a top level acceleration structure
+pub struct TlasInstance<'a, P: BindlessPlatform> {
+	pub blas: &'a RCDesc<P, AccelerationStructure>,
+	pub world_from_local: Affine3A,
+	/// Reported to shaders on hit, only the lower 24 bits are used
+	pub custom_index: u32,
+	/// Rays only intersect this instance if their cull mask shares a bit with this mask
+	pub mask: u8,
+}
+
+impl<P: BindlessPlatform> Copy for TlasInstance<'_, P> {}
+
+impl<P: BindlessPlatform> Clone for TlasInstance<'_, P> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<'a, P: BindlessPlatform> TlasInstance<'a, P> {
+	pub fn new(blas: &'a RCDesc<P, AccelerationStructure>, world_from_local: Affine3A, custom_index: u32) -> Self {
+		Self {
+			blas,
+			world_from_local,
+			custom_index,
+			mask: !0,
+		}
+	}
+}
+
+/// The geometry an acceleration structure should be sized for
+pub enum AccelerationStructureSizing<'a, P: BindlessPlatform> {
+	Triangles(&'a [BlasTriangles<'a, P>]),
+	Instances { max_instances: u32 },
+}
+
+impl<P: BindlessPlatform> AccelerationStructureSizing<'_, P> {
+	pub fn ty(&self) -> AccelerationStructureType {
+		match self {
+			AccelerationStructureSizing::Triangles(_) => AccelerationStructureType::BottomLevel,
+			AccelerationStructureSizing::Instances { .. } => AccelerationStructureType::TopLevel,
+		}
+	}
+
+	pub fn max_primitive_counts(&self) -> SmallVec<[u32; 1]> {
+		match self {
+			AccelerationStructureSizing::Triangles(triangles) => triangles.iter().map(|t| t.triangle_count).collect(),
+			AccelerationStructureSizing::Instances { max_instances } => SmallVec::from_slice(&[*max_instances]),
+		}
+	}
+}
+
+pub struct AccelerationStructureTable<P: BindlessPlatform> {
+	table: Arc<Table<AccelerationStructureInterface<P>>>,
+}
+
+impl<P: BindlessPlatform> AccelerationStructureTable<P> {
+	pub fn new(table_sync: &Arc<TableSync>, counts: DescriptorCounts, bindless: WeakBindless<P>) -> Self {
+		Self {
+			table: table_sync
+				.register(
+					counts.acceleration_structures,
+					AccelerationStructureInterface { bindless },
+				)
+				.unwrap(),
+		}
+	}
+}
+
+pub struct AccelerationStructureTableAccess<'a, P: BindlessPlatform>(pub &'a Bindless<P>);
+
+impl<P: BindlessPlatform> Deref for AccelerationStructureTableAccess<'_, P> {
+	type Target = AccelerationStructureTable<P>;
+
+	#[inline]
+	fn deref(&self) -> &Self::Target {
+		&self.0.acceleration_structure
+	}
+}
+
+bitflags::bitflags! {
+	/// Flags that control how an acceleration structure is built. The bits match the vulkan bits.
+	#[repr(transparent)]
+	#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+	pub struct BindlessAccelerationStructureFlags: u32 {
+		/// Allows [`AccelerationStructureBuildMode::Update`]
+		const ALLOW_UPDATE = 0b1;
+		/// Prioritize trace performance over build time
+		const PREFER_FAST_TRACE = 0b100;
+		/// Prioritize build time over trace performance
+		const PREFER_FAST_BUILD = 0b1000;
+	}
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BindlessAccelerationStructureCreateInfo<'a> {
+	pub flags: BindlessAccelerationStructureFlags,
+	/// Determines how the backing memory should be managed.
+	pub allocation_scheme: BindlessAllocationScheme,
+	/// Name of the acceleration structure, for tracking and debugging purposes
+	pub name: &'a str,
+}
+
+#[derive(Error)]
+pub enum AccelerationStructureAllocationError<P: BindlessPlatform> {
+	#[error("Platform Error: {0}")]
+	Platform(#[source] P::AllocationError),
+	#[error("Slot Allocation Error: {0}")]
+	Slot(#[from] SlotAllocationError),
+	#[error("Bottom level acceleration structure {name} must contain at least one geometry")]
+	NoGeometry { name: String },
+}
+
+impl<P: BindlessPlatform> Debug for AccelerationStructureAllocationError<P> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		Display::fmt(&self, f)
+	}
+}
+
+#[derive(Error)]
+pub enum AccelerationStructureBuildError {
+	#[error("Acceleration structure {name} is not of type {expected:?}")]
+	WrongType {
+		name: String,
+		expected: AccelerationStructureType,
+	},
+	#[error("Acceleration structure {name} was not created with `ALLOW_UPDATE`")]
+	UpdateNotAllowed { name: String },
+	#[error("Acceleration structure {name} was sized for {expected} geometries, but {actual} were supplied")]
+	GeometryCountMismatch {
+		name: String,
+		expected: usize,
+		actual: usize,
+	},
+	#[error("Acceleration structure {name} was sized for at most {max} primitives, but {actual} were supplied")]
+	TooManyPrimitives { name: String, max: u32, actual: u32 },
+	#[error("Buffer {name} is missing `ACCELERATION_STRUCTURE_BUILD_INPUT` usage")]
+	MissingBuildInputUsage { name: String },
+}
+
+impl Debug for AccelerationStructureBuildError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		Display::fmt(&self, f)
+	}
+}
+
+impl<P: BindlessPlatform> AccelerationStructureTableAccess<'_, P> {
+	/// Allocates a new slot for this acceleration structure
+	///
+	/// # Safety
+	/// The acceleration structure's device must be the same as the bindless device. Ownership of the acceleration
+	/// structure is transferred to this table. You may not access or drop it afterward, except by going though the
+	/// returned `RCDesc`.
+	#[inline]
+	pub unsafe fn alloc_slot(
+		&self,
+		acceleration_structure: AccelerationStructureSlot<P>,
+	) -> Result<RCDesc<P, AccelerationStructure>, SlotAllocationError> {
+		unsafe { Ok(RCDesc::new(self.table.alloc_slot(acceleration_structure)?)) }
+	}
+
+	pub(crate) fn flush_queue(&self) -> DrainFlushQueue<'_, AccelerationStructureInterface<P>> {
+		self.table.drain_flush_queue()
+	}
+
+	/// Allocate an acceleration structure large enough to be built with `sizing`. Its contents are undefined until
+	/// it's built with [`Recording::build_blas`] or [`Recording::build_tlas`].
+	///
+	/// [`Recording::build_blas`]: crate::pipeline::Recording::build_blas
+	/// [`Recording::build_tlas`]: crate::pipeline::Recording::build_tlas
+	pub fn alloc(
+		&self,
+		create_info: &BindlessAccelerationStructureCreateInfo,
+		sizing: AccelerationStructureSizing<P>,
+	) -> Result<RCDesc<P, AccelerationStructure>, AccelerationStructureAllocationError<P>> {
+		if matches!(sizing, AccelerationStructureSizing::Triangles(triangles) if triangles.is_empty()) {
+			return Err(AccelerationStructureAllocationError::NoGeometry {
+				name: create_info.name.to_string(),
+			});
+		}
+		unsafe {
+			let ty = sizing.ty();
+			let max_primitive_counts = sizing.max_primitive_counts();
+			let (acceleration_structure, sizes) = self
+				.0
+				.platform
+				.alloc_acceleration_structure(create_info, sizing)
+				.map_err(Into::<AccelerationStructureAllocationError<P>>::into)?;
+			Ok(self.alloc_slot(AccelerationStructureSlot {
+				platform: acceleration_structure,
+				ty,
+				flags: create_info.flags,
+				sizes,
+				max_primitive_counts,
+				strong_refs: Default::default(),
+				debug_name: create_info.name.to_string(),
+			})?)
+		}
+	}
+
+	/// Allocate a bottom level acceleration structure for `geometries`
+	pub fn alloc_blas(
+		&self,
+		create_info: &BindlessAccelerationStructureCreateInfo,
+		geometries: &[BlasTriangles<P>],
+	) -> Result<RCDesc<P, AccelerationStructure>, AccelerationStructureAllocationError<P>> {
+		self.alloc(create_info, AccelerationStructureSizing::Triangles(geometries))
+	}
+
+	/// Allocate a top level acceleration structure for up to `max_instances`
+	pub fn alloc_tlas(
+		&self,
+		create_info: &BindlessAccelerationStructureCreateInfo,
+		max_instances: u32,
+	) -> Result<RCDesc<P, AccelerationStructure>, AccelerationStructureAllocationError<P>> {
+		self.alloc(create_info, AccelerationStructureSizing::Instances { max_instances })
+	}
+}
+
+pub struct AccelerationStructureInterface<P: BindlessPlatform> {
+	bindless: WeakBindless<P>,
+}
+
+impl<P: BindlessPlatform> TableInterface for AccelerationStructureInterface<P> {
+	type Slot = AccelerationStructureSlot<P>;
+
+	fn drop_slots<'a>(&self, indices: impl DescriptorIndexIterator<'a, Self>) {
+		unsafe {
+			if let Some(bindless) = self.bindless.upgrade() {
+				bindless
+					.platform
+					.destroy_acceleration_structures(bindless.global_descriptor_set(), indices);
+			}
+		}
+	}
+
+	fn flush<'a>(&self, _flush_queue: impl DescriptorIndexIterator<'a, Self>) {
+		// do nothing, flushing of descriptors is handled differently
+	}
+}
@@ -1,4 +1,5 @@
 use crate::backing::table::{FrameGuard, TableSync};
+use crate::descriptor::acceleration_structure_table::{AccelerationStructureTable, AccelerationStructureTableAccess};
 use crate::descriptor::buffer_table::{BufferTable, BufferTableAccess};
 use crate::descriptor::descriptor_counts::DescriptorCounts;
 use crate::descriptor::image_table::{ImageTable, ImageTableAccess};
@@ -55,6 +56,7 @@ pub struct BindlessInner<P: BindlessPlatform> {
 	pub(super) buffer: BufferTable<P>,
 	pub(super) image: ImageTable<P>,
 	pub(super) sampler: SamplerTable<P>,
+	pub(super) acceleration_structure: AccelerationStructureTable<P>,
 }
 
 impl<P: BindlessPlatform> Deref for BindlessInner<P> {
@@ -103,7 +105,8 @@ impl<P: BindlessPlatform> BindlessInstance<P> {
 				BindlessInner {
 					buffer: BufferTable::new(&table_sync, counts, weak.clone()),
 					image: ImageTable::new(&table_sync, counts, weak.clone()),
-					sampler: SamplerTable::new(&table_sync, counts, weak),
+					sampler: SamplerTable::new(&table_sync, counts, weak.clone()),
+					acceleration_structure: AccelerationStructureTable::new(&table_sync, counts, weak),
 					descriptor_set: Some(platform.create_descriptor_set(counts)),
 					table_sync,
 					platform,
@@ -149,6 +152,11 @@ impl<P: BindlessPlatform> Bindless<P> {
 		SamplerTableAccess(self)
 	}
 
+	#[inline]
+	pub fn acceleration_structure(&self) -> AccelerationStructureTableAccess<'_, P> {
+		AccelerationStructureTableAccess(self)
+	}
+
 	/// Flush the bindless descriptor set. All newly allocated resources before this call will be written. Failing to
 	/// flush before enqueueing work is undefined behaviour.
 	pub fn flush(&self) {
@@ -160,6 +168,7 @@ impl<P: BindlessPlatform> Bindless<P> {
 				self.buffer().flush_queue(),
 				self.image().flush_queue(),
 				self.sampler().flush_queue(),
+				self.acceleration_structure().flush_queue(),
 			);
 		}
 	}
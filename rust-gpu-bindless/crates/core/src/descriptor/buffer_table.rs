@@ -142,6 +142,10 @@ bitflags::bitflags! {
 		const VERTEX_BUFFER = 0b1000_0000;
 		/// Can be the source of indirect parameters (e.g. indirect buffer, parameter buffer)
 		const INDIRECT_BUFFER = 0b1_0000_0000;
+		/// Allows querying the device address of the buffer
+		const SHADER_DEVICE_ADDRESS = 0b10_0000_0000_0000_0000;
+		/// Can be used as input of acceleration structure builds, implies [`Self::SHADER_DEVICE_ADDRESS`]
+		const ACCELERATION_STRUCTURE_BUILD_INPUT = 0b1000_0000_0000_0000_0000;
 	}
 }
 
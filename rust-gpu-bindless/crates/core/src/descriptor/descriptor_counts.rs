@@ -5,6 +5,8 @@ pub struct DescriptorCounts {
 	pub buffers: u32,
 	pub image: u32,
 	pub samplers: u32,
+	/// Requires the acceleration structure extension, keep at 0 otherwise
+	pub acceleration_structures: u32,
 }
 
 impl DescriptorCounts {
@@ -16,6 +18,7 @@ impl DescriptorCounts {
 		buffers: 10_000,
 		image: 10_000,
 		samplers: 400,
+		acceleration_structures: 0,
 	};
 
 	pub fn reasonable_defaults<P: BindlessPlatform>(platform: &P) -> Self {
@@ -38,8 +41,12 @@ impl DescriptorCounts {
 			buffers,
 			image,
 			samplers,
+			acceleration_structures,
 		} = *self;
-		buffers <= limit.buffers && image <= limit.image && samplers <= limit.samplers
+		buffers <= limit.buffers
+			&& image <= limit.image
+			&& samplers <= limit.samplers
+			&& acceleration_structures <= limit.acceleration_structures
 	}
 
 	pub fn min(self, other: Self) -> Self {
@@ -47,6 +54,7 @@ impl DescriptorCounts {
 			buffers: self.buffers.min(other.buffers),
 			image: self.image.min(other.image),
 			samplers: self.samplers.min(other.samplers),
+			acceleration_structures: self.acceleration_structures.min(other.acceleration_structures),
 		}
 	}
 }
@@ -105,6 +105,17 @@ impl From<Extent> for u32 {
 	}
 }
 
+impl Extent {
+	/// The extent of mip level `mip_level`, halving each dimension per level but never going below 1
+	pub fn mip(self, mip_level: u32) -> Self {
+		Extent {
+			width: u32::max(self.width >> mip_level, 1),
+			height: u32::max(self.height >> mip_level, 1),
+			depth: u32::max(self.depth >> mip_level, 1),
+		}
+	}
+}
+
 impl Default for Extent {
 	fn default() -> Self {
 		Extent {
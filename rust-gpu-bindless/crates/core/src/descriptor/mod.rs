@@ -1,3 +1,4 @@
+mod acceleration_structure_table;
 mod bindless;
 mod buffer_metadata_cpu;
 mod buffer_table;
@@ -9,6 +10,7 @@ mod mutdesc;
 mod rc;
 mod sampler_table;
 
+pub use acceleration_structure_table::*;
 pub use bindless::*;
 pub use buffer_metadata_cpu::*;
 pub use buffer_table::*;
@@ -1,4 +1,8 @@
-use crate::descriptor::{Bindless, BindlessBufferUsage, BindlessImageUsage};
+use crate::descriptor::{
+	AccelerationStructure, AccelerationStructureBuildError, AccelerationStructureBuildMode, AccelerationStructureType,
+	Bindless, BindlessBufferUsage, BindlessImageUsage, BlasTriangles, RCDesc, RCDescExt, StrongBackingRefs,
+	TlasInstance,
+};
 use crate::pipeline::RenderingAttachmentImage;
 use crate::pipeline::access_buffer::MutBufferAccess;
 use crate::pipeline::access_error::AccessError;
@@ -120,8 +124,42 @@ impl<'a, P: BindlessPipelinePlatform> Recording<'a, P> {
 		}
 	}
 
+	/// Copy `regions` of one buffer of a slice to another buffer of the same slice.
+	pub fn copy_buffer_to_buffer_slice_regions<
+		T: BufferStruct,
+		SA: BufferAccessType + TransferReadable,
+		DA: BufferAccessType + TransferWriteable,
+	>(
+		&mut self,
+		src: impl MutOrSharedBuffer<P, [T], SA>,
+		dst: &MutBufferAccess<P, [T], DA>,
+		regions: &[BufferSliceCopy],
+	) -> Result<(), RecordingError<P>> {
+		src.has_required_usage(BindlessBufferUsage::TRANSFER_SRC)?;
+		dst.has_required_usage(BindlessBufferUsage::TRANSFER_DST)?;
+		unsafe {
+			let (src_slot, dst_slot) = (src.inner_slot(), dst.inner_slot());
+			for region in regions {
+				for (slot, start) in [(src_slot, region.src_start), (dst_slot, region.dst_start)] {
+					if start.checked_add(region.len).is_none_or(|end| end > slot.len) {
+						return Err(CopyError::RegionOutOfBounds {
+							name: slot.debug_name().to_string(),
+							len: slot.len,
+							region: *region,
+						}
+						.into());
+					}
+				}
+			}
+			self.platform
+				.copy_buffer_to_buffer_slice_regions(src, dst, regions)
+				.map_err(Into::<RecordingError<P>>::into)
+		}
+	}
+
 	/// Copy data from a buffer to an image. It is assumed that the image data is tightly packed within the buffer.
-	/// Partial copies and copying to mips other than mip 0 is not yet possible.
+	/// Partial copies and copying to mips other than mip 0 is not yet possible, see
+	/// [`Self::copy_buffer_to_image_mip`].
 	pub fn copy_buffer_to_image<
 		BT: BufferContent + ?Sized,
 		BA: BufferAccessType + TransferReadable,
@@ -131,13 +169,41 @@ impl<'a, P: BindlessPipelinePlatform> Recording<'a, P> {
 		&mut self,
 		src_buffer: &MutBufferAccess<P, BT, BA>,
 		dst_image: &MutImageAccess<P, IT, IA>,
+	) -> Result<(), RecordingError<P>> {
+		self.copy_buffer_to_image_mip(src_buffer, dst_image, 0)
+	}
+
+	/// Copy data from a buffer to the mip level `mip_level` of an image, which has the extent of the image halved
+	/// `mip_level` times. It is assumed that the image data is tightly packed within the buffer. Partial copies are not
+	/// yet possible.
+	pub fn copy_buffer_to_image_mip<
+		BT: BufferContent + ?Sized,
+		BA: BufferAccessType + TransferReadable,
+		IT: ImageType,
+		IA: ImageAccessType + TransferWriteable,
+	>(
+		&mut self,
+		src_buffer: &MutBufferAccess<P, BT, BA>,
+		dst_image: &MutImageAccess<P, IT, IA>,
+		mip_level: u32,
 	) -> Result<(), RecordingError<P>> {
 		src_buffer.has_required_usage(BindlessBufferUsage::TRANSFER_SRC)?;
 		dst_image.has_required_usage(BindlessImageUsage::TRANSFER_DST)?;
+		unsafe {
+			let image = dst_image.inner_slot();
+			if mip_level >= image.mip_levels {
+				return Err(CopyError::MipOutOfBounds {
+					name: image.debug_name.clone(),
+					mip_levels: image.mip_levels,
+					mip_level,
+				}
+				.into());
+			}
+		}
 		// TODO soundness: missing bounds checks
 		unsafe {
 			self.platform
-				.copy_buffer_to_image(src_buffer, dst_image)
+				.copy_buffer_to_image(src_buffer, dst_image, mip_level)
 				.map_err(Into::<RecordingError<P>>::into)
 		}
 	}
@@ -209,6 +275,70 @@ impl<'a, P: BindlessPipelinePlatform> Recording<'a, P> {
 				.map_err(Into::<RecordingError<P>>::into)
 		}
 	}
+
+	/// Build or update a bottom level acceleration structure from `geometries`. There must be as many geometries as
+	/// the acceleration structure was allocated with, each not exceeding the triangle count it was allocated with.
+	/// Updates must keep the triangle counts of the previous build.
+	pub fn build_blas(
+		&mut self,
+		blas: &RCDesc<P, AccelerationStructure>,
+		geometries: &[BlasTriangles<P>],
+		mode: AccelerationStructureBuildMode,
+	) -> Result<(), RecordingError<P>> {
+		unsafe {
+			let slot = blas.inner_slot();
+			slot.validate_build(
+				AccelerationStructureType::BottomLevel,
+				geometries.iter().map(|geometry| geometry.triangle_count),
+				mode,
+			)?;
+			for geometry in geometries {
+				geometry.validate()?;
+			}
+			self.platform
+				.build_blas(slot, geometries, mode)
+				.map_err(Into::<RecordingError<P>>::into)
+		}
+	}
+
+	/// Build or update a top level acceleration structure from `instances`, not exceeding the maximum amount of
+	/// instances it was allocated with. The bottom level acceleration structures must have been built before and
+	/// are kept alive until the next build of this acceleration structure. Updates must keep the instance count of
+	/// the previous build.
+	pub fn build_tlas(
+		&mut self,
+		tlas: &RCDesc<P, AccelerationStructure>,
+		instances: &[TlasInstance<P>],
+		mode: AccelerationStructureBuildMode,
+	) -> Result<(), RecordingError<P>> {
+		unsafe {
+			let slot = tlas.inner_slot();
+			slot.validate_build(
+				AccelerationStructureType::TopLevel,
+				[instances.len() as u32].into_iter(),
+				mode,
+			)?;
+			for instance in instances {
+				let blas = instance.blas.inner_slot();
+				if blas.ty != AccelerationStructureType::BottomLevel {
+					return Err(AccelerationStructureBuildError::WrongType {
+						name: blas.debug_name.clone(),
+						expected: AccelerationStructureType::BottomLevel,
+					}
+					.into());
+				}
+			}
+			*slot.strong_refs.lock() = StrongBackingRefs(
+				instances
+					.iter()
+					.map(|instance| instance.blas.clone().into_any())
+					.collect(),
+			);
+			self.platform
+				.build_tlas(slot, instances, mode)
+				.map_err(Into::<RecordingError<P>>::into)
+		}
+	}
 }
 
 #[derive(Error)]
@@ -221,6 +351,8 @@ pub enum RecordingError<P: BindlessPipelinePlatform> {
 	CopyError(#[from] CopyError),
 	#[error("Rendering Error: {0}")]
 	RenderingError(#[from] RenderingError),
+	#[error("Acceleration Structure Build Error: {0}")]
+	AccelerationStructureBuildError(#[from] AccelerationStructureBuildError),
 }
 
 impl<P: BindlessPipelinePlatform> Debug for RecordingError<P> {
@@ -229,8 +361,29 @@ impl<P: BindlessPipelinePlatform> Debug for RecordingError<P> {
 	}
 }
 
+/// A region of [`Recording::copy_buffer_to_buffer_slice_regions`], in elements of the slice
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BufferSliceCopy {
+	pub src_start: usize,
+	pub dst_start: usize,
+	pub len: usize,
+}
+
 #[derive(Error)]
-pub enum CopyError {}
+pub enum CopyError {
+	#[error("Region {region:?} is out of bounds of buffer {name} with len {len}")]
+	RegionOutOfBounds {
+		name: String,
+		len: usize,
+		region: BufferSliceCopy,
+	},
+	#[error("Mip level {mip_level} is out of bounds of image {name} with {mip_levels} mip levels")]
+	MipOutOfBounds {
+		name: String,
+		mip_levels: u32,
+		mip_level: u32,
+	},
+}
 
 impl Debug for CopyError {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -0,0 +1,142 @@
+use crate::descriptor::BlasTriangles;
+use crate::platform::ash::{Ash, AshMemoryAllocation};
+use ash::vk::{
+	AccelerationStructureGeometryDataKHR, AccelerationStructureGeometryInstancesDataKHR,
+	AccelerationStructureGeometryKHR, AccelerationStructureGeometryTrianglesDataKHR, AccelerationStructureKHR,
+	DeviceAddress, DeviceOrHostAddressConstKHR, Format, GeometryFlagsKHR, GeometryInstanceFlagsKHR, GeometryTypeKHR,
+	IndexType,
+};
+use glam::Affine3A;
+
+pub struct AshAccelerationStructure {
+	pub acceleration_structure: AccelerationStructureKHR,
+	pub buffer: ash::vk::Buffer,
+	pub allocation: AshMemoryAllocation,
+	/// The address instances of top level acceleration structures use to reference this acceleration structure
+	pub device_address: DeviceAddress,
+}
+
+/// The geometry description of `triangles`. Sizing an acceleration structure ignores the addresses of the vertex and
+/// index data, so they may be 0.
+pub fn ash_triangle_geometry(
+	triangles: &BlasTriangles<Ash>,
+	vertex_data: DeviceAddress,
+	index_data: DeviceAddress,
+) -> AccelerationStructureGeometryKHR<'static> {
+	AccelerationStructureGeometryKHR::default()
+		.geometry_type(GeometryTypeKHR::TRIANGLES)
+		.flags(if triangles.opaque {
+			GeometryFlagsKHR::OPAQUE
+		} else {
+			GeometryFlagsKHR::empty()
+		})
+		.geometry(AccelerationStructureGeometryDataKHR {
+			triangles: AccelerationStructureGeometryTrianglesDataKHR::default()
+				.vertex_format(Format::R32G32B32_SFLOAT)
+				.vertex_data(DeviceOrHostAddressConstKHR {
+					device_address: vertex_data,
+				})
+				.vertex_stride(triangles.vertex_stride)
+				.max_vertex(triangles.vertex_count.saturating_sub(1))
+				.index_type(IndexType::UINT32)
+				.index_data(DeviceOrHostAddressConstKHR {
+					device_address: index_data,
+				}),
+		})
+}
+
+/// The geometry description of a top level acceleration structure, with `instance_data` pointing to a tightly packed
+/// array of [`ash_instance_words`].
+pub fn ash_instance_geometry(instance_data: DeviceAddress) -> AccelerationStructureGeometryKHR<'static> {
+	AccelerationStructureGeometryKHR::default()
+		.geometry_type(GeometryTypeKHR::INSTANCES)
+		.geometry(AccelerationStructureGeometryDataKHR {
+			instances: AccelerationStructureGeometryInstancesDataKHR::default()
+				.array_of_pointers(false)
+				.data(DeviceOrHostAddressConstKHR {
+					device_address: instance_data,
+				}),
+		})
+}
+
+pub const ASH_INSTANCE_WORDS: usize = 16;
+
+/// Packs an instance the same way as [`ash::vk::AccelerationStructureInstanceKHR`], but as plain words that can be
+/// written into a buffer. Triangle facing culling is disabled, as triangles are hit from both sides.
+pub fn ash_instance_words(
+	world_from_local: Affine3A,
+	custom_index: u32,
+	mask: u8,
+	blas_device_address: DeviceAddress,
+) -> [u32; ASH_INSTANCE_WORDS] {
+	let mut words = [0; ASH_INSTANCE_WORDS];
+	let columns = [
+		world_from_local.matrix3.x_axis,
+		world_from_local.matrix3.y_axis,
+		world_from_local.matrix3.z_axis,
+		world_from_local.translation,
+	];
+	// 3x4 row-major transform
+	for row in 0..3 {
+		for (column, axis) in columns.iter().enumerate() {
+			words[row * 4 + column] = axis[row].to_bits();
+		}
+	}
+	words[12] = (custom_index & 0xFF_FFFF) | ((mask as u32) << 24);
+	words[13] = GeometryInstanceFlagsKHR::TRIANGLE_FACING_CULL_DISABLE.as_raw() << 24;
+	words[14] = blas_device_address as u32;
+	words[15] = (blas_device_address >> 32) as u32;
+	words
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ash::vk::{
+		AccelerationStructureInstanceKHR, AccelerationStructureReferenceKHR, Packed24_8, TransformMatrixKHR,
+	};
+	use glam::{Quat, Vec3};
+	use std::mem::size_of;
+
+	#[test]
+	fn test_instance_words_match_ash() {
+		let transform = Affine3A::from_scale_rotation_translation(
+			Vec3::new(1., 2., 3.),
+			Quat::from_rotation_y(0.5),
+			Vec3::new(4., 5., 6.),
+		);
+		let matrix = transform.matrix3;
+		let t = transform.translation;
+		let expected = AccelerationStructureInstanceKHR {
+			transform: TransformMatrixKHR {
+				matrix: [
+					matrix.x_axis.x,
+					matrix.y_axis.x,
+					matrix.z_axis.x,
+					t.x,
+					matrix.x_axis.y,
+					matrix.y_axis.y,
+					matrix.z_axis.y,
+					t.y,
+					matrix.x_axis.z,
+					matrix.y_axis.z,
+					matrix.z_axis.z,
+					t.z,
+				],
+			},
+			instance_custom_index_and_mask: Packed24_8::new(0x12_3456, 0xAB),
+			instance_shader_binding_table_record_offset_and_flags: Packed24_8::new(
+				0,
+				GeometryInstanceFlagsKHR::TRIANGLE_FACING_CULL_DISABLE.as_raw() as u8,
+			),
+			acceleration_structure_reference: AccelerationStructureReferenceKHR {
+				device_handle: 0x0123_4567_89AB_CDEF,
+			},
+		};
+
+		assert_eq!(size_of::<AccelerationStructureInstanceKHR>(), ASH_INSTANCE_WORDS * 4);
+		let expected: [u32; ASH_INSTANCE_WORDS] = unsafe { std::mem::transmute(expected) };
+		let words = ash_instance_words(transform, 0x12_3456, 0xAB, 0x0123_4567_89AB_CDEF);
+		assert_eq!(words, expected);
+	}
+}
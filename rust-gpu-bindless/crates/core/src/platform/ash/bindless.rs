@@ -1,37 +1,46 @@
 use crate::backing::range_set::{DescriptorIndexIterator, DescriptorIndexRangeSet};
 use crate::backing::table::DrainFlushQueue;
 use crate::descriptor::{
-	Bindless, BindlessBufferCreateInfo, BindlessBufferUsage, BindlessImageCreateInfo, BindlessImageUsage,
-	BindlessSamplerCreateInfo, BufferAllocationError, BufferInterface, BufferSlot, DescriptorCounts,
-	ImageAllocationError, ImageInterface, SamplerAllocationError, SamplerInterface, WeakBindless,
+	AccelerationStructureAllocationError, AccelerationStructureBuildSizes, AccelerationStructureInterface,
+	AccelerationStructureSizing, Bindless, BindlessAccelerationStructureCreateInfo, BindlessBufferCreateInfo,
+	BindlessBufferUsage, BindlessImageCreateInfo, BindlessImageUsage, BindlessSamplerCreateInfo, BufferAllocationError,
+	BufferInterface, BufferSlot, DescriptorCounts, ImageAllocationError, ImageInterface, SamplerAllocationError,
+	SamplerInterface, WeakBindless,
 };
 use crate::platform::BindlessPlatform;
 use crate::platform::ash::image_format::FormatExt;
 use crate::platform::ash::{
-	AshExecutionManager, AshPendingExecution, bindless_image_type_to_vk_image_type,
-	bindless_image_type_to_vk_image_view_type,
+	AshAccelerationStructure, AshExecutionManager, AshPendingExecution, ash_instance_geometry, ash_triangle_geometry,
+	bindless_image_type_to_vk_image_type, bindless_image_type_to_vk_image_view_type,
 };
 use ash::ext::{debug_utils, mesh_shader};
-use ash::khr::{surface, swapchain};
+use ash::khr::{acceleration_structure, surface, swapchain};
 use ash::prelude::VkResult;
 use ash::vk::{
-	ComponentMapping, DebugUtilsObjectNameInfoEXT, DescriptorBindingFlags, DescriptorBufferInfo, DescriptorImageInfo,
-	DescriptorPool, DescriptorPoolCreateFlags, DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSet,
-	DescriptorSetAllocateInfo, DescriptorSetLayout, DescriptorSetLayoutBindingFlagsCreateInfo,
-	DescriptorSetLayoutCreateFlags, DescriptorSetLayoutCreateInfo, DescriptorType, Handle, ImageLayout,
-	ImageSubresourceRange, ImageTiling, ImageViewCreateInfo, LOD_CLAMP_NONE, PhysicalDeviceProperties2,
-	PhysicalDeviceVulkan12Properties, PipelineCache, PipelineLayout, PipelineLayoutCreateInfo, PushConstantRange,
-	SamplerCreateInfo, ShaderStageFlags, SharingMode, WriteDescriptorSet,
+	AccelerationStructureBuildGeometryInfoKHR, AccelerationStructureBuildSizesInfoKHR,
+	AccelerationStructureBuildTypeKHR, AccelerationStructureCreateInfoKHR, AccelerationStructureDeviceAddressInfoKHR,
+	BufferDeviceAddressInfo, BufferUsageFlags, BuildAccelerationStructureModeKHR, ComponentMapping,
+	DebugUtilsObjectNameInfoEXT, DescriptorBindingFlags, DescriptorBufferInfo, DescriptorImageInfo, DescriptorPool,
+	DescriptorPoolCreateFlags, DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSet, DescriptorSetAllocateInfo,
+	DescriptorSetLayout, DescriptorSetLayoutBindingFlagsCreateInfo, DescriptorSetLayoutCreateFlags,
+	DescriptorSetLayoutCreateInfo, DescriptorType, DeviceAddress, Handle, ImageLayout, ImageSubresourceRange,
+	ImageTiling, ImageViewCreateInfo, LOD_CLAMP_NONE, PhysicalDeviceAccelerationStructurePropertiesKHR,
+	PhysicalDeviceProperties2, PhysicalDeviceVulkan12Properties, PipelineCache, PipelineLayout,
+	PipelineLayoutCreateInfo, PushConstantRange, SamplerCreateInfo, ShaderStageFlags, SharingMode, WriteDescriptorSet,
+	WriteDescriptorSetAccelerationStructureKHR,
 };
 use gpu_allocator::AllocationError;
+use gpu_allocator::MemoryLocation;
 use gpu_allocator::vulkan::{Allocation, AllocationCreateDesc, Allocator};
 use parking_lot::lock_api::MutexGuard;
 use parking_lot::{Mutex, RawMutex};
 use presser::Slab;
 use rangemap::RangeSet;
 use rust_gpu_bindless_shaders::descriptor::{
-	BINDING_BUFFER, BINDING_SAMPLED_IMAGE, BINDING_SAMPLER, BINDING_STORAGE_IMAGE, BindlessPushConstant, ImageType,
+	BINDING_ACCELERATION_STRUCTURE, BINDING_BUFFER, BINDING_SAMPLED_IMAGE, BINDING_SAMPLER, BINDING_STORAGE_IMAGE,
+	BindlessPushConstant, ImageType,
 };
+use smallvec::SmallVec;
 use static_assertions::assert_impl_all;
 use std::cell::UnsafeCell;
 use std::ffi::CString;
@@ -42,14 +51,39 @@ use thiserror::Error;
 pub struct Ash {
 	pub create_info: AshCreateInfo,
 	pub execution_manager: AshExecutionManager,
+	/// queried once on creation, `None` if the acceleration structure extension is not enabled
+	acceleration_structure_properties: Option<AshAccelerationStructureProperties>,
 }
 assert_impl_all!(Bindless<Ash>: Send, Sync);
 
+/// The [`PhysicalDeviceAccelerationStructurePropertiesKHR`] bindless needs, without the `p_next` pointer that would
+/// make it neither `Send` nor `Sync`
+#[derive(Copy, Clone, Debug)]
+pub struct AshAccelerationStructureProperties {
+	pub min_acceleration_structure_scratch_offset_alignment: u32,
+	pub max_descriptor_set_update_after_bind_acceleration_structures: u32,
+}
+
 impl Ash {
 	pub fn new(create_info: AshCreateInfo, bindless: &WeakBindless<Self>) -> VkResult<Self> {
+		let acceleration_structure_properties =
+			create_info.extensions.acceleration_structure.is_some().then(|| unsafe {
+				let mut properties = PhysicalDeviceAccelerationStructurePropertiesKHR::default();
+				let mut properties2 = PhysicalDeviceProperties2::default().push_next(&mut properties);
+				create_info
+					.instance
+					.get_physical_device_properties2(create_info.physical_device, &mut properties2);
+				AshAccelerationStructureProperties {
+					min_acceleration_structure_scratch_offset_alignment: properties
+						.min_acceleration_structure_scratch_offset_alignment,
+					max_descriptor_set_update_after_bind_acceleration_structures: properties
+						.max_descriptor_set_update_after_bind_acceleration_structures,
+				}
+			});
 		Ok(Ash {
 			execution_manager: AshExecutionManager::new(bindless, &create_info)?,
 			create_info,
+			acceleration_structure_properties,
 		})
 	}
 
@@ -96,6 +130,21 @@ impl Ash {
 			})
 		}
 	}
+
+	/// Get the device address of a buffer, the buffer must have been created with
+	/// [`BindlessBufferUsage::SHADER_DEVICE_ADDRESS`].
+	pub unsafe fn buffer_device_address(&self, buffer: ash::vk::Buffer) -> DeviceAddress {
+		unsafe {
+			self.device
+				.get_buffer_device_address(&BufferDeviceAddressInfo::default().buffer(buffer))
+		}
+	}
+
+	/// The acceleration structure properties of the physical device, or `None` if the acceleration structure
+	/// extension is not enabled
+	pub fn acceleration_structure_properties(&self) -> Option<AshAccelerationStructureProperties> {
+		self.acceleration_structure_properties
+	}
 }
 
 impl Deref for Ash {
@@ -140,6 +189,7 @@ pub struct AshExtensions {
 	pub mesh_shader: Option<mesh_shader::Device>,
 	pub surface: Option<surface::Instance>,
 	pub swapchain: Option<swapchain::Device>,
+	pub acceleration_structure: Option<acceleration_structure::Device>,
 }
 
 impl AshExtensions {
@@ -158,6 +208,12 @@ impl AshExtensions {
 	pub fn swapchain(&self) -> &swapchain::Device {
 		self.swapchain.as_ref().expect("missing khr_swapchain")
 	}
+
+	pub fn acceleration_structure(&self) -> &acceleration_structure::Device {
+		self.acceleration_structure
+			.as_ref()
+			.expect("missing khr_acceleration_structure")
+	}
 }
 
 impl AshCreateInfo {
@@ -275,12 +331,19 @@ impl From<AshAllocationError> for SamplerAllocationError<Ash> {
 	}
 }
 
+impl From<AshAllocationError> for AccelerationStructureAllocationError<Ash> {
+	fn from(value: AshAllocationError) -> Self {
+		AccelerationStructureAllocationError::Platform(value)
+	}
+}
+
 unsafe impl BindlessPlatform for Ash {
 	type PlatformCreateInfo = AshCreateInfo;
 	type PlatformCreateError = ash::vk::Result;
 	type Buffer = AshBuffer;
 	type Image = AshImage;
 	type Sampler = ash::vk::Sampler;
+	type AccelerationStructure = AshAccelerationStructure;
 	type AllocationError = AshAllocationError;
 	type BindlessDescriptorSet = AshBindlessDescriptorSet;
 	type PendingExecution = AshPendingExecution;
@@ -305,13 +368,16 @@ unsafe impl BindlessPlatform for Ash {
 					vulkan12properties.max_descriptor_set_update_after_bind_sampled_images,
 				),
 				samplers: vulkan12properties.max_descriptor_set_update_after_bind_samplers,
+				acceleration_structures: self
+					.acceleration_structure_properties
+					.map_or(0, |p| p.max_descriptor_set_update_after_bind_acceleration_structures),
 			}
 		}
 	}
 
 	unsafe fn create_descriptor_set(&self, counts: DescriptorCounts) -> Self::BindlessDescriptorSet {
 		unsafe {
-			let bindings = [
+			let mut bindings = vec![
 				ash::vk::DescriptorSetLayoutBinding::default()
 					.binding(BINDING_BUFFER)
 					.descriptor_type(DescriptorType::STORAGE_BUFFER)
@@ -333,10 +399,22 @@ unsafe impl BindlessPlatform for Ash {
 					.descriptor_count(counts.samplers)
 					.stage_flags(self.shader_stages),
 			];
-			let binding_flags = [DescriptorBindingFlags::UPDATE_AFTER_BIND
-				| DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING
-				| DescriptorBindingFlags::PARTIALLY_BOUND; 4];
-			assert_eq!(bindings.len(), binding_flags.len());
+			// only declared if the acceleration structure extension is enabled
+			if counts.acceleration_structures > 0 {
+				bindings.push(
+					ash::vk::DescriptorSetLayoutBinding::default()
+						.binding(BINDING_ACCELERATION_STRUCTURE)
+						.descriptor_type(DescriptorType::ACCELERATION_STRUCTURE_KHR)
+						.descriptor_count(counts.acceleration_structures)
+						.stage_flags(self.shader_stages),
+				);
+			}
+			let binding_flags = vec![
+				DescriptorBindingFlags::UPDATE_AFTER_BIND
+					| DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING
+					| DescriptorBindingFlags::PARTIALLY_BOUND;
+				bindings.len()
+			];
 
 			let set_layout = self
 				.device
@@ -370,11 +448,16 @@ unsafe impl BindlessPlatform for Ash {
 				.create_descriptor_pool(
 					&DescriptorPoolCreateInfo::default()
 						.flags(DescriptorPoolCreateFlags::UPDATE_AFTER_BIND)
-						.pool_sizes(&bindings.map(|b| {
-							DescriptorPoolSize::default()
-								.ty(b.descriptor_type)
-								.descriptor_count(b.descriptor_count)
-						}))
+						.pool_sizes(
+							&bindings
+								.iter()
+								.map(|b| {
+									DescriptorPoolSize::default()
+										.ty(b.descriptor_type)
+										.descriptor_count(b.descriptor_count)
+								})
+								.collect::<Vec<_>>(),
+						)
 						.max_sets(1),
 					None,
 				)
@@ -415,6 +498,7 @@ unsafe impl BindlessPlatform for Ash {
 		mut buffers: DrainFlushQueue<BufferInterface<Self>>,
 		mut images: DrainFlushQueue<ImageInterface<Self>>,
 		mut samplers: DrainFlushQueue<SamplerInterface<Self>>,
+		mut acceleration_structures: DrainFlushQueue<AccelerationStructureInterface<Self>>,
 	) {
 		unsafe {
 			let (buffer_table, buffers) = buffers.into_inner();
@@ -533,10 +617,46 @@ unsafe impl BindlessPlatform for Ash {
 					})
 			});
 
+			let acceleration_structures = acceleration_structures.into_range_set();
+			let acceleration_structure_handles = acceleration_structures
+				.iter()
+				.map(|(_, acceleration_structure)| acceleration_structure.acceleration_structure)
+				.collect::<Vec<_>>();
+			let acceleration_structure_ranges = acceleration_structures
+				.iter_ranges()
+				.map(|(range, _)| (range.start.to_u32(), range.end.to_usize() - range.start.to_usize()))
+				.collect::<Vec<_>>();
+			let mut acceleration_structure_index = 0;
+			let mut acceleration_structure_infos = acceleration_structure_ranges
+				.iter()
+				.map(|(_, count)| {
+					let acceleration_structure_start = acceleration_structure_index;
+					acceleration_structure_index += count;
+					WriteDescriptorSetAccelerationStructureKHR::default().acceleration_structures(
+						&acceleration_structure_handles
+							[acceleration_structure_start..acceleration_structure_start + count],
+					)
+				})
+				.collect::<Vec<_>>();
+			let acceleration_structures = acceleration_structure_ranges
+				.iter()
+				.zip(acceleration_structure_infos.iter_mut())
+				.map(|((start, count), info)| {
+					WriteDescriptorSet::default()
+						.dst_set(set.set)
+						.dst_binding(BINDING_ACCELERATION_STRUCTURE)
+						.descriptor_type(DescriptorType::ACCELERATION_STRUCTURE_KHR)
+						.dst_array_element(*start)
+						// not implied by the acceleration structure info
+						.descriptor_count(*count as u32)
+						.push_next(info)
+				});
+
 			let writes = buffers
 				.chain(storage_images)
 				.chain(sampled_images)
 				.chain(samplers)
+				.chain(acceleration_structures)
 				.collect::<Vec<_>>();
 			self.device.update_descriptor_sets(&writes, &[]);
 		}
@@ -646,6 +766,82 @@ unsafe impl BindlessPlatform for Ash {
 		}
 	}
 
+	unsafe fn alloc_acceleration_structure(
+		&self,
+		create_info: &BindlessAccelerationStructureCreateInfo,
+		sizing: AccelerationStructureSizing<'_, Self>,
+	) -> Result<(Self::AccelerationStructure, AccelerationStructureBuildSizes), Self::AllocationError> {
+		unsafe {
+			let extension = self.extensions.acceleration_structure();
+			let ty = sizing.ty().to_ash();
+			let geometries = match &sizing {
+				AccelerationStructureSizing::Triangles(triangles) => triangles
+					.iter()
+					.map(|triangles| ash_triangle_geometry(triangles, 0, 0))
+					.collect::<SmallVec<[_; 1]>>(),
+				AccelerationStructureSizing::Instances { .. } => SmallVec::from_buf([ash_instance_geometry(0)]),
+			};
+			let mut sizes = AccelerationStructureBuildSizesInfoKHR::default();
+			extension.get_acceleration_structure_build_sizes(
+				AccelerationStructureBuildTypeKHR::DEVICE,
+				&AccelerationStructureBuildGeometryInfoKHR::default()
+					.ty(ty)
+					.flags(create_info.flags.to_ash_build_flags())
+					.mode(BuildAccelerationStructureModeKHR::BUILD)
+					.geometries(&geometries),
+				&sizing.max_primitive_counts(),
+				&mut sizes,
+			);
+
+			let buffer = self.device.create_buffer(
+				&ash::vk::BufferCreateInfo::default()
+					.usage(
+						BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR | BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+					)
+					.size(sizes.acceleration_structure_size)
+					.sharing_mode(SharingMode::EXCLUSIVE),
+				None,
+			)?;
+			self.set_debug_object_name(buffer, create_info.name)?;
+			let requirements = self.device.get_buffer_memory_requirements(buffer);
+			let memory_allocation = self.memory_allocator().allocate(&AllocationCreateDesc {
+				requirements,
+				name: create_info.name,
+				location: MemoryLocation::GpuOnly,
+				allocation_scheme: create_info.allocation_scheme.to_gpu_allocator_buffer(buffer),
+				linear: true,
+			})?;
+			self.device
+				.bind_buffer_memory(buffer, memory_allocation.memory(), memory_allocation.offset())?;
+
+			let acceleration_structure = extension.create_acceleration_structure(
+				&AccelerationStructureCreateInfoKHR::default()
+					.buffer(buffer)
+					.offset(0)
+					.size(sizes.acceleration_structure_size)
+					.ty(ty),
+				None,
+			)?;
+			self.set_debug_object_name(acceleration_structure, create_info.name)?;
+			let device_address = extension.get_acceleration_structure_device_address(
+				&AccelerationStructureDeviceAddressInfoKHR::default().acceleration_structure(acceleration_structure),
+			);
+			Ok((
+				AshAccelerationStructure {
+					acceleration_structure,
+					buffer,
+					allocation: AshMemoryAllocation::new(memory_allocation),
+					device_address,
+				},
+				AccelerationStructureBuildSizes {
+					size: sizes.acceleration_structure_size,
+					build_scratch_size: sizes.build_scratch_size,
+					update_scratch_size: sizes.update_scratch_size,
+				},
+			))
+		}
+	}
+
 	unsafe fn mapped_buffer_to_slab(buffer: &BufferSlot<Self>) -> &mut (impl Slab + '_) {
 		unsafe { buffer.allocation.get_mut() }
 	}
@@ -705,6 +901,25 @@ unsafe impl BindlessPlatform for Ash {
 			}
 		}
 	}
+
+	unsafe fn destroy_acceleration_structures<'a>(
+		&self,
+		_global_descriptor_set: &Self::BindlessDescriptorSet,
+		acceleration_structures: impl DescriptorIndexIterator<'a, AccelerationStructureInterface<Self>>,
+	) {
+		unsafe {
+			let extension = self.extensions.acceleration_structure();
+			let mut allocator = self.memory_allocator();
+			for (_, acceleration_structure) in acceleration_structures.into_iter() {
+				extension.destroy_acceleration_structure(acceleration_structure.acceleration_structure, None);
+				// Safety: We have exclusive access to AccelerationStructureSlot in this method, see destroy_buffers
+				if let Some(allocation) = acceleration_structure.allocation.take() {
+					allocator.free(allocation).unwrap();
+				}
+				self.device.destroy_buffer(acceleration_structure.buffer, None);
+			}
+		}
+	}
 }
 
 #[cfg(test)]
@@ -723,6 +938,7 @@ mod tests {
 			BindlessBufferUsage::INDEX_BUFFER,
 			BindlessBufferUsage::VERTEX_BUFFER,
 			BindlessBufferUsage::INDIRECT_BUFFER,
+			BindlessBufferUsage::SHADER_DEVICE_ADDRESS,
 		] {
 			assert_eq!(
 				Some(usage),
@@ -1,11 +1,12 @@
 use crate::descriptor::{
-	AddressMode, BindlessAllocationScheme, BindlessBufferUsage, BindlessImageUsage, BorderColor, Extent, Filter,
-	SampleCount,
+	AccelerationStructureBuildMode, AccelerationStructureType, AddressMode, BindlessAccelerationStructureFlags,
+	BindlessAllocationScheme, BindlessBufferUsage, BindlessImageUsage, BorderColor, Extent, Filter, SampleCount,
 };
 use crate::pipeline::{ImageAccessType, IndexType, LoadOp, RenderingAttachment, RenderingAttachmentImage, StoreOp};
 use crate::platform::ash::Ash;
 use ash::vk::{
-	AttachmentLoadOp, AttachmentStoreOp, Extent2D, ImageLayout, ImageType as VkImageType, RenderingAttachmentInfo,
+	AccelerationStructureTypeKHR, AttachmentLoadOp, AttachmentStoreOp, BuildAccelerationStructureFlagsKHR,
+	BuildAccelerationStructureModeKHR, Extent2D, ImageLayout, ImageType as VkImageType, RenderingAttachmentInfo,
 	ShaderStageFlags,
 };
 use ash::vk::{BufferUsageFlags, Extent3D, ImageUsageFlags, ImageViewType, SampleCountFlags};
@@ -55,6 +56,13 @@ impl BindlessBufferUsage {
 		if self.contains(BindlessBufferUsage::INDIRECT_BUFFER) {
 			out |= BufferUsageFlags::INDIRECT_BUFFER;
 		}
+		if self.contains(BindlessBufferUsage::SHADER_DEVICE_ADDRESS) {
+			out |= BufferUsageFlags::SHADER_DEVICE_ADDRESS;
+		}
+		if self.contains(BindlessBufferUsage::ACCELERATION_STRUCTURE_BUILD_INPUT) {
+			out |= BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
+				| BufferUsageFlags::SHADER_DEVICE_ADDRESS;
+		}
 		// empty flags are invalid in vulkan, this is reachable via a buffer that is only host mappable
 		assert!(!self.is_empty());
 		if out.is_empty() {
@@ -178,6 +186,30 @@ impl BindlessImageUsage {
 	}
 }
 
+impl AccelerationStructureType {
+	pub fn to_ash(&self) -> AccelerationStructureTypeKHR {
+		match self {
+			AccelerationStructureType::BottomLevel => AccelerationStructureTypeKHR::BOTTOM_LEVEL,
+			AccelerationStructureType::TopLevel => AccelerationStructureTypeKHR::TOP_LEVEL,
+		}
+	}
+}
+
+impl AccelerationStructureBuildMode {
+	pub fn to_ash(&self) -> BuildAccelerationStructureModeKHR {
+		match self {
+			AccelerationStructureBuildMode::Build => BuildAccelerationStructureModeKHR::BUILD,
+			AccelerationStructureBuildMode::Update => BuildAccelerationStructureModeKHR::UPDATE,
+		}
+	}
+}
+
+impl BindlessAccelerationStructureFlags {
+	pub fn to_ash_build_flags(&self) -> BuildAccelerationStructureFlagsKHR {
+		BuildAccelerationStructureFlagsKHR::from_raw(self.bits())
+	}
+}
+
 pub trait ShaderAshExt {
 	fn to_ash_shader_stage(&self) -> ShaderStageFlags;
 }
@@ -2,13 +2,14 @@ use crate::platform::ash::{AshCreateInfo, AshExtensions};
 use anyhow::anyhow;
 use ash::Entry;
 use ash::ext::{debug_utils, mesh_shader};
-use ash::khr::{surface, swapchain};
+use ash::khr::{acceleration_structure, ray_query, surface, swapchain};
 use ash::vk::{
 	ApplicationInfo, Bool32, DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT,
 	DebugUtilsMessengerCallbackDataEXT, DebugUtilsMessengerCreateInfoEXT, DeviceCreateInfo, DeviceQueueCreateInfo,
-	ExtendsDeviceCreateInfo, InstanceCreateInfo, PhysicalDeviceFeatures, PhysicalDeviceType,
-	PhysicalDeviceVulkan11Features, PhysicalDeviceVulkan12Features, PhysicalDeviceVulkan13Features,
-	PipelineCacheCreateInfo, QueueFlags, ShaderStageFlags, ValidationFeatureEnableEXT, ValidationFeaturesEXT,
+	ExtendsDeviceCreateInfo, InstanceCreateInfo, PhysicalDeviceAccelerationStructureFeaturesKHR,
+	PhysicalDeviceFeatures, PhysicalDeviceRayQueryFeaturesKHR, PhysicalDeviceType, PhysicalDeviceVulkan11Features,
+	PhysicalDeviceVulkan12Features, PhysicalDeviceVulkan13Features, PipelineCacheCreateInfo, QueueFlags,
+	ShaderStageFlags, ValidationFeatureEnableEXT, ValidationFeaturesEXT,
 };
 use gpu_allocator::vulkan::{Allocator, AllocatorCreateDesc};
 use gpu_allocator::{AllocationSizes, AllocatorDebugSettings};
@@ -248,12 +249,25 @@ pub fn ash_init_single_graphics_queue_with_push_next(
 				.0 as u32
 		};
 
+		let has_acceleration_structure = create_info.extensions.contains(&acceleration_structure::NAME);
 		let device = {
 			let extensions = create_info.extensions.iter().map(|x| x.as_ptr()).collect::<Vec<_>>();
 			let mut device_create_info = DeviceCreateInfo::default();
 			if let Some(device_push_next) = device_push_next {
 				device_create_info = device_create_info.push_next(device_push_next);
 			}
+			let mut acceleration_structure_features = PhysicalDeviceAccelerationStructureFeaturesKHR::default()
+				.acceleration_structure(true)
+				.descriptor_binding_acceleration_structure_update_after_bind(true);
+			if has_acceleration_structure {
+				// acceleration structure builds reference their inputs by device address
+				create_info.features_vk12 = create_info.features_vk12.buffer_device_address(true);
+				device_create_info = device_create_info.push_next(&mut acceleration_structure_features);
+			}
+			let mut ray_query_features = PhysicalDeviceRayQueryFeaturesKHR::default().ray_query(true);
+			if create_info.extensions.contains(&ray_query::NAME) {
+				device_create_info = device_create_info.push_next(&mut ray_query_features);
+			}
 			instance.create_device(
 				physical_device,
 				&device_create_info
@@ -275,7 +289,7 @@ pub fn ash_init_single_graphics_queue_with_push_next(
 			device: device.clone(),
 			physical_device,
 			debug_settings: AllocatorDebugSettings::default(),
-			buffer_device_address: false,
+			buffer_device_address: has_acceleration_structure,
 			allocation_sizes: AllocationSizes::default(),
 		})?;
 		let cache = device.create_pipeline_cache(&PipelineCacheCreateInfo::default(), None)?;
@@ -287,6 +301,9 @@ pub fn ash_init_single_graphics_queue_with_push_next(
 			.contains(&mesh_shader::NAME)
 			.then(|| mesh_shader::Device::new(&instance, &device));
 
+		let acceleration_structure =
+			has_acceleration_structure.then(|| acceleration_structure::Device::new(&instance, &device));
+
 		let surface = create_info
 			.instance_extensions
 			.contains(&surface::NAME)
@@ -312,6 +329,7 @@ pub fn ash_init_single_graphics_queue_with_push_next(
 				debug_utils,
 				surface,
 				swapchain,
+				acceleration_structure,
 				..AshExtensions::default()
 			},
 			destroy: Some(Box::new(move |create_info| {
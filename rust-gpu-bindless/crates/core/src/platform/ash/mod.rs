@@ -1,3 +1,5 @@
+/// Acceleration structure geometry and instance descriptions
+mod acceleration_structure;
 /// Conversion of `BufferAccess` and `ImageAccess`
 mod access_type;
 /// Extensions to ash directly. Usually functions that would return a `Vec<_>`, but are often called with only one
@@ -22,6 +24,7 @@ mod rendering;
 /// Extending tables with ash specific functionality, usually alloc methods taking ash CreateInfos
 mod table_ext;
 
+pub use acceleration_structure::*;
 pub use access_type::*;
 pub use ash_ext::*;
 pub use bindless::*;
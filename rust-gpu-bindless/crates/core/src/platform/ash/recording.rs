@@ -1,19 +1,26 @@
 use crate::descriptor::MutDescExt;
 use crate::descriptor::{
-	Bindless, BindlessAllocationScheme, BindlessBufferCreateInfo, BindlessBufferUsage, BufferSlot, ImageSlot,
+	AccelerationStructureBuildMode, AccelerationStructureSlot, Bindless, BindlessAllocationScheme,
+	BindlessBufferCreateInfo, BindlessBufferUsage, BlasTriangles, BufferAllocationError, BufferSlot, ImageSlot,
+	RCDescExt, TlasInstance,
 };
 use crate::pipeline::{
-	BindlessComputePipeline, BufferAccess, BufferAccessType, HasResourceContext, ImageAccess, ImageAccessType,
-	IndirectCommandReadable, MutBufferAccess, MutImageAccess, MutOrSharedBuffer, Recording, RecordingError,
-	RenderingAttachmentImage, TransferReadable, TransferWriteable,
+	BindlessComputePipeline, BufferAccess, BufferAccessType, BufferSliceCopy, HasResourceContext, ImageAccess,
+	ImageAccessType, IndirectCommandReadable, MutBufferAccess, MutImageAccess, MutOrSharedBuffer, Recording,
+	RecordingError, RenderingAttachmentImage, TransferReadable, TransferWriteable,
 };
 use crate::platform::ash::image_format::FormatExt;
-use crate::platform::ash::{Ash, AshExecution, AshPendingExecution};
+use crate::platform::ash::{
+	ASH_INSTANCE_WORDS, Ash, AshExecution, AshPendingExecution, ash_instance_geometry, ash_instance_words,
+	ash_triangle_geometry,
+};
 use crate::platform::{BindlessPipelinePlatform, RecordingContext, RecordingResourceContext};
 use ash::vk::{
-	BufferCopy, BufferImageCopy2, BufferMemoryBarrier2, CommandBuffer, CommandBufferBeginInfo, CommandBufferUsageFlags,
-	CopyBufferToImageInfo2, CopyImageToBufferInfo2, DependencyInfo, Fence, ImageAspectFlags, ImageMemoryBarrier2,
-	ImageSubresourceLayers, ImageSubresourceRange, MemoryBarrier2, Offset3D, PipelineBindPoint, PipelineStageFlags,
+	AccelerationStructureBuildGeometryInfoKHR, AccelerationStructureBuildRangeInfoKHR,
+	AccelerationStructureGeometryKHR, AccessFlags2, BufferCopy, BufferImageCopy2, BufferMemoryBarrier2, CommandBuffer,
+	CommandBufferBeginInfo, CommandBufferUsageFlags, CopyBufferToImageInfo2, CopyImageToBufferInfo2, DependencyInfo,
+	DeviceOrHostAddressKHR, Fence, ImageAspectFlags, ImageMemoryBarrier2, ImageSubresourceLayers,
+	ImageSubresourceRange, MemoryBarrier2, Offset3D, PipelineBindPoint, PipelineStageFlags, PipelineStageFlags2,
 	QUEUE_FAMILY_IGNORED, REMAINING_ARRAY_LAYERS, REMAINING_MIP_LEVELS, SubmitInfo, TimelineSemaphoreSubmitInfo,
 	WHOLE_SIZE,
 };
@@ -345,6 +352,84 @@ impl<'a> AshRecordingContext<'a> {
 		}
 	}
 
+	/// Build or update `dst` from `geometries`. A scratch buffer is allocated for the build, which like
+	/// [`Self::ash_push_param`] is kept alive until the execution finishes.
+	pub unsafe fn ash_build_acceleration_structure(
+		&mut self,
+		dst: &AccelerationStructureSlot<Ash>,
+		geometries: &[AccelerationStructureGeometryKHR],
+		ranges: &[AccelerationStructureBuildRangeInfoKHR],
+		mode: AccelerationStructureBuildMode,
+	) -> Result<(), AshRecordingError> {
+		unsafe {
+			let scratch_size = match mode {
+				AccelerationStructureBuildMode::Build => dst.sizes.build_scratch_size,
+				AccelerationStructureBuildMode::Update => dst.sizes.update_scratch_size,
+			};
+			let alignment = self
+				.bindless
+				.acceleration_structure_properties()
+				.expect("acceleration structure extension is not enabled")
+				.min_acceleration_structure_scratch_offset_alignment as u64;
+			let scratch = self.bindless.buffer().alloc_slice::<u8>(
+				&BindlessBufferCreateInfo {
+					usage: BindlessBufferUsage::STORAGE_BUFFER | BindlessBufferUsage::SHADER_DEVICE_ADDRESS,
+					name: "acceleration structure scratch",
+					allocation_scheme: BindlessAllocationScheme::AllocatorManaged,
+				},
+				(scratch_size + alignment) as usize,
+			)?;
+			let scratch_address = self
+				.bindless
+				.buffer_device_address(scratch.inner_slot().buffer)
+				.next_multiple_of(alignment);
+
+			// acceleration structure builds are not tracked by the access system, so conservatively synchronize
+			// with all previous and following commands
+			self.resource_context.push_memory_barrier(
+				MemoryBarrier2::default()
+					.src_stage_mask(PipelineStageFlags2::ALL_COMMANDS)
+					.src_access_mask(AccessFlags2::MEMORY_WRITE)
+					.dst_stage_mask(PipelineStageFlags2::ACCELERATION_STRUCTURE_BUILD_KHR)
+					.dst_access_mask(
+						AccessFlags2::ACCELERATION_STRUCTURE_READ_KHR
+							| AccessFlags2::ACCELERATION_STRUCTURE_WRITE_KHR
+							| AccessFlags2::SHADER_READ,
+					),
+			);
+			self.ash_flush();
+
+			let mut src = ash::vk::AccelerationStructureKHR::null();
+			if mode == AccelerationStructureBuildMode::Update {
+				src = dst.acceleration_structure;
+			}
+			let extension = self.bindless.extensions.acceleration_structure();
+			extension.cmd_build_acceleration_structures(
+				self.cmd,
+				&[AccelerationStructureBuildGeometryInfoKHR::default()
+					.ty(dst.ty.to_ash())
+					.flags(dst.flags.to_ash_build_flags())
+					.mode(mode.to_ash())
+					.src_acceleration_structure(src)
+					.dst_acceleration_structure(dst.acceleration_structure)
+					.geometries(geometries)
+					.scratch_data(DeviceOrHostAddressKHR {
+						device_address: scratch_address,
+					})],
+				&[ranges],
+			);
+
+			self.resource_context.push_memory_barrier(
+				MemoryBarrier2::default()
+					.src_stage_mask(PipelineStageFlags2::ACCELERATION_STRUCTURE_BUILD_KHR)
+					.src_access_mask(AccessFlags2::ACCELERATION_STRUCTURE_WRITE_KHR)
+					.dst_stage_mask(PipelineStageFlags2::ALL_COMMANDS)
+					.dst_access_mask(AccessFlags2::ACCELERATION_STRUCTURE_READ_KHR | AccessFlags2::MEMORY_READ),
+			);
+			Ok(())
+		}
+	}
+
 	pub unsafe fn ash_end(mut self) -> Result<CommandBuffer, AshRecordingError> {
 		unsafe {
 			self.ash_flush();
@@ -426,6 +511,38 @@ unsafe impl<'a> RecordingContext<'a, Ash> for AshRecordingContext<'a> {
 		}
 	}
 
+	unsafe fn copy_buffer_to_buffer_slice_regions<
+		T: BufferStruct,
+		SA: BufferAccessType + TransferReadable,
+		DA: BufferAccessType + TransferWriteable,
+	>(
+		&mut self,
+		src: impl MutOrSharedBuffer<Ash, [T], SA>,
+		dst: &MutBufferAccess<Ash, [T], DA>,
+		regions: &[BufferSliceCopy],
+	) -> Result<(), AshRecordingError> {
+		unsafe {
+			if regions.is_empty() {
+				return Ok(());
+			}
+			self.ash_flush();
+			let device = &self.bindless.platform.device;
+			let src = src.inner_slot();
+			let dst = dst.inner_slot();
+			let stride = size_of::<T::Transfer>() as u64;
+			let regions = regions
+				.iter()
+				.map(|region| BufferCopy {
+					src_offset: region.src_start as u64 * stride,
+					dst_offset: region.dst_start as u64 * stride,
+					size: region.len as u64 * stride,
+				})
+				.collect::<SmallVec<[_; 8]>>();
+			device.cmd_copy_buffer(self.cmd, src.buffer, dst.buffer, &regions);
+			Ok(())
+		}
+	}
+
 	unsafe fn copy_buffer_to_image<
 		BT: BufferContent + ?Sized,
 		BA: BufferAccessType + TransferReadable,
@@ -435,6 +552,7 @@ unsafe impl<'a> RecordingContext<'a, Ash> for AshRecordingContext<'a> {
 		&mut self,
 		src_buffer: &MutBufferAccess<Ash, BT, BA>,
 		dst_image: &MutImageAccess<Ash, IT, IA>,
+		mip_level: u32,
 	) -> Result<(), AshRecordingError> {
 		unsafe {
 			self.ash_flush();
@@ -453,12 +571,12 @@ unsafe impl<'a> RecordingContext<'a, Ash> for AshRecordingContext<'a> {
 						buffer_image_height: 0,
 						image_subresource: ImageSubresourceLayers {
 							aspect_mask: image.format.aspect(),
-							mip_level: 0,
+							mip_level,
 							base_array_layer: 0,
 							layer_count: image.array_layers,
 						},
 						image_offset: Offset3D::default(),
-						image_extent: image.extent.into(),
+						image_extent: image.extent.mip(mip_level).into(),
 						..Default::default()
 					}]),
 			);
@@ -576,6 +694,71 @@ unsafe impl<'a> RecordingContext<'a, Ash> for AshRecordingContext<'a> {
 			Ok(())
 		}
 	}
+
+	unsafe fn build_blas(
+		&mut self,
+		blas: &AccelerationStructureSlot<Ash>,
+		geometries: &[BlasTriangles<Ash>],
+		mode: AccelerationStructureBuildMode,
+	) -> Result<(), AshRecordingError> {
+		unsafe {
+			let ash_geometries = geometries
+				.iter()
+				.map(|triangles| {
+					ash_triangle_geometry(
+						triangles,
+						self.bindless.buffer_device_address(triangles.vertices.buffer),
+						self.bindless.buffer_device_address(triangles.indices.buffer),
+					)
+				})
+				.collect::<SmallVec<[_; 1]>>();
+			let ranges = geometries
+				.iter()
+				.map(|triangles| {
+					AccelerationStructureBuildRangeInfoKHR::default().primitive_count(triangles.triangle_count)
+				})
+				.collect::<SmallVec<[_; 1]>>();
+			self.ash_build_acceleration_structure(blas, &ash_geometries, &ranges, mode)
+		}
+	}
+
+	unsafe fn build_tlas(
+		&mut self,
+		tlas: &AccelerationStructureSlot<Ash>,
+		instances: &[TlasInstance<Ash>],
+		mode: AccelerationStructureBuildMode,
+	) -> Result<(), AshRecordingError> {
+		unsafe {
+			let mut ash_instances = instances
+				.iter()
+				.map(|instance| {
+					ash_instance_words(
+						instance.world_from_local,
+						instance.custom_index,
+						instance.mask,
+						instance.blas.inner_slot().device_address,
+					)
+				})
+				.collect::<Vec<_>>();
+			// buffers must not be empty
+			if ash_instances.is_empty() {
+				ash_instances.push([0; ASH_INSTANCE_WORDS]);
+			}
+			// like scratch buffers, kept alive until the execution finishes
+			let instance_buffer = self.bindless.buffer().alloc_from_iter(
+				&BindlessBufferCreateInfo {
+					usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::ACCELERATION_STRUCTURE_BUILD_INPUT,
+					name: "tlas instances",
+					allocation_scheme: BindlessAllocationScheme::AllocatorManaged,
+				},
+				ash_instances,
+			)?;
+			let geometry =
+				ash_instance_geometry(self.bindless.buffer_device_address(instance_buffer.inner_slot().buffer));
+			let range = AccelerationStructureBuildRangeInfoKHR::default().primitive_count(instances.len() as u32);
+			self.ash_build_acceleration_structure(tlas, &[geometry], &[range], mode)
+		}
+	}
 }
 
 #[derive(Error)]
@@ -584,6 +767,8 @@ pub enum AshRecordingError {
 	Vk(#[from] ash::vk::Result),
 	#[error("No barriers must be inserted while rendering: {collector:?}")]
 	BarrierWhileRendering { collector: Box<AshBarrierCollector> },
+	#[error("Buffer Allocation Error: {0}")]
+	BufferAllocation(#[from] BufferAllocationError<Ash>),
 }
 
 impl Debug for AshRecordingError {
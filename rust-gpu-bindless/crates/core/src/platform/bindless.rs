@@ -1,9 +1,10 @@
 use crate::backing::range_set::DescriptorIndexIterator;
 use crate::backing::table::DrainFlushQueue;
 use crate::descriptor::{
-	Bindless, BindlessBufferCreateInfo, BindlessImageCreateInfo, BindlessSamplerCreateInfo, BufferAllocationError,
-	BufferInterface, BufferSlot, DescriptorCounts, ImageAllocationError, ImageInterface, SamplerAllocationError,
-	SamplerInterface, WeakBindless,
+	AccelerationStructureAllocationError, AccelerationStructureBuildSizes, AccelerationStructureInterface,
+	AccelerationStructureSizing, Bindless, BindlessAccelerationStructureCreateInfo, BindlessBufferCreateInfo,
+	BindlessImageCreateInfo, BindlessSamplerCreateInfo, BufferAllocationError, BufferInterface, BufferSlot,
+	DescriptorCounts, ImageAllocationError, ImageInterface, SamplerAllocationError, SamplerInterface, WeakBindless,
 };
 use rust_gpu_bindless_shaders::descriptor::ImageType;
 use std::error::Error;
@@ -16,13 +17,15 @@ pub unsafe trait BindlessPlatform: Sized + Send + Sync + 'static {
 	type Buffer: 'static + Send + Sync;
 	type Image: 'static + Send + Sync;
 	type Sampler: 'static + Send + Sync;
+	type AccelerationStructure: 'static + Send + Sync;
 	type AllocationError: 'static
 		+ Error
 		+ Send
 		+ Sync
 		+ Into<BufferAllocationError<Self>>
 		+ Into<ImageAllocationError<Self>>
-		+ Into<SamplerAllocationError<Self>>;
+		+ Into<SamplerAllocationError<Self>>
+		+ Into<AccelerationStructureAllocationError<Self>>;
 	type BindlessDescriptorSet: 'static + Send + Sync;
 	type PendingExecution: PendingExecution<Self>;
 
@@ -46,7 +49,7 @@ pub unsafe trait BindlessPlatform: Sized + Send + Sync + 'static {
 	/// Bindless should start to shut down. No further executions may happen after.
 	unsafe fn bindless_shutdown(&self, bindless: &Bindless<Self>);
 
-	/// Update the [`BindlessDescriptorSet`] with these changed buffers, images and samplers.
+	/// Update the [`BindlessDescriptorSet`] with these changed buffers, images, samplers and acceleration structures.
 	///
 	/// # Safety
 	/// Must be called while holding the associated [`TableSync`]'s [`FlushGuard`].
@@ -59,6 +62,7 @@ pub unsafe trait BindlessPlatform: Sized + Send + Sync + 'static {
 		buffers: DrainFlushQueue<BufferInterface<Self>>,
 		images: DrainFlushQueue<ImageInterface<Self>>,
 		samplers: DrainFlushQueue<SamplerInterface<Self>>,
+		acceleration_structures: DrainFlushQueue<AccelerationStructureInterface<Self>>,
 	);
 
 	unsafe fn destroy_descriptor_set(&self, set: Self::BindlessDescriptorSet);
@@ -79,6 +83,14 @@ pub unsafe trait BindlessPlatform: Sized + Send + Sync + 'static {
 		create_info: &BindlessSamplerCreateInfo,
 	) -> Result<Self::Sampler, Self::AllocationError>;
 
+	/// Allocate an acceleration structure large enough to be built from any geometry within the limits of `sizing`,
+	/// returning the sizes required to build it.
+	unsafe fn alloc_acceleration_structure(
+		&self,
+		create_info: &BindlessAccelerationStructureCreateInfo,
+		sizing: AccelerationStructureSizing<'_, Self>,
+	) -> Result<(Self::AccelerationStructure, AccelerationStructureBuildSizes), Self::AllocationError>;
+
 	/// Turn a mapped Buffer into a Slab. You may assume that the buffer is mappable, aka. has either
 	/// [`BindlessBufferUsage::MAP_WRITE`] or [`BindlessBufferUsage::MAP_READ`]. You also have exclusive access
 	/// to the Buffer.
@@ -111,6 +123,17 @@ pub unsafe trait BindlessPlatform: Sized + Send + Sync + 'static {
 		global_descriptor_set: &Self::BindlessDescriptorSet,
 		samplers: impl DescriptorIndexIterator<'a, SamplerInterface<Self>>,
 	);
+
+	/// Destroy specified acceleration structures. You have exclusive access to the associated
+	/// [`AccelerationStructureSlot`]s, even if they are just passed by standard reference. After this method call
+	/// returns, the [`AccelerationStructureSlot`] will be dropped and otherwise not accessed anymore.
+	///
+	/// [`AccelerationStructureSlot`]: crate::descriptor::AccelerationStructureSlot
+	unsafe fn destroy_acceleration_structures<'a>(
+		&self,
+		global_descriptor_set: &Self::BindlessDescriptorSet,
+		acceleration_structures: impl DescriptorIndexIterator<'a, AccelerationStructureInterface<Self>>,
+	);
 }
 
 pub unsafe trait PendingExecution<P: BindlessPlatform>:
@@ -1,7 +1,10 @@
-use crate::descriptor::{Bindless, BufferSlot, ImageSlot};
+use crate::descriptor::{
+	AccelerationStructureBuildMode, AccelerationStructureSlot, Bindless, BlasTriangles, BufferSlot, ImageSlot,
+	TlasInstance,
+};
 use crate::pipeline::{
 	BindlessComputePipeline, BindlessGraphicsPipeline, BindlessMeshGraphicsPipeline, BufferAccess, BufferAccessType,
-	ColorAttachment, DepthStencilAttachment, DrawIndexedIndirectCommand, DrawIndirectCommand,
+	BufferSliceCopy, ColorAttachment, DepthStencilAttachment, DrawIndexedIndirectCommand, DrawIndirectCommand,
 	GraphicsPipelineCreateInfo, HasResourceContext, ImageAccess, ImageAccessType, IndexReadable, IndexTypeTrait,
 	IndirectCommandReadable, MeshGraphicsPipelineCreateInfo, MutBufferAccess, MutImageAccess, MutOrSharedBuffer,
 	Recording, RecordingError, RenderPassFormat, RenderingAttachment, RenderingAttachmentImage, TransferReadable,
@@ -80,8 +83,21 @@ pub unsafe trait RecordingContext<'a, P: BindlessPipelinePlatform>: HasResourceC
 		dst: &MutBufferAccess<P, [T], DA>,
 	) -> Result<(), P::RecordingError>;
 
-	/// Copy data from a buffer to an image. It is assumed that the image data is tightly packed within the buffer.
-	/// Partial copies and copying to mips other than mip 0 is not yet possible.
+	/// Copy `regions` of one buffer of a slice to another buffer of the same slice. The regions must have been bounds
+	/// checked.
+	unsafe fn copy_buffer_to_buffer_slice_regions<
+		T: BufferStruct,
+		SA: BufferAccessType + TransferReadable,
+		DA: BufferAccessType + TransferWriteable,
+	>(
+		&mut self,
+		src: impl MutOrSharedBuffer<P, [T], SA>,
+		dst: &MutBufferAccess<P, [T], DA>,
+		regions: &[BufferSliceCopy],
+	) -> Result<(), P::RecordingError>;
+
+	/// Copy data from a buffer to the mip level `mip_level` of an image. It is assumed that the image data is tightly
+	/// packed within the buffer. Partial copies are not yet possible.
 	unsafe fn copy_buffer_to_image<
 		BT: BufferContent + ?Sized,
 		BA: BufferAccessType + TransferReadable,
@@ -91,6 +107,7 @@ pub unsafe trait RecordingContext<'a, P: BindlessPipelinePlatform>: HasResourceC
 		&mut self,
 		src: &MutBufferAccess<P, BT, BA>,
 		dst: &MutImageAccess<P, IT, IA>,
+		mip_level: u32,
 	) -> Result<(), P::RecordingError>;
 
 	/// Copy data from an image to a buffer. It is assumed that the image data is tightly packed within the buffer.
@@ -131,6 +148,22 @@ pub unsafe trait RecordingContext<'a, P: BindlessPipelinePlatform>: HasResourceC
 		indirect: impl MutOrSharedBuffer<P, [u32; 3], A>,
 		param: T,
 	) -> Result<(), P::RecordingError>;
+
+	/// Build or update a bottom level acceleration structure, arguments have already been validated
+	unsafe fn build_blas(
+		&mut self,
+		blas: &AccelerationStructureSlot<P>,
+		geometries: &[BlasTriangles<P>],
+		mode: AccelerationStructureBuildMode,
+	) -> Result<(), P::RecordingError>;
+
+	/// Build or update a top level acceleration structure, arguments have already been validated
+	unsafe fn build_tlas(
+		&mut self,
+		tlas: &AccelerationStructureSlot<P>,
+		instances: &[TlasInstance<P>],
+		mode: AccelerationStructureBuildMode,
+	) -> Result<(), P::RecordingError>;
 }
 
 pub unsafe trait RecordingResourceContext<P: BindlessPipelinePlatform>: 'static {
@@ -101,12 +101,14 @@ pub fn bindless(attr: proc_macro::TokenStream, item: proc_macro::TokenStream) ->
 
 	// the fn_ident_inner *could* be put within the entry point fn,
 	// but putting it outside significantly improves editor performance in rustrover
+	// on the CPU, the entry point is just the inner fn, to be called per invocation with `CpuDescriptors`
 	Ok(quote! {
 		#[allow(non_camel_case_types)]
 		#vis type #entry_shader_type_ident = #entry_shader_type;
 		#[allow(non_camel_case_types)]
 		#vis type #param_type_ident = #param_type;
 
+		#[cfg(target_arch = "spirv")]
 		#[#crate_shaders::spirv(#attr)]
 		#[allow(clippy::too_many_arguments)]
 		#vis fn #entry_ident(#entry_args) {
@@ -114,8 +116,13 @@ pub fn bindless(attr: proc_macro::TokenStream, item: proc_macro::TokenStream) ->
 			#inner_ident(#inner_params);
 		}
 
+		#[cfg(target_arch = "spirv")]
 		#[allow(clippy::too_many_arguments)]
 		fn #inner_ident(#inner_args) #inner_block
+
+		#[cfg(not(target_arch = "spirv"))]
+		#[allow(clippy::too_many_arguments)]
+		#vis fn #entry_ident(#inner_args) #inner_block
 	})
 }
 
@@ -164,6 +171,7 @@ fn gen_bindless_descriptors(context: &mut BindlessContext) -> Result<SymDescript
 	let buffers = format_ident!("__bindless_buffers");
 	let buffers_mut = format_ident!("__bindless_buffers_mut");
 	let samplers = format_ident!("__bindless_samplers");
+	let acceleration_structures = format_ident!("__bindless_acceleration_structures");
 	let descriptors = format_ident!("__bindless_descriptors");
 
 	let image_args;
@@ -185,12 +193,24 @@ fn gen_bindless_descriptors(context: &mut BindlessContext) -> Result<SymDescript
 	}
 	standard_image_types!(make_image_args);
 
+	let (acceleration_structure_args, acceleration_structure_values) = if cfg!(feature = "ray_query") {
+		(
+			quote! {
+				#[spirv(descriptor_set = 0, binding = 4)] #acceleration_structures: &#crate_shaders::spirv_std::RuntimeArray<#crate_shaders::descriptor::AccelerationStructure>,
+			},
+			quote!(acceleration_structures: #acceleration_structures,),
+		)
+	} else {
+		(TokenStream::new(), TokenStream::new())
+	};
+
 	// these "plain" spirv here are correct, as they are non-macro attributes to function arguments, not proc macros!
 	context.entry_args.append_tokens(quote! {
 			#[spirv(descriptor_set = 0, binding = 0, storage_buffer)] #buffers: &#crate_shaders::spirv_std::RuntimeArray<#crate_shaders::spirv_std::TypedBuffer<[u32]>>,
 			#[spirv(descriptor_set = 0, binding = 0, storage_buffer)] #buffers_mut: &mut #crate_shaders::spirv_std::RuntimeArray<#crate_shaders::spirv_std::TypedBuffer<[u32]>>,
 			#image_args
 			#[spirv(descriptor_set = 0, binding = 3)] #samplers: &#crate_shaders::spirv_std::RuntimeArray<#crate_shaders::descriptor::Sampler>,
+			#acceleration_structure_args
 		});
 	context.entry_content.append_tokens(quote! {
 		let #descriptors = #crate_shaders::descriptor::Descriptors {
@@ -198,6 +218,7 @@ fn gen_bindless_descriptors(context: &mut BindlessContext) -> Result<SymDescript
 			buffers_mut: #buffers_mut,
 			#image_values
 			samplers: #samplers,
+			#acceleration_structure_values
 			meta: #crate_shaders::buffer_content::Metadata {},
 		};
 	});
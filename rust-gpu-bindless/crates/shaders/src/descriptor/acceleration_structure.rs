@@ -0,0 +1,138 @@
+use crate::descriptor::descriptor_content::DescContent;
+#[cfg(target_arch = "spirv")]
+use core::marker::PhantomData;
+use glam::{Vec2, Vec3};
+
+pub use spirv_std::ray_tracing::AccelerationStructure;
+
+impl DescContent for AccelerationStructure {}
+
+/// An [`AccelerationStructure`] accessed through [`Descriptors`], which rays can be traced against using ray queries.
+///
+/// Requires the `ray_query` feature of this crate as well as the `RayQueryKHR` capability and `SPV_KHR_ray_query`
+/// extension on the shader crate. Only available on the GPU, as the CPU backend can't trace rays.
+///
+/// [`Descriptors`]: crate::descriptor::Descriptors
+#[cfg(target_arch = "spirv")]
+#[derive(Copy, Clone)]
+pub struct AccelerationStructureAccess<'a> {
+	inner: &'a AccelerationStructure,
+	_phantom: PhantomData<&'a ()>,
+}
+
+/// A ray to trace against an [`AccelerationStructure`], only intersections between `t_min` and `t_max` are reported.
+#[derive(Copy, Clone, Debug)]
+pub struct RayQueryRay {
+	pub origin: Vec3,
+	pub t_min: f32,
+	pub direction: Vec3,
+	pub t_max: f32,
+	/// Only instances whose mask shares a bit with the cull mask are intersected
+	pub cull_mask: u32,
+}
+
+impl RayQueryRay {
+	pub fn new(origin: Vec3, t_min: f32, direction: Vec3, t_max: f32) -> Self {
+		Self {
+			origin,
+			t_min,
+			direction,
+			t_max,
+			cull_mask: !0,
+		}
+	}
+}
+
+/// The closest triangle hit by a [`RayQueryRay`]
+#[derive(Copy, Clone, Debug)]
+pub struct RayQueryHit {
+	/// `t` of the hit along the ray, or [`f32::INFINITY`] if nothing was hit
+	pub t: f32,
+	/// the custom index of the instance that was hit, as declared when building the top level acceleration structure
+	pub instance_custom_index: u32,
+	/// the index of the instance that was hit
+	pub instance_id: u32,
+	/// the index of the geometry within the bottom level acceleration structure
+	pub geometry_index: u32,
+	/// the index of the triangle within its geometry
+	pub primitive_index: u32,
+	/// barycentrics of the hit, relative to the second and third vertex of the triangle
+	pub barycentrics: Vec2,
+}
+
+impl RayQueryHit {
+	pub const MISS: Self = Self {
+		t: f32::INFINITY,
+		instance_custom_index: 0,
+		instance_id: 0,
+		geometry_index: 0,
+		primitive_index: 0,
+		barycentrics: Vec2::ZERO,
+	};
+
+	pub fn is_hit(&self) -> bool {
+		self.t != f32::INFINITY
+	}
+}
+
+#[cfg(target_arch = "spirv")]
+impl<'a> AccelerationStructureAccess<'a> {
+	pub(crate) fn new(inner: &'a AccelerationStructure) -> Self {
+		Self {
+			inner,
+			_phantom: PhantomData,
+		}
+	}
+
+	pub fn inner(&self) -> &'a AccelerationStructure {
+		self.inner
+	}
+
+	/// Trace `ray` treating all geometry as opaque and return the closest hit
+	pub fn trace_closest(&self, ray: RayQueryRay) -> RayQueryHit {
+		unsafe {
+			use spirv_std::ray_tracing::{CommittedIntersection, RayFlags};
+			spirv_std::ray_query!(let mut query);
+			query.initialize(
+				self.inner,
+				RayFlags::OPAQUE,
+				ray.cull_mask,
+				ray.origin,
+				ray.t_min,
+				ray.direction,
+				ray.t_max,
+			);
+			while query.proceed() {}
+			match query.get_committed_intersection_type() {
+				CommittedIntersection::Triangle => RayQueryHit {
+					t: query.get_committed_intersection_t(),
+					instance_custom_index: query.get_committed_intersection_instance_custom_index(),
+					instance_id: query.get_committed_intersection_instance_id(),
+					geometry_index: query.get_committed_intersection_geometry_index(),
+					primitive_index: query.get_committed_intersection_primitive_index(),
+					barycentrics: query.get_committed_intersection_barycentrics(),
+				},
+				_ => RayQueryHit::MISS,
+			}
+		}
+	}
+
+	/// Trace `ray` treating all geometry as opaque and return whether anything was hit, terminating on the first hit
+	pub fn trace_any(&self, ray: RayQueryRay) -> bool {
+		unsafe {
+			use spirv_std::ray_tracing::{CommittedIntersection, RayFlags};
+			spirv_std::ray_query!(let mut query);
+			query.initialize(
+				self.inner,
+				RayFlags::OPAQUE | RayFlags::TERMINATE_ON_FIRST_HIT,
+				ray.cull_mask,
+				ray.origin,
+				ray.t_min,
+				ray.direction,
+				ray.t_max,
+			);
+			while query.proceed() {}
+			!matches!(query.get_committed_intersection_type(), CommittedIntersection::None)
+		}
+	}
+}
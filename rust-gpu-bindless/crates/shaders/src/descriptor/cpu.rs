@@ -0,0 +1,507 @@
+//! A host memory backend of [`Descriptors`], to execute shader code on the CPU. On any target but spirv, `Descriptors`
+//! is a view into a [`CpuDescriptors`] owning all buffers, images and samplers, and the `#[bindless]` macro emits each
+//! entry point as a plain function that can be called once per invocation.
+//!
+//! Intended for unit tests of shader code, it is neither fast nor does it support any shader intrinsics like atomics,
+//! barriers or ray queries.
+
+use crate::buffer_content::{BufferContent, BufferStruct, Metadata, MetadataCpuInterface};
+use crate::descriptor::image_types::standard_image_types;
+use crate::descriptor::reference::{AliveDescRef, Desc};
+use crate::descriptor::{
+	Buffer, BufferSlice, DescContent, DescriptorAccess, DescriptorId, DescriptorIndex, DescriptorType,
+	DescriptorVersion, Image, ImageType, MutBuffer, MutBufferSlice, MutImage, Sampler, StrongDesc, TransientAccess,
+};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::Deref;
+use glam::{IVec2, IVec3, IVec4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4};
+
+const CPU_BUFFER_TYPE: u32 = 0;
+const CPU_IMAGE_TYPE: u32 = 1;
+const CPU_SAMPLER_TYPE: u32 = 2;
+
+macro_rules! decl_cpu_descriptors {
+    ($($image:ident: $sampled:ident $storage:ident,)*) => {
+		pub struct Descriptors<'a> {
+			pub buffers: &'a [CpuBuffer],
+			$(
+				pub $storage: &'a [<crate::descriptor::$image as ImageType>::StorageSpvImage],
+				pub $sampled: &'a [<crate::descriptor::$image as ImageType>::SampledSpvImage],
+			)*
+			pub samplers: &'a [CpuSampler],
+			pub meta: Metadata,
+		}
+
+		/// Owns all resources in host memory, see the [module level docs](self).
+		#[derive(Default)]
+		pub struct CpuDescriptors {
+			buffers: Vec<CpuBuffer>,
+			$($sampled: Vec<<crate::descriptor::$image as ImageType>::SampledSpvImage>,)*
+			samplers: Vec<CpuSampler>,
+		}
+
+		impl CpuDescriptors {
+			/// Access to all resources, to be passed to shader code
+			pub fn descriptors(&self) -> Descriptors<'_> {
+				Descriptors {
+					buffers: &self.buffers,
+					$(
+						$storage: &self.$sampled,
+						$sampled: &self.$sampled,
+					)*
+					samplers: &self.samplers,
+					meta: Metadata,
+				}
+			}
+		}
+
+		$(
+			impl CpuImageType for crate::descriptor::$image {
+				fn table(descriptors: &mut CpuDescriptors) -> &mut Vec<Self::SampledSpvImage> {
+					&mut descriptors.$sampled
+				}
+			}
+
+			impl<'a> DescriptorAccess<'a, MutImage<crate::descriptor::$image>> for &'a Descriptors<'_> {
+				type AccessType = &'a <crate::descriptor::$image as ImageType>::StorageSpvImage;
+
+				fn access(self, desc: &Desc<impl AliveDescRef, MutImage<crate::descriptor::$image>>) -> Self::AccessType {
+					&self.$storage[desc.id().index().to_usize()]
+				}
+			}
+
+			impl<'a> DescriptorAccess<'a, Image<crate::descriptor::$image>> for &'a Descriptors<'_> {
+				type AccessType = &'a <crate::descriptor::$image as ImageType>::SampledSpvImage;
+
+				fn access(self, desc: &Desc<impl AliveDescRef, Image<crate::descriptor::$image>>) -> Self::AccessType {
+					&self.$sampled[desc.id().index().to_usize()]
+				}
+			}
+		)*
+	};
+}
+standard_image_types!(decl_cpu_descriptors);
+
+impl<'a, T: ?Sized + BufferContent + 'static> DescriptorAccess<'a, Buffer<T>> for &'a Descriptors<'_> {
+	type AccessType = BufferSlice<'a, T>;
+
+	fn access(self, desc: &Desc<impl AliveDescRef, Buffer<T>>) -> Self::AccessType {
+		unsafe { BufferSlice::from_slice(self.buffers[desc.id().index().to_usize()].as_slice(), self.meta) }
+	}
+}
+
+impl<'a, T: ?Sized + BufferContent + 'static> DescriptorAccess<'a, MutBuffer<T>> for &'a mut Descriptors<'_> {
+	type AccessType = MutBufferSlice<'a, T>;
+
+	fn access(self, desc: &Desc<impl AliveDescRef, MutBuffer<T>>) -> Self::AccessType {
+		unsafe { MutBufferSlice::from_mut_slice(self.buffers[desc.id().index().to_usize()].as_mut_slice(), self.meta) }
+	}
+}
+
+impl<'a> DescriptorAccess<'a, Sampler> for &'a Descriptors<'_> {
+	type AccessType = CpuSampler;
+
+	fn access(self, desc: &Desc<impl AliveDescRef, Sampler>) -> Self::AccessType {
+		self.samplers[desc.id().index().to_usize()]
+	}
+}
+
+// Safety: resources are never freed and every access is bounds checked
+unsafe impl<'a> TransientAccess<'a> for CpuDescriptors {}
+
+impl CpuDescriptors {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn alloc_buffer<T: BufferStruct>(&mut self, t: T) -> StrongDesc<Buffer<T>> {
+		self.push_buffer(CpuBuffer::from_structs([t]))
+	}
+
+	pub fn alloc_mut_buffer<T: BufferStruct>(&mut self, t: T) -> StrongDesc<MutBuffer<T>> {
+		self.push_buffer(CpuBuffer::from_structs([t]))
+	}
+
+	pub fn alloc_slice<T: BufferStruct>(&mut self, iter: impl IntoIterator<Item = T>) -> StrongDesc<Buffer<[T]>> {
+		self.push_buffer(CpuBuffer::from_structs(iter))
+	}
+
+	pub fn alloc_mut_slice<T: BufferStruct>(
+		&mut self,
+		iter: impl IntoIterator<Item = T>,
+	) -> StrongDesc<MutBuffer<[T]>> {
+		self.push_buffer(CpuBuffer::from_structs(iter))
+	}
+
+	pub fn alloc_image<T: CpuImageType>(&mut self, image: T::SampledSpvImage) -> StrongDesc<Image<T>> {
+		self.push_image::<T, _>(image)
+	}
+
+	pub fn alloc_mut_image<T: CpuImageType>(&mut self, image: T::SampledSpvImage) -> StrongDesc<MutImage<T>> {
+		self.push_image::<T, _>(image)
+	}
+
+	pub fn alloc_sampler(&mut self, sampler: CpuSampler) -> StrongDesc<Sampler> {
+		self.samplers.push(sampler);
+		unsafe { StrongDesc::new(cpu_descriptor_id(CPU_SAMPLER_TYPE, self.samplers.len() - 1)) }
+	}
+
+	fn push_buffer<C: DescContent>(&mut self, buffer: CpuBuffer) -> StrongDesc<C> {
+		self.buffers.push(buffer);
+		unsafe { StrongDesc::new(cpu_descriptor_id(CPU_BUFFER_TYPE, self.buffers.len() - 1)) }
+	}
+
+	fn push_image<T: CpuImageType, C: DescContent>(&mut self, image: T::SampledSpvImage) -> StrongDesc<C> {
+		let table = T::table(self);
+		table.push(image);
+		unsafe { StrongDesc::new(cpu_descriptor_id(CPU_IMAGE_TYPE, table.len() - 1)) }
+	}
+}
+
+fn cpu_descriptor_id(desc_type: u32, index: usize) -> DescriptorId {
+	unsafe {
+		DescriptorId::new(
+			DescriptorType::new_unchecked(desc_type),
+			DescriptorIndex::new(index as u32).expect("too many descriptors"),
+			DescriptorVersion::new_unchecked(0),
+		)
+	}
+}
+
+/// An [`ImageType`] that [`CpuDescriptors`] has a table for
+pub trait CpuImageType: ImageType {
+	fn table(descriptors: &mut CpuDescriptors) -> &mut Vec<Self::SampledSpvImage>;
+}
+
+/// A buffer in host memory, which may be mutated while shared just like a buffer on the GPU
+pub struct CpuBuffer(Box<[Cell<u32>]>);
+
+impl CpuBuffer {
+	fn from_structs<T: BufferStruct>(iter: impl IntoIterator<Item = T>) -> Self {
+		let iter = iter.into_iter();
+		let mut buffer = Vec::with_capacity(iter.size_hint().0 * mem::size_of::<T::Transfer>() / 4);
+		for t in iter {
+			let transfer = unsafe { t.write_cpu(&mut CpuMetadata(Metadata)) };
+			let offset = buffer.len();
+			buffer.resize(offset + mem::size_of::<T::Transfer>().div_ceil(4), Cell::new(0));
+			unsafe {
+				buffer[offset..]
+					.as_mut_ptr()
+					.cast::<T::Transfer>()
+					.write_unaligned(transfer);
+			}
+		}
+		Self(buffer.into_boxed_slice())
+	}
+
+	/// # Safety
+	/// The buffer must not be written to while the returned slice is alive.
+	pub unsafe fn as_slice(&self) -> &[u32] {
+		unsafe { core::slice::from_raw_parts(self.0.as_ptr().cast::<u32>(), self.0.len()) }
+	}
+
+	/// # Safety
+	/// Accesses to the buffer must not alias with the returned slice.
+	#[allow(clippy::mut_from_ref)]
+	pub unsafe fn as_mut_slice(&self) -> &mut [u32] {
+		unsafe { core::slice::from_raw_parts_mut(self.0.as_ptr().cast::<u32>().cast_mut(), self.0.len()) }
+	}
+}
+
+struct CpuMetadata(Metadata);
+
+impl Deref for CpuMetadata {
+	type Target = Metadata;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+unsafe impl MetadataCpuInterface for CpuMetadata {
+	fn visit_strong_descriptor<C: DescContent>(&mut self, _desc: StrongDesc<C>) {}
+}
+
+/// The sampled type of a [`CpuImage`], converting its texels from and to their bit representation
+pub trait CpuSampleType: Copy + Send + Sync + 'static {
+	type Vec4: Copy;
+
+	fn from_bits(bits: [u32; 4]) -> Self::Vec4;
+
+	fn to_bits(texel: Self::Vec4) -> [u32; 4];
+}
+
+impl CpuSampleType for f32 {
+	type Vec4 = Vec4;
+
+	fn from_bits(bits: [u32; 4]) -> Self::Vec4 {
+		Vec4::from_array(bits.map(f32::from_bits))
+	}
+
+	fn to_bits(texel: Self::Vec4) -> [u32; 4] {
+		texel.to_array().map(f32::to_bits)
+	}
+}
+
+impl CpuSampleType for u32 {
+	type Vec4 = UVec4;
+
+	fn from_bits(bits: [u32; 4]) -> Self::Vec4 {
+		UVec4::from_array(bits)
+	}
+
+	fn to_bits(texel: Self::Vec4) -> [u32; 4] {
+		texel.to_array()
+	}
+}
+
+impl CpuSampleType for i32 {
+	type Vec4 = IVec4;
+
+	fn from_bits(bits: [u32; 4]) -> Self::Vec4 {
+		IVec4::from_array(bits.map(|b| b as i32))
+	}
+
+	fn to_bits(texel: Self::Vec4) -> [u32; 4] {
+		texel.to_array().map(|t| t as u32)
+	}
+}
+
+/// Integer texel coordinates of a [`CpuImage`]
+pub trait CpuImageCoordinate: Copy {
+	fn to_uvec3(self) -> UVec3;
+}
+
+impl CpuImageCoordinate for u32 {
+	fn to_uvec3(self) -> UVec3 {
+		UVec3::new(self, 0, 0)
+	}
+}
+
+impl CpuImageCoordinate for i32 {
+	fn to_uvec3(self) -> UVec3 {
+		UVec3::new(self as u32, 0, 0)
+	}
+}
+
+impl CpuImageCoordinate for UVec2 {
+	fn to_uvec3(self) -> UVec3 {
+		self.extend(0)
+	}
+}
+
+impl CpuImageCoordinate for IVec2 {
+	fn to_uvec3(self) -> UVec3 {
+		self.as_uvec2().extend(0)
+	}
+}
+
+impl CpuImageCoordinate for UVec3 {
+	fn to_uvec3(self) -> UVec3 {
+		self
+	}
+}
+
+impl CpuImageCoordinate for IVec3 {
+	fn to_uvec3(self) -> UVec3 {
+		self.as_uvec3()
+	}
+}
+
+/// Normalized coordinates to sample a [`CpuImage`] with
+pub trait CpuSampleCoordinate: Copy {
+	/// the number of dimensions filtered over
+	const DIMENSIONS: usize;
+
+	fn to_vec3(self) -> Vec3;
+}
+
+impl CpuSampleCoordinate for f32 {
+	const DIMENSIONS: usize = 1;
+
+	fn to_vec3(self) -> Vec3 {
+		Vec3::new(self, 0., 0.)
+	}
+}
+
+impl CpuSampleCoordinate for Vec2 {
+	const DIMENSIONS: usize = 2;
+
+	fn to_vec3(self) -> Vec3 {
+		self.extend(0.)
+	}
+}
+
+impl CpuSampleCoordinate for Vec3 {
+	const DIMENSIONS: usize = 3;
+
+	fn to_vec3(self) -> Vec3 {
+		self
+	}
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum CpuFilter {
+	#[default]
+	Nearest,
+	Linear,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum CpuAddressMode {
+	#[default]
+	Repeat,
+	MirroredRepeat,
+	ClampToEdge,
+	/// texels outside the image are transparent black
+	ClampToBorder,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CpuSampler {
+	pub filter: CpuFilter,
+	pub address_mode: CpuAddressMode,
+}
+
+impl CpuSampler {
+	/// The texel at `coordinate` along an axis of `size` texels, or None if it lies in the border
+	fn address(&self, coordinate: i32, size: u32) -> Option<u32> {
+		let size = size as i32;
+		let coordinate = match self.address_mode {
+			CpuAddressMode::Repeat => coordinate.rem_euclid(size),
+			CpuAddressMode::MirroredRepeat => {
+				let period = coordinate.rem_euclid(2 * size);
+				if period < size { period } else { 2 * size - 1 - period }
+			}
+			CpuAddressMode::ClampToEdge => coordinate.clamp(0, size - 1),
+			CpuAddressMode::ClampToBorder => {
+				if !(0..size).contains(&coordinate) {
+					return None;
+				}
+				coordinate
+			}
+		};
+		Some(coordinate as u32)
+	}
+}
+
+/// An image in host memory with the same accessors as sampled and storage images on the GPU. It only has a single mip
+/// level, so sampling ignores any lod or gradients.
+pub struct CpuImage<S> {
+	extent: UVec3,
+	texels: Box<[Cell<[u32; 4]>]>,
+	_phantom: PhantomData<S>,
+}
+
+impl<S: CpuSampleType> CpuImage<S> {
+	/// An image of `extent` with its `texels` in row-major order
+	pub fn new(extent: impl CpuImageCoordinate, texels: impl IntoIterator<Item = S::Vec4>) -> Self {
+		let extent = extent.to_uvec3().max(UVec3::ONE);
+		let texels: Box<[_]> = texels.into_iter().map(|t| Cell::new(S::to_bits(t))).collect();
+		assert_eq!(
+			texels.len(),
+			extent.element_product() as usize,
+			"texel count does not match image extent {extent}"
+		);
+		Self {
+			extent,
+			texels,
+			_phantom: PhantomData,
+		}
+	}
+
+	pub fn extent(&self) -> UVec3 {
+		self.extent
+	}
+
+	fn texel_index(&self, coordinate: UVec3) -> usize {
+		assert!(
+			coordinate.cmplt(self.extent).all(),
+			"texel {coordinate} out of bounds of image with extent {}",
+			self.extent
+		);
+		((coordinate.z * self.extent.y + coordinate.y) * self.extent.x + coordinate.x) as usize
+	}
+
+	pub fn read(&self, coordinate: impl CpuImageCoordinate) -> S::Vec4 {
+		S::from_bits(self.texels[self.texel_index(coordinate.to_uvec3())].get())
+	}
+
+	/// # Safety
+	/// Writes must not alias with any other access of the same texel, same as a storage image on the GPU.
+	pub unsafe fn write(&self, coordinate: impl CpuImageCoordinate, texel: S::Vec4) {
+		self.texels[self.texel_index(coordinate.to_uvec3())].set(S::to_bits(texel));
+	}
+
+	pub fn fetch(&self, coordinate: impl CpuImageCoordinate) -> S::Vec4 {
+		self.read(coordinate)
+	}
+
+	pub fn fetch_with_lod(&self, coordinate: impl CpuImageCoordinate, lod: u32) -> S::Vec4 {
+		assert_eq!(lod, 0, "CpuImage only has a single mip level");
+		self.read(coordinate)
+	}
+}
+
+impl CpuImage<f32> {
+	pub fn sample<C: CpuSampleCoordinate>(&self, sampler: CpuSampler, coordinate: C) -> Vec4 {
+		let texel = coordinate.to_vec3() * self.extent.as_vec3();
+		match sampler.filter {
+			CpuFilter::Nearest => self.load_addressed(sampler, texel.floor().as_ivec3(), C::DIMENSIONS),
+			CpuFilter::Linear => {
+				let texel = texel - 0.5;
+				let base = texel.floor();
+				let t = texel - base;
+				let base = base.as_ivec3();
+				let mut sum = Vec4::ZERO;
+				for corner in 0..1 << C::DIMENSIONS {
+					let mut offset = IVec3::ZERO;
+					let mut weight = 1.;
+					for axis in 0..C::DIMENSIONS {
+						if corner >> axis & 1 == 1 {
+							offset[axis] = 1;
+							weight *= t[axis];
+						} else {
+							weight *= 1. - t[axis];
+						}
+					}
+					if weight > 0. {
+						sum += weight * self.load_addressed(sampler, base + offset, C::DIMENSIONS);
+					}
+				}
+				sum
+			}
+		}
+	}
+
+	pub fn sample_by_lod<C: CpuSampleCoordinate>(&self, sampler: CpuSampler, coordinate: C, lod: f32) -> Vec4 {
+		let _ = lod;
+		self.sample(sampler, coordinate)
+	}
+
+	pub fn sample_by_gradient<C: CpuSampleCoordinate>(
+		&self,
+		sampler: CpuSampler,
+		coordinate: C,
+		gradient_dx: C,
+		gradient_dy: C,
+	) -> Vec4 {
+		let _ = (gradient_dx, gradient_dy);
+		self.sample(sampler, coordinate)
+	}
+
+	fn load_addressed(&self, sampler: CpuSampler, texel: IVec3, dimensions: usize) -> Vec4 {
+		let mut coordinate = UVec3::ZERO;
+		for axis in 0..dimensions {
+			match sampler.address(texel[axis], self.extent[axis]) {
+				Some(c) => coordinate[axis] = c,
+				None => return Vec4::ZERO,
+			}
+		}
+		self.read(coordinate)
+	}
+}
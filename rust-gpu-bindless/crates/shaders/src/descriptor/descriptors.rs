@@ -1,11 +1,18 @@
-use crate::buffer_content::{BufferContent, BufferStruct, Metadata};
-use crate::descriptor::image_types::standard_image_types;
+use crate::buffer_content::BufferStruct;
+#[cfg(not(target_arch = "spirv"))]
+use crate::descriptor::Descriptors;
 use crate::descriptor::reference::{AliveDescRef, Desc};
-use crate::descriptor::{
-	Buffer, BufferSlice, DescContent, DescriptorId, Image, ImageType, MutBuffer, MutBufferSlice, MutImage,
-	TransientAccess, UnsafeDesc,
+#[cfg(all(target_arch = "spirv", feature = "ray_query"))]
+use crate::descriptor::{AccelerationStructure, AccelerationStructureAccess};
+use crate::descriptor::{Buffer, DescContent, DescriptorId, TransientAccess, UnsafeDesc};
+#[cfg(target_arch = "spirv")]
+use crate::{
+	buffer_content::{BufferContent, Metadata},
+	descriptor::image_types::standard_image_types,
+	descriptor::{BufferSlice, Image, ImageType, MutBuffer, MutBufferSlice, MutImage},
 };
 use bytemuck_derive::{Pod, Zeroable};
+#[cfg(target_arch = "spirv")]
 use spirv_std::{RuntimeArray, Sampler, TypedBuffer};
 
 /// Some struct that facilitates access to a [`AliveDescRef`] pointing to some [`DescContent`]
@@ -15,6 +22,7 @@ pub trait DescriptorAccess<'a, C: DescContent> {
 	fn access(self, desc: &Desc<impl AliveDescRef, C>) -> Self::AccessType;
 }
 
+#[cfg(target_arch = "spirv")]
 macro_rules! decl_descriptors {
     ($($image:ident: $sampled:ident $storage:ident,)*) => {
 		pub struct Descriptors<'a> {
@@ -25,6 +33,8 @@ macro_rules! decl_descriptors {
 				pub $sampled: &'a RuntimeArray<<crate::descriptor::$image as ImageType>::SampledSpvImage>,
 			)*
 			pub samplers: &'a RuntimeArray<Sampler>,
+			#[cfg(feature = "ray_query")]
+			pub acceleration_structures: &'a RuntimeArray<AccelerationStructure>,
 			pub meta: Metadata,
 		}
 		$(
@@ -46,8 +56,10 @@ macro_rules! decl_descriptors {
 		)*
 	};
 }
+#[cfg(target_arch = "spirv")]
 standard_image_types!(decl_descriptors);
 
+#[cfg(target_arch = "spirv")]
 impl<'a, T: ?Sized + BufferContent + 'static> DescriptorAccess<'a, Buffer<T>> for &'a Descriptors<'_> {
 	type AccessType = BufferSlice<'a, T>;
 
@@ -56,6 +68,7 @@ impl<'a, T: ?Sized + BufferContent + 'static> DescriptorAccess<'a, Buffer<T>> fo
 	}
 }
 
+#[cfg(target_arch = "spirv")]
 impl<'a, T: ?Sized + BufferContent + 'static> DescriptorAccess<'a, MutBuffer<T>> for &'a mut Descriptors<'_> {
 	type AccessType = MutBufferSlice<'a, T>;
 
@@ -64,6 +77,7 @@ impl<'a, T: ?Sized + BufferContent + 'static> DescriptorAccess<'a, MutBuffer<T>>
 	}
 }
 
+#[cfg(target_arch = "spirv")]
 impl<'a> DescriptorAccess<'a, Sampler> for &'a Descriptors<'_> {
 	type AccessType = Sampler;
 
@@ -72,6 +86,15 @@ impl<'a> DescriptorAccess<'a, Sampler> for &'a Descriptors<'_> {
 	}
 }
 
+#[cfg(all(target_arch = "spirv", feature = "ray_query"))]
+impl<'a> DescriptorAccess<'a, AccelerationStructure> for &'a Descriptors<'_> {
+	type AccessType = AccelerationStructureAccess<'a>;
+
+	fn access(self, desc: &Desc<impl AliveDescRef, AccelerationStructure>) -> Self::AccessType {
+		unsafe { AccelerationStructureAccess::new(self.acceleration_structures.index(desc.id().index().to_usize())) }
+	}
+}
+
 unsafe impl<'a> TransientAccess<'a> for Descriptors<'a> {}
 
 /// All bindless push constants are this particular struct, with T being the declared push_param.
@@ -1,7 +1,9 @@
 use crate::descriptor::descriptor_content::DescContent;
 use core::marker::PhantomData;
 pub use spirv_std::image::SampleType;
-use spirv_std::image::{Arrayed, Dimensionality, Image as SpvImage, ImageDepth, ImageFormat, Multisampled, Sampled};
+use spirv_std::image::{Arrayed, Dimensionality, ImageFormat, Multisampled};
+#[cfg(target_arch = "spirv")]
+use spirv_std::image::{Image as SpvImage, ImageDepth, Sampled};
 
 pub struct Image<T: ImageType> {
 	_phantom: PhantomData<T>,
@@ -77,6 +79,7 @@ unsafe impl<
 	const ARRAYED: u32 = ARRAYED;
 	const MULTISAMPLED: u32 = MULTISAMPLED;
 
+	#[cfg(target_arch = "spirv")]
 	type SampledSpvImage = SpvImage<
 		SampledType,
 		DIM,
@@ -87,6 +90,7 @@ unsafe impl<
 		{ ImageFormat::Unknown as u32 },
 		4,
 	>;
+	#[cfg(target_arch = "spirv")]
 	type StorageSpvImage = SpvImage<
 		SampledType,
 		DIM,
@@ -97,4 +101,8 @@ unsafe impl<
 		{ ImageFormat::Unknown as u32 },
 		4,
 	>;
+	#[cfg(not(target_arch = "spirv"))]
+	type SampledSpvImage = crate::descriptor::CpuImage<SampledType>;
+	#[cfg(not(target_arch = "spirv"))]
+	type StorageSpvImage = crate::descriptor::CpuImage<SampledType>;
 }
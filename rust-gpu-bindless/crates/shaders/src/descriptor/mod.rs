@@ -1,4 +1,7 @@
+mod acceleration_structure;
 mod buffer;
+#[cfg(not(target_arch = "spirv"))]
+mod cpu;
 mod descriptor_content;
 mod descriptors;
 mod id;
@@ -14,7 +17,10 @@ mod weak;
 #[macro_use]
 mod image_types;
 
+pub use acceleration_structure::*;
 pub use buffer::*;
+#[cfg(not(target_arch = "spirv"))]
+pub use cpu::*;
 pub use descriptor_content::*;
 pub use descriptors::*;
 pub use id::*;
@@ -30,3 +36,5 @@ pub const BINDING_BUFFER: u32 = 0;
 pub const BINDING_STORAGE_IMAGE: u32 = 1;
 pub const BINDING_SAMPLED_IMAGE: u32 = 2;
 pub const BINDING_SAMPLER: u32 = 3;
+/// Only present with the `ray_query` feature
+pub const BINDING_ACCELERATION_STRUCTURE: u32 = 4;
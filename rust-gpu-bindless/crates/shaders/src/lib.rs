@@ -2,7 +2,7 @@
 // otherwise you won't see any warnings
 #![deny(warnings)]
 
-#[cfg(test)]
+#[cfg(not(target_arch = "spirv"))]
 extern crate alloc;
 
 pub mod buffer_content;
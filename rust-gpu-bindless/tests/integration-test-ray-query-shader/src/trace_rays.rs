@@ -0,0 +1,62 @@
+use glam::{UVec3, Vec3};
+use rust_gpu_bindless_macros::{BufferStruct, BufferStructPlain, bindless};
+use rust_gpu_bindless_shaders::descriptor::{
+	AccelerationStructure, Buffer, Descriptors, MutBuffer, RayQueryHit, RayQueryRay, TransientDesc,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, BufferStructPlain)]
+pub struct TestRay {
+	pub origin: Vec3,
+	pub t_max: f32,
+	pub direction: Vec3,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, BufferStructPlain)]
+pub struct TestHit {
+	/// `t` of the closest hit, or [`f32::INFINITY`] on a miss
+	pub t: f32,
+	pub instance_custom_index: u32,
+	pub instance_id: u32,
+	pub primitive_index: u32,
+	/// whether `trace_any` reported a hit, as 0 or 1
+	pub any_hit: u32,
+}
+
+impl TestHit {
+	pub fn new(hit: RayQueryHit, any_hit: bool) -> Self {
+		Self {
+			t: hit.t,
+			instance_custom_index: hit.instance_custom_index,
+			instance_id: hit.instance_id,
+			primitive_index: hit.primitive_index,
+			any_hit: any_hit as u32,
+		}
+	}
+}
+
+#[derive(Copy, Clone, BufferStruct)]
+pub struct Param<'a> {
+	pub tlas: TransientDesc<'a, AccelerationStructure>,
+	pub rays: TransientDesc<'a, Buffer<[TestRay]>>,
+	pub hits: TransientDesc<'a, MutBuffer<[TestHit]>>,
+}
+
+// wg of 1 is silly slow but doesn't matter
+#[bindless(compute(threads(1)))]
+pub fn trace_rays(
+	#[bindless(descriptors)] mut descriptors: Descriptors<'_>,
+	#[bindless(param)] param: &Param<'static>,
+	#[spirv(workgroup_id)] wg_id: UVec3,
+) {
+	unsafe {
+		let index = wg_id.x as usize;
+		let ray = param.rays.access(&descriptors).load(index);
+		let ray = RayQueryRay::new(ray.origin, 0., ray.direction, ray.t_max);
+
+		let tlas = param.tlas.access(&descriptors);
+		let hit = TestHit::new(tlas.trace_closest(ray), tlas.trace_any(ray));
+		param.hits.access(&mut descriptors).store(index, hit);
+	}
+}
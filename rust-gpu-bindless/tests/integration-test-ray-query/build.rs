@@ -0,0 +1,10 @@
+use rust_gpu_bindless_shader_builder::ShaderSymbolsBuilder;
+use rust_gpu_bindless_shader_builder::spirv_builder::Capability;
+
+fn main() -> anyhow::Result<()> {
+	ShaderSymbolsBuilder::new("integration-test-ray-query-shader", "spirv-unknown-vulkan1.2")?
+		.capability(Capability::RayQueryKHR)
+		.extension("SPV_KHR_ray_query")
+		.build()?;
+	Ok(())
+}
@@ -0,0 +1,283 @@
+#![cfg(test)]
+
+use approx::assert_relative_eq;
+use ash::khr::{acceleration_structure, deferred_host_operations, ray_query};
+use glam::{Affine3A, Vec3, vec3};
+use integration_test::debugger;
+use integration_test_ray_query_shader::trace_rays::{Param, TestHit, TestRay};
+use pollster::block_on;
+use rust_gpu_bindless_core::descriptor::{
+	AccelerationStructure, AccelerationStructureAllocationError, AccelerationStructureBuildError,
+	AccelerationStructureBuildMode, Bindless, BindlessAccelerationStructureCreateInfo,
+	BindlessAccelerationStructureFlags, BindlessAllocationScheme, BindlessBufferCreateInfo, BindlessBufferUsage,
+	BindlessInstance, BlasTriangles, Buffer, DescriptorCounts, MutDescBufferExt, RCDesc, RCDescExt, TlasInstance,
+};
+use rust_gpu_bindless_core::pipeline::{HostAccess, MutBufferAccessExt, RecordingError, ShaderReadWrite};
+use rust_gpu_bindless_core::platform::BindlessPipelinePlatform;
+use rust_gpu_bindless_core::platform::ash::{Ash, AshSingleGraphicsQueueCreateInfo, ash_init_single_graphics_queue};
+
+fn create_bindless() -> anyhow::Result<BindlessInstance<Ash>> {
+	unsafe {
+		Ok(BindlessInstance::<Ash>::new(
+			ash_init_single_graphics_queue(AshSingleGraphicsQueueCreateInfo {
+				debug: debugger(),
+				extensions: &[
+					acceleration_structure::NAME,
+					deferred_host_operations::NAME,
+					ray_query::NAME,
+				],
+				..AshSingleGraphicsQueueCreateInfo::default()
+			})?,
+			DescriptorCounts {
+				acceleration_structures: 16,
+				..DescriptorCounts::REASONABLE_DEFAULTS
+			},
+		))
+	}
+}
+
+#[test]
+fn test_acceleration_structure_ray_query_ash() -> anyhow::Result<()> {
+	let bindless = create_bindless()?;
+	block_on(test_acceleration_structure_ray_query(&bindless))?;
+	Ok(())
+}
+
+#[test]
+fn test_acceleration_structure_errors_ash() -> anyhow::Result<()> {
+	let bindless = create_bindless()?;
+	test_acceleration_structure_errors(&bindless)?;
+	Ok(())
+}
+
+const AS_CI: BindlessAccelerationStructureCreateInfo = BindlessAccelerationStructureCreateInfo {
+	flags: BindlessAccelerationStructureFlags::PREFER_FAST_TRACE,
+	allocation_scheme: BindlessAllocationScheme::AllocatorManaged,
+	name: "as",
+};
+
+/// A quad spanning `[-1, 1]` on the xy plane, triangle 0 covers the half with `x > y`, triangle 1 the other half
+fn quad<P: BindlessPipelinePlatform>(
+	bindless: &Bindless<P>,
+	usage: BindlessBufferUsage,
+) -> anyhow::Result<(RCDesc<P, Buffer<[Vec3]>>, RCDesc<P, Buffer<[u32]>>)> {
+	let vertices = bindless.buffer().alloc_shared_from_iter(
+		&BindlessBufferCreateInfo {
+			name: "vertices",
+			usage,
+			allocation_scheme: BindlessAllocationScheme::AllocatorManaged,
+		},
+		[
+			vec3(-1., -1., 0.),
+			vec3(1., -1., 0.),
+			vec3(1., 1., 0.),
+			vec3(-1., 1., 0.),
+		],
+	)?;
+	let indices = bindless.buffer().alloc_shared_from_iter(
+		&BindlessBufferCreateInfo {
+			name: "indices",
+			usage,
+			allocation_scheme: BindlessAllocationScheme::AllocatorManaged,
+		},
+		[0, 1, 2, 0, 2, 3],
+	)?;
+	Ok((vertices, indices))
+}
+
+fn down(origin: Vec3, t_max: f32) -> TestRay {
+	TestRay {
+		origin,
+		t_max,
+		direction: Vec3::NEG_Z,
+	}
+}
+
+async fn trace<P: BindlessPipelinePlatform>(
+	bindless: &Bindless<P>,
+	tlas: &RCDesc<P, AccelerationStructure>,
+	rays: &[TestRay],
+) -> anyhow::Result<Vec<TestHit>> {
+	let pipeline = bindless.create_compute_pipeline(crate::shader::trace_rays::trace_rays::new())?;
+	let rays_buffer = bindless.buffer().alloc_shared_from_iter(
+		&BindlessBufferCreateInfo {
+			name: "rays",
+			usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+			allocation_scheme: BindlessAllocationScheme::AllocatorManaged,
+		},
+		rays.iter().copied(),
+	)?;
+	let hits = bindless.execute(|cmd| {
+		let hits = bindless.buffer().alloc_slice::<TestHit>(
+			&BindlessBufferCreateInfo {
+				name: "hits",
+				usage: BindlessBufferUsage::MAP_READ | BindlessBufferUsage::STORAGE_BUFFER,
+				allocation_scheme: BindlessAllocationScheme::AllocatorManaged,
+			},
+			rays.len(),
+		)?;
+		let hits = hits.access::<ShaderReadWrite>(cmd)?;
+		cmd.dispatch(
+			&pipeline,
+			[rays.len() as u32, 1, 1],
+			Param {
+				tlas: tlas.to_transient(cmd),
+				rays: rays_buffer.to_transient(cmd),
+				hits: hits.to_mut_transient()?,
+			},
+		)?;
+		Ok(hits.transition::<HostAccess>()?.into_desc())
+	})?;
+	Ok(hits.mapped().await?.read_iter().collect())
+}
+
+fn assert_hit(hit: TestHit, t: f32, instance_custom_index: u32, instance_id: u32, primitive_index: u32) {
+	assert_relative_eq!(hit.t, t, epsilon = 1e-4);
+	assert_eq!(hit.instance_custom_index, instance_custom_index);
+	assert_eq!(hit.instance_id, instance_id);
+	assert_eq!(hit.primitive_index, primitive_index);
+	assert_eq!(hit.any_hit, 1);
+}
+
+fn assert_miss(hit: TestHit) {
+	assert_eq!(hit.t, f32::INFINITY);
+	assert_eq!(hit.any_hit, 0);
+}
+
+async fn test_acceleration_structure_ray_query<P: BindlessPipelinePlatform>(
+	bindless: &Bindless<P>,
+) -> anyhow::Result<()> {
+	let (vertices, indices) = quad(
+		bindless,
+		BindlessBufferUsage::MAP_WRITE
+			| BindlessBufferUsage::STORAGE_BUFFER
+			| BindlessBufferUsage::ACCELERATION_STRUCTURE_BUILD_INPUT,
+	)?;
+	let geometries = [BlasTriangles::new(&vertices, &indices)];
+	assert_eq!(geometries[0].vertex_stride, 12);
+	assert_eq!(geometries[0].vertex_count, 4);
+	assert_eq!(geometries[0].triangle_count, 2);
+
+	let blas = bindless.acceleration_structure().alloc_blas(
+		&BindlessAccelerationStructureCreateInfo { name: "blas", ..AS_CI },
+		&geometries,
+	)?;
+	let tlas = bindless.acceleration_structure().alloc_tlas(
+		&BindlessAccelerationStructureCreateInfo {
+			flags: BindlessAccelerationStructureFlags::PREFER_FAST_TRACE
+				| BindlessAccelerationStructureFlags::ALLOW_UPDATE,
+			name: "tlas",
+			..AS_CI
+		},
+		2,
+	)?;
+
+	let instances = |moved: Vec3| {
+		[
+			TlasInstance::new(&blas, Affine3A::IDENTITY, 7),
+			TlasInstance::new(&blas, Affine3A::from_translation(moved), 9),
+		]
+	};
+	bindless.execute(|cmd| {
+		cmd.build_blas(&blas, &geometries, AccelerationStructureBuildMode::Build)?;
+		// the build of the blas is synchronized with the tlas build reading it
+		cmd.build_tlas(
+			&tlas,
+			&instances(vec3(10., 0., -1.)),
+			AccelerationStructureBuildMode::Build,
+		)?;
+		Ok(())
+	})?;
+
+	let hits = trace(
+		bindless,
+		&tlas,
+		&[
+			down(vec3(0.5, -0.5, 2.), 10.),
+			down(vec3(-0.5, 0.5, 2.), 10.),
+			down(vec3(10., 0., 2.), 10.),
+			down(vec3(5., 0., 2.), 10.),
+			down(vec3(0.5, -0.5, 2.), 1.),
+		],
+	)
+	.await?;
+	assert_hit(hits[0], 2., 7, 0, 0);
+	assert_hit(hits[1], 2., 7, 0, 1);
+	assert_hit(hits[2], 3., 9, 1, 0);
+	assert_miss(hits[3]);
+	assert_miss(hits[4]);
+
+	// refit the tlas with the second instance moved
+	bindless.execute(|cmd| {
+		cmd.build_tlas(
+			&tlas,
+			&instances(vec3(20., 0., 0.)),
+			AccelerationStructureBuildMode::Update,
+		)?;
+		Ok(())
+	})?;
+	let hits = trace(
+		bindless,
+		&tlas,
+		&[down(vec3(10., 0., 2.), 10.), down(vec3(20., 0., 2.), 10.)],
+	)
+	.await?;
+	assert_miss(hits[0]);
+	assert_hit(hits[1], 2., 9, 1, 0);
+	Ok(())
+}
+
+fn test_acceleration_structure_errors<P: BindlessPipelinePlatform>(bindless: &Bindless<P>) -> anyhow::Result<()> {
+	let acceleration_structure = bindless.acceleration_structure();
+	assert!(matches!(
+		acceleration_structure.alloc_blas(&AS_CI, &[]),
+		Err(AccelerationStructureAllocationError::NoGeometry { .. })
+	));
+
+	let (vertices, indices) = quad(
+		bindless,
+		BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::STORAGE_BUFFER,
+	)?;
+	let geometries = [BlasTriangles::new(&vertices, &indices)];
+	let blas = acceleration_structure.alloc_blas(&AS_CI, &geometries)?;
+	let tlas = acceleration_structure.alloc_tlas(&AS_CI, 1)?;
+
+	let build_error = |result: Result<(), RecordingError<P>>| match result {
+		Err(RecordingError::AccelerationStructureBuildError(err)) => err,
+		other => panic!("expected an acceleration structure build error, got {other:?}"),
+	};
+	assert!(matches!(
+		build_error(bindless.execute(|cmd| cmd.build_blas(&blas, &geometries, AccelerationStructureBuildMode::Build))),
+		AccelerationStructureBuildError::MissingBuildInputUsage { .. }
+	));
+	assert!(matches!(
+		build_error(bindless.execute(|cmd| cmd.build_blas(&blas, &geometries, AccelerationStructureBuildMode::Update))),
+		AccelerationStructureBuildError::UpdateNotAllowed { .. }
+	));
+	assert!(matches!(
+		build_error(bindless.execute(|cmd| cmd.build_blas(&blas, &[], AccelerationStructureBuildMode::Build))),
+		AccelerationStructureBuildError::GeometryCountMismatch {
+			expected: 1,
+			actual: 0,
+			..
+		}
+	));
+	assert!(matches!(
+		build_error(bindless.execute(|cmd| cmd.build_blas(&tlas, &geometries, AccelerationStructureBuildMode::Build))),
+		AccelerationStructureBuildError::WrongType { .. }
+	));
+	let instance = TlasInstance::new(&blas, Affine3A::IDENTITY, 0);
+	assert!(matches!(
+		build_error(bindless.execute(|cmd| cmd.build_tlas(&blas, &[instance], AccelerationStructureBuildMode::Build))),
+		AccelerationStructureBuildError::WrongType { .. }
+	));
+	assert!(matches!(
+		build_error(bindless.execute(|cmd| cmd.build_tlas(
+			&tlas,
+			&[instance, instance],
+			AccelerationStructureBuildMode::Build
+		))),
+		AccelerationStructureBuildError::TooManyPrimitives { max: 1, actual: 2, .. }
+	));
+	Ok(())
+}
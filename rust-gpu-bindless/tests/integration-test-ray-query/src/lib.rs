@@ -0,0 +1,6 @@
+//! Integration tests requiring acceleration structures and ray queries. They live in their own crate, as the
+//! `ray_query` feature declares the acceleration structure binding in every shader of the shader crate, which requires
+//! the extensions to be enabled on the device.
+
+pub mod acceleration_structure;
+pub mod shader;
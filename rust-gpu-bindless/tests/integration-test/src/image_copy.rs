@@ -28,6 +28,21 @@ fn test_image_copy_ash() -> anyhow::Result<()> {
 	}
 }
 
+#[test]
+fn test_image_copy_mip_ash() -> anyhow::Result<()> {
+	unsafe {
+		let bindless = BindlessInstance::<Ash>::new(
+			ash_init_single_graphics_queue(AshSingleGraphicsQueueCreateInfo {
+				debug: debugger(),
+				..AshSingleGraphicsQueueCreateInfo::default()
+			})?,
+			DescriptorCounts::REASONABLE_DEFAULTS,
+		);
+		test_image_copy_mip(&bindless)?;
+		Ok(())
+	}
+}
+
 async fn test_image_copy<P: BindlessPipelinePlatform>(bindless: &Bindless<P>) -> anyhow::Result<()> {
 	let extent = UVec2::new(32, 32);
 	let format = Format::R8G8B8A8_UNORM;
@@ -71,3 +86,49 @@ async fn test_image_copy<P: BindlessPipelinePlatform>(bindless: &Bindless<P>) ->
 	assert_eq!(&*result, &*pixels);
 	Ok(())
 }
+
+fn test_image_copy_mip<P: BindlessPipelinePlatform>(bindless: &Bindless<P>) -> anyhow::Result<()> {
+	let extent = UVec2::new(32, 8);
+	let mip_levels = 6;
+	let staging = (0..mip_levels)
+		.map(|mip_level| {
+			let mip = Extent::from(extent).mip(mip_level);
+			let len = (mip.width * mip.height * 4) as usize;
+			bindless.buffer().alloc_from_iter(
+				&BindlessBufferCreateInfo {
+					name: "staging_upload",
+					usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::TRANSFER_SRC,
+					allocation_scheme: BindlessAllocationScheme::AllocatorManaged,
+				},
+				(0..len).map(|i| i as u8),
+			)
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	let image = bindless.image().alloc::<Image2d>(&BindlessImageCreateInfo {
+		format: Format::R8G8B8A8_UNORM,
+		extent: Extent::from(extent),
+		mip_levels,
+		usage: BindlessImageUsage::TRANSFER_DST,
+		..BindlessImageCreateInfo::default()
+	})?;
+
+	let out_of_bounds = bindless.execute(|cmd| {
+		let image = image.access::<TransferWrite>(cmd)?;
+		for (mip_level, staging) in staging.into_iter().enumerate() {
+			let staging = staging.access::<TransferRead>(cmd)?;
+			cmd.copy_buffer_to_image_mip(&staging, &image, mip_level as u32)?;
+		}
+		let staging = bindless.buffer().alloc_from_iter(
+			&BindlessBufferCreateInfo {
+				name: "staging_out_of_bounds",
+				usage: BindlessBufferUsage::MAP_WRITE | BindlessBufferUsage::TRANSFER_SRC,
+				allocation_scheme: BindlessAllocationScheme::AllocatorManaged,
+			},
+			[0u8; 4],
+		)?;
+		let staging = staging.access::<TransferRead>(cmd)?;
+		Ok(cmd.copy_buffer_to_image_mip(&staging, &image, mip_levels).is_err())
+	})?;
+	assert!(out_of_bounds);
+	Ok(())
+}